@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+
+use crate::{
+    Attempts, Config, ReferralCodeError, apply_random_case, assemble, check_config, dedup_key,
+    rng::{CodeRng, rng_for},
+    segment,
+};
+
+/// Attempts at exchanging characters with later codes before characters
+/// are drawn again instead.
+const SWAP_ATTEMPTS: usize = 64;
+
+/// Generates `config.count` unique codes, with the characters of every
+/// random position spread evenly across the batch.
+///
+/// Independently drawn codes leave some characters rare or missing from a
+/// position by chance, which partners checking samples statistically may
+/// flag. Here each random position holds every distinct character of its
+/// alphabet the same number of times, give or take one, in a random order.
+///
+/// Duplicates and codes rejected by reserved prefixes or `no_repeats` are
+/// fixed by exchanging characters with later codes, which keeps the counts.
+/// A code that can't be fixed this way has one of its characters drawn
+/// again, or, close to a full keyspace, several, so the batch is then only
+/// approximately balanced.
+///
+/// # Errors
+///
+/// Returns the same errors as [`crate::generate`].
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, Pattern, generate_balanced};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Length(6),
+///     count: 1000,
+///     ..Config::default()
+/// };
+/// let codes = generate_balanced(&config).unwrap();
+///
+/// // Every digit starts exactly a tenth of the codes.
+/// let zeros = codes.iter().filter(|code| code.starts_with('0')).count();
+/// assert_eq!(zeros, 100);
+/// ```
+pub fn generate_balanced(config: &Config) -> Result<Vec<String>, ReferralCodeError> {
+    generate_balanced_with(config, &mut rng_for(config))
+}
+
+fn generate_balanced_with<R: CodeRng + ?Sized>(
+    config: &Config,
+    rng: &mut R,
+) -> Result<Vec<String>, ReferralCodeError> {
+    check_config(config)?;
+
+    let count = config.count;
+    let alphabets = segment::free_alphabets(config);
+    let columns: Vec<Vec<char>> = alphabets
+        .iter()
+        .map(|alphabet| balanced_column(alphabet, count, rng))
+        .collect();
+    let mut free: Vec<Vec<char>> = (0..count)
+        .map(|j| columns.iter().map(|column| column[j]).collect())
+        .collect();
+
+    let code_of = |free: &[char]| assemble(config, segment::with_derived(config, free.to_vec()));
+    let finish = |free: Vec<char>, rng: &mut R| {
+        let mut random = segment::with_derived(config, free);
+        apply_random_case(config, &mut random, rng);
+        assemble(config, random)
+    };
+    let mut seen = HashSet::with_capacity(count);
+    let mut draws = Attempts::new(config, 0, count);
+    let mut admit =
+        |code: &str| config.accepts(code) && seen.insert(dedup_key(config, code).into_owned());
+
+    for j in 0..count {
+        if admit(&code_of(&free[j])) {
+            continue;
+        }
+
+        let mut fixed = false;
+        if !alphabets.is_empty() && j + 1 < count {
+            for _ in 0..SWAP_ATTEMPTS {
+                let p = rng.index(alphabets.len());
+                let k = j + 1 + rng.index(count - j - 1);
+                swap(&mut free, p, j, k);
+                if admit(&code_of(&free[j])) {
+                    fixed = true;
+                    break;
+                }
+                swap(&mut free, p, j, k);
+            }
+        }
+
+        // Single characters are drawn again first, so that the characters
+        // that don't matter keep their counts.
+        let mut attempts = 0;
+        while !fixed {
+            if !draws.take() {
+                let generated = free.into_iter().take(j).map(|free| finish(free, rng));
+                return Err(draws.exceeded(generated.collect()));
+            }
+            let p = rng.index(alphabets.len());
+            let previous = free[j][p];
+            free[j][p] = alphabets[p][rng.index(alphabets[p].len())];
+            fixed = admit(&code_of(&free[j]));
+
+            attempts += 1;
+            if !fixed && attempts < SWAP_ATTEMPTS {
+                free[j][p] = previous;
+            }
+        }
+    }
+
+    Ok(free.into_iter().map(|free| finish(free, rng)).collect())
+}
+
+/// Returns `count` characters of `alphabet`, each appearing `count /
+/// alphabet.len()` times or once more, in random order.
+fn balanced_column<R: CodeRng + ?Sized>(alphabet: &[char], count: usize, rng: &mut R) -> Vec<char> {
+    let mut column = Vec::with_capacity(count);
+    let mut remainder = alphabet.to_vec();
+    shuffle(&mut remainder, rng);
+
+    for _ in 0..count / alphabet.len() {
+        column.extend_from_slice(alphabet);
+    }
+    column.extend_from_slice(&remainder[..count % alphabet.len()]);
+    shuffle(&mut column, rng);
+
+    column
+}
+
+fn shuffle<T, R: CodeRng + ?Sized>(items: &mut [T], rng: &mut R) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, rng.index(i + 1));
+    }
+}
+
+/// Exchanges the characters of codes `j` and `k` at free position `p`.
+fn swap(free: &mut [Vec<char>], p: usize, j: usize, k: usize) {
+    let c = free[j][p];
+    free[j][p] = free[k][p];
+    free[k][p] = c;
+}
+
+#[test]
+fn test_generate_balanced_spreads_characters_evenly() {
+    use std::collections::HashMap;
+
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Custom("ABCDEFGHJKLMNPQRSTUVWXYZ".to_string()),
+        pattern: Pattern::Pattern("R-####".to_string()),
+        count: 230,
+        ..Config::default()
+    };
+    let counts = |codes: &[String], position: usize| {
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        for code in codes {
+            *counts
+                .entry(code.chars().nth(position).unwrap())
+                .or_default() += 1;
+        }
+        counts
+    };
+
+    let codes = generate_balanced(&config).unwrap();
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 230);
+    for position in 2..6 {
+        let counts = counts(&codes, position);
+        assert_eq!(counts.len(), 24);
+        assert!(counts.values().all(|n| (9..=10).contains(n)), "{counts:?}");
+    }
+
+    // 'A' can't start a code, but the other positions stay close to even.
+    let reserved = Config {
+        reserved_prefixes: vec!["R-A".to_string()],
+        ..config
+    };
+    let codes = generate_balanced(&reserved).unwrap();
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 230);
+    assert!(codes.iter().all(|code| reserved.accepts(code)));
+    assert!(!counts(&codes, 2).contains_key(&'A'));
+    for position in 3..6 {
+        let counts = counts(&codes, position);
+        assert!(counts.values().all(|n| (8..=11).contains(n)), "{counts:?}");
+    }
+
+    // Even a full keyspace comes out whole.
+    let full = Config {
+        charset: Charset::Custom("AB".to_string()),
+        pattern: Pattern::Length(3),
+        count: 8,
+        ..Config::default()
+    };
+    let codes: HashSet<String> = generate_balanced(&full).unwrap().into_iter().collect();
+    assert_eq!(codes.len(), 8);
+    assert!(generate_balanced(&full.with_count(9)).is_err());
+    assert!(generate_balanced(&full.with_count(0)).unwrap().is_empty());
+}
@@ -0,0 +1,709 @@
+#[cfg(feature = "rng")]
+use std::collections::HashSet;
+
+#[cfg(feature = "rng")]
+use crate::{
+    Attempts, ReferralCodeError, check_config, dedup_key, generate_one_with, generate_with,
+    is_feasible,
+    rng::{SeedableRng, StdRng, backend, rng_for, sample_indices},
+    segment,
+};
+use crate::{Config, RngSource, registry::config_hash};
+
+/// A named set of unique codes generated from a single configuration.
+///
+/// Codes are kept in generation order.
+#[derive(Clone, Debug)]
+pub struct Batch {
+    /// Identifier of the batch, e.g. a campaign or job identifier.
+    pub id: String,
+    /// The configuration the codes were generated with.
+    pub config: Config,
+    /// The generated codes, in generation order.
+    pub codes: Vec<String>,
+    /// Configurations the batch was issued under before `config`, oldest first.
+    ///
+    /// A batch only has these when a [`crate::Registry`] extended its random
+    /// length to keep issuing codes after its keyspace saturated; codes
+    /// issued before that keep the format they were issued in.
+    pub superseded: Vec<Config>,
+    /// The experiment the batch is a variant of, if it was generated by
+    /// [`crate::Registry::generate_experiment`].
+    pub assignment: Option<Assignment>,
+    /// Positions in `codes` of canary codes, in increasing order.
+    ///
+    /// Canaries are valid codes that are never handed out: any attempt to
+    /// redeem one reveals a leaked or brute-forced batch. See
+    /// [`Batch::generate_with_canaries`].
+    pub canaries: Vec<usize>,
+    /// How the batch was generated, if known.
+    ///
+    /// Batches generated by this crate record it, and registry files keep
+    /// it; batches built by hand or loaded from older files have none.
+    pub provenance: Option<Provenance>,
+}
+
+/// The variant of a format experiment a batch was assigned to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assignment {
+    /// Identifier of the experiment.
+    pub experiment: String,
+    /// Name of the variant within the experiment.
+    pub variant: String,
+}
+
+/// How a batch was generated, recorded so old exports can be audited and,
+/// when seeded, reproduced.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "rng")] {
+/// use referral_codes::{Batch, Config, RngPolicy};
+///
+/// let config = Config::default().with_count(10);
+/// let batch = Batch::generate_seeded("spring", &config, 42).unwrap();
+/// let provenance = batch.provenance.as_ref().unwrap();
+///
+/// assert_eq!(provenance.crate_version, env!("CARGO_PKG_VERSION"));
+/// assert_eq!(provenance.rng, RngPolicy::Seeded);
+/// assert!(provenance.matches(&config));
+/// assert!(!provenance.matches(&config.with_count(11)));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    /// Version of the crate that generated the batch.
+    pub crate_version: String,
+    /// How candidates were made unique.
+    pub strategy: Strategy,
+    /// How the random number generator was seeded.
+    pub rng: RngPolicy,
+    /// The source codes were drawn from, or `None` for a seeded batch.
+    ///
+    /// When codes of several sources were mixed in, this is the one that
+    /// isn't cryptographically secure, if any.
+    pub source: Option<RngSource>,
+    /// Hash of the configuration the batch was generated with.
+    ///
+    /// The hash is the 64-bit FNV-1a hash of the configuration as written
+    /// in registry files, stable across crate versions.
+    pub config_hash: u64,
+}
+
+impl Provenance {
+    /// Records the generation of a batch with this crate version.
+    #[cfg(feature = "rng")]
+    pub(crate) fn new(config: &Config, strategy: Strategy, rng: RngPolicy) -> Self {
+        Provenance {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            strategy,
+            rng,
+            source: (rng == RngPolicy::OsSeeded).then_some(config.rng),
+            config_hash: config_hash(config),
+        }
+    }
+
+    /// Returns `true` if the batch was generated with `config`.
+    pub fn matches(&self, config: &Config) -> bool {
+        self.config_hash == config_hash(config)
+    }
+}
+
+/// How the codes of a batch were made unique.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Strategy {
+    /// Codes were drawn independently, and candidates already in the batch
+    /// were drawn again.
+    Rejection,
+    /// Candidates already in the batch or in any batch of the
+    /// [`crate::Registry`] were drawn again.
+    RegistryRejection,
+}
+
+/// How the random number generator of a batch was seeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RngPolicy {
+    /// The [`crate::RngSource`] of the configuration, seeded by the
+    /// operating system: the batch can't be reproduced.
+    OsSeeded,
+    /// A generator seeded by the caller, see [`Batch::generate_seeded`]: the
+    /// batch can be reproduced with the same seed, which is never recorded.
+    Seeded,
+}
+
+impl Batch {
+    /// Generates a new batch of `config.count` unique codes.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Identifier of the batch
+    /// * `config` - Configuration specifying the pattern, charset, and count of codes to generate
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`crate::generate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Batch, Config};
+    ///
+    /// let batch = Batch::generate("spring", &Config::default().with_count(10)).unwrap();
+    /// assert_eq!(batch.codes.len(), 10);
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn generate(id: impl Into<String>, config: &Config) -> Result<Self, ReferralCodeError> {
+        Ok(Batch {
+            id: id.into(),
+            config: config.clone(),
+            codes: generate_with(config, &mut rng_for(config))?,
+            superseded: Vec::new(),
+            assignment: None,
+            canaries: Vec::new(),
+            provenance: Some(Provenance::new(
+                config,
+                Strategy::Rejection,
+                RngPolicy::OsSeeded,
+            )),
+        })
+    }
+
+    /// Generates a batch deterministically from a seed.
+    ///
+    /// The same seed and configuration always produce the same codes in the
+    /// same order, which makes generation idempotent: a retried job can
+    /// recreate exactly the batch it produced before.
+    ///
+    /// The seed must be kept secret, since anyone knowing it can recompute
+    /// every code of the batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`crate::generate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Batch, Config};
+    ///
+    /// let config = Config::default().with_count(10);
+    /// let first = Batch::generate_seeded("spring", &config, 42).unwrap();
+    /// let retry = Batch::generate_seeded("spring", &config, 42).unwrap();
+    ///
+    /// assert_eq!(first.codes, retry.codes);
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn generate_seeded(
+        id: impl Into<String>,
+        config: &Config,
+        seed: u64,
+    ) -> Result<Self, ReferralCodeError> {
+        Ok(Batch {
+            id: id.into(),
+            config: config.clone(),
+            codes: generate_with(config, &mut StdRng::seed_from_u64(seed))?,
+            superseded: Vec::new(),
+            assignment: None,
+            canaries: Vec::new(),
+            provenance: Some(Provenance::new(
+                config,
+                Strategy::Rejection,
+                RngPolicy::Seeded,
+            )),
+        })
+    }
+
+    /// Generates a batch of `config.count` codes plus a fraction of canaries.
+    ///
+    /// `ceil(fraction * config.count)` more codes are generated and flagged
+    /// in [`Batch::canaries`], at random positions. They are structurally
+    /// valid and unique like every other code, so they can't be told apart
+    /// from the outside. Hand out [`Batch::distributable`] codes only, keep
+    /// the canaries in the manifest, and alert on any redemption attempt
+    /// against one with [`Batch::is_canary`] or [`crate::Registry::is_canary`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::InvalidProbability` if `fraction` is not
+    /// between 0 and 1, or the same errors as [`crate::generate`] for the
+    /// codes and canaries together.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Batch, Config};
+    ///
+    /// let batch = Batch::generate_with_canaries("spring", &Config::default().with_count(100), 0.05)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(batch.codes.len(), 105);
+    /// assert_eq!(batch.distributable().count(), 100);
+    /// assert!(batch.is_canary(&batch.codes[batch.canaries[0]]));
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn generate_with_canaries(
+        id: impl Into<String>,
+        config: &Config,
+        fraction: f64,
+    ) -> Result<Self, ReferralCodeError> {
+        let mut rng = rng_for(config);
+        let canaries = place_canaries(config.count, fraction, &mut rng)?;
+        let total = config.count + canaries.len();
+
+        Ok(Batch {
+            id: id.into(),
+            config: config.clone(),
+            codes: generate_with(&config.with_count(total), &mut rng)?,
+            superseded: Vec::new(),
+            assignment: None,
+            canaries,
+            provenance: Some(Provenance::new(
+                config,
+                Strategy::Rejection,
+                RngPolicy::OsSeeded,
+            )),
+        })
+    }
+
+    /// Generates a batch of codes plus a fraction of canaries
+    /// deterministically from a seed.
+    ///
+    /// See [`Batch::generate_with_canaries`] and [`Batch::generate_seeded`]:
+    /// the positions of the canaries are drawn from the seed too, so the
+    /// same seed and configuration always produce the same codes and
+    /// canaries.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Batch::generate_with_canaries`].
+    #[cfg(feature = "rng")]
+    pub fn generate_seeded_with_canaries(
+        id: impl Into<String>,
+        config: &Config,
+        fraction: f64,
+        seed: u64,
+    ) -> Result<Self, ReferralCodeError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let canaries = place_canaries(config.count, fraction, &mut rng)?;
+        let total = config.count + canaries.len();
+
+        Ok(Batch {
+            id: id.into(),
+            config: config.clone(),
+            codes: generate_with(&config.with_count(total), &mut rng)?,
+            superseded: Vec::new(),
+            assignment: None,
+            canaries,
+            provenance: Some(Provenance::new(
+                config,
+                Strategy::Rejection,
+                RngPolicy::Seeded,
+            )),
+        })
+    }
+
+    /// Returns `true` if `code` is one of the batch's canaries.
+    pub fn is_canary(&self, code: &str) -> bool {
+        self.canaries
+            .iter()
+            .any(|i| self.codes.get(*i).is_some_and(|c| c == code))
+    }
+
+    /// Returns the codes that can be handed out: every code but the canaries.
+    pub fn distributable(&self) -> impl Iterator<Item = &String> {
+        self.codes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.canaries.binary_search(i).is_err())
+            .map(|(_, code)| code)
+    }
+
+    /// Replaces the codes at the given positions with fresh unique codes.
+    ///
+    /// Use this when some codes of a batch can't be used, e.g. because they
+    /// were misprinted: the rest of the batch is kept as-is, and each replaced
+    /// code gets a new code at the same position. Replacements are unique
+    /// against the whole batch, including the codes being replaced, so a
+    /// discarded code is never issued again. Digests and Merkle roots are
+    /// computed from the codes, so they reflect the replacements. The
+    /// provenance of a seeded batch records it as [`RngPolicy::OsSeeded`]
+    /// once a code is replaced, since the seed can't reproduce it anymore.
+    ///
+    /// # Arguments
+    ///
+    /// * `indices` - Positions of the codes to replace
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(replacements)` - Pairs of `(old, new)` codes, in the order of
+    ///   `indices`; repeated indices are replaced once
+    /// * `Err(ReferralCodeError::NotInBatch)` - If an index is out of range
+    /// * `Err(ReferralCodeError::NonFeasibleConfig)` - If the keyspace can't
+    ///   hold the batch plus its replacements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Batch, Config};
+    ///
+    /// let mut batch = Batch::generate("spring", &Config::default().with_count(10)).unwrap();
+    /// let misprinted = batch.codes[2].clone();
+    ///
+    /// let replacements = batch.regenerate_subset(&[2]).unwrap();
+    ///
+    /// assert_eq!(replacements[0].0, misprinted);
+    /// assert_eq!(batch.codes[2], replacements[0].1);
+    /// assert!(!batch.codes.contains(&misprinted));
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn regenerate_subset(
+        &mut self,
+        indices: &[usize],
+    ) -> Result<Vec<(String, String)>, ReferralCodeError> {
+        if indices.iter().any(|i| *i >= self.codes.len()) {
+            return Err(ReferralCodeError::NotInBatch);
+        }
+
+        let mut unique: Vec<usize> = indices.to_vec();
+        unique.sort();
+        unique.dedup();
+
+        if !is_feasible(&self.config.with_count(self.codes.len() + unique.len())) {
+            return Err(ReferralCodeError::NonFeasibleConfig);
+        }
+
+        let mut taken = self.taken(&self.config);
+        let mut attempts = Attempts::new(&self.config, taken.len(), unique.len());
+        let mut rng = rng_for(&self.config);
+        let mut fresh = Vec::with_capacity(unique.len());
+
+        // Drawn before any code is replaced, so a spent budget leaves the
+        // batch as it was.
+        while fresh.len() < unique.len() {
+            let Some(candidate) = generate_one_with(&self.config, &mut rng, &mut attempts) else {
+                return Err(attempts.exceeded(fresh));
+            };
+            if taken.insert(dedup_key(&self.config, &candidate).into_owned()) {
+                fresh.push(candidate);
+            }
+        }
+
+        let mut replaced = HashSet::new();
+        let positions = indices.iter().filter(|i| replaced.insert(**i));
+        let mut replacements = Vec::with_capacity(unique.len());
+        for (&i, new) in positions.zip(fresh) {
+            let old = std::mem::replace(&mut self.codes[i], new.clone());
+            replacements.push((old, new));
+        }
+        if !replacements.is_empty() {
+            self.mix_in_os_seeded(self.config.rng);
+        }
+
+        Ok(replacements)
+    }
+
+    /// Appends `additional` codes, unique against the existing batch contents.
+    ///
+    /// Feasibility is checked against the remaining keyspace: existing codes
+    /// that match `config` already occupy part of its keyspace, so the
+    /// keyspace must hold them plus the additional codes. `config` is usually
+    /// the batch's own configuration, but any configuration can be used; its
+    /// `count` is ignored. As with [`Batch::regenerate_subset`], a seeded
+    /// batch is recorded as [`RngPolicy::OsSeeded`] once codes are added.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration for the new codes
+    /// * `additional` - Number of codes to add
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(codes)` - The newly added codes
+    /// * `Err(ReferralCodeError)` - The same errors as [`crate::generate`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Batch, Config};
+    ///
+    /// let config = Config::default().with_count(100);
+    /// let mut batch = Batch::generate("spring", &config).unwrap();
+    ///
+    /// let added = batch.extend(&config, 50).unwrap().len();
+    ///
+    /// assert_eq!(added, 50);
+    /// assert_eq!(batch.codes.len(), 150);
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn extend(
+        &mut self,
+        config: &Config,
+        additional: usize,
+    ) -> Result<&[String], ReferralCodeError> {
+        let occupied = self
+            .codes
+            .iter()
+            .filter(|c| segment::random_chars(config, c).is_some())
+            .count();
+        check_config(&config.with_count(occupied + additional))?;
+
+        let mut taken = self.taken(config);
+        let start = self.codes.len();
+        self.codes.reserve(additional);
+        let mut attempts = Attempts::new(config, occupied, additional);
+        let mut rng = rng_for(config);
+
+        while self.codes.len() < start + additional {
+            let Some(candidate) = generate_one_with(config, &mut rng, &mut attempts) else {
+                // A spent budget leaves the batch as it was.
+                let generated = self.codes.split_off(start);
+                return Err(attempts.exceeded(generated));
+            };
+            if taken.insert(dedup_key(config, &candidate).into_owned()) {
+                self.codes.push(candidate);
+            }
+        }
+        if additional > 0 {
+            self.mix_in_os_seeded(config.rng);
+        }
+
+        Ok(&self.codes[start..])
+    }
+
+    /// Records that codes drawn from `source`, seeded by the operating
+    /// system, were mixed into the batch, which can't be reproduced from its
+    /// seed anymore.
+    #[cfg(feature = "rng")]
+    pub(crate) fn mix_in_os_seeded(&mut self, source: RngSource) {
+        if let Some(provenance) = &mut self.provenance {
+            provenance.rng = RngPolicy::OsSeeded;
+            if provenance.source.is_none_or(|s| s.is_secure()) {
+                provenance.source = Some(source);
+            }
+        }
+    }
+
+    /// Returns the deduplication keys of the batch's codes under `config`.
+    #[cfg(feature = "rng")]
+    fn taken(&self, config: &Config) -> HashSet<String> {
+        self.codes
+            .iter()
+            .map(|c| dedup_key(config, c).into_owned())
+            .collect()
+    }
+
+    /// Replaces the given codes with fresh unique codes.
+    ///
+    /// This is [`Batch::regenerate_subset`] with codes instead of positions.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::NotInBatch` if a code is not part of the
+    /// batch, or the same errors as [`Batch::regenerate_subset`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Batch, Config};
+    ///
+    /// let mut batch = Batch::generate("spring", &Config::default().with_count(10)).unwrap();
+    /// let misprinted = vec![batch.codes[0].clone(), batch.codes[5].clone()];
+    ///
+    /// let replacements = batch.regenerate_codes(&misprinted).unwrap();
+    ///
+    /// assert_eq!(replacements.len(), 2);
+    /// assert!(batch.regenerate_codes(&["unknown"]).is_err());
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn regenerate_codes<S: AsRef<str>>(
+        &mut self,
+        codes: &[S],
+    ) -> Result<Vec<(String, String)>, ReferralCodeError> {
+        let indices = codes
+            .iter()
+            .map(|code| {
+                self.codes
+                    .iter()
+                    .position(|c| c == code.as_ref())
+                    .ok_or(ReferralCodeError::NotInBatch)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.regenerate_subset(&indices)
+    }
+
+    /// Returns the SHA-256 digest of the batch contents.
+    ///
+    /// The digest covers the codes only, sorted and each terminated by a
+    /// newline, so it does not depend on generation order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Batch, Config};
+    ///
+    /// let config = Config::default().with_count(10);
+    /// let batch = Batch::generate_seeded("spring", &config, 42).unwrap();
+    ///
+    /// assert_eq!(batch.digest(), Batch::generate_seeded("retry", &config, 42).unwrap().digest());
+    /// # }
+    /// ```
+    #[cfg(feature = "crypto")]
+    pub fn digest(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut sorted: Vec<&String> = self.codes.iter().collect();
+        sorted.sort();
+
+        let mut hasher = Sha256::new();
+        for code in sorted {
+            hasher.update(code.as_bytes());
+            hasher.update(b"\n");
+        }
+
+        hasher.finalize().into()
+    }
+}
+
+/// Returns the positions of `ceil(fraction * count)` canaries among `count`
+/// codes and the canaries, in increasing order, drawn with the generator of
+/// the batch.
+#[cfg(feature = "rng")]
+pub(crate) fn place_canaries<R: backend::Rng + ?Sized>(
+    count: usize,
+    fraction: f64,
+    rng: &mut R,
+) -> Result<Vec<usize>, ReferralCodeError> {
+    if !(0.0..=1.0).contains(&fraction) {
+        return Err(ReferralCodeError::InvalidProbability);
+    }
+
+    let canaries = (count as f64 * fraction).ceil() as usize;
+    let mut positions = sample_indices(rng, count + canaries, canaries);
+    positions.sort_unstable();
+
+    Ok(positions)
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_generate_seeded_differs_by_seed() {
+    let config = Config::default().with_count(20);
+
+    let a = Batch::generate_seeded("a", &config, 1).unwrap();
+    let b = Batch::generate_seeded("b", &config, 2).unwrap();
+
+    assert_eq!(a.codes.len(), 20);
+    assert_ne!(a.codes, b.codes);
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_regenerate_subset_keeps_other_codes() {
+    let mut batch = Batch::generate("test", &Config::default().with_count(10)).unwrap();
+    let before = batch.codes.clone();
+
+    let replacements = batch.regenerate_subset(&[1, 4, 1]).unwrap();
+
+    assert_eq!(replacements.len(), 2);
+    for (i, (old, new)) in before.iter().zip(&batch.codes).enumerate() {
+        assert_eq!(old == new, i != 1 && i != 4);
+    }
+    assert_eq!(batch.codes.iter().collect::<HashSet<_>>().len(), 10);
+    assert!(batch.regenerate_subset(&[10]).is_err());
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_extend_respects_remaining_keyspace() {
+    let config = Config {
+        charset: crate::Charset::Numeric,
+        pattern: crate::Pattern::Length(2),
+        count: 60,
+        ..Config::default()
+    };
+    let mut batch = Batch::generate("test", &config).unwrap();
+
+    assert!(batch.extend(&config, 41).is_err());
+    assert_eq!(batch.extend(&config, 40).unwrap().len(), 40);
+    assert_eq!(batch.codes.iter().collect::<HashSet<_>>().len(), 100);
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_seeded_canaries_are_reproducible() {
+    let config = Config::default().with_count(40);
+
+    let a = Batch::generate_seeded_with_canaries("a", &config, 0.1, 7).unwrap();
+    let b = Batch::generate_seeded_with_canaries("a", &config, 0.1, 7).unwrap();
+    let c = Batch::generate_seeded_with_canaries("a", &config, 0.1, 8).unwrap();
+
+    assert_eq!(a.canaries.len(), 4);
+    assert_eq!(a.codes, b.codes);
+    assert_eq!(a.canaries, b.canaries);
+    assert_ne!((&a.codes, &a.canaries), (&c.codes, &c.canaries));
+    assert_eq!(a.provenance.unwrap().rng, RngPolicy::Seeded);
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_mixing_in_codes_clears_the_seeded_policy() {
+    let config = Config::default().with_count(10);
+    let policy = |batch: &Batch| batch.provenance.as_ref().unwrap().rng;
+
+    let mut extended = Batch::generate_seeded("a", &config, 1).unwrap();
+    extended.extend(&config, 0).unwrap();
+    assert_eq!(policy(&extended), RngPolicy::Seeded);
+    extended.extend(&config, 5).unwrap();
+    assert_eq!(policy(&extended), RngPolicy::OsSeeded);
+
+    let mut regenerated = Batch::generate_seeded("a", &config, 1).unwrap();
+    regenerated.regenerate_subset(&[]).unwrap();
+    assert_eq!(policy(&regenerated), RngPolicy::Seeded);
+    regenerated.regenerate_subset(&[3]).unwrap();
+    assert_eq!(policy(&regenerated), RngPolicy::OsSeeded);
+    let provenance = regenerated.provenance.as_ref().unwrap();
+    assert_eq!(provenance.source, Some(RngSource::Secure));
+
+    // A source that isn't secure is never hidden by a secure one.
+    let fast = Config {
+        rng: RngSource::Fast,
+        ..config.clone()
+    };
+    regenerated.extend(&fast, 1).unwrap();
+    regenerated.extend(&config, 1).unwrap();
+    let provenance = regenerated.provenance.as_ref().unwrap();
+    assert_eq!(provenance.source, Some(RngSource::Fast));
+    assert_eq!(
+        Batch::generate_seeded("a", &config, 1)
+            .unwrap()
+            .provenance
+            .unwrap()
+            .source,
+        None
+    );
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_spent_budget_leaves_the_batch_unchanged() {
+    let config = Config::default().with_count(10);
+    let mut batch = Batch::generate("test", &config).unwrap();
+    let before = batch.codes.clone();
+    batch.config.max_attempts = Some(0);
+    let limited = batch.config.clone();
+
+    assert!(matches!(
+        batch.extend(&limited, 5),
+        Err(ReferralCodeError::CollisionBudgetExceeded { attempts: 0, .. })
+    ));
+    assert!(matches!(
+        batch.regenerate_subset(&[0, 3]),
+        Err(ReferralCodeError::CollisionBudgetExceeded { .. })
+    ));
+    assert_eq!(batch.codes, before);
+}
@@ -0,0 +1,253 @@
+//! Throughput of the generation strategies, measured in-process.
+//!
+//! [`measure`] runs a strategy on the real code paths for a while and
+//! reports how many codes it produced per second, so capacity planning and
+//! regression checks can live in ordinary test suites. Allocations are
+//! counted too when the binary installs [`CountingAllocator`] as its global
+//! allocator.
+//!
+//! # Examples
+//!
+//! Comparing million-code batches of an ASCII charset, drawn by byte, and
+//! of a non-ASCII one, drawn from its decoded characters:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use referral_codes::{Charset, Config, bench::{self, Strategy}};
+//!
+//! let ascii = Config::default().with_count(1_000_000);
+//! let greek = Config {
+//!     charset: Charset::Custom("αβγδεζηθικλμνξοπρστυφχψω".to_string()),
+//!     ..ascii.clone()
+//! };
+//!
+//! for config in [&ascii, &greek] {
+//!     let measurement = bench::measure(config, Strategy::Generate, Duration::from_secs(5)).unwrap();
+//!     println!("{:?}: {:.0} codes/s", config.charset, measurement.codes_per_second());
+//! }
+//! ```
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::HashSet,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Config, ReferralCodeError, generate, generate_balanced, generate_one, generate_reserved,
+    generate_scoped,
+};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// A global allocator counting allocations, for [`measure`] to report.
+///
+/// It hands every request to the system allocator. Counts cover the whole
+/// process, so allocations of other threads running during a measurement,
+/// such as concurrent tests, are included.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CountingAllocator;
+
+// SAFETY: every call is forwarded unchanged to `System`, which upholds the
+// `GlobalAlloc` contract.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc`.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc_zeroed`.
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::realloc`.
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::dealloc`.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+fn count() {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    INSTALLED.store(true, Ordering::Relaxed);
+}
+
+/// A way of producing a batch of codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Strategy {
+    /// [`crate::generate`].
+    Generate,
+    /// [`crate::generate_one`], called once per code.
+    OneByOne,
+    /// [`crate::generate_balanced`].
+    Balanced,
+    /// [`crate::generate_scoped`] with the given number of threads.
+    Scoped(usize),
+    /// [`crate::generate_reserved`] against a `Mutex<HashSet<String>>`.
+    Reserved,
+    /// [`crate::generate_parallel`].
+    #[cfg(feature = "parallel")]
+    Parallel,
+}
+
+impl Strategy {
+    fn run(self, config: &Config) -> Result<usize, ReferralCodeError> {
+        Ok(match self {
+            Strategy::Generate => generate(config)?.len(),
+            Strategy::OneByOne => {
+                for _ in 0..config.count {
+                    generate_one(config)?;
+                }
+                config.count
+            }
+            Strategy::Balanced => generate_balanced(config)?.len(),
+            Strategy::Scoped(threads) => generate_scoped(config, threads)?.len(),
+            Strategy::Reserved => {
+                let store = Mutex::new(HashSet::with_capacity(config.count));
+                generate_reserved(config, &store)?.len()
+            }
+            #[cfg(feature = "parallel")]
+            Strategy::Parallel => crate::generate_parallel(config)?.len(),
+        })
+    }
+}
+
+/// What [`measure`] observed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measurement {
+    /// The strategy measured.
+    pub strategy: Strategy,
+    /// The number of batches of `config.count` codes produced.
+    pub batches: u64,
+    /// The number of codes produced.
+    pub codes: u64,
+    /// The time spent producing them.
+    pub elapsed: Duration,
+    /// The number of allocations, reallocations included, made meanwhile,
+    /// or `None` if [`CountingAllocator`] is not the global allocator.
+    pub allocations: Option<u64>,
+}
+
+impl Measurement {
+    /// Returns the number of codes produced per second.
+    pub fn codes_per_second(&self) -> f64 {
+        self.codes as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Returns the average number of allocations per code.
+    pub fn allocations_per_code(&self) -> Option<f64> {
+        Some(self.allocations? as f64 / self.codes as f64)
+    }
+}
+
+/// Produces batches of `config.count` codes with a strategy for at least
+/// `duration`, and reports the throughput.
+///
+/// At least one batch is produced, however short `duration` is. Pick a
+/// count close to production batch sizes: per-batch work, such as checking
+/// the configuration, is measured too.
+///
+/// # Errors
+///
+/// Returns the error of the strategy, such as `NonFeasibleConfig`.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use referral_codes::{
+///     Config,
+///     bench::{self, CountingAllocator, Strategy},
+/// };
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+///
+/// # fn main() {
+/// let config = Config::default().with_count(100);
+/// let measurement = bench::measure(&config, Strategy::Generate, Duration::from_millis(10)).unwrap();
+///
+/// assert!(measurement.codes >= 100);
+/// assert!(measurement.codes_per_second() > 0.0);
+/// assert!(measurement.allocations_per_code().unwrap() > 0.0);
+/// # }
+/// ```
+pub fn measure(
+    config: &Config,
+    strategy: Strategy,
+    duration: Duration,
+) -> Result<Measurement, ReferralCodeError> {
+    let mut batches = 0;
+    let mut codes = 0;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+
+    while batches == 0 || start.elapsed() < duration {
+        codes += strategy.run(config)? as u64;
+        batches += 1;
+    }
+
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    Ok(Measurement {
+        strategy,
+        batches,
+        codes,
+        elapsed,
+        allocations: INSTALLED.load(Ordering::Relaxed).then_some(allocations),
+    })
+}
+
+#[test]
+fn test_measure_runs_every_strategy() {
+    use crate::{Charset, Pattern};
+
+    let config = Config::default().with_count(50);
+    for strategy in [
+        Strategy::Generate,
+        Strategy::OneByOne,
+        Strategy::Balanced,
+        Strategy::Scoped(2),
+        Strategy::Reserved,
+        #[cfg(feature = "parallel")]
+        Strategy::Parallel,
+    ] {
+        let measurement = measure(&config, strategy, Duration::from_millis(5)).unwrap();
+        assert_eq!(measurement.strategy, strategy);
+        assert!(measurement.batches >= 1);
+        assert_eq!(measurement.codes, measurement.batches * 50);
+        assert!(measurement.elapsed >= Duration::from_millis(5));
+        assert!(measurement.codes_per_second() > 0.0);
+        // Tests run with the system allocator.
+        assert_eq!(measurement.allocations, None);
+    }
+
+    let measurement = measure(&config, Strategy::Generate, Duration::ZERO).unwrap();
+    assert_eq!(measurement.batches, 1);
+
+    let infeasible = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(1),
+        count: 11,
+        ..Config::default()
+    };
+    assert!(matches!(
+        measure(&infeasible, Strategy::Generate, Duration::ZERO),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+}
@@ -0,0 +1,335 @@
+//! Command-line interface to generate and validate referral codes.
+//!
+//! ```text
+//! referral-codes gen --pattern 'REF-####' --charset alphanumeric --count 1000 --format csv
+//! referral-codes gen --length 12 --charset crockford-base32 --group 4 --case upper
+//! referral-codes validate --pattern 'REF-####' REF-a1B2 REF-0000
+//! ```
+//!
+//! `validate` reads codes from standard input, one per line, when none are
+//! given, and exits with status 1 if any is invalid.
+
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    process::ExitCode,
+};
+
+use rand::{SeedableRng, rngs::StdRng};
+use referral_codes::{
+    Charset, Config, Generator, Grouping, LetterCase, Pattern, RngSource, Verifier, VerifyOutcome,
+};
+
+const USAGE: &str = "\
+Usage: referral-codes gen [OPTIONS]
+       referral-codes validate [OPTIONS] [CODE]...
+
+Options:
+  --pattern <PATTERN>  Pattern string, '#' marking random characters
+  --length <N>         Code of N random characters, instead of a pattern [default: 8]
+  --charset <CHARSET>  Charset name, such as numeric, or characters [default: alphanumeric]
+  --prefix <PREFIX>    Text before every code
+  --suffix <SUFFIX>    Text after every code
+  --count <N>          Number of codes to generate [default: 1]
+  --seed <SEED>        Seed, to generate the same codes on every run
+  --rng <SOURCE>       Random source: secure, os or fast [default: secure]
+  --max-attempts <N>   Draws to make before giving up [default: enough for --count]
+  --group <N>          Write a separator after every N characters
+  --separator <CHAR>   Separator between groups [default: -]
+  --case <CASE>        Letter case of written codes: upper or lower
+  --format <FORMAT>    Output format: lines, csv or json [default: lines]";
+
+/// How results are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Lines,
+    Csv,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Gen {
+        config: Config,
+        seed: Option<u64>,
+        format: Format,
+    },
+    Validate {
+        config: Config,
+        codes: Vec<String>,
+        format: Format,
+    },
+}
+
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let command = args.next().ok_or("missing command")?;
+
+    let mut config = Config {
+        count: 1,
+        ..Config::default()
+    };
+    let mut seed = None;
+    let mut group = None;
+    let mut separator = '-';
+    let mut format = Format::Lines;
+    let mut codes = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            codes.push(arg);
+            continue;
+        }
+
+        let value = args.next().ok_or(format!("missing value of {arg}"))?;
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("invalid {arg}: {value}"))
+        };
+        match arg.as_str() {
+            "--pattern" => config.pattern = Pattern::Pattern(value.clone()),
+            "--length" => config.pattern = Pattern::Length(number()? as usize),
+            "--charset" => {
+                config.charset = value
+                    .parse::<Charset>()
+                    .map_err(|e| format!("invalid --charset: {e}"))?
+            }
+            "--prefix" => config.prefix = Some(value),
+            "--suffix" => config.suffix = Some(value),
+            "--count" => config.count = number()? as usize,
+            "--seed" => seed = Some(number()?),
+            "--rng" => {
+                config.rng = match value.as_str() {
+                    "secure" => RngSource::Secure,
+                    "os" => RngSource::Os,
+                    "fast" => RngSource::Fast,
+                    _ => return Err(format!("invalid --rng: {value}")),
+                }
+            }
+            "--max-attempts" => config.max_attempts = Some(number()? as usize),
+            "--group" => group = Some(number()? as usize),
+            "--separator" => {
+                let mut chars = value.chars();
+                separator = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(format!("invalid --separator: {value}")),
+                }
+            }
+            "--case" => {
+                config.formatting.case = match value.as_str() {
+                    "upper" => Some(LetterCase::Upper),
+                    "lower" => Some(LetterCase::Lower),
+                    _ => return Err(format!("invalid --case: {value}")),
+                }
+            }
+            "--format" => {
+                format = match value.as_str() {
+                    "lines" => Format::Lines,
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    _ => return Err(format!("invalid --format: {value}")),
+                }
+            }
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+
+    config.formatting.group = group.map(|size| Grouping { size, separator });
+
+    match command.as_str() {
+        "gen" if codes.is_empty() => Ok(Command::Gen {
+            config,
+            seed,
+            format,
+        }),
+        "gen" => Err(format!("unexpected argument {}", codes[0])),
+        "validate" => Ok(Command::Validate {
+            config,
+            codes,
+            format,
+        }),
+        _ => Err(format!("unknown command {command}")),
+    }
+}
+
+/// Quotes a CSV field if it holds a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+fn outcome_name(outcome: VerifyOutcome) -> &'static str {
+    match outcome {
+        VerifyOutcome::Valid => "valid",
+        VerifyOutcome::Malformed => "malformed",
+        VerifyOutcome::BadCheck => "bad-check",
+        VerifyOutcome::Revoked => "revoked",
+        _ => "invalid",
+    }
+}
+
+/// Writes rows of `(code, outcome)`, the outcome left out when generating.
+fn write_rows(
+    out: &mut impl Write,
+    format: Format,
+    rows: &[(String, Option<&str>)],
+) -> io::Result<()> {
+    match format {
+        Format::Lines => {
+            for (code, outcome) in rows {
+                match outcome {
+                    Some(outcome) => writeln!(out, "{code}\t{outcome}")?,
+                    None => writeln!(out, "{code}")?,
+                }
+            }
+        }
+        Format::Csv => {
+            let validating = rows.iter().any(|(_, outcome)| outcome.is_some());
+            writeln!(out, "{}", if validating { "code,outcome" } else { "code" })?;
+            for (code, outcome) in rows {
+                match outcome {
+                    Some(outcome) => writeln!(out, "{},{outcome}", csv_field(code))?,
+                    None => writeln!(out, "{}", csv_field(code))?,
+                }
+            }
+        }
+        Format::Json => {
+            let json = match rows.iter().all(|(_, outcome)| outcome.is_none()) {
+                true => serde_json::json!(rows.iter().map(|(code, _)| code).collect::<Vec<_>>()),
+                false => serde_json::json!(
+                    rows.iter()
+                        .map(
+                            |(code, outcome)| serde_json::json!({"code": code, "outcome": outcome})
+                        )
+                        .collect::<Vec<_>>()
+                ),
+            };
+            writeln!(out, "{json}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn run(command: Command, out: &mut impl Write) -> Result<bool, String> {
+    match command {
+        Command::Gen {
+            config,
+            seed,
+            format,
+        } => {
+            let codes = match seed {
+                Some(seed) => {
+                    Generator::new(config.clone(), StdRng::seed_from_u64(seed)).generate()
+                }
+                None => referral_codes::generate(&config),
+            }
+            .map_err(|e| e.to_string())?;
+            let rows: Vec<_> = codes
+                .iter()
+                .map(|code| (config.format_code(code), None))
+                .collect();
+
+            write_rows(out, format, &rows).map_err(|e| e.to_string())?;
+            Ok(true)
+        }
+        Command::Validate {
+            config,
+            mut codes,
+            format,
+        } => {
+            if codes.is_empty() {
+                for line in io::stdin().lock().lines() {
+                    codes.push(line.map_err(|e| e.to_string())?);
+                }
+            }
+
+            let verifier = Verifier::new(config);
+            let rows: Vec<_> = codes
+                .into_iter()
+                .map(|code| {
+                    let outcome = outcome_name(verifier.check(&code));
+                    (code, Some(outcome))
+                })
+                .collect();
+
+            write_rows(out, format, &rows).map_err(|e| e.to_string())?;
+            Ok(rows.iter().all(|(_, outcome)| *outcome == Some("valid")))
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let result = parse(env::args().skip(1)).and_then(|command| run(command, &mut io::stdout()));
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[test]
+fn test_gen_and_validate() {
+    let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+    let output = |line: &str| {
+        let mut out = Vec::new();
+        let valid = run(parse(args(line)).unwrap(), &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), valid)
+    };
+
+    let (csv, _) = output("gen --pattern R-### --charset numeric --count 3 --seed 1 --format csv");
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "code");
+    assert!(
+        lines[1..]
+            .iter()
+            .all(|code| code.len() == 5 && code.starts_with("R-"))
+    );
+    assert_eq!(
+        output("gen --pattern R-### --charset numeric --count 3 --seed 1 --format csv").0,
+        csv
+    );
+
+    let (json, _) = output("gen --length 4 --charset AB --count 2 --format json");
+    assert_eq!(serde_json::from_str::<Vec<String>>(&json).unwrap().len(), 2);
+
+    let (lines, valid) = output("validate --pattern R-### --charset numeric R-123 R-12a");
+    assert_eq!(lines, "R-123\tvalid\nR-12a\tmalformed\n");
+    assert!(!valid);
+
+    let grouped = "--length 6 --charset abc --group 3 --separator . --case upper";
+    let (code, _) = output(&format!("gen {grouped}"));
+    let code = code.trim();
+    assert!(
+        code.len() == 7 && code.chars().nth(3) == Some('.'),
+        "{code}"
+    );
+    assert_eq!(code, code.to_uppercase());
+    let typed = code.to_lowercase().replace('.', "");
+    assert!(output(&format!("validate {grouped} {typed}")).1);
+
+    assert_eq!(
+        parse(args("gen --count many")),
+        Err("invalid --count: many".to_string())
+    );
+    assert!(parse(args("gen --format xml")).is_err());
+    assert!(matches!(
+        parse(args("gen --rng os")),
+        Ok(Command::Gen { config, .. }) if config.rng == RngSource::Os
+    ));
+    assert!(parse(args("gen --rng weak")).is_err());
+    assert!(matches!(
+        parse(args("gen --max-attempts 50")),
+        Ok(Command::Gen { config, .. }) if config.max_attempts == Some(50)
+    ));
+    assert!(parse(args("gen R-123")).is_err());
+    assert!(parse(args("list")).is_err());
+}
@@ -0,0 +1,193 @@
+use alloc::{vec, vec::Vec};
+
+use crate::Config;
+use crate::segment::{self, Slot, slots};
+
+/// Offensive words blocked by [`Config::with_profanity_filter`], one per line.
+#[cfg(feature = "profanity")]
+const PROFANITY: &str = include_str!("profanity.txt");
+
+impl Config {
+    /// Returns `true` if `code` contains one of the blocked words, in any
+    /// letter case.
+    ///
+    /// Empty words block nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Config;
+    ///
+    /// let config = Config {
+    ///     blocked_words: vec!["BAD".to_string()],
+    ///     ..Config::default()
+    /// };
+    ///
+    /// assert!(config.is_blocked("x7bAd2Qz"));
+    /// assert!(!config.is_blocked("x7bAx2Qz"));
+    /// ```
+    pub fn is_blocked(&self, code: &str) -> bool {
+        if self.blocked_words.is_empty() {
+            return false;
+        }
+
+        let code = code.to_lowercase();
+        self.blocked_words
+            .iter()
+            .any(|w| !w.is_empty() && code.contains(&w.to_lowercase()))
+    }
+
+    /// Returns this configuration with a built-in list of offensive English
+    /// words added to its blocked words.
+    ///
+    /// The list is matched like any blocked word, so it also catches words
+    /// spelled across literals. It is short and only covers words spelled
+    /// out in full: add the words of other languages and audiences to
+    /// [`Config::blocked_words`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Config;
+    ///
+    /// let config = Config::default().with_profanity_filter();
+    /// assert!(config.is_blocked("aSHiTb12"));
+    /// ```
+    #[cfg(feature = "profanity")]
+    pub fn with_profanity_filter(&self) -> Config {
+        let mut config = self.clone();
+        for word in PROFANITY.lines().filter(|w| !w.is_empty()) {
+            if !config
+                .blocked_words
+                .iter()
+                .any(|b| b.eq_ignore_ascii_case(word))
+            {
+                config.blocked_words.push(word.to_string());
+            }
+        }
+
+        config
+    }
+
+    /// Returns an upper bound on the number of possible codes containing a
+    /// blocked word.
+    ///
+    /// Codes are counted once for every place a word can appear in them, so
+    /// codes containing several words are counted several times. Derived
+    /// characters, such as check characters, are assumed to always match.
+    pub(crate) fn blocked_count(&self) -> u128 {
+        // The characters each position can hold, if drawn freely.
+        let derived = self.derived_positions();
+        let mut index = 0;
+        let slots: Vec<Option<Vec<char>>> = slots(self)
+            .into_iter()
+            .map(|slot| match slot {
+                Slot::Literal(c) => Some(vec![c]),
+                Slot::Random(class) => {
+                    index += 1;
+                    (!derived.contains(&(index - 1)))
+                        .then(|| segment::distinct_alphabet(self, class))
+                }
+                Slot::Check => None,
+            })
+            .collect();
+        let mut words: Vec<Vec<char>> = self
+            .blocked_words
+            .iter()
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase().chars().collect())
+            .collect();
+        words.sort();
+        words.dedup();
+
+        words
+            .iter()
+            .flat_map(|word| {
+                (0..(slots.len() + 1).saturating_sub(word.len()))
+                    .map(|start| count_containing_at(&slots, word, start))
+            })
+            .fold(0, u128::saturating_add)
+    }
+}
+
+/// Counts the codes whose positions can hold `slots` that contain `word`
+/// at `start`.
+///
+/// Positions holding `None` are derived and assumed to always match.
+fn count_containing_at(slots: &[Option<Vec<char>>], word: &[char], start: usize) -> u128 {
+    let mut count = 1u128;
+
+    for (i, chars) in slots.iter().enumerate() {
+        let Some(chars) = chars else {
+            continue;
+        };
+        let choices = match i.checked_sub(start).and_then(|offset| word.get(offset)) {
+            Some(c) => chars
+                .iter()
+                .filter(|a| a.to_lowercase().eq(c.to_lowercase()))
+                .count(),
+            None => chars.len(),
+        };
+        count = count.saturating_mul(choices as u128);
+    }
+
+    count
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_blocked_words_are_never_generated() {
+    use crate::{Charset, Pattern, ReferralCodeError, generate};
+
+    let config = Config {
+        charset: Charset::Custom("ABab".to_string()),
+        pattern: Pattern::Pattern("#-##".to_string()),
+        blocked_words: vec!["A-b".to_string(), String::new()],
+        ..Config::default()
+    };
+
+    // "a-b" spans a literal and matches 2 * 2 * 4 codes of 64.
+    assert_eq!(config.blocked_count(), 16);
+    assert_eq!(config.keyspace(), Some(48));
+
+    let codes = generate(&config.with_count(48)).unwrap();
+    assert!(
+        codes
+            .iter()
+            .all(|code| !code.to_lowercase().starts_with("a-b"))
+    );
+    assert!(matches!(
+        generate(&config.with_count(49)),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+
+    // Overlapping places are counted twice, so the bound is not exact.
+    let overlapping = Config {
+        blocked_words: vec!["B".to_string()],
+        ..config
+    };
+    assert_eq!(overlapping.blocked_count(), 3 * 2 * 16);
+    assert_eq!(overlapping.keyspace(), Some(0));
+}
+
+#[cfg(feature = "profanity")]
+#[test]
+fn test_profanity_filter_extends_blocked_words() {
+    let config = Config {
+        blocked_words: vec!["SHIT".to_string()],
+        ..Config::default()
+    }
+    .with_profanity_filter();
+
+    assert_eq!(
+        config
+            .blocked_words
+            .iter()
+            .filter(|w| w.eq_ignore_ascii_case("shit"))
+            .count(),
+        1
+    );
+    assert_eq!(config.blocked_words.len(), PROFANITY.lines().count());
+    assert!(config.is_blocked("x-FuCk-1"));
+    assert_eq!(config.with_profanity_filter(), config);
+}
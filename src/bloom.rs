@@ -0,0 +1,70 @@
+//! The Bloom filter of codes shared by verification bundles and streamed
+//! generation.
+
+/// The largest number of hashes per code of a filter.
+pub(crate) const MAX_HASHES: u32 = 32;
+
+/// A Bloom filter of normalized codes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BloomFilter {
+    pub(crate) hashes: u32,
+    pub(crate) len: u64,
+    pub(crate) bits: Vec<u8>,
+}
+
+impl BloomFilter {
+    /// Creates a filter sized for `n` codes at the given false positive rate.
+    pub(crate) fn new(n: usize, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let len = (-(n.max(1) as f64) * false_positive_rate.ln() / (ln2 * ln2)).ceil() as u64;
+        let len = len.max(8);
+        let hashes = ((len as f64 / n.max(1) as f64) * ln2).round() as u32;
+
+        BloomFilter {
+            hashes: hashes.clamp(1, MAX_HASHES),
+            len,
+            bits: vec![0; len.div_ceil(8) as usize],
+        }
+    }
+
+    /// Adds a code, returning `false` if the filter may already hold it.
+    pub(crate) fn insert(&mut self, key: &str) -> bool {
+        let mut added = false;
+        for bit in positions(key, self.hashes, self.len) {
+            let byte = &mut self.bits[(bit / 8) as usize];
+            added |= *byte & (1 << (bit % 8)) == 0;
+            *byte |= 1 << (bit % 8);
+        }
+
+        added
+    }
+
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        positions(key, self.hashes, self.len)
+            .all(|bit| self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+    }
+
+    /// Returns the chance that a code never added is taken for one that
+    /// was, given the bits set so far.
+    #[cfg(feature = "bloom")]
+    pub(crate) fn false_positive_rate(&self) -> f64 {
+        let set: u64 = self.bits.iter().map(|b| u64::from(b.count_ones())).sum();
+
+        (set as f64 / self.len as f64).powi(self.hashes as i32)
+    }
+}
+
+/// Returns the bits a code sets in a filter of `len` bits.
+fn positions(key: &str, hashes: u32, len: u64) -> impl Iterator<Item = u64> {
+    let h1 = fnv1a(key.bytes());
+    let h2 = fnv1a(key.bytes().chain([0])) | 1;
+
+    (0..u64::from(hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % len)
+}
+
+/// Returns the 64-bit FNV-1a hash of some bytes.
+fn fnv1a(bytes: impl Iterator<Item = u8>) -> u64 {
+    bytes.fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,200 @@
+#[cfg(test)]
+use std::collections::HashSet;
+
+use crate::{Config, ReferralCodeError, estimate::expected_attempts, generate};
+
+/// The draws left to generation before it gives up, see
+/// [`Config::max_attempts`].
+///
+/// Every code drawn counts, whether it is kept, a duplicate or turned down
+/// by the configuration, as one beginning with a reserved prefix is.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Attempts {
+    made: usize,
+    max: usize,
+}
+
+impl Attempts {
+    /// Returns the budget for drawing `count` new codes of `config`, of
+    /// which `occupied` codes are already taken.
+    pub(crate) fn new(config: &Config, occupied: usize, count: usize) -> Self {
+        let max = config.max_attempts.unwrap_or_else(|| {
+            let keyspaces = config.keyspace().zip(config.unconstrained().keyspace());
+            default_attempts(keyspaces, occupied, count)
+        });
+
+        Attempts { made: 0, max }
+    }
+
+    /// Returns the budget for drawing `count` codes of `config` that only
+    /// differ in `messages` ways, as signed codes differ by the characters
+    /// before their signature.
+    #[cfg(feature = "crypto")]
+    pub(crate) fn among(config: &Config, messages: Option<u128>, count: usize) -> Self {
+        let max = config.max_attempts.unwrap_or_else(|| {
+            default_attempts(messages.map(|messages| (messages, messages)), 0, count)
+        });
+
+        Attempts { made: 0, max }
+    }
+
+    /// Counts a draw, or returns `false` if the budget is spent.
+    pub(crate) fn take(&mut self) -> bool {
+        if self.made == self.max {
+            return false;
+        }
+
+        self.made += 1;
+        true
+    }
+
+    /// Returns a budget of the draws left, for a candidate drawn alongside
+    /// others, see [`Attempts::spend`].
+    #[cfg(feature = "parallel")]
+    pub(crate) fn left(&self) -> Attempts {
+        Attempts {
+            made: 0,
+            max: self.max - self.made,
+        }
+    }
+
+    /// Returns the number of draws made.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn made(&self) -> usize {
+        self.made
+    }
+
+    /// Counts `draws` draws at once, or returns `false` if they don't fit
+    /// in the budget, which is then spent.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn spend(&mut self, draws: usize) -> bool {
+        if draws > self.max - self.made {
+            self.made = self.max;
+            return false;
+        }
+
+        self.made += draws;
+        true
+    }
+
+    /// Returns the error of a spent budget, with the codes drawn so far.
+    pub(crate) fn exceeded(&self, generated: Vec<String>) -> ReferralCodeError {
+        ReferralCodeError::CollisionBudgetExceeded {
+            generated,
+            attempts: self.made,
+        }
+    }
+}
+
+/// Returns the number of draws generation makes by default for `count`
+/// new codes, of which `occupied` are already taken.
+///
+/// Ten draws per code, and at least a thousand, only run out when most
+/// draws are duplicates, which happens once codes fill nearly the whole
+/// keyspace. The budget then grows to four times the draws expected, so
+/// that only configurations whose keyspace is smaller than reported, or
+/// luck far out of the ordinary, run out of it.
+///
+/// `keyspaces` are the number of codes, if known, and of the codes drawn
+/// before those the configuration turns down are.
+fn default_attempts(keyspaces: Option<(u128, u128)>, occupied: usize, count: usize) -> usize {
+    let floor = count.saturating_mul(10).max(1_000);
+    let Some((keyspace, drawn)) = keyspaces else {
+        return floor;
+    };
+
+    // The draws of the new codes are those of all the codes but the
+    // occupied ones, and every draw the configuration turns down is drawn
+    // again.
+    let (keyspace, occupied) = (keyspace as f64, occupied as f64);
+    let expected = (expected_attempts(keyspace, occupied + count as f64)
+        - expected_attempts(keyspace, occupied))
+        * (drawn as f64 / keyspace);
+
+    floor.max((4.0 * expected) as usize)
+}
+
+/// Generates `config.count` unique codes, giving up after a number of draws.
+///
+/// This is [`crate::generate`] with `max_attempts` in place of
+/// [`Config::max_attempts`], or with the default budget if both are `None`.
+/// The codes drawn before the budget ran out are returned in the error.
+///
+/// # Errors
+///
+/// * `ReferralCodeError::CollisionBudgetExceeded` - If `max_attempts` draws
+///   did not give `config.count` unique codes
+/// * Any other error of [`crate::generate`]
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, Pattern, ReferralCodeError, generate_bounded};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Length(3),
+///     count: 1_000,
+///     ..Config::default()
+/// };
+///
+/// match generate_bounded(&config, Some(1_000)) {
+///     Err(ReferralCodeError::CollisionBudgetExceeded { generated, attempts }) => {
+///         assert_eq!(attempts, 1_000);
+///         assert!(generated.len() < 1_000);
+///     }
+///     other => panic!("unexpected {other:?}"),
+/// }
+/// assert_eq!(generate_bounded(&config.with_count(10), None).unwrap().len(), 10);
+/// ```
+pub fn generate_bounded(
+    config: &Config,
+    max_attempts: Option<usize>,
+) -> Result<Vec<String>, ReferralCodeError> {
+    generate(&Config {
+        max_attempts: max_attempts.or(config.max_attempts),
+        ..config.clone()
+    })
+}
+
+#[test]
+fn test_generate_bounded_gives_up_with_partial_codes() {
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(2),
+        count: 100,
+        ..Config::default()
+    };
+
+    let Err(ReferralCodeError::CollisionBudgetExceeded {
+        generated,
+        attempts,
+    }) = generate_bounded(&config, Some(50))
+    else {
+        panic!("expected the budget to run out");
+    };
+    assert_eq!(attempts, 50);
+    assert!(!generated.is_empty() && generated.len() <= 50);
+    assert_eq!(
+        generated.iter().collect::<HashSet<_>>().len(),
+        generated.len()
+    );
+
+    // The default budget easily covers half of the keyspace.
+    assert_eq!(
+        generate_bounded(&config.with_count(50), None)
+            .unwrap()
+            .len(),
+        50
+    );
+    assert!(matches!(
+        generate_bounded(&config.with_count(0), Some(0)),
+        Ok(codes) if codes.is_empty()
+    ));
+    assert!(matches!(
+        generate_bounded(&config.with_count(101), None),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+}
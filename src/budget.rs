@@ -0,0 +1,121 @@
+use crate::{
+    Config, Pattern, Placement, ReferralCodeError, ReferralLink, check_config, link::percent_encode,
+};
+
+impl Config {
+    /// Returns this configuration with the longest codes whose links fit in
+    /// `max_len` characters.
+    ///
+    /// Links are built as [`ReferralLink::build`] does, so percent-encoding
+    /// is accounted for: every random character is counted at the length of
+    /// the longest encoded character of the charset, in either case with
+    /// `random_case`. The prefix, suffix and checksum characters take their
+    /// share, and the pattern is replaced by a [`Pattern::Length`] of the
+    /// remaining random positions. To fit a link in an SMS, subtract the
+    /// rest of the message from `max_len`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The URL codes are added to, such as a short domain
+    /// * `placement` - Where the code goes in the URL
+    /// * `max_len` - The maximum length of the link
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::InvalidUrl` if the base URL can't carry
+    /// codes, `ReferralCodeError::NoRandomPositions` if not even one random
+    /// position fits, and otherwise the same errors as [`crate::generate`]
+    /// for the fitted pattern, such as `NonFeasibleConfig` if it can't make
+    /// `count` codes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, Pattern, Placement};
+    ///
+    /// // 30 characters leave 14 after "https://ex.co/r/", 12 after the prefix.
+    /// let config = Config::default()
+    ///     .with_prefix("R-")
+    ///     .fit_link("https://ex.co/r", Placement::PathSegment, 30)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(config.pattern, Pattern::Length(12));
+    /// ```
+    pub fn fit_link(
+        &self,
+        base_url: &str,
+        placement: Placement,
+        max_len: usize,
+    ) -> Result<Config, ReferralCodeError> {
+        let link = ReferralLink::build(base_url, "", placement)?;
+        let encoded_len = |s: &str| percent_encode(s).len();
+
+        let fixed = link.as_str().len()
+            + self.prefix.as_deref().map_or(0, encoded_len)
+            + self.suffix.as_deref().map_or(0, encoded_len)
+            + self.checksum.as_ref().map_or(0, |checksum| {
+                let widest = checksum
+                    .output_alphabet()
+                    .chars()
+                    .map(|c| encoded_len(c.encode_utf8(&mut [0; 4])))
+                    .max();
+                checksum.len() * widest.unwrap_or(0)
+            });
+        let per_char = self
+            .charset
+            .as_str()
+            .chars()
+            .flat_map(|c| match self.random_case {
+                Some(_) => c.to_lowercase().chain(c.to_uppercase()).collect(),
+                None => vec![c],
+            })
+            .map(|c| encoded_len(c.encode_utf8(&mut [0; 4])))
+            .max()
+            .unwrap_or(1);
+
+        let length = max_len.saturating_sub(fixed) / per_char;
+        if length == 0 {
+            return Err(ReferralCodeError::NoRandomPositions);
+        }
+
+        let config = self.with_pattern(Pattern::Length(length));
+        check_config(&config)?;
+
+        Ok(config)
+    }
+}
+
+#[test]
+fn test_fit_link_accounts_for_encoding() {
+    use crate::{Charset, generate};
+
+    let config = Config {
+        charset: Charset::Custom("AB+".to_string()),
+        suffix: Some("!".to_string()),
+        count: 4,
+        ..Config::default()
+    };
+    let query = || Placement::Query("ref".to_string());
+
+    // "https://ex.co/?ref=" takes 19 characters, the suffix 3 as "%21", and
+    // every random character up to 3, as "%2B".
+    let fitted = config.fit_link("https://ex.co/", query(), 40).unwrap();
+    assert_eq!(fitted.pattern, Pattern::Length(6));
+    for code in generate(&fitted).unwrap() {
+        let link = ReferralLink::build("https://ex.co/", &code, query()).unwrap();
+        assert!(link.as_str().len() <= 40, "{link}");
+    }
+
+    assert!(matches!(
+        config.fit_link("https://ex.co/", query(), 24),
+        Err(ReferralCodeError::NoRandomPositions)
+    ));
+    assert!(matches!(
+        config.fit_link("https://ex.co/", query(), 25),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+    assert!(matches!(
+        config.fit_link("not a url", query(), 100),
+        Err(ReferralCodeError::InvalidUrl)
+    ));
+}
@@ -0,0 +1,312 @@
+use alloc::string::String;
+
+#[cfg(feature = "std")]
+use crate::Expiry;
+use crate::{
+    Charset, Checksum, ChecksumCoverage, Config, EmbeddedFields, FormatTag, Formatting, OcrCheck,
+    Pattern, ReferralCodeError, Set,
+};
+
+/// Builds a [`Config`] field by field, checking it as a whole at the end.
+///
+/// Fields left unset keep their [`Config::default`] value, so code using
+/// the builder keeps compiling when `Config` gains fields.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, Pattern, ReferralCodeError};
+///
+/// let config = Config::builder()
+///     .pattern(Pattern::Length(10))
+///     .charset(Charset::Numeric)
+///     .count(50)
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.count, 50);
+///
+/// let empty = Config::builder().charset(Charset::Custom(String::new())).build();
+/// assert!(matches!(empty, Err(ReferralCodeError::EmptyCharset)));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl Config {
+    /// Returns a builder starting from the default configuration.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+impl ConfigBuilder {
+    /// Sets [`Config::pattern`].
+    pub fn pattern(mut self, pattern: Pattern) -> Self {
+        self.config.pattern = pattern;
+        self
+    }
+
+    /// Sets [`Config::count`].
+    pub fn count(mut self, count: usize) -> Self {
+        self.config.count = count;
+        self
+    }
+
+    /// Sets [`Config::charset`].
+    pub fn charset(mut self, charset: Charset) -> Self {
+        self.config.charset = charset;
+        self
+    }
+
+    /// Sets [`Config::prefix`].
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.config.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets [`Config::suffix`].
+    pub fn suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.config.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Sets [`Config::ocr_check`].
+    pub fn ocr_check(mut self, ocr_check: OcrCheck) -> Self {
+        self.config.ocr_check = Some(ocr_check);
+        self
+    }
+
+    /// Sets [`Config::format_tag`].
+    pub fn format_tag(mut self, format_tag: FormatTag) -> Self {
+        self.config.format_tag = Some(format_tag);
+        self
+    }
+
+    /// Sets [`Config::expiry`].
+    #[cfg(feature = "std")]
+    pub fn expiry(mut self, expiry: Expiry) -> Self {
+        self.config.expiry = Some(expiry);
+        self
+    }
+
+    /// Sets [`Config::fields`].
+    pub fn fields(mut self, fields: EmbeddedFields) -> Self {
+        self.config.fields = Some(fields);
+        self
+    }
+
+    /// Sets [`Config::checksum`].
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.config.checksum = Some(checksum);
+        self
+    }
+
+    /// Sets [`Config::checksum_coverage`].
+    pub fn checksum_coverage(mut self, coverage: ChecksumCoverage) -> Self {
+        self.config.checksum_coverage = coverage;
+        self
+    }
+
+    /// Sets [`Config::random_case`].
+    pub fn random_case(mut self, p: f64) -> Self {
+        self.config.random_case = Some(p);
+        self
+    }
+
+    /// Adds a prefix to [`Config::reserved_prefixes`].
+    pub fn reserved_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.config.reserved_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Sets [`Config::no_repeats`].
+    pub fn no_repeats(mut self, no_repeats: bool) -> Self {
+        self.config.no_repeats = no_repeats;
+        self
+    }
+
+    /// Adds a word to [`Config::blocked_words`].
+    pub fn blocked_word(mut self, word: impl Into<String>) -> Self {
+        self.config.blocked_words.push(word.into());
+        self
+    }
+
+    /// Sets [`Config::formatting`].
+    pub fn formatting(mut self, formatting: Formatting) -> Self {
+        self.config.formatting = formatting;
+        self
+    }
+
+    /// Checks the configuration and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Config::validate`].
+    pub fn build(self) -> Result<Config, ReferralCodeError> {
+        self.config.validate()?;
+
+        Ok(self.config)
+    }
+}
+
+impl Config {
+    /// Checks the configuration as a whole, reporting what
+    /// [`crate::generate`] would fail with before any code is drawn.
+    ///
+    /// Custom charsets may not repeat characters; see [`Charset::weighted`]
+    /// to draw some characters more often. Use [`Config::check_untrusted`]
+    /// for configurations from untrusted input, which also bounds their
+    /// size.
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::EmptyPattern` - If the pattern has no character
+    /// * `ReferralCodeError::EmptyCharset` - If a custom charset, including
+    ///   that of a segment, is empty
+    /// * `ReferralCodeError::DuplicateCharsetChars` - If a custom charset,
+    ///   including that of a segment, lists a character twice
+    /// * `ReferralCodeError::NonFeasibleConfig` or
+    ///   `ReferralCodeError::NoRandomPositions` - If the format can't hold
+    ///   `count` unique codes, which is only checked with code generation
+    /// * Any other error of [`crate::generate`] for a configuration whose
+    ///   parts don't fit together, such as `InvalidCheckPositions`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern, ReferralCodeError};
+    ///
+    /// assert!(Config::default().validate().is_ok());
+    /// assert!(matches!(
+    ///     Config::default().with_pattern(Pattern::Pattern(String::new())).validate(),
+    ///     Err(ReferralCodeError::EmptyPattern)
+    /// ));
+    /// let repeated = Config {
+    ///     charset: Charset::Custom("A7A".to_string()),
+    ///     ..Config::default()
+    /// };
+    /// assert!(matches!(
+    ///     repeated.validate(),
+    ///     Err(ReferralCodeError::DuplicateCharsetChars('A'))
+    /// ));
+    /// ```
+    pub fn validate(&self) -> Result<(), ReferralCodeError> {
+        let empty_pattern = match &self.pattern {
+            Pattern::Length(n) => *n == 0,
+            Pattern::Pattern(s) | Pattern::Template(s) | Pattern::WithPlaceholder(s, _) => {
+                s.is_empty()
+            }
+            Pattern::Segments(segments) => segments.is_empty(),
+        };
+        if empty_pattern {
+            return Err(ReferralCodeError::EmptyPattern);
+        }
+        let pools = match &self.pattern {
+            Pattern::Segments(segments) => &segments[..],
+            _ => &[],
+        };
+        if self.charset.as_str().is_empty()
+            || pools.iter().any(|(_, charset)| charset.as_str().is_empty())
+        {
+            return Err(ReferralCodeError::EmptyCharset);
+        }
+        for charset in core::iter::once(&self.charset).chain(pools.iter().map(|(_, c)| c)) {
+            let mut seen = Set::new();
+            if let Some(c) = charset.as_str().chars().find(|c| !seen.insert(*c)) {
+                return Err(ReferralCodeError::DuplicateCharsetChars(c));
+            }
+        }
+
+        #[cfg(feature = "rng")]
+        crate::check_config(self)?;
+        #[cfg(not(feature = "rng"))]
+        crate::check_format(self)?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_builder_checks_the_whole_config() {
+    let config = Config::builder()
+        .pattern(Pattern::Pattern("R-####".to_string()))
+        .charset(Charset::Numeric)
+        .prefix("X")
+        .checksum(Checksum::Iso7064Mod97_10)
+        .reserved_prefix("XR-0")
+        .reserved_prefix("XR-1")
+        .build()
+        .unwrap();
+    assert_eq!(
+        config,
+        Config {
+            pattern: Pattern::Pattern("R-####".to_string()),
+            charset: Charset::Numeric,
+            prefix: Some("X".to_string()),
+            checksum: Some(Checksum::Iso7064Mod97_10),
+            reserved_prefixes: vec!["XR-0".to_string(), "XR-1".to_string()],
+            ..Config::default()
+        }
+    );
+
+    assert!(matches!(
+        Config::builder().pattern(Pattern::Length(0)).build(),
+        Err(ReferralCodeError::EmptyPattern)
+    ));
+    assert!(matches!(
+        Config::builder()
+            .pattern(Pattern::Pattern(String::new()))
+            .build(),
+        Err(ReferralCodeError::EmptyPattern)
+    ));
+    assert!(matches!(
+        Config::builder().ocr_check(OcrCheck::new(0, 8)).build(),
+        Err(ReferralCodeError::InvalidCheckPositions)
+    ));
+    assert!(matches!(
+        Config::builder().random_case(2.0).build(),
+        Err(ReferralCodeError::InvalidProbability)
+    ));
+
+    #[cfg(feature = "rng")]
+    assert!(matches!(
+        Config::builder()
+            .charset(Charset::Numeric)
+            .pattern(Pattern::Length(2))
+            .count(101)
+            .build(),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+    #[cfg(feature = "rng")]
+    assert!(matches!(
+        Config::default()
+            .with_pattern(Pattern::Pattern("ABC".to_string()))
+            .with_count(2)
+            .validate(),
+        Err(ReferralCodeError::NoRandomPositions)
+    ));
+
+    let repeated = Config {
+        pattern: Pattern::Segments(vec![(
+            Pattern::Length(2),
+            Charset::Custom("XYX".to_string()),
+        )]),
+        ..Config::default()
+    };
+    assert!(matches!(
+        repeated.validate(),
+        Err(ReferralCodeError::DuplicateCharsetChars('X'))
+    ));
+    let weighted = Config {
+        charset: Charset::weighted(&[('X', 2), ('Y', 1)]).unwrap(),
+        ..Config::default()
+    };
+    assert!(weighted.validate().is_ok());
+
+    // Keyspaces too large to count don't overflow.
+    let long = Config::default().with_pattern(Pattern::Length(500));
+    assert!(long.validate().is_ok());
+    #[cfg(feature = "rng")]
+    assert_eq!(crate::generate(&long.with_count(2)).unwrap().len(), 2);
+}
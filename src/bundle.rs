@@ -0,0 +1,285 @@
+use crate::{
+    Config, ReferralCodeError, SpecVersion,
+    bloom::{BloomFilter, MAX_HASHES},
+    dedup_key, registry, verify_as,
+};
+
+/// The first bytes of every serialized bundle, followed by the format version.
+const MAGIC: &[u8; 4] = b"RCVB";
+
+/// The bundle format version written by [`VerificationBundle::to_bytes`].
+const VERSION: u8 = 1;
+
+/// Everything needed to check codes without access to the issuing service.
+///
+/// A bundle holds the format of the codes, the [`SpecVersion`] whose rules
+/// normalize and verify them, which includes the check character and
+/// checksum parameters, and optionally a Bloom filter of the issued codes.
+/// Ship it to edge workers to turn away malformed codes, codes with a
+/// wrong check character and, with the filter, most codes that were never
+/// issued, before traffic reaches the redemption service. A bundle never
+/// rejects an issued code, but only the service can tell that a code is
+/// issued and still redeemable.
+///
+/// The count of the configuration is not part of the bundle.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, Pattern, VerificationBundle};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Pattern("REF-####".to_string()),
+///     ..Config::default()
+/// };
+/// let bundle = VerificationBundle::new(&config)
+///     .with_issued_codes(["REF-1234", "REF-5678"], 0.001)
+///     .unwrap();
+///
+/// let shipped = VerificationBundle::from_bytes(&bundle.to_bytes()).unwrap();
+///
+/// assert!(shipped.verify("REF-1234"));
+/// assert!(!shipped.verify("REF-12A4"));
+/// assert!(!shipped.verify("REF-0000"));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerificationBundle {
+    config: Config,
+    version: SpecVersion,
+    filter: Option<BloomFilter>,
+}
+
+impl VerificationBundle {
+    /// Creates a bundle verifying codes of `config` by the rules of
+    /// [`SpecVersion::LATEST`], without a filter of issued codes.
+    pub fn new(config: &Config) -> Self {
+        VerificationBundle {
+            config: config.with_count(0),
+            version: SpecVersion::LATEST,
+            filter: None,
+        }
+    }
+
+    /// Returns this bundle verifying codes by the rules of `version` instead.
+    pub fn with_spec_version(self, version: SpecVersion) -> Self {
+        VerificationBundle { version, ..self }
+    }
+
+    /// Returns this bundle with a Bloom filter of the issued codes.
+    ///
+    /// The filter takes about 1.44 × log2(1 / `false_positive_rate`) bits
+    /// per code: around 10 bits for a rate of 1%, 20 bits for 0.1%.
+    ///
+    /// # Arguments
+    ///
+    /// * `codes` - Every issued code
+    /// * `false_positive_rate` - The share of valid but never issued codes
+    ///   the filter lets through
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::InvalidProbability` if
+    /// `false_positive_rate` is not strictly between 0 and 1.
+    pub fn with_issued_codes<I, S>(
+        self,
+        codes: I,
+        false_positive_rate: f64,
+    ) -> Result<Self, ReferralCodeError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(ReferralCodeError::InvalidProbability);
+        }
+
+        let keys: Vec<String> = codes
+            .into_iter()
+            .map(|code| dedup_key(&self.config, code.as_ref()).into_owned())
+            .collect();
+        let mut filter = BloomFilter::new(keys.len(), false_positive_rate);
+        for key in &keys {
+            filter.insert(key);
+        }
+
+        Ok(VerificationBundle {
+            filter: Some(filter),
+            ..self
+        })
+    }
+
+    /// Returns the configuration codes are verified against.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the version of the rules codes are verified by.
+    pub fn spec_version(&self) -> SpecVersion {
+        self.version
+    }
+
+    /// Returns `true` if the code is valid and, with a filter, may have been
+    /// issued.
+    pub fn verify(&self, code: &str) -> bool {
+        verify_as(code, &self.config, self.version)
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.contains(&dedup_key(&self.config, code)))
+    }
+
+    /// Serializes the bundle.
+    ///
+    /// The layout is the bytes `RCVB`, the format version and the spec
+    /// version as one byte each, the configuration as written in registry
+    /// files, preceded by its length as a little-endian `u32`, and a byte
+    /// telling whether a filter follows. A filter is its number of hashes as
+    /// one byte, its number of bits as a little-endian `u64` and the bits,
+    /// least significant first. The filter hashes codes, lowercased with
+    /// `random_case`, with 64-bit FNV-1a, and reads position `i` as
+    /// `(h1 + i × h2) mod bits`, where `h2` is the hash of the code followed
+    /// by a zero byte, made odd.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let config = registry::encode_config(&self.config);
+        let mut bytes = Vec::with_capacity(
+            16 + config.len() + self.filter.as_ref().map_or(0, |f| f.bits.len()),
+        );
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(match self.version {
+            SpecVersion::V1 => 1,
+        });
+        bytes.extend_from_slice(&(config.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(config.as_bytes());
+
+        match &self.filter {
+            Some(filter) => {
+                bytes.push(1);
+                bytes.push(filter.hashes as u8);
+                bytes.extend_from_slice(&filter.len.to_le_bytes());
+                bytes.extend_from_slice(&filter.bits);
+            }
+            None => bytes.push(0),
+        }
+
+        bytes
+    }
+
+    /// Reads a bundle written by [`VerificationBundle::to_bytes`].
+    ///
+    /// # Returns
+    ///
+    /// * `Some(bundle)` - The bundle
+    /// * `None` - If the bytes are malformed or written in a version this
+    ///   crate does not know
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes);
+
+        if reader.take(4)? != MAGIC || reader.byte()? != VERSION {
+            return None;
+        }
+        let version = match reader.byte()? {
+            1 => SpecVersion::V1,
+            _ => return None,
+        };
+        let len = u32::from_le_bytes(reader.take(4)?.try_into().ok()?) as usize;
+        let config = std::str::from_utf8(reader.take(len)?).ok()?;
+        let config = registry::decode_config(config, registry::VERSION)?;
+
+        let filter = match reader.byte()? {
+            0 => None,
+            1 => {
+                let hashes = u32::from(reader.byte()?);
+                let len = u64::from_le_bytes(reader.take(8)?.try_into().ok()?);
+                if !(1..=MAX_HASHES).contains(&hashes) || len == 0 {
+                    return None;
+                }
+                let bits = reader
+                    .take(usize::try_from(len.div_ceil(8)).ok()?)?
+                    .to_vec();
+                Some(BloomFilter { hashes, len, bits })
+            }
+            _ => return None,
+        };
+
+        reader.0.is_empty().then_some(VerificationBundle {
+            config,
+            version,
+            filter,
+        })
+    }
+}
+
+/// Reads a serialized bundle from the front.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+}
+
+#[test]
+fn test_bundle_round_trip_and_false_positive_rate() {
+    use crate::{Charset, Checksum, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(5),
+        checksum: Some(Checksum::Iso7064Mod97_10),
+        count: 1_000,
+        ..Config::default()
+    };
+    let all: Vec<String> = (0..100_000)
+        .map(|n| {
+            let body = format!("{n:05}");
+            let check = Checksum::Iso7064Mod97_10.compute(&body).unwrap();
+            format!("{body}{check}")
+        })
+        .collect();
+    let (issued, others) = all.split_at(1_000);
+
+    let bundle = VerificationBundle::new(&config)
+        .with_issued_codes(issued, 0.01)
+        .unwrap();
+    let shipped = VerificationBundle::from_bytes(&bundle.to_bytes()).unwrap();
+    assert_eq!(shipped, bundle);
+    assert_eq!(shipped.config().count, 0);
+    // About 1.2 bytes per code for a rate of 1%.
+    let unfiltered = VerificationBundle::new(&config);
+    assert!(bundle.to_bytes().len() - unfiltered.to_bytes().len() < 1_210);
+
+    assert!(issued.iter().all(|code| shipped.verify(code)));
+    let passed = others.iter().filter(|code| shipped.verify(code)).count();
+    assert!(passed < others.len() / 50, "{passed}");
+    assert!(!shipped.verify("1234500"));
+
+    assert!(others.iter().all(|code| unfiltered.verify(code)));
+    let bytes = unfiltered.to_bytes();
+    assert_eq!(VerificationBundle::from_bytes(&bytes), Some(unfiltered));
+    for truncated in 0..bytes.len() {
+        assert_eq!(VerificationBundle::from_bytes(&bytes[..truncated]), None);
+    }
+    assert_eq!(
+        VerificationBundle::from_bytes(&[bytes.as_slice(), &[0]].concat()),
+        None
+    );
+
+    for rate in [0.0, 1.0, f64::NAN] {
+        assert!(matches!(
+            VerificationBundle::new(&config).with_issued_codes(issued, rate),
+            Err(ReferralCodeError::InvalidProbability)
+        ));
+    }
+}
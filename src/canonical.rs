@@ -0,0 +1,663 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{fmt, str::FromStr};
+
+use crate::{CharClass, Charset, ChecksumCoverage, Config, Pattern, ReferralCodeError, Segment};
+
+/// The version written by [`Charset::to_canonical`] and [`Pattern::to_canonical`].
+const VERSION: &str = "v1";
+
+/// Splits `kind:version:rest` into its version and the rest.
+fn split<'a>(s: &'a str, kind: &str) -> Result<(&'a str, &'a str), ReferralCodeError> {
+    s.strip_prefix(kind)
+        .and_then(|s| s.strip_prefix(':'))
+        .and_then(|s| s.split_once(':'))
+        .ok_or(ReferralCodeError::InvalidSerialization)
+}
+
+/// Splits `length:rest` into the first `length` bytes of the rest and what
+/// follows them.
+fn split_sized(s: &str) -> Result<(&str, &str), ReferralCodeError> {
+    let (len, rest) = s
+        .split_once(':')
+        .ok_or(ReferralCodeError::InvalidSerialization)?;
+    let len: usize = len
+        .parse()
+        .map_err(|_| ReferralCodeError::InvalidSerialization)?;
+
+    match (rest.get(..len), rest.get(len..)) {
+        (Some(part), Some(rest)) => Ok((part, rest)),
+        _ => Err(ReferralCodeError::InvalidSerialization),
+    }
+}
+
+/// Writes the weights of a weighted charset as `c:w` pairs, each character
+/// and its weight, separated by commas, such as `A:1,7:3`.
+fn write_weights(weights: &[(char, u32)]) -> String {
+    let pairs: Vec<String> = weights.iter().map(|(c, w)| format!("{c}:{w}")).collect();
+
+    pairs.join(",")
+}
+
+/// Parses weights written by [`write_weights`].
+///
+/// Every pair starts with its character, so `:` and `,` can be weighted
+/// too.
+fn parse_weights(mut s: &str) -> Option<Vec<(char, u32)>> {
+    let mut weights = Vec::new();
+
+    while !s.is_empty() {
+        let mut chars = s.chars();
+        let c = chars.next()?;
+        let pair = chars.as_str().strip_prefix(':')?;
+        let (weight, rest) = match pair.split_once(',') {
+            Some((_, "")) => return None,
+            Some((weight, rest)) => (weight, rest),
+            None => (pair, ""),
+        };
+        weights.push((c, weight.parse().ok()?));
+        s = rest;
+    }
+
+    Some(weights)
+}
+
+/// The charsets known by name, see [`Charset::name`].
+pub(crate) const NAMED: [Charset; 5] = [
+    Charset::Numeric,
+    Charset::Alphabetic,
+    Charset::Alphanumeric,
+    Charset::AlphanumericUnambiguous,
+    Charset::CrockfordBase32,
+];
+
+impl Charset {
+    /// Returns the name of this charset, as written in canonical forms, or
+    /// `None` for a custom charset.
+    pub(crate) fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::Numeric => Some("numeric"),
+            Self::Alphabetic => Some("alphabetic"),
+            Self::Alphanumeric => Some("alphanumeric"),
+            Self::AlphanumericUnambiguous => Some("alphanumeric-unambiguous"),
+            Self::CrockfordBase32 => Some("crockford-base32"),
+            Self::Custom(_) | Self::Weighted(_) => None,
+        }
+    }
+
+    /// Returns the named charset called `name`.
+    pub(crate) fn from_name(name: &str) -> Option<Charset> {
+        NAMED
+            .into_iter()
+            .find(|charset| charset.name() == Some(name))
+    }
+
+    /// Returns the canonical, versioned text form of this charset.
+    ///
+    /// The form is `charset:v1:` followed by `numeric`, `alphabetic`,
+    /// `alphanumeric`, `alphanumeric-unambiguous`, `crockford-base32` or
+    /// `custom:` and the custom characters, verbatim, or `weighted:` and
+    /// every character with its weight, as `c:w` pairs separated by commas.
+    /// It is
+    /// the form used in manifests, and [`Charset::from_canonical`] keeps
+    /// parsing every version it was ever written in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// assert_eq!(Charset::Numeric.to_canonical(), "charset:v1:numeric");
+    /// assert_eq!(Charset::Custom("AB:C".to_string()).to_canonical(), "charset:v1:custom:AB:C");
+    /// assert_eq!(
+    ///     Charset::Weighted(vec![('A', 1), ('7', 3)]).to_canonical(),
+    ///     "charset:v1:weighted:A:1,7:3"
+    /// );
+    /// ```
+    pub fn to_canonical(&self) -> String {
+        match (self.name(), self) {
+            (Some(name), _) => format!("charset:{VERSION}:{name}"),
+            (None, Self::Weighted(weights)) => {
+                format!("charset:{VERSION}:weighted:{}", write_weights(weights))
+            }
+            (None, charset) => format!("charset:{VERSION}:custom:{}", charset.as_str()),
+        }
+    }
+
+    /// Parses a charset written by [`Charset::to_canonical`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(charset)` - The parsed charset
+    /// * `Err(ReferralCodeError::InvalidSerialization)` - If the text is not
+    ///   a canonical charset, or was written in an unknown version
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// let text = Charset::Custom("XYZ".to_string()).to_canonical();
+    ///
+    /// assert_eq!(Charset::from_canonical(&text).unwrap().as_str(), "XYZ");
+    /// assert!(Charset::from_canonical("charset:v0:numeric").is_err());
+    /// ```
+    pub fn from_canonical(s: &str) -> Result<Self, ReferralCodeError> {
+        match split(s, "charset")? {
+            ("v1", body) => Self::from_name(body)
+                .or_else(|| {
+                    body.strip_prefix("custom:")
+                        .map(|s| Self::Custom(s.to_string()))
+                })
+                .or_else(|| {
+                    body.strip_prefix("weighted:")
+                        .and_then(parse_weights)
+                        .map(Self::Weighted)
+                })
+                .ok_or(ReferralCodeError::InvalidSerialization),
+            _ => Err(ReferralCodeError::InvalidSerialization),
+        }
+    }
+}
+
+impl Pattern {
+    /// Returns the canonical, versioned text form of this pattern.
+    ///
+    /// The form is `pattern:v1:` followed by `length:` and the length,
+    /// `pattern:` and the pattern string, `template:` and the template
+    /// string, or `placeholder:`, the placeholder, `:` and the pattern
+    /// string. Strings are written verbatim. Segments are written as
+    /// `segments:` followed by the canonical pattern and charset of every
+    /// segment, each after its length in bytes and `:`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Pattern;
+    ///
+    /// assert_eq!(Pattern::Length(8).to_canonical(), "pattern:v1:length:8");
+    /// assert_eq!(Pattern::parse("AA-99").unwrap().to_canonical(), "pattern:v1:template:AA-99");
+    /// ```
+    pub fn to_canonical(&self) -> String {
+        match self {
+            Self::Length(n) => format!("pattern:{VERSION}:length:{n}"),
+            Self::Pattern(s) => format!("pattern:{VERSION}:pattern:{s}"),
+            Self::Template(s) => format!("pattern:{VERSION}:template:{s}"),
+            Self::WithPlaceholder(s, p) => format!("pattern:{VERSION}:placeholder:{p}:{s}"),
+            Self::Segments(segments) => {
+                let mut s = format!("pattern:{VERSION}:segments:");
+                for (pattern, charset) in segments {
+                    for part in [pattern.to_canonical(), charset.to_canonical()] {
+                        s.push_str(&format!("{}:{part}", part.len()));
+                    }
+                }
+                s
+            }
+        }
+    }
+
+    /// Parses a pattern written by [`Pattern::to_canonical`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(pattern)` - The parsed pattern
+    /// * `Err(ReferralCodeError::InvalidSerialization)` - If the text is not
+    ///   a canonical pattern, or was written in an unknown version
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Pattern;
+    ///
+    /// let pattern = Pattern::from_canonical("pattern:v1:pattern:ABC-###").unwrap();
+    ///
+    /// assert_eq!(pattern.size(), 3);
+    /// assert!(Pattern::from_canonical("pattern:v1:length:many").is_err());
+    /// ```
+    pub fn from_canonical(s: &str) -> Result<Self, ReferralCodeError> {
+        let ("v1", body) = split(s, "pattern")? else {
+            return Err(ReferralCodeError::InvalidSerialization);
+        };
+        let (kind, value) = body
+            .split_once(':')
+            .ok_or(ReferralCodeError::InvalidSerialization)?;
+
+        match kind {
+            "length" => value
+                .parse()
+                .map(Self::Length)
+                .map_err(|_| ReferralCodeError::InvalidSerialization),
+            "pattern" => Ok(Self::Pattern(value.to_string())),
+            "template" => Ok(Self::Template(value.to_string())),
+            "placeholder" => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(p), Some(':')) => {
+                        Ok(Self::WithPlaceholder(chars.as_str().to_string(), p))
+                    }
+                    _ => Err(ReferralCodeError::InvalidSerialization),
+                }
+            }
+            "segments" => {
+                let mut rest = value;
+                let mut segments = Vec::new();
+                while !rest.is_empty() {
+                    let (pattern, tail) = split_sized(rest)?;
+                    let (charset, tail) = split_sized(tail)?;
+                    segments.push((
+                        Self::from_canonical(pattern)?,
+                        Charset::from_canonical(charset)?,
+                    ));
+                    rest = tail;
+                }
+                Ok(Self::Segments(segments))
+            }
+            _ => Err(ReferralCodeError::InvalidSerialization),
+        }
+    }
+}
+
+impl fmt::Display for Charset {
+    /// Writes the name of a named charset, such as `alphanumeric`, the
+    /// characters of a custom one, after `custom:` if they spell a name or
+    /// start with `custom:` or `weighted:`, and `weighted:` and the weights
+    /// of a weighted one, as [`Charset::to_canonical`] does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chars = self.as_str();
+
+        match (self.name(), self) {
+            (Some(name), _) => f.write_str(name),
+            (None, Self::Weighted(weights)) => write!(f, "weighted:{}", write_weights(weights)),
+            (None, _)
+                if Charset::from_name(&chars).is_some()
+                    || chars.starts_with("custom:")
+                    || chars.starts_with("weighted:") =>
+            {
+                write!(f, "custom:{chars}")
+            }
+            (None, _) => f.write_str(&chars),
+        }
+    }
+}
+
+impl FromStr for Charset {
+    type Err = ReferralCodeError;
+
+    /// Parses a charset written by its `Display` implementation: a name,
+    /// such as `numeric`, the characters of a custom charset, or
+    /// `weighted:` and the weights of a weighted one, such as
+    /// `weighted:A:1,7:3`.
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::EmptyCharset` - If there are no characters
+    /// * `ReferralCodeError::InvalidSerialization` - If the weights of a
+    ///   weighted charset are not `c:w` pairs separated by commas
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// assert_eq!("numeric".parse::<Charset>().unwrap(), Charset::Numeric);
+    /// assert_eq!("ABC".parse::<Charset>().unwrap().as_str(), "ABC");
+    /// assert_eq!("custom:numeric".parse::<Charset>().unwrap().len(), 7);
+    /// assert_eq!("weighted:A:1,7:3".parse::<Charset>().unwrap().as_str(), "A7");
+    /// assert_eq!(Charset::CrockfordBase32.to_string(), "crockford-base32");
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let charset = match (s.strip_prefix("custom:"), s.strip_prefix("weighted:")) {
+            (Some(chars), _) => Charset::Custom(chars.to_string()),
+            (_, Some(weights)) => Charset::Weighted(
+                parse_weights(weights).ok_or(ReferralCodeError::InvalidSerialization)?,
+            ),
+            _ => Charset::from_name(s).unwrap_or(Charset::Custom(s.to_string())),
+        };
+
+        match charset.is_empty() {
+            true => Err(ReferralCodeError::EmptyCharset),
+            false => Ok(charset),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    /// Writes the pattern string, see [`Pattern::pattern`].
+    ///
+    /// Templates and patterns with a custom placeholder are written with
+    /// their own placeholders, so only `Length` and `Pattern` parse back to
+    /// an equivalent pattern.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern())
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = ReferralCodeError;
+
+    /// Parses a pattern string, where `#` marks random positions.
+    ///
+    /// Use [`Pattern::parse`] for a template.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::EmptyPattern` if the string is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Pattern;
+    ///
+    /// let pattern: Pattern = "ABC-####".parse().unwrap();
+    ///
+    /// assert_eq!(pattern, Pattern::Pattern("ABC-####".to_string()));
+    /// assert_eq!(pattern.to_string(), "ABC-####");
+    /// assert!("".parse::<Pattern>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.is_empty() {
+            true => Err(ReferralCodeError::EmptyPattern),
+            false => Ok(Pattern::Pattern(s.to_string())),
+        }
+    }
+}
+
+impl Config {
+    /// Returns an equivalent configuration with every setting written in a
+    /// single way, so canonicalized configurations can be compared, hashed
+    /// and deduplicated.
+    ///
+    /// * Patterns become templates if they have letter or digit
+    ///   placeholders, and pattern strings otherwise, with a custom
+    ///   placeholder only if they have a literal `#`.
+    /// * Custom charsets are sorted, unless an OCR check, format tag, expiry
+    ///   or embedded fields depend on the order of their characters, and
+    ///   become `Numeric`, `Alphabetic` or `Alphanumeric` if they have the
+    ///   same characters. Other named charsets become custom ones.
+    /// * Empty prefixes and suffixes are removed, as is the checksum
+    ///   coverage without a checksum.
+    /// * Reserved prefixes are sorted and deduplicated.
+    /// * Blocked words are lowercased, sorted and deduplicated, and empty
+    ///   ones removed.
+    ///
+    /// Canonicalized configurations accept the same codes, but sorting a
+    /// charset changes which codes a seed produces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Custom("9876543210".to_string()),
+    ///     pattern: Pattern::Length(4),
+    ///     ..Config::default()
+    /// };
+    /// let canonical = config.canonicalize();
+    ///
+    /// assert_eq!(canonical.charset, Charset::Numeric);
+    /// assert_eq!(canonical.pattern, Pattern::Pattern("####".to_string()));
+    /// assert_eq!(canonical, canonical.canonicalize());
+    /// ```
+    pub fn canonicalize(&self) -> Config {
+        let ordered =
+            self.ocr_check.is_some() || self.format_tag.is_some() || self.fields.is_some();
+        #[cfg(feature = "std")]
+        let ordered = ordered || self.expiry.is_some();
+        let mut reserved_prefixes = self.reserved_prefixes.clone();
+        reserved_prefixes.sort();
+        reserved_prefixes.dedup();
+        let mut blocked_words: Vec<String> = self
+            .blocked_words
+            .iter()
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect();
+        blocked_words.sort();
+        blocked_words.dedup();
+
+        Config {
+            pattern: canonical_pattern(&self.pattern, !ordered),
+            charset: canonical_charset(&self.charset, !ordered),
+            prefix: self.prefix.clone().filter(|s| !s.is_empty()),
+            suffix: self.suffix.clone().filter(|s| !s.is_empty()),
+            checksum_coverage: match self.checksum {
+                Some(_) => self.checksum_coverage,
+                None => ChecksumCoverage::default(),
+            },
+            reserved_prefixes,
+            blocked_words,
+            ..self.clone()
+        }
+    }
+}
+
+/// Returns the named charset with the characters of `charset`, in the same
+/// order unless `sort`, or else the custom charset, sorted if `sort`.
+///
+/// Weighted charsets lose their characters of weight 0, and stay weighted
+/// unless every other character has weight 1, which draws them alike.
+pub(crate) fn canonical_charset(charset: &Charset, sort: bool) -> Charset {
+    if let Charset::Weighted(_) = charset
+        && charset.weights().any(|(_, w)| w != 1)
+    {
+        let mut weights: Vec<(char, u32)> = charset.weights().collect();
+        if sort {
+            weights.sort_unstable();
+        }
+        return Charset::Weighted(weights);
+    }
+
+    let sorted = |s: &str| {
+        let mut chars: Vec<char> = s.chars().collect();
+        chars.sort_unstable();
+        chars.into_iter().collect::<String>()
+    };
+    let key = |s: &str| if sort { sorted(s) } else { s.to_string() };
+    let charset = key(&charset.as_str());
+    // Charsets named later stay custom, so that configurations spelling
+    // their characters out keep their fingerprint.
+    let named = [Charset::Numeric, Charset::Alphabetic, Charset::Alphanumeric];
+
+    named
+        .into_iter()
+        .find(|named| key(&named.as_str()) == charset)
+        .unwrap_or(Charset::Custom(charset))
+}
+
+/// Writes a pattern as a template if it has letter or digit placeholders,
+/// with its literals escaped, and as a pattern string otherwise, with a
+/// custom placeholder if it has a literal `#`.
+///
+/// Segments are canonicalized one by one, along with their charsets,
+/// sorted if `sort`.
+pub(crate) fn canonical_pattern(pattern: &Pattern, sort: bool) -> Pattern {
+    if let Pattern::Segments(segments) = pattern {
+        return Pattern::Segments(
+            segments
+                .iter()
+                .map(|(pattern, charset)| {
+                    (
+                        canonical_pattern(pattern, sort),
+                        canonical_charset(charset, sort),
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    let segments: Vec<Segment> = pattern.segments().collect();
+    let in_literals = |c: char| {
+        segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Literal(s) if s.contains(c)))
+    };
+    let render = |placeholder: char, escape: bool| -> String {
+        segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(s) if escape => s
+                    .chars()
+                    .flat_map(|c| match c {
+                        '#' | 'A' | '9' | '\\' => vec!['\\', c],
+                        c => vec![c],
+                    })
+                    .collect(),
+                Segment::Literal(s) => s.to_string(),
+                Segment::Placeholder(class, n) => {
+                    let c = match class {
+                        CharClass::Charset | CharClass::Pool(_) => placeholder,
+                        CharClass::Letter => 'A',
+                        CharClass::Digit => '9',
+                    };
+                    c.to_string().repeat(*n)
+                }
+            })
+            .collect()
+    };
+
+    // Only templates have typed placeholders.
+    let typed = pattern
+        .classes()
+        .iter()
+        .any(|class| matches!(class, CharClass::Letter | CharClass::Digit));
+    if typed {
+        return Pattern::Template(render('#', true));
+    }
+    if !in_literals('#') {
+        return Pattern::Pattern(render('#', false));
+    }
+
+    // Literals can't contain every character of the private use area.
+    let placeholder = ('\u{e000}'..='\u{f8ff}')
+        .find(|c| !in_literals(*c))
+        .unwrap();
+    Pattern::WithPlaceholder(render(placeholder, false), placeholder)
+}
+
+#[test]
+fn test_canonical_round_trip() {
+    let charsets = [
+        Charset::Numeric,
+        Charset::Alphabetic,
+        Charset::Alphanumeric,
+        Charset::AlphanumericUnambiguous,
+        Charset::CrockfordBase32,
+        Charset::Custom(String::new()),
+        Charset::Custom("charset:v1:numeric".to_string()),
+        Charset::Custom("weighted:A:1".to_string()),
+        Charset::Weighted(vec![]),
+        Charset::Weighted(vec![(':', 2), (',', 1), ('é', 30)]),
+    ];
+    for charset in charsets {
+        let parsed = Charset::from_canonical(&charset.to_canonical()).unwrap();
+        assert_eq!(parsed.to_canonical(), charset.to_canonical());
+        if !charset.is_empty() {
+            assert_eq!(charset.to_string().parse::<Charset>().unwrap(), charset);
+        }
+    }
+
+    let patterns = [
+        Pattern::Length(0),
+        Pattern::Pattern("A:B:###".to_string()),
+        Pattern::Template("AA-99".to_string()),
+        Pattern::WithPlaceholder("#::-?".to_string(), ':'),
+        Pattern::Segments(vec![]),
+        Pattern::Segments(vec![
+            (
+                Pattern::Pattern("#:#".to_string()),
+                Charset::Custom("é:1".to_string()),
+            ),
+            (Pattern::Length(2), Charset::Numeric),
+        ]),
+    ];
+    for pattern in patterns {
+        let parsed = Pattern::from_canonical(&pattern.to_canonical()).unwrap();
+        assert_eq!(parsed.to_canonical(), pattern.to_canonical());
+    }
+
+    assert!(Charset::from_canonical("numeric").is_err());
+    for weights in ["A", "A:", "A:1,", "A:x", "AB:1"] {
+        assert!(Charset::from_canonical(&format!("charset:v1:weighted:{weights}")).is_err());
+        assert!(format!("weighted:{weights}").parse::<Charset>().is_err());
+    }
+    assert!(Pattern::from_canonical("charset:v1:length:3").is_err());
+    assert!(Pattern::from_canonical("pattern:v1:segments:19:pattern:v1:length:2").is_err());
+    assert!(Pattern::from_canonical("pattern:v1:segments:4:pattern:v1:length:2").is_err());
+}
+
+#[test]
+fn test_canonicalize_is_idempotent_and_keeps_order_when_needed() {
+    use crate::OcrCheck;
+
+    let shuffled = Config {
+        charset: Charset::Custom("CAB".to_string()),
+        pattern: Pattern::WithPlaceholder("#??".to_string(), '?'),
+        suffix: Some(String::new()),
+        ..Config::default()
+    };
+    let canonical = shuffled.canonicalize();
+
+    assert_eq!(canonical.charset, Charset::Custom("ABC".to_string()));
+    assert_eq!(canonical.suffix, None);
+    assert_eq!(
+        canonical.pattern,
+        Pattern::WithPlaceholder("#\u{e000}\u{e000}".to_string(), '\u{e000}')
+    );
+    assert_eq!(canonical, canonical.canonicalize());
+    assert_eq!(
+        canonical,
+        Config {
+            pattern: Pattern::WithPlaceholder("#**".to_string(), '*'),
+            charset: Charset::Custom("BCA".to_string()),
+            ..Config::default()
+        }
+        .canonicalize()
+    );
+
+    // OCR check characters depend on the order of the charset.
+    let checked = Config {
+        ocr_check: Some(OcrCheck::new(0, 1)),
+        ..shuffled
+    };
+    assert_eq!(
+        checked.canonicalize().charset,
+        Charset::Custom("CAB".to_string())
+    );
+}
+
+#[test]
+fn test_display_round_trips_through_from_str() {
+    let charsets = [
+        Charset::Numeric,
+        Charset::AlphanumericUnambiguous,
+        Charset::Custom("XYZ".to_string()),
+        Charset::Custom("alphabetic".to_string()),
+        Charset::Custom("custom:".to_string()),
+    ];
+    for charset in charsets {
+        assert_eq!(charset.to_string().parse::<Charset>().unwrap(), charset);
+    }
+    assert_eq!(
+        Charset::Custom("alphabetic".to_string()).to_string(),
+        "custom:alphabetic"
+    );
+    assert!(matches!(
+        "custom:".parse::<Charset>(),
+        Err(ReferralCodeError::EmptyCharset)
+    ));
+
+    assert_eq!(
+        Pattern::Length(3)
+            .to_string()
+            .parse::<Pattern>()
+            .unwrap()
+            .size(),
+        3
+    );
+    assert_eq!(Pattern::parse("AA-99").unwrap().to_string(), "AA-99");
+}
@@ -0,0 +1,209 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{Charset, ReferralCodeError, Set};
+
+impl Charset {
+    /// Reads a custom charset from a charset file.
+    ///
+    /// The file lists one glyph per line, optionally followed by whitespace
+    /// and a positive weight. A glyph of weight `w` is `w` times as likely
+    /// to be drawn as a glyph of weight 1. Blank lines are ignored, and
+    /// glyphs are kept in file order.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The charset file
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(charset)` - The charset described by the file, a
+    ///   `Charset::Custom` if no glyph has a weight other than 1, and a
+    ///   `Charset::Weighted` otherwise
+    /// * `Err(ReferralCodeError::InvalidCharsetFile)` - If a line is not a
+    ///   single visible glyph with an optional valid weight, or repeats a glyph
+    /// * `Err(ReferralCodeError::EmptyCharset)` - If the file lists no glyph
+    /// * `Err(ReferralCodeError::TooLarge)` - If the weights add up to more
+    ///   than `usize::MAX`
+    /// * `Err(ReferralCodeError::Io)` - If reading fails, including invalid UTF-8
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, ReferralCodeError};
+    ///
+    /// let file = "A\nB 3\n\n7\n";
+    /// let charset = Charset::from_reader(file.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(charset, Charset::Weighted(vec![('A', 1), ('B', 3), ('7', 1)]));
+    /// assert_eq!(Charset::from_reader("A\nB\n".as_bytes()).unwrap().as_str(), "AB");
+    /// assert!(matches!(
+    ///     Charset::from_reader("A\nAB\n".as_bytes()),
+    ///     Err(ReferralCodeError::InvalidCharsetFile { line: 2, .. })
+    /// ));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, ReferralCodeError> {
+        let mut weights = Vec::new();
+        let mut seen = Set::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let invalid = |reason| ReferralCodeError::InvalidCharsetFile {
+                line: i + 1,
+                reason,
+            };
+            let mut fields = line.split_whitespace();
+
+            let Some(glyph) = fields.next() else {
+                continue;
+            };
+            let mut glyph_chars = glyph.chars();
+            let (Some(c), None) = (glyph_chars.next(), glyph_chars.next()) else {
+                return Err(invalid("expected a single character"));
+            };
+            if c.is_control() {
+                return Err(invalid("control characters are not allowed"));
+            }
+            if !seen.insert(c) {
+                return Err(invalid("repeated character"));
+            }
+
+            let weight = match fields.next() {
+                Some(weight) => weight
+                    .parse()
+                    .ok()
+                    .filter(|w| *w > 0)
+                    .ok_or(invalid("weight must be a positive integer"))?,
+                None => 1,
+            };
+            if fields.next().is_some() {
+                return Err(invalid("unexpected text after the weight"));
+            }
+
+            weights.push((c, weight));
+        }
+
+        Self::weighted(&weights)
+    }
+
+    /// Reads a custom charset from the charset file at `path`.
+    ///
+    /// See [`Charset::from_reader`] for the file format.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Charset::from_reader`], or
+    /// `ReferralCodeError::Io` if the file cannot be opened.
+    #[cfg(feature = "std")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ReferralCodeError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Builds a charset drawing each character in proportion to its weight.
+    ///
+    /// This is the charset a charset file listing the same characters and
+    /// weights describes: a character of weight `w` is drawn `w` times as
+    /// often as a character of weight 1, and counts once in the keyspace.
+    /// Characters of weight 0 are left out, and the charset is a
+    /// `Charset::Custom` if every other one has weight 1.
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::InvalidCharacter` - If a character is a control
+    ///   character or is listed twice
+    /// * `ReferralCodeError::EmptyCharset` - If no character has a weight
+    /// * `ReferralCodeError::TooLarge` - If the weights add up to more than
+    ///   `usize::MAX`, which draws can't index
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// let charset = Charset::weighted(&[('A', 1), ('7', 3), ('X', 0)]).unwrap();
+    ///
+    /// assert_eq!(charset, Charset::Weighted(vec![('A', 1), ('7', 3)]));
+    /// assert_eq!(charset.len(), 2);
+    /// ```
+    pub fn weighted(weights: &[(char, u32)]) -> Result<Self, ReferralCodeError> {
+        let mut seen = Set::new();
+        let mut total: usize = 0;
+
+        for &(c, weight) in weights {
+            if c.is_control() || !seen.insert(c) {
+                return Err(ReferralCodeError::InvalidCharacter(c));
+            }
+            total = total
+                .checked_add(weight as usize)
+                .ok_or(ReferralCodeError::TooLarge {
+                    field: "weight",
+                    limit: usize::MAX,
+                })?;
+        }
+
+        if total == 0 {
+            return Err(ReferralCodeError::EmptyCharset);
+        }
+
+        let weights: Vec<(char, u32)> = weights.iter().copied().filter(|(_, w)| *w > 0).collect();
+        Ok(match weights.iter().all(|(_, w)| *w == 1) {
+            true => Charset::Custom(weights.into_iter().map(|(c, _)| c).collect()),
+            false => Charset::Weighted(weights),
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_charset_file_errors() {
+    let line = |file: &str| match Charset::from_reader(file.as_bytes()) {
+        Err(ReferralCodeError::InvalidCharsetFile { line, .. }) => Some(line),
+        _ => None,
+    };
+
+    assert_eq!(line("A\nB 0\n"), Some(2));
+    assert_eq!(line("A 4294967296\n"), Some(1));
+    assert_eq!(line("A -1\n"), Some(1));
+    assert_eq!(line("A 2 3\n"), Some(1));
+    assert_eq!(line("\n\n\u{7}\n"), Some(3));
+    assert!(matches!(
+        Charset::from_reader("\n \n".as_bytes()),
+        Err(ReferralCodeError::EmptyCharset)
+    ));
+    assert!(Charset::from_file("/nonexistent/charset.txt").is_err());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_weighted_charset_matches_charset_files() {
+    let weights = [('A', 1), ('B', 3), ('7', 1)];
+    assert_eq!(
+        Charset::weighted(&weights).unwrap(),
+        Charset::from_reader("A\nB 3\n7\n".as_bytes()).unwrap()
+    );
+
+    assert!(matches!(
+        Charset::weighted(&[('A', 1), ('A', 2)]),
+        Err(ReferralCodeError::InvalidCharacter('A'))
+    ));
+    assert_eq!(
+        Charset::weighted(&[('A', 1), ('B', 1), ('C', 0)]).unwrap(),
+        Charset::Custom("AB".to_string())
+    );
+    assert_eq!(
+        Charset::weighted(&[('A', 1), ('B', u32::MAX)])
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(matches!(
+        Charset::weighted(&[('A', 0)]),
+        Err(ReferralCodeError::EmptyCharset)
+    ));
+}
@@ -0,0 +1,443 @@
+use alloc::string::String;
+
+use crate::{
+    Config,
+    segment::{self, Segment},
+};
+
+const DIGITS: &str = "0123456789";
+const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const ALPHANUMERIC: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// A standard check character system appended to the random characters.
+///
+/// When set on a [`Config`], check characters follow the pattern, before any
+/// suffix. They are computed over the characters selected by
+/// [`Config::checksum_coverage`]. Letters are read case-insensitively, and check characters are
+/// always uppercase.
+///
+/// The ISO 7064 systems are available in their pure forms, which detect all
+/// single substitutions and nearly all transpositions, and in their hybrid
+/// forms, whose check character stays within the input alphabet. The Luhn
+/// algorithm and its mod N extension detect all single substitutions and
+/// most adjacent transpositions, and are what many payment and gift card
+/// systems check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Checksum {
+    /// ISO 7064 MOD 11-2, over digits, with a check digit or `X`.
+    #[cfg_attr(feature = "serde", serde(rename = "iso7064-11-2"))]
+    Iso7064Mod11_2,
+    /// ISO 7064 MOD 37-2, over digits and letters, with a check character
+    /// that is a digit, a letter or `*`.
+    #[cfg_attr(feature = "serde", serde(rename = "iso7064-37-2"))]
+    Iso7064Mod37_2,
+    /// ISO 7064 MOD 97-10, over digits, with two check digits.
+    #[cfg_attr(feature = "serde", serde(rename = "iso7064-97-10"))]
+    Iso7064Mod97_10,
+    /// ISO 7064 MOD 11,10, over digits, with a check digit.
+    #[cfg_attr(feature = "serde", serde(rename = "iso7064-11-10"))]
+    Iso7064Mod11_10,
+    /// ISO 7064 MOD 27,26, over letters, with a check letter.
+    #[cfg_attr(feature = "serde", serde(rename = "iso7064-27-26"))]
+    Iso7064Mod27_26,
+    /// ISO 7064 MOD 37,36, over digits and letters, with a check digit or letter.
+    #[cfg_attr(feature = "serde", serde(rename = "iso7064-37-36"))]
+    Iso7064Mod37_36,
+    /// The Luhn algorithm, over digits, with a check digit.
+    #[cfg_attr(feature = "serde", serde(rename = "luhn"))]
+    Luhn,
+    /// The Luhn mod N algorithm with N = 36, over digits and letters, with a
+    /// check digit or letter.
+    ///
+    /// Characters count for their position in `0-9A-Z`.
+    #[cfg_attr(feature = "serde", serde(rename = "luhn-mod-36"))]
+    LuhnMod36,
+}
+
+/// The characters a [`Checksum`] is computed over.
+///
+/// Partner validators differ on this, so it must match theirs exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ChecksumCoverage {
+    /// Every random character, OCR check characters included, in order.
+    #[default]
+    Random,
+    /// Every character preceding the check characters: the prefix, the
+    /// pattern's literals and its random characters.
+    Full,
+}
+
+impl Checksum {
+    /// Returns the characters the checksum is computed over.
+    pub(crate) fn input_alphabet(&self) -> &'static str {
+        match self {
+            Self::Iso7064Mod11_2 | Self::Iso7064Mod97_10 | Self::Iso7064Mod11_10 | Self::Luhn => {
+                DIGITS
+            }
+            Self::Iso7064Mod27_26 => LETTERS,
+            Self::Iso7064Mod37_2 | Self::Iso7064Mod37_36 | Self::LuhnMod36 => ALPHANUMERIC,
+        }
+    }
+
+    /// Returns the characters check characters are drawn from.
+    pub(crate) fn output_alphabet(&self) -> &'static str {
+        match self {
+            Self::Iso7064Mod11_2 => "0123456789X",
+            Self::Iso7064Mod37_2 => "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ*",
+            _ => self.input_alphabet(),
+        }
+    }
+
+    /// Returns the number of check characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Checksum;
+    ///
+    /// assert_eq!(Checksum::Iso7064Mod11_2.len(), 1);
+    /// assert_eq!(Checksum::Iso7064Mod97_10.len(), 2);
+    /// ```
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Iso7064Mod97_10 => 2,
+            _ => 1,
+        }
+    }
+
+    /// Returns `true` if the checksum can be computed over `c`.
+    fn accepts(&self, c: char) -> bool {
+        self.input_alphabet().contains(c.to_ascii_uppercase())
+    }
+
+    /// Returns `true` if every character the checksum covers under `config`
+    /// is accepted.
+    pub(crate) fn is_valid_for(&self, config: &Config) -> bool {
+        let accepts = |s: &str| s.chars().all(|c| self.accepts(c));
+
+        let literals = match config.checksum_coverage {
+            ChecksumCoverage::Random => true,
+            ChecksumCoverage::Full => {
+                accepts(config.prefix.as_deref().unwrap_or(""))
+                    && config.pattern.segments().all(|segment| match segment {
+                        Segment::Literal(s) => accepts(s),
+                        Segment::Placeholder(..) => true,
+                    })
+            }
+        };
+
+        literals
+            && config
+                .pattern
+                .classes()
+                .iter()
+                .all(|class| accepts(&class.alphabet(config)))
+    }
+
+    /// Computes the check characters of `data`.
+    ///
+    /// Returns `None` if `data` contains a character outside the input alphabet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Checksum;
+    ///
+    /// assert_eq!(Checksum::Iso7064Mod11_2.compute("0794").as_deref(), Some("0"));
+    /// assert_eq!(Checksum::Iso7064Mod97_10.compute("794").as_deref(), Some("44"));
+    /// assert_eq!(Checksum::Iso7064Mod11_2.compute("07A4"), None);
+    /// ```
+    pub fn compute(&self, data: &str) -> Option<String> {
+        let check = self.check(data.chars())?;
+
+        Some(check[..self.len()].iter().collect())
+    }
+
+    /// Computes the check characters of `data` without allocating.
+    ///
+    /// Only the first [`Checksum::len`] characters are meaningful.
+    pub(crate) fn check(&self, data: impl IntoIterator<Item = char>) -> Option<[char; 2]> {
+        let alphabet = self.input_alphabet();
+        let values = data
+            .into_iter()
+            .map(|c| alphabet.find(c.to_ascii_uppercase()));
+
+        let output = self.output_alphabet();
+
+        match self {
+            Self::Iso7064Mod11_2 => pure(values, 11, 2, 1, output),
+            Self::Iso7064Mod37_2 => pure(values, 37, 2, 1, output),
+            Self::Iso7064Mod97_10 => pure(values, 97, 10, 2, output),
+            Self::Iso7064Mod11_10 => hybrid(values, 10, output),
+            Self::Iso7064Mod27_26 => hybrid(values, 26, output),
+            Self::Iso7064Mod37_36 => hybrid(values, 36, output),
+            Self::Luhn => luhn(values, 10, output),
+            Self::LuhnMod36 => luhn(values, 36, output),
+        }
+    }
+}
+
+/// ISO 7064 pure system MOD `modulus`-`radix` with `len` check characters.
+///
+/// Returns `None` if a value is `None`, i.e. outside the input alphabet.
+fn pure(
+    mut values: impl Iterator<Item = Option<usize>>,
+    modulus: usize,
+    radix: usize,
+    len: usize,
+    output: &str,
+) -> Option<[char; 2]> {
+    let mut p = values.try_fold(0, |p, a| Some((p + a?) * radix % modulus))?;
+    for _ in 1..len {
+        p = p * radix % modulus;
+    }
+    let check = (modulus + 1 - p) % modulus;
+
+    let at = |i: usize| char::from(output.as_bytes()[i]);
+    Some(match len {
+        1 => [at(check), '\0'],
+        _ => [at(check / radix % radix), at(check % radix)],
+    })
+}
+
+/// ISO 7064 hybrid system MOD `modulus`+1,`modulus`.
+///
+/// Returns `None` if a value is `None`, i.e. outside the input alphabet.
+fn hybrid(
+    mut values: impl Iterator<Item = Option<usize>>,
+    modulus: usize,
+    output: &str,
+) -> Option<[char; 2]> {
+    let p = values.try_fold(modulus, |p, a| {
+        let s = match (p + a?) % modulus {
+            0 => modulus,
+            s => s,
+        };
+        Some(s * 2 % (modulus + 1))
+    })?;
+    let check = (modulus + 1 - p) % modulus;
+
+    Some([char::from(output.as_bytes()[check]), '\0'])
+}
+
+/// Luhn mod `n`, doubling every second value from the right, starting with
+/// the last.
+///
+/// Returns `None` if a value is `None`, i.e. outside the input alphabet.
+fn luhn(
+    mut values: impl Iterator<Item = Option<usize>>,
+    n: usize,
+    output: &str,
+) -> Option<[char; 2]> {
+    let addend = |a: usize, factor: usize| {
+        let a = a * factor;
+        a / n + a % n
+    };
+
+    // Which values are doubled depends on the number of values, which is
+    // only known at the end: sum both ways.
+    let (len, doubled_even, doubled_odd) = values.try_fold((0, 0, 0), |(i, even, odd), a| {
+        let a = a?;
+        Some(match i % 2 {
+            0 => (i + 1, even + addend(a, 2), odd + addend(a, 1)),
+            _ => (i + 1, even + addend(a, 1), odd + addend(a, 2)),
+        })
+    })?;
+    let sum = match len % 2 {
+        1 => doubled_even,
+        _ => doubled_odd,
+    };
+    let check = (n - sum % n) % n;
+
+    Some([char::from(output.as_bytes()[check]), '\0'])
+}
+
+/// Splits the body of a code (prefix and suffix removed) into the pattern
+/// part and its check characters.
+pub(crate) fn split<'a>(config: &Config, body: &'a str) -> Option<(&'a str, &'a str)> {
+    let len = config.checksum.as_ref().map_or(0, Checksum::len);
+    if len == 0 {
+        return Some((body, ""));
+    }
+
+    let (at, _) = body.char_indices().rev().nth(len - 1)?;
+    Some(body.split_at(at))
+}
+
+/// Verifies that a code matches the configuration and its checksum.
+///
+/// # Arguments
+///
+/// * `code` - The code to verify, as scanned or typed
+/// * `config` - The configuration the code was generated with
+///
+/// # Returns
+///
+/// `true` if the code matches the pattern and, when `config.checksum` is set,
+/// its check characters are correct.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Checksum, Config, verify_checksum};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     checksum: Some(Checksum::Iso7064Mod97_10),
+///     ..Config::default()
+/// };
+///
+/// let check = Checksum::Iso7064Mod97_10.compute("12345678").unwrap();
+/// let code = format!("12345678{check}");
+/// assert_eq!(code, "1234567889");
+/// assert!(verify_checksum(&code, &config));
+/// assert!(!verify_checksum("1234567899", &config));
+/// ```
+pub fn verify_checksum(code: &str, config: &Config) -> bool {
+    let Some(random) = segment::random_chars(config, code) else {
+        return false;
+    };
+    let Some(checksum) = &config.checksum else {
+        return true;
+    };
+
+    let body = code
+        .strip_prefix(config.prefix.as_deref().unwrap_or(""))
+        .and_then(|c| c.strip_suffix(config.suffix.as_deref().unwrap_or("")));
+    let Some((pattern, check)) = body.and_then(|body| split(config, body)) else {
+        return false;
+    };
+
+    let expected = match config.checksum_coverage {
+        ChecksumCoverage::Random => checksum.check(random.iter().copied()),
+        ChecksumCoverage::Full => {
+            let prefix = config.prefix.as_deref().unwrap_or("");
+            checksum.check(prefix.chars().chain(pattern.chars()))
+        }
+    };
+    let Some(expected) = expected else {
+        return false;
+    };
+    let expected = expected[..checksum.len()].iter().copied();
+
+    match config.random_case {
+        Some(_) => check.chars().map(|c| c.to_ascii_uppercase()).eq(expected),
+        None => check.chars().eq(expected),
+    }
+}
+
+#[test]
+fn test_iso7064_reference_values() {
+    assert_eq!(
+        Checksum::Iso7064Mod11_2.compute("079").as_deref(),
+        Some("X")
+    );
+    assert_eq!(
+        Checksum::Iso7064Mod37_2.compute("G123498654321").as_deref(),
+        Some("H")
+    );
+    assert_eq!(
+        Checksum::Iso7064Mod11_10.compute("0794").as_deref(),
+        Some("5")
+    );
+    assert_eq!(
+        Checksum::Iso7064Mod37_36
+            .compute("A12425GABC1234002")
+            .as_deref(),
+        Some("M")
+    );
+}
+
+#[test]
+fn test_luhn_reference_values() {
+    assert_eq!(Checksum::Luhn.compute("7992739871").as_deref(), Some("3"));
+    assert_eq!(
+        Checksum::Luhn.compute("453914880343646").as_deref(),
+        Some("7")
+    );
+    assert_eq!(Checksum::Luhn.compute("").as_deref(), Some("0"));
+    assert_eq!(Checksum::LuhnMod36.compute("ABC123").as_deref(), Some("I"));
+    assert_eq!(Checksum::LuhnMod36.compute("abc123").as_deref(), Some("I"));
+    assert_eq!(Checksum::LuhnMod36.compute("AB-1"), None);
+
+    // Every single substitution changes the check character.
+    for checksum in [Checksum::Luhn, Checksum::LuhnMod36] {
+        let alphabet = checksum.input_alphabet();
+        let data = &alphabet[..6];
+        let check = checksum.compute(data).unwrap();
+        for (i, _) in data.char_indices() {
+            for c in alphabet
+                .chars()
+                .filter(|c| *c != data.as_bytes()[i] as char)
+            {
+                let mut tampered = data.to_string();
+                tampered.replace_range(i..i + 1, &c.to_string());
+                assert_ne!(checksum.compute(&tampered).unwrap(), check, "{tampered}");
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_checksum_with_ocr_check_and_suffix() {
+    let config = Config {
+        charset: crate::Charset::Custom("0123456789ABCDEF".to_string()),
+        pattern: crate::Pattern::Pattern("####-####".to_string()),
+        suffix: Some("-EU".to_string()),
+        ocr_check: Some(crate::OcrCheck::new(0, 7)),
+        checksum: Some(Checksum::Iso7064Mod37_36),
+        ..Config::default()
+    };
+
+    let code = crate::generate_one(&config).unwrap();
+    assert_eq!(code.len(), "####-#####-EU".len());
+    assert!(verify_checksum(&code, &config));
+    assert!(crate::verify_ocr_check(&code, &config));
+
+    let (body, _) = code.split_at(code.len() - "-EU".len());
+    let check = body.chars().last().unwrap();
+    let other = if check == '0' { '1' } else { '0' };
+    let tampered = format!("{}{other}-EU", &body[..body.len() - 1]);
+    assert!(!verify_checksum(&tampered, &config));
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_full_coverage_includes_prefix_and_literals() {
+    let random = Config {
+        charset: crate::Charset::Numeric,
+        pattern: crate::Pattern::Pattern("12####".to_string()),
+        prefix: Some("9".to_string()),
+        checksum: Some(Checksum::Iso7064Mod11_10),
+        ..Config::default()
+    };
+    let full = Config {
+        checksum_coverage: ChecksumCoverage::Full,
+        ..random.clone()
+    };
+
+    let code = crate::generate_one(&full).unwrap();
+    let (body, check) = code.split_at(code.len() - 1);
+    assert_eq!(
+        Checksum::Iso7064Mod11_10.compute(body).as_deref(),
+        Some(check)
+    );
+    assert!(verify_checksum(&code, &full));
+
+    // Literals outside the input alphabet can't be covered.
+    let dashed = full.with_pattern(crate::Pattern::Pattern("12-####".to_string()));
+    assert!(crate::generate(&dashed).is_err());
+    assert!(
+        crate::generate(&Config {
+            checksum_coverage: ChecksumCoverage::Random,
+            ..dashed
+        })
+        .is_ok()
+    );
+}
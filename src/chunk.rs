@@ -0,0 +1,112 @@
+use sha2::{Digest, Sha256};
+
+use crate::Batch;
+
+/// An ordered, self-verifying slice of a batch, for publishing codes to a queue.
+///
+/// Chunks of a batch are numbered from 0 by `seq`, so a consumer can restore
+/// their order and detect gaps. The digest covers the batch identifier, the
+/// sequence number and the codes in order, so a chunk that was corrupted,
+/// truncated or relabeled fails [`CodeChunk::verify`].
+///
+/// With the `serde` feature, chunks implement `Serialize` and `Deserialize`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodeChunk {
+    /// Identifier of the batch the codes belong to.
+    pub batch_id: String,
+    /// Zero-based position of the chunk within the batch.
+    pub seq: u64,
+    /// The codes of the chunk, in batch order.
+    pub codes: Vec<String>,
+    /// SHA-256 digest of the batch identifier, sequence number and codes.
+    pub digest: [u8; 32],
+}
+
+impl CodeChunk {
+    /// Creates a chunk, computing its digest.
+    pub fn new(batch_id: impl Into<String>, seq: u64, codes: Vec<String>) -> Self {
+        let batch_id = batch_id.into();
+        let digest = chunk_digest(&batch_id, seq, &codes);
+
+        CodeChunk {
+            batch_id,
+            seq,
+            codes,
+            digest,
+        }
+    }
+
+    /// Returns `true` if the digest matches the chunk's contents.
+    pub fn verify(&self) -> bool {
+        chunk_digest(&self.batch_id, self.seq, &self.codes) == self.digest
+    }
+}
+
+fn chunk_digest(batch_id: &str, seq: u64, codes: &[String]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update((batch_id.len() as u64).to_le_bytes());
+    hasher.update(batch_id.as_bytes());
+    hasher.update(seq.to_le_bytes());
+
+    for code in codes {
+        hasher.update((code.len() as u64).to_le_bytes());
+        hasher.update(code.as_bytes());
+    }
+
+    hasher.finalize().into()
+}
+
+impl Batch {
+    /// Splits the batch into chunks of at most `size` codes, in batch order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Batch, Config};
+    ///
+    /// let batch = Batch::generate("spring", &Config::default().with_count(25)).unwrap();
+    /// let chunks = batch.chunks(10);
+    ///
+    /// assert_eq!(chunks.len(), 3);
+    /// assert_eq!(chunks[2].seq, 2);
+    /// assert_eq!(chunks[2].codes.len(), 5);
+    /// assert!(chunks.iter().all(|chunk| chunk.verify()));
+    /// # }
+    /// ```
+    pub fn chunks(&self, size: usize) -> Vec<CodeChunk> {
+        self.codes
+            .chunks(size)
+            .enumerate()
+            .map(|(seq, codes)| CodeChunk::new(self.id.clone(), seq as u64, codes.to_vec()))
+            .collect()
+    }
+}
+
+#[test]
+fn test_chunk_detects_tampering() {
+    let chunk = CodeChunk::new("spring", 3, vec!["AB".to_string(), "C".to_string()]);
+    assert!(chunk.verify());
+
+    let regrouped = CodeChunk {
+        codes: vec!["A".to_string(), "BC".to_string()],
+        ..chunk.clone()
+    };
+    let moved = CodeChunk {
+        seq: 4,
+        ..chunk.clone()
+    };
+    let relabeled = CodeChunk {
+        batch_id: "summer".to_string(),
+        ..chunk
+    };
+
+    assert!(!regrouped.verify());
+    assert!(!moved.verify());
+    assert!(!relabeled.verify());
+}
@@ -0,0 +1,290 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+use crate::{Batch, input_mask::json_string};
+
+/// The header of every token: HMAC-SHA256, as written by JWT libraries.
+const HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+/// Signed claims about an issued code, carried in a link alongside it.
+///
+/// Claims are written as a JSON Web Token signed with HMAC-SHA256 (`HS256`),
+/// with the claims `code`, `batch` and, if the code expires, `exp`. A
+/// service holding the key validates a link offline, without a registry
+/// lookup, and JWT libraries read the tokens too.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use referral_codes::Claims;
+///
+/// let now = SystemTime::now();
+/// let claims = Claims {
+///     code: "AbC123xY".to_string(),
+///     batch: "spring".to_string(),
+///     expires: Some(now + Duration::from_secs(3600)),
+/// };
+/// let token = claims.sign(b"secret");
+///
+/// let verified = Claims::verify(&token, b"secret", now).unwrap();
+/// assert_eq!(verified.code, "AbC123xY");
+/// assert!(Claims::verify(&token, b"other", now).is_none());
+/// assert!(Claims::verify(&token, b"secret", now + Duration::from_secs(7200)).is_none());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Claims {
+    /// The code.
+    pub code: String,
+    /// The identifier of the batch the code belongs to.
+    pub batch: String,
+    /// When the claims stop verifying, if ever.
+    ///
+    /// Tokens count whole seconds since 1970, so this is rounded down to the
+    /// second, and to 1970 if earlier.
+    pub expires: Option<SystemTime>,
+}
+
+impl Claims {
+    /// Returns a token of these claims signed with `key`.
+    pub fn sign(&self, key: &[u8]) -> String {
+        let mut payload = format!(
+            "{{\"code\":{},\"batch\":{}",
+            json_string(&self.code),
+            json_string(&self.batch)
+        );
+        if let Some(expires) = self.expires {
+            let seconds = expires
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            payload.push_str(&format!(",\"exp\":{seconds}"));
+        }
+        payload.push('}');
+
+        let signed = format!(
+            "{}.{}",
+            base64url(HEADER.as_bytes()),
+            base64url(payload.as_bytes())
+        );
+        let signature = mac(key, &signed).finalize().into_bytes();
+
+        format!("{signed}.{}", base64url(&signature))
+    }
+
+    /// Verifies a token signed with `key` and returns its claims.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The token, as returned by [`Claims::sign`]
+    /// * `key` - The key the token was signed with
+    /// * `now` - The current time, which the claims must not have expired at
+    ///
+    /// # Returns
+    ///
+    /// * `Some(claims)` - The claims, if the token is valid
+    /// * `None` - If the token is malformed, wasn't signed with `key` and
+    ///   `HS256`, or its claims expired at or before `now`
+    pub fn verify(token: &str, key: &[u8], now: SystemTime) -> Option<Claims> {
+        let (signed, signature) = token.rsplit_once('.')?;
+        let (header, payload) = signed.split_once('.')?;
+        mac(key, signed)
+            .verify_slice(&from_base64url(signature)?)
+            .ok()?;
+
+        if from_base64url(header)? != HEADER.as_bytes() {
+            return None;
+        }
+        let claims = parse_payload(&String::from_utf8(from_base64url(payload)?).ok()?)?;
+
+        match claims.expires {
+            Some(expires) if expires <= now => None,
+            _ => Some(claims),
+        }
+    }
+}
+
+impl Batch {
+    /// Returns signed claims for every code of the batch, in batch order.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to sign with, see [`Claims::sign`]
+    /// * `expires` - When the claims stop verifying, if ever
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use std::time::SystemTime;
+    /// use referral_codes::{Batch, Claims, Config};
+    ///
+    /// let batch = Batch::generate("spring", &Config::default().with_count(3)).unwrap();
+    /// let tokens = batch.sign_claims(b"secret", None);
+    ///
+    /// let claims = Claims::verify(&tokens[1], b"secret", SystemTime::now()).unwrap();
+    /// assert_eq!(claims.code, batch.codes[1]);
+    /// assert_eq!(claims.batch, "spring");
+    /// # }
+    /// ```
+    pub fn sign_claims(&self, key: &[u8], expires: Option<SystemTime>) -> Vec<String> {
+        self.codes
+            .iter()
+            .map(|code| {
+                Claims {
+                    code: code.clone(),
+                    batch: self.id.clone(),
+                    expires,
+                }
+                .sign(key)
+            })
+            .collect()
+    }
+}
+
+fn mac(key: &[u8], signed: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(signed.as_bytes());
+    mac
+}
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes bytes as unpadded base64url, as JWTs are.
+fn base64url(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(BASE64URL[(n >> (18 - 6 * i) & 0x3f) as usize].into());
+        }
+    }
+
+    encoded
+}
+
+/// Decodes unpadded base64url, rejecting anything [`base64url`] wouldn't write.
+fn from_base64url(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+
+    for chunk in encoded.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let digit = BASE64URL.iter().position(|d| d == c)? as u32;
+            n |= digit << (18 - 6 * i);
+        }
+        let len = chunk.len() - 1;
+        if n & (0xff_ffff >> (8 * len)) != 0 {
+            return None;
+        }
+        bytes.extend((0..len).map(|i| (n >> (16 - 8 * i)) as u8));
+    }
+
+    Some(bytes)
+}
+
+/// Reads the payload written by [`Claims::sign`].
+fn parse_payload(payload: &str) -> Option<Claims> {
+    let rest = payload.strip_prefix("{\"code\":")?;
+    let (code, rest) = parse_string(rest)?;
+    let rest = rest.strip_prefix(",\"batch\":")?;
+    let (batch, rest) = parse_string(rest)?;
+
+    let expires = match rest.strip_prefix(",\"exp\":") {
+        Some(rest) => {
+            let seconds = rest.strip_suffix('}')?;
+            if !seconds.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            Some(UNIX_EPOCH.checked_add(Duration::from_secs(seconds.parse().ok()?))?)
+        }
+        None if rest == "}" => None,
+        None => return None,
+    };
+
+    Some(Claims {
+        code,
+        batch,
+        expires,
+    })
+}
+
+/// Reads a JSON string literal, returning it and the rest of the input.
+fn parse_string(input: &str) -> Option<(String, &str)> {
+    let mut chars = input.strip_prefix('"')?.char_indices();
+    let mut string = String::new();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, &input[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                '"' => string.push('"'),
+                '\\' => string.push('\\'),
+                'u' => {
+                    let hex: String = (0..4)
+                        .map(|_| chars.next().map(|(_, c)| c))
+                        .collect::<Option<_>>()?;
+                    string.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                _ => return None,
+            },
+            c => string.push(c),
+        }
+    }
+
+    None
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_claims_round_trip_and_reject_tampering() {
+    use crate::Config;
+
+    let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let claims = Claims {
+        code: "R\"\\\u{1}é".to_string(),
+        batch: "spring".to_string(),
+        expires: Some(now + Duration::from_millis(1500)),
+    };
+    let token = claims.sign(b"key");
+
+    // Pinned, so links stay valid across releases.
+    assert_eq!(
+        token,
+        "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.\
+         eyJjb2RlIjoiUlwiXFxcdTAwMDHDqSIsImJhdGNoIjoic3ByaW5nIiwiZXhwIjoxNzAwMDAwMDAxfQ.\
+         yVuudcYJLDby-qRtyGT-e50Cup1xzBQcRMh6VWaWcs8"
+    );
+    let verified = Claims::verify(&token, b"key", now).unwrap();
+    assert_eq!(verified.code, claims.code);
+    assert_eq!(verified.expires, Some(now + Duration::from_secs(1)));
+    assert!(Claims::verify(&token, b"key", now + Duration::from_secs(1)).is_none());
+
+    let mut tampered = token.clone().into_bytes();
+    let last = tampered.len() - 1;
+    tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+    assert!(Claims::verify(std::str::from_utf8(&tampered).unwrap(), b"key", now).is_none());
+    assert!(Claims::verify("", b"key", now).is_none());
+    assert!(Claims::verify("a.b.c", b"key", now).is_none());
+
+    let batch = Batch::generate("b", &Config::default().with_count(4)).unwrap();
+    for (code, token) in batch.codes.iter().zip(batch.sign_claims(b"key", None)) {
+        let claims = Claims::verify(&token, b"key", now).unwrap();
+        assert_eq!((&claims.code, claims.batch.as_str()), (code, "b"));
+        assert_eq!(claims.expires, None);
+    }
+
+    for bytes in [&b""[..], b"f", b"fo", b"foo", b"\xff\xfe\xfd\xfc"] {
+        assert_eq!(from_base64url(&base64url(bytes)).unwrap(), bytes);
+    }
+    assert_eq!(base64url(b"\xfb\xff"), "-_8");
+    assert!(from_base64url("-_9").is_none());
+}
@@ -0,0 +1,124 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::{Config, ReferralCodeError, check_format, validate};
+
+/// Routes codes to the campaign whose format they have.
+///
+/// Services running several campaigns, each with its own prefix, pattern
+/// or charset, find out which campaign an incoming code belongs to before
+/// looking it up. A code belongs to a campaign if it is valid under its
+/// configuration, as [`crate::validate`] checks it: the literals, the
+/// characters of every random position and, if enabled, the check
+/// characters must all match. Campaigns are tried in the order they were
+/// given, so when the formats of two campaigns overlap, the first one
+/// wins: give the most specific formats first.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Classifier, Config, Pattern};
+///
+/// let summer = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Pattern("S-####".to_string()),
+///     ..Config::default()
+/// };
+/// let partners = Config {
+///     charset: Charset::CrockfordBase32,
+///     pattern: Pattern::Pattern("P-######".to_string()),
+///     ..Config::default()
+/// };
+/// let classifier = Classifier::new([
+///     ("summer".to_string(), summer),
+///     ("partners".to_string(), partners),
+/// ])
+/// .unwrap();
+///
+/// assert_eq!(classifier.classify("S-1234"), Some("summer"));
+/// assert_eq!(classifier.classify("P-7Q2K9X"), Some("partners"));
+/// assert_eq!(classifier.classify("S-12A4"), None);
+/// assert_eq!(classifier.classify("nobody's code"), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Classifier {
+    campaigns: Vec<(String, Config)>,
+}
+
+impl Classifier {
+    /// Creates a classifier of codes of some named configurations, tried
+    /// in the order given.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first configuration whose parts don't fit
+    /// together, as [`crate::ConfigRegistry::new`] does.
+    pub fn new<I>(configs: I) -> Result<Self, ReferralCodeError>
+    where
+        I: IntoIterator<Item = (String, Config)>,
+    {
+        let campaigns = configs
+            .into_iter()
+            .map(|(campaign, config)| {
+                check_format(&config)?;
+                Ok((campaign, config))
+            })
+            .collect::<Result<_, ReferralCodeError>>()?;
+
+        Ok(Classifier { campaigns })
+    }
+
+    /// Returns the name of the first campaign `code` is valid under.
+    pub fn classify(&self, code: &str) -> Option<&str> {
+        self.campaigns
+            .iter()
+            .find(|(_, config)| validate(code, config).is_ok())
+            .map(|(campaign, _)| campaign.as_str())
+    }
+
+    /// Returns the configuration of a campaign.
+    pub fn config(&self, campaign: &str) -> Option<&Config> {
+        self.campaigns
+            .iter()
+            .find(|(name, _)| name == campaign)
+            .map(|(_, config)| config)
+    }
+}
+
+#[test]
+fn test_classifier_routes_codes_to_their_campaign() {
+    use crate::{Charset, Checksum, OcrCheck, Pattern};
+
+    let numeric = |pattern: &str| Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Pattern(pattern.to_string()),
+        ..Config::default()
+    };
+    let checked = Config {
+        checksum: Some(Checksum::Iso7064Mod97_10),
+        ..numeric("###")
+    };
+    let classifier = Classifier::new([
+        ("checked".to_string(), checked.clone()),
+        ("short".to_string(), numeric("#####")),
+        ("prefixed".to_string(), numeric("A-###")),
+        ("suffixed".to_string(), numeric("###-B")),
+    ])
+    .unwrap();
+
+    // "79444" carries the check digits of "794", and also fits "#####",
+    // which comes later.
+    assert_eq!(classifier.classify("79444"), Some("checked"));
+    assert_eq!(classifier.classify("79445"), Some("short"));
+    assert_eq!(classifier.classify("A-123"), Some("prefixed"));
+    assert_eq!(classifier.classify("123-B"), Some("suffixed"));
+    assert_eq!(classifier.classify("123-A"), None);
+    assert_eq!(classifier.config("checked"), Some(&checked));
+    assert_eq!(classifier.config("missing"), None);
+    assert_eq!(Classifier::default().classify("79444"), None);
+
+    let invalid = Config {
+        ocr_check: Some(OcrCheck::new(0, 99)),
+        ..Config::default()
+    };
+    assert!(Classifier::new([("invalid".to_string(), invalid)]).is_err());
+}
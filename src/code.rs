@@ -0,0 +1,261 @@
+use alloc::string::{String, ToString};
+use core::{fmt, ops::Range};
+#[cfg(feature = "rng")]
+use std::time::SystemTime;
+
+use crate::{ChecksumCoverage, Config, checksum, segment};
+#[cfg(feature = "rng")]
+use crate::{ReferralCodeError, generate, generate_one};
+
+/// A code along with the parts of its format.
+///
+/// The prefix, the pattern part (the random core and its literals), the
+/// check characters and the suffix are located once, when the code is
+/// generated or parsed, so they can be logged, indexed or recomputed without
+/// parsing the string again.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Checksum, Code, Config, Pattern};
+///
+/// let config = Config {
+///     pattern: Pattern::Pattern("##-##".to_string()),
+///     prefix: Some("REF-".to_string()),
+///     checksum: Some(Checksum::Iso7064Mod37_2),
+///     ..Config::default()
+/// };
+///
+/// let code = Code::parse(&config, "REF-a1-b27").unwrap();
+///
+/// assert_eq!(code.prefix(), "REF-");
+/// assert_eq!(code.core(), "a1-b2");
+/// assert_eq!(code.random(), "a1b2");
+/// assert_eq!(code.check(), "7");
+/// assert_eq!(code.suffix(), "");
+/// assert_eq!(code.compute_check(&config).as_deref(), Some("7"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Code {
+    text: String,
+    core: Range<usize>,
+    check: Range<usize>,
+    random: String,
+}
+
+impl Code {
+    /// Splits a code of `config` into its parts.
+    ///
+    /// Check characters are located but not verified; compare them with
+    /// [`Code::compute_check`] or use [`crate::verify_checksum`].
+    ///
+    /// # Returns
+    ///
+    /// * `Some(code)` - The code and its parts
+    /// * `None` - If the code does not match the configuration
+    pub fn parse(config: &Config, code: &str) -> Option<Code> {
+        let random = segment::random_chars(config, code)?.iter().collect();
+        let start = config.prefix.as_deref().map_or(0, str::len);
+        let end = code.len() - config.suffix.as_deref().map_or(0, str::len);
+        let (core, _) = checksum::split(config, &code[start..end])?;
+
+        Some(Code {
+            text: code.to_string(),
+            core: start..start + core.len(),
+            check: start + core.len()..end,
+            random,
+        })
+    }
+
+    /// Returns the whole code.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the configured prefix the code begins with.
+    pub fn prefix(&self) -> &str {
+        &self.text[..self.core.start]
+    }
+
+    /// Returns the pattern part of the code: its random characters and literals.
+    pub fn core(&self) -> &str {
+        &self.text[self.core.clone()]
+    }
+
+    /// Returns the random characters of the code, in the charset's own spelling.
+    ///
+    /// With `random_case`, this is the same for every case of the code, so
+    /// it can serve as an index key.
+    pub fn random(&self) -> &str {
+        &self.random
+    }
+
+    /// Returns the check characters of the code, empty without a checksum.
+    pub fn check(&self) -> &str {
+        &self.text[self.check.clone()]
+    }
+
+    /// Returns the configured suffix the code ends with.
+    pub fn suffix(&self) -> &str {
+        &self.text[self.check.end..]
+    }
+
+    /// Computes the check characters the code should have under `config`.
+    ///
+    /// Returns `None` if `config` has no checksum or its checksum can't be
+    /// computed over the code.
+    pub fn compute_check(&self, config: &Config) -> Option<String> {
+        let checksum = config.checksum.as_ref()?;
+        let check = match config.checksum_coverage {
+            ChecksumCoverage::Random => checksum.check(self.random.chars()),
+            ChecksumCoverage::Full => {
+                checksum.check(self.prefix().chars().chain(self.core().chars()))
+            }
+        }?;
+
+        Some(check[..checksum.len()].iter().collect())
+    }
+
+    /// Returns the whole code, consuming the parts.
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl AsRef<str> for Code {
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<Code> for String {
+    fn from(code: Code) -> String {
+        code.text
+    }
+}
+
+/// Generates a single code along with its parts.
+///
+/// This is [`generate_one`], returning a [`Code`].
+///
+/// # Errors
+///
+/// Returns the same errors as [`generate_one`].
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, generate_code};
+///
+/// let code = generate_code(&Config::default().with_prefix("REF-")).unwrap();
+///
+/// assert_eq!(code.prefix(), "REF-");
+/// assert_eq!(code.random().len(), 8);
+/// ```
+#[cfg(feature = "rng")]
+pub fn generate_code(config: &Config) -> Result<Code, ReferralCodeError> {
+    let code = generate_one(config)?;
+
+    Ok(Code::parse(config, &code).expect("generated codes match their configuration"))
+}
+
+/// A code generated by [`generate_codes`], with what downstream systems
+/// store alongside it.
+///
+/// The parts of the code, such as [`Code::prefix`] and [`Code::random`],
+/// are located once, so they can be persisted without parsing the code.
+#[cfg(feature = "rng")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedCode {
+    /// The code and its parts.
+    pub code: Code,
+    /// The position of the code in its batch, from 0.
+    pub index: usize,
+    /// The time the batch of the code was generated.
+    pub created_at: SystemTime,
+}
+
+/// Generates `config.count` unique codes along with their parts, position
+/// and generation time.
+///
+/// This is [`generate`], returning a [`GeneratedCode`] for every code, in
+/// the same order. Every code of a batch has the same `created_at`.
+///
+/// # Errors
+///
+/// Returns the same errors as [`generate`].
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, generate_codes};
+///
+/// let config = Config::default().with_prefix("REF-").with_count(3);
+/// let codes = generate_codes(&config).unwrap();
+///
+/// assert_eq!(codes[2].index, 2);
+/// assert_eq!(codes[0].code.prefix(), "REF-");
+/// assert_eq!(codes[0].code.random().len(), 8);
+/// assert_eq!(codes[0].created_at, codes[2].created_at);
+/// ```
+#[cfg(feature = "rng")]
+pub fn generate_codes(config: &Config) -> Result<Vec<GeneratedCode>, ReferralCodeError> {
+    let codes = generate(config)?;
+    let created_at = SystemTime::now();
+
+    Ok(codes
+        .iter()
+        .enumerate()
+        .map(|(index, code)| GeneratedCode {
+            code: Code::parse(config, code).expect("generated codes match their configuration"),
+            index,
+            created_at,
+        })
+        .collect())
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_code_parts_of_generated_codes() {
+    use crate::{Checksum, OcrCheck, Pattern, verify_checksum};
+
+    let config = Config {
+        pattern: Pattern::Pattern("AB-######".to_string()),
+        prefix: Some("é".to_string()),
+        suffix: Some("!".to_string()),
+        ocr_check: Some(OcrCheck::new(0, 5)),
+        checksum: Some(Checksum::Iso7064Mod37_36),
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+
+    for _ in 0..20 {
+        let code = generate_code(&config).unwrap();
+        let parts = [code.prefix(), code.core(), code.check(), code.suffix()];
+
+        assert_eq!(parts.concat(), code.as_str());
+        assert_eq!(parts[0], "é");
+        assert_eq!(parts[1].chars().count(), 9);
+        assert_eq!(parts[3], "!");
+        assert_eq!(code.random().chars().count(), 6);
+        assert!(verify_checksum(code.as_str(), &config));
+        assert_eq!(code.compute_check(&config).as_deref(), Some(code.check()));
+    }
+    assert_eq!(Code::parse(&config, "éXY-123456!"), None);
+
+    let before = SystemTime::now();
+    let codes = generate_codes(&config.with_count(50)).unwrap();
+    assert_eq!(codes.len(), 50);
+    for (i, generated) in codes.iter().enumerate() {
+        assert_eq!(generated.index, i);
+        assert!(generated.created_at >= before);
+        assert_eq!(generated.code.suffix(), "!");
+        assert!(verify_checksum(generated.code.as_str(), &config));
+    }
+}
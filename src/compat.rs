@@ -0,0 +1,121 @@
+use alloc::string::{String, ToString};
+
+use crate::{Charset, Config, Pattern};
+
+/// Options mirroring the JavaScript `voucher-code-generator` package.
+///
+/// Every field has the same name, meaning and default as the corresponding
+/// option of `voucher_codes.generate(...)`, so an existing Node configuration
+/// can be ported field by field:
+///
+/// - `length` (default 8) is ignored when a `pattern` is given
+/// - `count` (default 1)
+/// - `charset` (default [`VoucherCodesOptions::ALPHANUMERIC`])
+/// - `prefix` and `postfix` (default empty)
+/// - `pattern` (default `length` times `#`), where `#` is a random character
+///
+/// Codes generated from the resulting [`Config`] have exactly the same format
+/// as the ones produced by the JavaScript library.
+#[derive(Clone, Debug)]
+pub struct VoucherCodesOptions {
+    /// Number of random characters, used when no `pattern` is given.
+    pub length: usize,
+    /// Number of codes to generate.
+    pub count: usize,
+    /// Characters to draw from.
+    pub charset: String,
+    /// Literal prepended to every code.
+    pub prefix: String,
+    /// Literal appended to every code.
+    pub postfix: String,
+    /// Pattern where `#` is a random character; overrides `length`.
+    pub pattern: Option<String>,
+}
+
+impl VoucherCodesOptions {
+    /// Equivalent of `voucher_codes.charset("numbers")`.
+    pub const NUMBERS: &'static str = "0123456789";
+    /// Equivalent of `voucher_codes.charset("alphabetic")`.
+    pub const ALPHABETIC: &'static str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    /// Equivalent of `voucher_codes.charset("alphanumeric")`.
+    pub const ALPHANUMERIC: &'static str =
+        "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+}
+
+impl Default for VoucherCodesOptions {
+    fn default() -> Self {
+        VoucherCodesOptions {
+            length: 8,
+            count: 1,
+            charset: Self::ALPHANUMERIC.to_string(),
+            prefix: String::new(),
+            postfix: String::new(),
+            pattern: None,
+        }
+    }
+}
+
+impl Config {
+    /// Builds a configuration compatible with the JavaScript `voucher-code-generator` package.
+    ///
+    /// The charset is kept as a custom charset in the exact order given,
+    /// so canonical ordering also matches the JavaScript charset strings.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options, as passed to `voucher_codes.generate(...)`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Config, VoucherCodesOptions};
+    ///
+    /// // voucher_codes.generate({ prefix: "promo-", postfix: "-2015", pattern: "##-###" })
+    /// let config = Config::voucher_codes_compat(VoucherCodesOptions {
+    ///     prefix: "promo-".to_string(),
+    ///     postfix: "-2015".to_string(),
+    ///     pattern: Some("##-###".to_string()),
+    ///     ..VoucherCodesOptions::default()
+    /// });
+    ///
+    /// let code = referral_codes::generate_one(&config).unwrap();
+    /// assert!(code.starts_with("promo-") && code.ends_with("-2015"));
+    /// assert_eq!(code.len(), "promo-##-###-2015".len());
+    /// # }
+    /// ```
+    pub fn voucher_codes_compat(options: VoucherCodesOptions) -> Self {
+        let non_empty = |s: String| (!s.is_empty()).then_some(s);
+
+        Config {
+            pattern: match options.pattern {
+                Some(pattern) => Pattern::Pattern(pattern),
+                None => Pattern::Length(options.length),
+            },
+            count: options.count,
+            charset: Charset::Custom(options.charset),
+            prefix: non_empty(options.prefix),
+            suffix: non_empty(options.postfix),
+            ..Config::default()
+        }
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_voucher_codes_compat_defaults() {
+    let config = Config::voucher_codes_compat(VoucherCodesOptions {
+        count: 5,
+        charset: VoucherCodesOptions::NUMBERS.to_string(),
+        ..VoucherCodesOptions::default()
+    });
+
+    let codes = crate::generate(&config).unwrap();
+
+    assert_eq!(codes.len(), 5);
+    assert!(
+        codes
+            .iter()
+            .all(|c| c.len() == 8 && c.chars().all(|c| c.is_ascii_digit()))
+    );
+}
@@ -0,0 +1,163 @@
+use alloc::vec::Vec;
+
+use crate::{
+    Config,
+    segment::{Slot, slots},
+};
+
+/// The result of comparing two configurations for overlapping codes.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct CompatibilityReport {
+    /// Whether codes of both configurations have the same length, prefix
+    /// and suffix included.
+    pub same_length: bool,
+    /// Zero-based positions in the full code at which no character is valid
+    /// under both configurations.
+    ///
+    /// Always empty when the lengths differ.
+    pub disjoint_positions: Vec<usize>,
+}
+
+impl CompatibilityReport {
+    /// Returns `true` if no string can be a code of both configurations.
+    pub fn is_compatible(&self) -> bool {
+        !self.same_length || !self.disjoint_positions.is_empty()
+    }
+}
+
+/// The expanded shape of a code, with the format tag as a literal.
+fn tagged_slots(config: &Config) -> Vec<Slot> {
+    let mut slots = slots(config);
+
+    if let Some(tag) = &config.format_tag
+        && let Some(slot) = slots
+            .iter_mut()
+            .filter(|slot| matches!(slot, Slot::Random(_)))
+            .nth(tag.position)
+        && let Some(c) = tag.char(&config.charset)
+    {
+        *slot = Slot::Literal(c);
+    }
+
+    slots
+}
+
+impl Config {
+    /// Checks whether codes of this configuration could be mistaken for codes of `other`.
+    ///
+    /// Two formats overlap when some string is accepted by both, position by
+    /// position: literals must match, and random positions must share at
+    /// least one character. Format tags count as literals. When either
+    /// configuration uses `random_case`, characters are compared ignoring
+    /// case, since such codes are normalized before comparison.
+    ///
+    /// The analysis is conservative: check characters are assumed to accept
+    /// anything, so formats reported as overlapping may in practice be told
+    /// apart by their OCR check or checksum characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The configuration to compare with
+    ///
+    /// # Returns
+    ///
+    /// A `CompatibilityReport` telling whether both formats can run side by side.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern};
+    ///
+    /// let spring = Config::default().with_pattern(Pattern::Pattern("SP-######".to_string()));
+    /// let summer = Config::default().with_pattern(Pattern::Pattern("SU-######".to_string()));
+    /// let numeric = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Length(9),
+    ///     ..Config::default()
+    /// };
+    ///
+    /// assert!(spring.compatible_with(&summer).is_compatible());
+    /// assert_eq!(spring.compatible_with(&summer).disjoint_positions, vec![1]);
+    /// assert!(spring.compatible_with(&numeric).is_compatible());
+    /// assert!(!numeric.compatible_with(&numeric.clone()).is_compatible());
+    /// ```
+    pub fn compatible_with(&self, other: &Config) -> CompatibilityReport {
+        let ours = tagged_slots(self);
+        let theirs = tagged_slots(other);
+        let ignore_case = self.random_case.is_some() || other.random_case.is_some();
+
+        if ours.len() != theirs.len() {
+            return CompatibilityReport::default();
+        }
+
+        let same =
+            |a: char, b: char| a == b || ignore_case && a.to_lowercase().eq(b.to_lowercase());
+        let accepts = |slot: &Slot, config: &Config, c: char| match slot {
+            Slot::Literal(l) => same(*l, c),
+            Slot::Random(class) => class.alphabet(config).chars().any(|a| same(a, c)),
+            Slot::Check => true,
+        };
+        let overlaps = |a: &Slot, b: &Slot| match a {
+            Slot::Literal(l) => accepts(b, other, *l),
+            Slot::Random(class) => class.alphabet(self).chars().any(|c| accepts(b, other, c)),
+            Slot::Check => match b {
+                Slot::Random(class) => !class.alphabet(other).is_empty(),
+                _ => true,
+            },
+        };
+
+        CompatibilityReport {
+            same_length: true,
+            disjoint_positions: ours
+                .iter()
+                .zip(&theirs)
+                .enumerate()
+                .filter(|(_, (a, b))| !overlaps(a, b))
+                .map(|(position, _)| position)
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn test_compatible_with_normalization_and_tags() {
+    use crate::{Charset, FormatId, FormatTag, Pattern};
+
+    let upper = Config {
+        charset: Charset::Custom("ABC".to_string()),
+        pattern: Pattern::Length(4),
+        ..Config::default()
+    };
+    let lower = Config {
+        charset: Charset::Custom("abc".to_string()),
+        ..upper.clone()
+    };
+    let random_case = Config {
+        random_case: Some(0.5),
+        ..lower.clone()
+    };
+
+    assert_eq!(
+        upper.compatible_with(&lower).disjoint_positions,
+        vec![0, 1, 2, 3]
+    );
+    assert!(!upper.compatible_with(&random_case).is_compatible());
+    assert!(
+        upper
+            .compatible_with(&upper.with_prefix("X"))
+            .is_compatible()
+    );
+
+    let tagged = |id| Config {
+        format_tag: Some(FormatTag {
+            position: 2,
+            id: FormatId(id),
+        }),
+        ..upper.clone()
+    };
+    assert_eq!(
+        tagged(0).compatible_with(&tagged(1)).disjoint_positions,
+        vec![2]
+    );
+    assert!(!tagged(0).compatible_with(&upper).is_compatible());
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::{Config, ReferralCodeError, check_format};
+
+/// The configurations of a [`ConfigRegistry`] at one point in time.
+pub type ConfigSet = HashMap<String, Arc<Config>>;
+
+/// Named configurations, replaceable at runtime.
+///
+/// Issuing services look up the configuration of a campaign by name, and
+/// roll out new formats by replacing the whole set with
+/// [`ConfigRegistry::replace`], without restarting. Replacing is atomic:
+/// a lookup sees either the old set or the new one, never a mix, and
+/// lookups in progress keep the configuration they got. Take a
+/// [`ConfigRegistry::snapshot`] to look up several campaigns in the same
+/// set.
+///
+/// Share the registry between threads with an `Arc`.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, ConfigRegistry, Pattern};
+///
+/// let registry = ConfigRegistry::new([("summer".to_string(), Config::default())]).unwrap();
+/// let before = registry.get("summer").unwrap();
+///
+/// let winter = Config::default().with_pattern(Pattern::Pattern("W-####".to_string()));
+/// registry.replace([("winter".to_string(), winter)]).unwrap();
+///
+/// assert!(registry.get("summer").is_none());
+/// assert!(registry.get("winter").is_some());
+/// assert_eq!(*before, Config::default());
+/// ```
+#[derive(Debug, Default)]
+pub struct ConfigRegistry {
+    current: RwLock<Arc<ConfigSet>>,
+}
+
+impl ConfigRegistry {
+    /// Creates a registry holding some named configurations.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first configuration whose parts don't fit
+    /// together, such as an OCR check or format tag it can't hold.
+    pub fn new<I>(configs: I) -> Result<Self, ReferralCodeError>
+    where
+        I: IntoIterator<Item = (String, Config)>,
+    {
+        Ok(ConfigRegistry {
+            current: RwLock::new(Arc::new(config_set(configs)?)),
+        })
+    }
+
+    /// Returns the configuration of a campaign.
+    pub fn get(&self, campaign: &str) -> Option<Arc<Config>> {
+        self.snapshot().get(campaign).cloned()
+    }
+
+    /// Returns the current set of configurations, which later replacements
+    /// leave unchanged.
+    pub fn snapshot(&self) -> Arc<ConfigSet> {
+        Arc::clone(&self.current.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Replaces every configuration at once, returning the previous ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`ConfigRegistry::new`], in which case
+    /// the registry is left unchanged.
+    pub fn replace<I>(&self, configs: I) -> Result<Arc<ConfigSet>, ReferralCodeError>
+    where
+        I: IntoIterator<Item = (String, Config)>,
+    {
+        let configs = Arc::new(config_set(configs)?);
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+
+        Ok(std::mem::replace(&mut *current, configs))
+    }
+}
+
+fn config_set<I>(configs: I) -> Result<ConfigSet, ReferralCodeError>
+where
+    I: IntoIterator<Item = (String, Config)>,
+{
+    configs
+        .into_iter()
+        .map(|(campaign, config)| {
+            check_format(&config)?;
+            Ok((campaign, Arc::new(config)))
+        })
+        .collect()
+}
+
+#[test]
+fn test_config_registry_swaps_atomically() {
+    use std::thread;
+
+    use crate::{OcrCheck, Pattern};
+
+    let config =
+        |pattern: &str| Config::default().with_pattern(Pattern::Pattern(pattern.to_string()));
+    let registry = Arc::new(
+        ConfigRegistry::new([
+            ("a".to_string(), config("A-###")),
+            ("b".to_string(), config("A-####")),
+        ])
+        .unwrap(),
+    );
+
+    // Every snapshot holds one whole set, old or new.
+    let reader = {
+        let registry = Arc::clone(&registry);
+        thread::spawn(move || {
+            for _ in 0..10_000 {
+                let snapshot = registry.snapshot();
+                let a = snapshot["a"].pattern.size();
+                assert_eq!(snapshot["b"].pattern.size(), a + 1);
+            }
+        })
+    };
+    for size in 0..1_000 {
+        let pattern = "#".repeat(size);
+        registry
+            .replace([
+                ("a".to_string(), config(&pattern)),
+                ("b".to_string(), config(&format!("{pattern}#"))),
+            ])
+            .unwrap();
+    }
+    reader.join().unwrap();
+
+    let before = registry.snapshot();
+    let invalid = Config {
+        ocr_check: Some(OcrCheck::new(0, 99)),
+        ..Config::default()
+    };
+    assert!(matches!(
+        registry.replace([("c".to_string(), invalid)]),
+        Err(ReferralCodeError::InvalidCheckPositions)
+    ));
+    assert_eq!(registry.snapshot(), before);
+    assert!(registry.get("c").is_none());
+    assert!(ConfigRegistry::default().get("a").is_none());
+}
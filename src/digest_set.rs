@@ -0,0 +1,301 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    io::{self, BufRead, Write},
+    sync::Mutex,
+};
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+use crate::{Reservation, UniquenessStore};
+
+/// The first line of every digest file, followed by the format version.
+const HEADER: &str = "referral-codes-digests ";
+
+/// The digest file format version written by this crate.
+const VERSION: u32 = 1;
+
+/// Bytes of HMAC-SHA256 kept per code.
+const DIGEST_LEN: usize = 16;
+
+/// A compact record of issued codes, holding keyed digests instead of codes.
+///
+/// Each code is stored as the first 128 bits of its HMAC-SHA256 under a
+/// secret key, so a saved set tells, with the key, whether a code was issued
+/// before, but lists no code: even with the key, codes can only be tested
+/// one at a time. Two distinct codes share a digest with negligible
+/// probability (around 2^-69 for a billion codes). Keep the key across runs: a set loaded with
+/// another key matches nothing.
+///
+/// Digests are grouped by run, so that only a window of recent runs is kept
+/// with [`DigestSet::retain_runs`]. A `Mutex<DigestSet>` is a
+/// [`UniquenessStore`]; pass it to [`crate::generate_reserved`] to keep
+/// codes unique across runs.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::DigestSet;
+///
+/// let mut issued = DigestSet::new(b"secret".to_vec());
+/// assert!(issued.insert("AbC123xY"));
+///
+/// let mut file = Vec::new();
+/// issued.save(&mut file).unwrap();
+/// let mut loaded = DigestSet::load(b"secret".to_vec(), file.as_slice()).unwrap();
+///
+/// assert!(loaded.contains("AbC123xY"));
+/// assert!(!loaded.insert("AbC123xY"));
+/// assert!(!String::from_utf8(file).unwrap().contains("AbC123xY"));
+/// ```
+#[derive(Clone)]
+pub struct DigestSet {
+    key: Vec<u8>,
+    digests: HashMap<[u8; DIGEST_LEN], u64>,
+    run: u64,
+}
+
+impl fmt::Debug for DigestSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DigestSet")
+            .field("len", &self.digests.len())
+            .field("run", &self.run)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DigestSet {
+    /// Creates an empty set, whose codes are digested with a secret key.
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        DigestSet {
+            key: key.into(),
+            digests: HashMap::new(),
+            run: 0,
+        }
+    }
+
+    /// Returns the number of codes in the set.
+    pub fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    /// Returns `true` if the set holds no code.
+    pub fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+
+    /// Returns the current run, which inserted codes are recorded in.
+    pub fn run(&self) -> u64 {
+        self.run
+    }
+
+    /// Starts a new run and returns it.
+    pub fn begin_run(&mut self) -> u64 {
+        self.run += 1;
+        self.run
+    }
+
+    /// Adds a code to the set, in the current run.
+    ///
+    /// Codes are digested as given: with `random_case`, insert the
+    /// lowercased code, as [`crate::generate_reserved`] does.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the code was not in the set.
+    pub fn insert(&mut self, code: &str) -> bool {
+        let digest = self.digest(code);
+
+        match self.digests.contains_key(&digest) {
+            true => false,
+            false => {
+                self.digests.insert(digest, self.run);
+                true
+            }
+        }
+    }
+
+    /// Returns `true` if the code is in the set.
+    pub fn contains(&self, code: &str) -> bool {
+        self.digests.contains_key(&self.digest(code))
+    }
+
+    /// Forgets the codes of every run but the last `runs`, the current
+    /// run included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::DigestSet;
+    ///
+    /// let mut issued = DigestSet::new(b"secret".to_vec());
+    /// issued.insert("OLD");
+    /// issued.begin_run();
+    /// issued.insert("NEW");
+    ///
+    /// issued.retain_runs(1);
+    ///
+    /// assert!(!issued.contains("OLD"));
+    /// assert!(issued.contains("NEW"));
+    /// ```
+    pub fn retain_runs(&mut self, runs: u64) {
+        let run = self.run;
+        self.digests.retain(|_, r| run - *r < runs);
+    }
+
+    /// Writes the set, without its key, one digest per line.
+    ///
+    /// The first line holds the format version, and every other line a run
+    /// and a digest in hexadecimal. Digests are written in no particular
+    /// order.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error produced by the writer.
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{HEADER}{VERSION}")?;
+        writeln!(writer, "run {}", self.run)?;
+
+        for (digest, run) in &self.digests {
+            let hex: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+            writeln!(writer, "{run} {hex}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a set written by [`DigestSet::save`].
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key the set was built with
+    /// * `reader` - The saved set
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error produced by the reader, or an error of kind
+    /// `InvalidData` if the file is malformed.
+    pub fn load<R: BufRead>(key: impl Into<Vec<u8>>, reader: R) -> io::Result<Self> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut lines = reader.lines();
+
+        let version = lines
+            .next()
+            .transpose()?
+            .and_then(|header| header.strip_prefix(HEADER)?.parse::<u32>().ok())
+            .ok_or_else(|| invalid("not a digest file"))?;
+        if version != VERSION {
+            return Err(invalid("unsupported digest file version"));
+        }
+        let run = lines
+            .next()
+            .transpose()?
+            .and_then(|line| line.strip_prefix("run ")?.parse::<u64>().ok())
+            .ok_or_else(|| invalid("missing current run"))?;
+
+        let mut set = DigestSet {
+            run,
+            ..DigestSet::new(key)
+        };
+        for line in lines {
+            let line = line?;
+            let (digest_run, digest) = line
+                .split_once(' ')
+                .and_then(|(r, hex)| Some((r.parse::<u64>().ok()?, parse_hex(hex)?)))
+                .filter(|(r, _)| *r <= run)
+                .ok_or_else(|| invalid("malformed digest"))?;
+            set.digests.insert(digest, digest_run);
+        }
+
+        Ok(set)
+    }
+
+    fn digest(&self, code: &str) -> [u8; DIGEST_LEN] {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(code.as_bytes());
+
+        let mut digest = [0; DIGEST_LEN];
+        digest.copy_from_slice(&mac.finalize().into_bytes()[..DIGEST_LEN]);
+        digest
+    }
+}
+
+impl UniquenessStore for Mutex<DigestSet> {
+    type Error = Infallible;
+
+    fn reserve(&self, code: &str) -> Result<Reservation, Infallible> {
+        let mut set = self.lock().unwrap_or_else(|e| e.into_inner());
+
+        Ok(match set.insert(code) {
+            true => Reservation::Reserved,
+            false => Reservation::Taken,
+        })
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<[u8; DIGEST_LEN]> {
+    if hex.len() != 2 * DIGEST_LEN || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut digest = [0; DIGEST_LEN];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+
+    Some(digest)
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_digest_set_dedups_across_runs() {
+    use crate::{Charset, Config, Pattern, generate_reserved};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(2),
+        count: 60,
+        ..Config::default()
+    };
+
+    let store = Mutex::new(DigestSet::new(b"key".to_vec()));
+    let first = generate_reserved(&config, &store).unwrap();
+
+    let mut file = Vec::new();
+    store.lock().unwrap().save(&mut file).unwrap();
+    let mut loaded = DigestSet::load(b"key".to_vec(), file.as_slice()).unwrap();
+    assert_eq!(loaded.len(), 60);
+    assert!(first.iter().all(|code| loaded.contains(code)));
+    let other_key = DigestSet::load(b"other".to_vec(), file.as_slice()).unwrap();
+    assert!(first.iter().all(|code| !other_key.contains(code)));
+
+    assert_eq!(loaded.begin_run(), 1);
+    let store = Mutex::new(loaded);
+    let second = generate_reserved(&config.with_count(40), &store).unwrap();
+    assert!(second.iter().all(|code| !first.contains(code)));
+    assert!(generate_reserved(&config.with_count(1), &store).is_err());
+
+    // Forgetting the first run frees its codes again.
+    let mut set = store.into_inner().unwrap();
+    set.retain_runs(1);
+    assert_eq!(set.len(), 40);
+    assert!(first.iter().all(|code| !set.contains(code)));
+
+    let digest = format!("0 {}", "ab".repeat(DIGEST_LEN));
+    for malformed in [
+        String::new(),
+        format!("referral-codes-digests 2\nrun 0\n{digest}\n"),
+        format!("referral-codes-digests 1\n{digest}\n"),
+        "referral-codes-digests 1\nrun 0\n0 00\n".to_string(),
+        format!("referral-codes-digests 1\nrun 0\n1{}\n", &digest[1..]),
+    ] {
+        assert!(
+            DigestSet::load(b"key".to_vec(), malformed.as_bytes()).is_err(),
+            "{malformed:?}"
+        );
+    }
+}
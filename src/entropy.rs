@@ -0,0 +1,161 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::Charset;
+#[cfg(feature = "std")]
+use crate::Config;
+
+/// Shannon entropy, in bits, of drawing a character of `charset`.
+///
+/// Repeated characters and weights make their symbol more likely, which
+/// lowers entropy. With `caseless`, characters that only differ in case
+/// count as one symbol.
+#[cfg(feature = "std")]
+pub(crate) fn shannon_entropy(charset: &Charset, caseless: bool) -> f64 {
+    let mut frequencies: HashMap<String, u64> = HashMap::new();
+    let mut total = 0;
+
+    for (c, weight) in charset.weights() {
+        let symbol = match caseless {
+            true => c.to_lowercase().collect(),
+            false => c.to_string(),
+        };
+        *frequencies.entry(symbol).or_default() += u64::from(weight);
+        total += u64::from(weight);
+    }
+
+    // Folded from +0.0, so a single symbol or none carry 0 bits, not -0.
+    frequencies
+        .values()
+        .map(|n| {
+            let p = *n as f64 / total as f64;
+            -p * p.log2()
+        })
+        .fold(0.0, |bits, symbol| bits + symbol)
+}
+
+impl Charset {
+    /// Returns the number of distinct characters in this charset.
+    ///
+    /// This differs from [`Charset::len`] for custom charsets that repeat
+    /// characters. Weighted charsets list each character once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// assert_eq!(Charset::Custom("AAB".to_string()).effective_len(), 2);
+    /// ```
+    pub fn effective_len(&self) -> usize {
+        let mut chars: Vec<char> = self.as_str().chars().collect();
+        chars.sort();
+        chars.dedup();
+        chars.len()
+    }
+
+    /// Returns the maximum number of bits each character can carry.
+    ///
+    /// This is `log2` of the number of distinct characters, and equals the
+    /// entropy per character when characters are drawn uniformly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// assert_eq!(Charset::Custom("ABCD".to_string()).bits_per_char(), 2.0);
+    /// assert!((Charset::Alphanumeric.bits_per_char() - 5.954).abs() < 0.001);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn bits_per_char(&self) -> f64 {
+        match self.effective_len() {
+            0 => 0.0,
+            n => (n as f64).log2(),
+        }
+    }
+
+    /// Returns the Shannon entropy, in bits, of a single generated character.
+    ///
+    /// This is the number security reviews should use: it accounts for any
+    /// non-uniformity in how characters are drawn, such as a custom charset
+    /// listing a character twice or a weighted charset, and is never above
+    /// [`Charset::bits_per_char`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// assert_eq!(Charset::Custom("AB".to_string()).entropy(), 1.0);
+    /// assert!(Charset::Custom("AAB".to_string()).entropy() < 1.0);
+    /// assert_eq!(
+    ///     Charset::Weighted(vec![('A', 2), ('B', 1)]).entropy(),
+    ///     Charset::Custom("AAB".to_string()).entropy()
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn entropy(&self) -> f64 {
+        shannon_entropy(self, false)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Config {
+    /// Returns the entropy, in bits, of a generated code.
+    ///
+    /// This is the sum of the Shannon entropies of the random positions.
+    /// Literals, prefixes, suffixes, format tags, expiries and embedded fields
+    /// carry no entropy, check characters are derived from the other
+    /// characters, and with `random_case` letters only count once regardless
+    /// of case, since codes are matched case-insensitively.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Custom("0123456789ABCDEF".to_string()),
+    ///     pattern: Pattern::Pattern("REF-####-####".to_string()),
+    ///     ..Config::default()
+    /// };
+    ///
+    /// assert_eq!(config.entropy_bits(), 32.0);
+    /// ```
+    pub fn entropy_bits(&self) -> f64 {
+        let checks = self.derived_positions();
+
+        self.pattern
+            .classes()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !checks.contains(i))
+            .map(|(_, class)| shannon_entropy(class.charset(self), self.random_case.is_some()))
+            .fold(0.0, |bits, position| bits + position)
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_entropy_bits_excludes_checks_and_folds_case() {
+    let config = Config {
+        charset: Charset::Alphabetic,
+        pattern: crate::Pattern::Length(4),
+        ..Config::default()
+    };
+
+    let checked = Config {
+        ocr_check: Some(crate::OcrCheck::new(0, 1)),
+        ..config.clone()
+    };
+    let random_case = Config {
+        random_case: Some(0.5),
+        ..config.clone()
+    };
+
+    assert!((config.entropy_bits() - 4.0 * 52f64.log2()).abs() < 1e-9);
+    assert!((checked.entropy_bits() - 2.0 * 52f64.log2()).abs() < 1e-9);
+    assert!((random_case.entropy_bits() - 4.0 * 26f64.log2()).abs() < 1e-9);
+}
@@ -0,0 +1,174 @@
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::{Config, ReferralCodeError, assemble, check_format, segment};
+
+/// Iterator over every code of a configuration, see [`enumerate_all`].
+struct Enumeration {
+    config: Config,
+    alphabets: Vec<Vec<char>>,
+    /// Index into each alphabet of the next code, or `None` once exhausted.
+    digits: Option<Vec<usize>>,
+}
+
+impl Iterator for Enumeration {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let digits = self.digits.as_mut()?;
+            let free = digits.iter().zip(&self.alphabets).map(|(d, a)| a[*d]);
+            let code = assemble(&self.config, segment::with_derived(&self.config, free));
+
+            // Advance like an odometer, the last position turning fastest.
+            let mut exhausted = true;
+            for (digit, alphabet) in digits.iter_mut().zip(&self.alphabets).rev() {
+                *digit += 1;
+                if *digit < alphabet.len() {
+                    exhausted = false;
+                    break;
+                }
+                *digit = 0;
+            }
+            if exhausted {
+                self.digits = None;
+            }
+
+            if self.config.accepts(&code) {
+                return Some(code);
+            }
+        }
+    }
+}
+
+/// Returns every code a configuration can produce, in charset order.
+///
+/// Codes are yielded lazily, ordered by the charset index of their random
+/// characters from left to right. Format tag, OCR check and checksum
+/// characters are filled in, and codes beginning with a reserved prefix or,
+/// with `no_repeats`, repeating a character are skipped. With `random_case`, each code is yielded once, in the charset's
+/// own spelling. `config.count` is ignored.
+///
+/// The iterator yields the whole keyspace, so only use it with small ones,
+/// e.g. to build complete lookup tables for short test formats.
+///
+/// # Arguments
+///
+/// * `config` - The configuration to enumerate
+///
+/// # Returns
+///
+/// * `Ok(codes)` - An iterator over every code
+/// * `Err(ReferralCodeError)` - If the OCR check, format tag or checksum
+///   does not fit the configuration, or `random_case` is not a probability
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, Pattern, enumerate_all};
+///
+/// let config = Config {
+///     charset: Charset::Custom("XYZ".to_string()),
+///     pattern: Pattern::Pattern("#-#".to_string()),
+///     ..Config::default()
+/// };
+///
+/// let codes: Vec<String> = enumerate_all(&config).unwrap().collect();
+///
+/// assert_eq!(codes.len(), 9);
+/// assert_eq!(&codes[..4], ["X-X", "X-Y", "X-Z", "Y-X"]);
+/// ```
+pub fn enumerate_all(
+    config: &Config,
+) -> Result<impl Iterator<Item = String> + use<>, ReferralCodeError> {
+    check_format(config)?;
+
+    let alphabets = segment::free_alphabets(config);
+    let digits = alphabets
+        .iter()
+        .all(|alphabet| !alphabet.is_empty())
+        .then(|| vec![0; alphabets.len()]);
+
+    Ok(Enumeration {
+        config: config.clone(),
+        alphabets,
+        digits,
+    })
+}
+
+#[test]
+fn test_enumerate_all_covers_the_keyspace() {
+    use std::collections::HashSet;
+
+    use crate::{Charset, OcrCheck, Pattern, verify_ocr_check};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(4),
+        ocr_check: Some(OcrCheck::new(1, 3)),
+        reserved_prefixes: vec!["9".to_string()],
+        ..Config::default()
+    };
+
+    let codes: Vec<String> = enumerate_all(&config).unwrap().collect();
+
+    assert_eq!(codes.len(), 90);
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 90);
+    assert!(codes.iter().all(|code| verify_ocr_check(code, &config)));
+    assert!(codes.iter().all(|code| !code.starts_with('9')));
+
+    let random_case = Config {
+        charset: Charset::Alphabetic,
+        pattern: Pattern::Length(1),
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+    assert_eq!(enumerate_all(&random_case).unwrap().count(), 26);
+
+    let empty = Config {
+        charset: Charset::Custom(String::new()),
+        ..Config::default()
+    };
+    assert_eq!(enumerate_all(&empty).unwrap().count(), 0);
+    assert!(
+        enumerate_all(&Config {
+            ocr_check: Some(OcrCheck::new(0, 8)),
+            ..Config::default()
+        })
+        .is_err()
+    );
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_single_code_keyspaces_are_exact() {
+    use crate::{Charset, Pattern, SpecVersion, generate, generate_one, verify_as};
+
+    for charset in ["A", "AAA"] {
+        for pattern in [
+            Pattern::Length(0),
+            Pattern::Length(1),
+            Pattern::Length(3),
+            Pattern::Pattern("X#".to_string()),
+            Pattern::Pattern("XY".to_string()),
+        ] {
+            let config = Config {
+                charset: Charset::Custom(charset.to_string()),
+                pattern,
+                ..Config::default()
+            };
+            let codes: Vec<String> = enumerate_all(&config).unwrap().collect();
+
+            assert_eq!(config.keyspace(), Some(1), "{config:?}");
+            assert_eq!(codes, [generate_one(&config).unwrap()], "{config:?}");
+            assert!(verify_as(&codes[0], &config, SpecVersion::LATEST));
+            assert!(matches!(
+                generate(&config.with_count(2)),
+                Err(ReferralCodeError::NonFeasibleConfig | ReferralCodeError::NoRandomPositions)
+            ));
+            assert!(generate(&config.with_count(0)).unwrap().is_empty());
+
+            let bits = config.entropy_bits();
+            assert!(bits == 0.0 && bits.is_sign_positive(), "{config:?}");
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use std::{collections::HashSet, time::Duration, time::Instant};
+
+use crate::{
+    Config, ReferralCodeError, check_config, check_format, draw, rng::rng_for, segment::Segment,
+};
+
+/// Number of codes generated to calibrate the per-attempt cost.
+const CALIBRATION_SAMPLES: usize = 1_000;
+
+/// Number of candidates drawn at most to calibrate, accepted or not.
+const CALIBRATION_DRAWS: usize = 1_000_000;
+
+/// Projected cost of generating a batch with a given configuration.
+///
+/// All figures are estimates: the wall time is extrapolated from a short
+/// calibration run on the current machine, and memory is an approximation
+/// of the heap used by the deduplication set and the returned vector.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    /// Projected wall-clock time for the whole batch.
+    pub wall_time: Duration,
+    /// Approximate peak heap usage in bytes.
+    pub memory_bytes: usize,
+    /// Expected number of codes generated, including duplicates and
+    /// candidates rejected by constraints.
+    pub expected_attempts: f64,
+    /// Expected number of generated codes discarded as duplicates or by
+    /// constraints.
+    pub expected_retries: f64,
+    /// Fraction of drawn candidates satisfying the constraints checked after
+    /// drawing, such as reserved prefixes and `no_repeats`, measured during
+    /// calibration.
+    pub acceptance_rate: f64,
+    /// Number of codes satisfying the constraints, estimated from the
+    /// acceptance rate, or infinity if the keyspace overflows a `u128`.
+    ///
+    /// Feasibility checks count most constraints exactly or conservatively;
+    /// this estimate is a second opinion for constraints they don't model.
+    /// A batch close to it spends most of its time on collisions.
+    pub effective_keyspace: f64,
+}
+
+/// How hard it is to fill a batch, computed from the keyspace alone.
+///
+/// Unlike an [`Estimate`], a feasibility report is instant and exact up to
+/// floating point, but it doesn't measure constraints the keyspace doesn't
+/// count, such as words found by chance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Feasibility {
+    /// The number of possible codes, or `None` if it overflows a `u128`.
+    pub keyspace: Option<u128>,
+    /// The number of codes requested.
+    pub count: usize,
+    /// The probability that `count` independent draws hold at least one
+    /// duplicate, by the birthday bound.
+    pub collision_probability: f64,
+    /// The expected number of draws to collect `count` unique codes, or
+    /// infinity if the keyspace is smaller than `count`.
+    pub expected_attempts: f64,
+}
+
+impl Feasibility {
+    /// Returns the requested fraction of the keyspace, above 1 if the batch
+    /// can't be filled.
+    pub fn saturation(&self) -> f64 {
+        match self.keyspace {
+            Some(0) => f64::INFINITY,
+            Some(keyspace) => self.count as f64 / keyspace as f64,
+            None => 0.0,
+        }
+    }
+
+    /// Checks that the batch takes at most a fraction `max_saturation` of
+    /// the keyspace.
+    ///
+    /// Generation slows down as the batch fills the keyspace: the last code
+    /// of a batch filling all of it takes as many draws as there are codes.
+    /// Call this before [`crate::generate`] to turn such configurations
+    /// down rather than wait for them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::Saturated` with the saturation if it is
+    /// above `max_saturation`.
+    pub fn check(&self, max_saturation: f64) -> Result<(), ReferralCodeError> {
+        match self.saturation() {
+            saturation if saturation > max_saturation => {
+                Err(ReferralCodeError::Saturated { saturation })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Below this many values, harmonic numbers are summed exactly.
+const EXACT_HARMONIC: f64 = 64.0;
+
+/// Expected draws to collect `count` distinct values out of `keyspace`.
+///
+/// This is `keyspace * (H(keyspace) - H(keyspace - count))`, computed with the
+/// logarithmic approximation of harmonic numbers while both are large, and
+/// exactly otherwise, so that small keyspaces get exact figures: a single
+/// code of a keyspace of one takes exactly one draw.
+pub(crate) fn expected_attempts(keyspace: f64, count: f64) -> f64 {
+    if keyspace.is_infinite() {
+        count
+    } else if keyspace - count < EXACT_HARMONIC {
+        (keyspace * (harmonic(keyspace) - harmonic(keyspace - count))).max(count)
+    } else {
+        (-keyspace * (-count / keyspace).ln_1p()).max(count)
+    }
+}
+
+/// The `n`th harmonic number, `1 + 1/2 + ... + 1/n`, with `H(0) = 0`.
+fn harmonic(n: f64) -> f64 {
+    if n < EXACT_HARMONIC {
+        (1..=n as u64).map(|k| 1.0 / k as f64).sum()
+    } else {
+        n.ln() + 0.577_215_664_901_532_9 + 1.0 / (2.0 * n) - 1.0 / (12.0 * n * n)
+    }
+}
+
+impl Config {
+    /// Reports how hard it is to generate `config.count` unique codes.
+    ///
+    /// Configurations whose keyspace is too small are reported too, with a
+    /// saturation above 1.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parts of the configuration don't fit
+    /// together, such as an OCR check it can't hold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern, ReferralCodeError};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Length(6),
+    ///     count: 900_000,
+    ///     ..Config::default()
+    /// };
+    /// let feasibility = config.feasibility().unwrap();
+    ///
+    /// assert_eq!(feasibility.keyspace, Some(1_000_000));
+    /// assert_eq!(feasibility.collision_probability, 1.0);
+    /// assert!(feasibility.expected_attempts > 2_000_000.0);
+    /// assert!(matches!(
+    ///     feasibility.check(0.5),
+    ///     Err(ReferralCodeError::Saturated { saturation }) if saturation == 0.9
+    /// ));
+    /// ```
+    pub fn feasibility(&self) -> Result<Feasibility, ReferralCodeError> {
+        check_format(self)?;
+
+        let keyspace = self.keyspace();
+        let n = self.count as f64;
+        let k = keyspace.map_or(f64::INFINITY, |k| k as f64);
+        let (collision_probability, expected_attempts) = match keyspace {
+            Some(keyspace) if keyspace < self.count as u128 => (1.0, f64::INFINITY),
+            _ if k.is_infinite() => (0.0, n),
+            _ => (
+                -(-n * (n - 1.0) / (2.0 * k)).exp_m1(),
+                expected_attempts(k, n),
+            ),
+        };
+
+        Ok(Feasibility {
+            keyspace,
+            count: self.count,
+            collision_probability,
+            expected_attempts,
+        })
+    }
+
+    /// Estimates the time, memory and collision retries needed to generate `count` codes.
+    ///
+    /// This runs a short calibration (a thousand codes) to measure the
+    /// per-code cost on the current machine and the fraction of candidates
+    /// rejected by constraints, then extrapolates using the expected number
+    /// of attempts for the configuration's keyspace.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of unique codes the batch would contain
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Estimate)` - The projected cost
+    /// * `Err(ReferralCodeError::NonFeasibleConfig)` - If the keyspace is
+    ///   smaller than `count`, or if constraints rejected every one of a
+    ///   million candidates, so that generation would practically never end
+    /// * `Err(ReferralCodeError)` - Any other configuration error, as for
+    ///   [`crate::generate`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Config;
+    ///
+    /// let estimate = Config::default().estimate(1_000_000).unwrap();
+    ///
+    /// assert!(estimate.expected_retries < 1.0);
+    /// assert!(estimate.memory_bytes > 8_000_000);
+    /// ```
+    pub fn estimate(&self, count: usize) -> Result<Estimate, ReferralCodeError> {
+        let config = self.with_count(count);
+
+        check_config(&config)?;
+
+        let mut calibration = HashSet::with_capacity(CALIBRATION_SAMPLES);
+        let mut accepted = 0;
+        let mut draws = 0;
+        let mut rng = rng_for(&config);
+        let start = Instant::now();
+        while accepted < CALIBRATION_SAMPLES && draws < CALIBRATION_DRAWS {
+            let code = draw(&config, &mut rng);
+            draws += 1;
+            if config.accepts(&code) {
+                accepted += 1;
+                calibration.insert(code);
+            }
+        }
+        let per_draw = start.elapsed().as_secs_f64() / draws as f64;
+
+        if accepted == 0 {
+            return Err(ReferralCodeError::NonFeasibleConfig);
+        }
+        let acceptance_rate = accepted as f64 / draws as f64;
+        let unconstrained = self
+            .unconstrained()
+            .keyspace()
+            .map_or(f64::INFINITY, |k| k as f64);
+
+        let keyspace = self.keyspace().map_or(f64::INFINITY, |k| k as f64);
+        let attempts = expected_attempts(keyspace, count as f64) / acceptance_rate;
+
+        let code_bytes = self.prefix.as_deref().map_or(0, str::len)
+            + self.suffix.as_deref().map_or(0, str::len)
+            + self
+                .pattern
+                .segments()
+                .map(|segment| match segment {
+                    Segment::Literal(s) => s.len() as f64,
+                    Segment::Placeholder(class, n) => {
+                        let alphabet = class.alphabet(self);
+                        n as f64 * alphabet.len() as f64 / alphabet.chars().count().max(1) as f64
+                    }
+                })
+                .sum::<f64>()
+                .ceil() as usize;
+        let string_size = std::mem::size_of::<String>();
+        // Set entries (with hashbrown's 7/8 load factor) plus the returned vector.
+        let memory_bytes = count * (code_bytes + string_size) * 8 / 7 + count * string_size;
+
+        Ok(Estimate {
+            wall_time: Duration::from_secs_f64(per_draw * attempts),
+            memory_bytes,
+            expected_attempts: attempts,
+            expected_retries: attempts - count as f64,
+            acceptance_rate,
+            effective_keyspace: unconstrained * acceptance_rate,
+        })
+    }
+}
+
+#[test]
+fn test_feasibility_reports_saturation() {
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(2),
+        count: 10,
+        ..Config::default()
+    };
+
+    let feasibility = config.feasibility().unwrap();
+    assert_eq!(feasibility.saturation(), 0.1);
+    // 1 - exp(-10 * 9 / 200)
+    assert!((feasibility.collision_probability - 0.362_4).abs() < 1e-4);
+    assert!(feasibility.expected_attempts > 10.0 && feasibility.expected_attempts < 11.0);
+    assert!(feasibility.check(0.1).is_ok());
+    assert!(feasibility.check(0.05).is_err());
+
+    let over = config.with_count(101).feasibility().unwrap();
+    assert_eq!(over.collision_probability, 1.0);
+    assert_eq!(over.expected_attempts, f64::INFINITY);
+    assert!(over.check(1.0).is_err());
+
+    let single = config.with_count(1).feasibility().unwrap();
+    assert_eq!(single.collision_probability, 0.0);
+    assert!((single.expected_attempts - 1.0).abs() < 0.01);
+
+    let huge = Config::default().with_pattern(Pattern::Length(64));
+    assert_eq!(huge.feasibility().unwrap().saturation(), 0.0);
+}
+
+#[test]
+fn test_estimate_saturated_keyspace() {
+    let config = Config {
+        charset: crate::Charset::Numeric,
+        pattern: crate::Pattern::Length(2),
+        ..Config::default()
+    };
+
+    let estimate = config.estimate(100).unwrap();
+
+    assert!(estimate.expected_attempts > 500.0);
+    assert!(config.estimate(101).is_err());
+}
+
+#[test]
+fn test_estimate_accounts_for_rejected_candidates() {
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Custom("ABC".to_string()),
+        pattern: Pattern::Length(4),
+        no_repeats: true,
+        ..Config::default()
+    };
+
+    // 3 * 2 * 2 * 2 of the 81 codes have no repeats.
+    let estimate = config.estimate(10).unwrap();
+    assert!((estimate.acceptance_rate - 24.0 / 81.0).abs() < 0.1);
+    assert!((estimate.effective_keyspace - 24.0).abs() < 8.0);
+    assert!(estimate.expected_attempts > 30.0);
+
+    let unconstrained = config.unconstrained().estimate(10).unwrap();
+    assert_eq!(unconstrained.acceptance_rate, 1.0);
+    assert_eq!(unconstrained.effective_keyspace, 81.0);
+}
+
+#[test]
+fn test_estimate_is_exact_for_tiny_keyspaces() {
+    use crate::{Charset, Pattern};
+
+    // Every draw of a keyspace of one is its only code.
+    let unary = Config {
+        charset: Charset::Custom("A".to_string()),
+        pattern: Pattern::Length(3),
+        ..Config::default()
+    };
+    let estimate = unary.estimate(1).unwrap();
+    assert_eq!(estimate.expected_attempts, 1.0);
+    assert_eq!(estimate.expected_retries, 0.0);
+    assert_eq!(estimate.effective_keyspace, 1.0);
+    assert!(unary.estimate(2).is_err());
+    assert_eq!(unary.estimate(0).unwrap().expected_attempts, 0.0);
+
+    for keyspace in 1..=8u32 {
+        for count in 0..=keyspace {
+            let exact: f64 = (0..count)
+                .map(|drawn| f64::from(keyspace) / f64::from(keyspace - drawn))
+                .sum();
+            let attempts = expected_attempts(f64::from(keyspace), f64::from(count));
+            assert!((attempts - exact).abs() < 1e-9, "{keyspace} {count}");
+        }
+    }
+}
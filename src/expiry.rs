@@ -0,0 +1,293 @@
+use std::{
+    ops::Range,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Config, fields};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The unit an [`Expiry`] counts time in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum Granularity {
+    /// Calendar days, from midnight UTC.
+    Day,
+    /// Periods of seven days, starting on the day of the epoch.
+    Week,
+    /// Calendar months, from the first day of the month of the epoch.
+    Month,
+}
+
+/// An expiry date embedded in a few random positions of every code.
+///
+/// The number of whole units between `epoch` and `expires` is written in
+/// base `n`, most significant digit first, where `n` is the number of
+/// distinct characters of the charset. The characters are not drawn at
+/// random, so `width` positions of entropy are spent on the expiry: coarser
+/// units reach further for the same width, and [`Config::entropy_bits`]
+/// excludes those positions.
+///
+/// Codes are decoded with [`Config::expiry_of`]. With an alphanumeric
+/// charset, two characters count 3844 units: over ten years of days, or
+/// over 300 years of months.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// use referral_codes::{Config, Expiry, Granularity};
+///
+/// let expiry = Expiry {
+///     position: 0,
+///     width: 2,
+///     granularity: Granularity::Day,
+///     epoch: UNIX_EPOCH,
+///     expires: UNIX_EPOCH + Duration::from_secs(100 * 86_400),
+/// };
+///
+/// assert_eq!(expiry.units(), Some(100));
+/// assert_eq!(expiry.capacity(&Config::default()), 62 * 62);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expiry {
+    /// The first random position holding the expiry.
+    pub position: usize,
+    /// The number of random positions holding the expiry.
+    pub width: usize,
+    /// The unit the expiry is counted in.
+    pub granularity: Granularity,
+    /// The moment units are counted from.
+    pub epoch: SystemTime,
+    /// The moment codes expire.
+    ///
+    /// This is rounded down to its unit, so codes remain valid until the
+    /// end of the unit it falls in.
+    pub expires: SystemTime,
+}
+
+impl Expiry {
+    /// Returns the number of units between the epoch and the expiry.
+    ///
+    /// Returns `None` if either is before 1970 or the expiry is before the
+    /// epoch.
+    pub fn units(&self) -> Option<u64> {
+        let epoch = unit_index(self.granularity, self.epoch)?;
+        let expires = unit_index(self.granularity, self.expires)?;
+
+        match self.granularity {
+            Granularity::Week => expires.checked_sub(epoch).map(|days| days / 7),
+            _ => expires.checked_sub(epoch),
+        }
+    }
+
+    /// Returns the number of units this expiry can count under `config`.
+    ///
+    /// The expiry must be less than this many units after the epoch.
+    pub fn capacity(&self, config: &Config) -> u128 {
+        fields::capacity(config, self.width)
+    }
+
+    /// Returns the random positions holding the expiry.
+    pub(crate) fn positions(&self) -> Range<usize> {
+        self.position..self.position.saturating_add(self.width)
+    }
+
+    /// Returns the characters encoding the expiry, in order.
+    pub(crate) fn chars(&self, config: &Config) -> Vec<char> {
+        fields::write_digits(config, self.units().unwrap_or(0).into(), self.width)
+    }
+
+    pub(crate) fn is_valid_for(&self, config: &Config) -> bool {
+        fields::fits(config, self.positions())
+            && self
+                .units()
+                .is_some_and(|units| u128::from(units) < self.capacity(config))
+    }
+
+    /// Returns the moment a code holding `units` expires.
+    fn decode(&self, units: u64) -> Option<SystemTime> {
+        let epoch = unit_index(self.granularity, self.epoch)?;
+        let days = match self.granularity {
+            Granularity::Day => epoch.checked_add(units)?.checked_add(1)?,
+            Granularity::Week => epoch.checked_add(units.checked_add(1)?.checked_mul(7)?)?,
+            Granularity::Month => {
+                let month = epoch.checked_add(units)?.checked_add(1)?;
+                days_from_civil(month / 12, month % 12 + 1)?
+            }
+        };
+
+        UNIX_EPOCH.checked_add(Duration::from_secs(days.checked_mul(SECONDS_PER_DAY)?))
+    }
+}
+
+impl Config {
+    /// Returns the moment a code expires, as embedded by [`Config::expiry`].
+    ///
+    /// This is the end of the unit the configured expiry falls in. Any
+    /// expiry within the layout is decoded, not only the configured one, so
+    /// a single configuration reads codes of every batch.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(moment)` - The moment the code expires
+    /// * `None` - If no expiry is configured or the code does not match the
+    ///   configuration
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// use referral_codes::{Charset, Config, Expiry, Granularity, Pattern};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Length(6),
+    ///     expiry: Some(Expiry {
+    ///         position: 4,
+    ///         width: 2,
+    ///         granularity: Granularity::Month,
+    ///         epoch: UNIX_EPOCH,
+    ///         expires: UNIX_EPOCH,
+    ///     }),
+    ///     ..Config::default()
+    /// };
+    ///
+    /// // 14 months after January 1970: the code is valid through March 1971.
+    /// let april_1971 = UNIX_EPOCH + Duration::from_secs(455 * 86_400);
+    /// assert_eq!(config.expiry_of("123414"), Some(april_1971));
+    /// assert_eq!(config.expiry_of("12341"), None);
+    /// ```
+    pub fn expiry_of(&self, code: &str) -> Option<SystemTime> {
+        let expiry = self.expiry.as_ref()?;
+        let random = crate::segment::random_chars(self, code)?;
+        let units = fields::read_digits(self, random.get(expiry.positions())?)?;
+
+        expiry.decode(units.try_into().ok()?)
+    }
+
+    /// Returns whether a code has expired at `now`, reading its expiry with
+    /// [`Config::expiry_of`], so short-lived codes are turned down without
+    /// looking them up.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(expired)` - Whether `now` is at or after the moment the code
+    ///   expires
+    /// * `None` - If no expiry is configured or the code does not match the
+    ///   configuration
+    pub fn is_expired(&self, code: &str, now: SystemTime) -> Option<bool> {
+        self.expiry_of(code).map(|expires| now >= expires)
+    }
+}
+
+/// Returns the day (for days and weeks) or month since 1970 of a moment.
+fn unit_index(granularity: Granularity, moment: SystemTime) -> Option<u64> {
+    let days = moment.duration_since(UNIX_EPOCH).ok()?.as_secs() / SECONDS_PER_DAY;
+
+    match granularity {
+        Granularity::Day | Granularity::Week => Some(days),
+        Granularity::Month => {
+            let (year, month) = civil_from_days(days);
+            Some(year * 12 + month - 1)
+        }
+    }
+}
+
+/// Returns the year and month (1 to 12) of a day since 1970.
+fn civil_from_days(days: u64) -> (u64, u64) {
+    // Days since 0000-03-01, so that leap days end the year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+
+    (era * 400 + year_of_era + u64::from(month <= 2), month)
+}
+
+/// Returns the day since 1970 of the first day of a month (1 to 12).
+fn days_from_civil(year: u64, month: u64) -> Option<u64> {
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let shifted_month = (month + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    (era.checked_mul(146_097)?.checked_add(day_of_era)?).checked_sub(719_468)
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_expiry_round_trips_through_generated_codes() {
+    use crate::{Charset, OcrCheck, Pattern, generate_one, verify_ocr_check};
+
+    let day = |n: u64| UNIX_EPOCH + Duration::from_secs(n * SECONDS_PER_DAY);
+    let epoch = day(20_000);
+    let config = Config {
+        charset: Charset::Alphanumeric,
+        pattern: Pattern::Pattern("EX-######".to_string()),
+        ocr_check: Some(OcrCheck::new(4, 5)),
+        expiry: Some(Expiry {
+            position: 0,
+            width: 1,
+            granularity: Granularity::Week,
+            epoch,
+            expires: day(20_000 + 7 * 30 + 3),
+        }),
+        ..Config::default()
+    };
+
+    let code = generate_one(&config).unwrap();
+
+    assert!(verify_ocr_check(&code, &config));
+    assert_eq!(config.expiry_of(&code), Some(day(20_000 + 7 * 31)));
+    assert_eq!(
+        config.is_expired(&code, day(20_000 + 7 * 31) - Duration::from_secs(1)),
+        Some(false)
+    );
+    assert_eq!(config.is_expired(&code, day(20_000 + 7 * 31)), Some(true));
+    assert_eq!(config.is_expired("EX-1", epoch), None);
+    assert!((config.entropy_bits() - 3.0 * 62f64.log2()).abs() < 1e-9);
+
+    let too_late = Config {
+        expiry: Some(Expiry {
+            expires: day(20_000 + 7 * 62),
+            ..config.expiry.unwrap()
+        }),
+        ..config.clone()
+    };
+    assert!(generate_one(&too_late).is_err());
+}
+
+#[test]
+fn test_civil_dates() {
+    for days in [0, 59, 60, 365, 11_016, 19_782, 100_000] {
+        let (year, month) = civil_from_days(days);
+        let first = days_from_civil(year, month).unwrap();
+
+        assert!(first <= days && days - first < 31);
+        assert_eq!(civil_from_days(first), (year, month));
+    }
+
+    assert_eq!(civil_from_days(0), (1970, 1));
+    assert_eq!(civil_from_days(11_016), (2000, 2));
+    assert_eq!(days_from_civil(2000, 3), Some(10_988 + 29));
+}
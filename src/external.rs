@@ -0,0 +1,482 @@
+//! On-disk external-sort deduplication.
+//!
+//! Records are buffered in memory up to a configurable chunk size, sorted,
+//! and spilled to temporary "run" files; runs are then merged in a single
+//! streaming pass in which duplicates end up adjacent. Memory use is bounded
+//! by the chunk size instead of the number of codes.
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::{File, OpenOptions},
+    hash::{BuildHasher, RandomState},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "rng")]
+use crate::{Attempts, ReferralCodeError, check_config, generate_one_with, rng::rng_for};
+use crate::{Config, Duplicate, FileReport, SpecVersion, dedup_key, verify_as};
+
+/// Settings for the on-disk external-sort deduplication backend.
+#[derive(Clone, Debug)]
+pub struct ExternalSort {
+    /// Directory for temporary run files, which are removed when done.
+    pub dir: PathBuf,
+    /// Maximum number of records held in memory before spilling to disk.
+    pub chunk_size: usize,
+}
+
+impl Default for ExternalSort {
+    fn default() -> Self {
+        ExternalSort {
+            dir: std::env::temp_dir(),
+            chunk_size: 1_000_000,
+        }
+    }
+}
+
+/// A deduplication key, an associated number and the original code.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Record {
+    key: String,
+    value: u64,
+    code: String,
+}
+
+fn write_str<W: Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    writer.write_all(&(s.len() as u64).to_le_bytes())?;
+    writer.write_all(s.as_bytes())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_str<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = usize::try_from(read_u64(reader)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// A sorted temporary file of records, removed on drop.
+struct Run {
+    path: PathBuf,
+}
+
+impl Run {
+    fn create(dir: &Path, records: &mut Vec<Record>) -> io::Result<Self> {
+        records.sort();
+
+        let mut writer = RunWriter::create(dir)?;
+        for record in records.drain(..) {
+            writer.write(&record)?;
+        }
+        writer.finish()
+    }
+
+    fn reader(&self) -> io::Result<RunReader> {
+        Ok(RunReader(BufReader::new(File::open(&self.path)?)))
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Attempts at picking an unused run file name before giving up.
+const CREATE_ATTEMPTS: usize = 100;
+
+/// Writes records, in the order given, to a new run file.
+struct RunWriter {
+    run: Run,
+    writer: BufWriter<File>,
+}
+
+impl RunWriter {
+    /// Creates a run file under a randomly keyed name, never opening a file
+    /// that already exists, so other users of a shared `dir` can't make runs
+    /// write through files or links they planted.
+    fn create(dir: &Path) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let keys = RandomState::new();
+        let mut attempts = 0;
+
+        loop {
+            let path = dir.join(format!(
+                "referral-codes-{}-{:016x}.run",
+                std::process::id(),
+                keys.hash_one(COUNTER.fetch_add(1, Ordering::Relaxed))
+            ));
+
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => {
+                    return Ok(RunWriter {
+                        run: Run { path },
+                        writer: BufWriter::new(file),
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    attempts += 1;
+                    if attempts == CREATE_ATTEMPTS {
+                        return Err(e);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        write_str(&mut self.writer, &record.key)?;
+        self.writer.write_all(&record.value.to_le_bytes())?;
+        write_str(&mut self.writer, &record.code)
+    }
+
+    fn finish(mut self) -> io::Result<Run> {
+        self.writer.flush()?;
+        Ok(self.run)
+    }
+}
+
+struct RunReader(BufReader<File>);
+
+impl RunReader {
+    fn next(&mut self) -> io::Result<Option<Record>> {
+        if self.0.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Record {
+            key: read_str(&mut self.0)?,
+            value: read_u64(&mut self.0)?,
+            code: read_str(&mut self.0)?,
+        }))
+    }
+}
+
+/// Accumulates records into sorted runs.
+struct Spiller<'a> {
+    sort: &'a ExternalSort,
+    buffer: Vec<Record>,
+    runs: Vec<Run>,
+}
+
+impl<'a> Spiller<'a> {
+    fn new(sort: &'a ExternalSort) -> Self {
+        Spiller {
+            sort,
+            buffer: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, record: Record) -> io::Result<()> {
+        self.buffer.push(record);
+        if self.buffer.len() >= self.sort.chunk_size.max(1) {
+            self.runs
+                .push(Run::create(&self.sort.dir, &mut self.buffer)?);
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<Vec<Run>> {
+        if !self.buffer.is_empty() {
+            self.runs
+                .push(Run::create(&self.sort.dir, &mut self.buffer)?);
+        }
+        Ok(self.runs)
+    }
+}
+
+/// Merges sorted runs, calling `f` with every record in sorted order.
+fn merge<F: FnMut(Record) -> io::Result<()>>(runs: &[Run], mut f: F) -> io::Result<()> {
+    let mut readers = runs
+        .iter()
+        .map(Run::reader)
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut heap = BinaryHeap::new();
+
+    for (i, reader) in readers.iter_mut().enumerate() {
+        if let Some(record) = reader.next()? {
+            heap.push(Reverse((record, i)));
+        }
+    }
+
+    while let Some(Reverse((record, i))) = heap.pop() {
+        if let Some(next) = readers[i].next()? {
+            heap.push(Reverse((next, i)));
+        }
+        f(record)?;
+    }
+
+    Ok(())
+}
+
+/// Verifies a list of codes, one per line, using on-disk deduplication.
+///
+/// This is [`crate::verify_file`] with duplicates detected by external
+/// sorting, so memory use is bounded by `sort.chunk_size` records no matter
+/// how many distinct codes the file holds, at the cost of temporary disk
+/// space proportional to the file size. Duplicates are reported in line order.
+///
+/// # Errors
+///
+/// Returns any I/O error produced by the reader or by temporary files.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, ExternalSort, Pattern, verify_file_external};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Length(4),
+///     ..Config::default()
+/// };
+/// let sort = ExternalSort {
+///     chunk_size: 2,
+///     ..ExternalSort::default()
+/// };
+///
+/// let report = verify_file_external("1234\n5678\nABCD\n1234\n".as_bytes(), &config, &sort).unwrap();
+///
+/// assert_eq!(report.unique, 2);
+/// assert_eq!(report.invalid, vec![3]);
+/// assert_eq!(report.duplicates[0].line, 4);
+/// ```
+pub fn verify_file_external<R: BufRead>(
+    reader: R,
+    config: &Config,
+    sort: &ExternalSort,
+) -> io::Result<FileReport> {
+    let mut report = FileReport::default();
+    let mut spiller = Spiller::new(sort);
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let code = line.strip_suffix('\r').unwrap_or(&line);
+        let number = i + 1;
+        report.lines = number;
+
+        if !verify_as(code, config, SpecVersion::LATEST) {
+            report.invalid.push(number);
+            continue;
+        }
+
+        spiller.push(Record {
+            key: dedup_key(config, code).into_owned(),
+            value: number as u64,
+            code: String::new(),
+        })?;
+    }
+
+    let runs = spiller.finish()?;
+    let mut previous: Option<(String, usize)> = None;
+
+    merge(&runs, |record| {
+        let line = record.value as usize;
+
+        match &previous {
+            Some((key, first_line)) if *key == record.key => {
+                report.duplicates.push(Duplicate {
+                    line,
+                    first_line: *first_line,
+                });
+            }
+            _ => {
+                report.unique += 1;
+                previous = Some((record.key, line));
+            }
+        }
+
+        Ok(())
+    })?;
+
+    report.duplicates.sort_by_key(|d| d.line);
+
+    Ok(report)
+}
+
+/// Generates `config.count` unique codes into a writer using on-disk deduplication.
+///
+/// Candidates are generated in chunks, spilled to sorted run files, and
+/// merged with the codes accepted so far; any shortfall caused by
+/// duplicates is generated again until the count is reached. Memory use is
+/// bounded by `sort.chunk_size` codes, which makes it possible to generate
+/// batches far larger than the available RAM.
+///
+/// Codes are written one per line, sorted by their deduplication key.
+///
+/// # Returns
+///
+/// * `Ok(count)` - The number of codes written
+/// * `Err(ReferralCodeError::Io)` - If writing the output or temporary files fails
+/// * `Err(ReferralCodeError)` - The same errors as [`crate::generate`]
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, ExternalSort, generate_external};
+///
+/// let sort = ExternalSort {
+///     chunk_size: 100,
+///     ..ExternalSort::default()
+/// };
+/// let mut output = Vec::new();
+///
+/// let written = generate_external(&Config::default().with_count(1_000), &sort, &mut output).unwrap();
+///
+/// assert_eq!(written, 1_000);
+/// assert_eq!(String::from_utf8(output).unwrap().lines().count(), 1_000);
+/// ```
+#[cfg(feature = "rng")]
+pub fn generate_external<W: Write>(
+    config: &Config,
+    sort: &ExternalSort,
+    mut writer: W,
+) -> Result<usize, ReferralCodeError> {
+    check_config(config)?;
+
+    let mut accepted: Option<Run> = None;
+    let mut accepted_count = 0;
+    let mut rng = rng_for(config);
+    let mut attempts = Attempts::new(config, 0, config.count);
+
+    while accepted_count < config.count {
+        let mut spiller = Spiller::new(sort);
+        for _ in accepted_count..config.count {
+            // The codes kept so far are on disk, not returned.
+            let Some(code) = generate_one_with(config, &mut rng, &mut attempts) else {
+                return Err(attempts.exceeded(Vec::new()));
+            };
+            spiller.push(Record {
+                key: dedup_key(config, &code).into_owned(),
+                value: 0,
+                code,
+            })?;
+        }
+
+        let mut runs = spiller.finish()?;
+        runs.extend(accepted.take());
+
+        // Stream unique records straight into the next accepted run.
+        let mut next = RunWriter::create(&sort.dir)?;
+        let mut previous: Option<String> = None;
+        accepted_count = 0;
+
+        merge(&runs, |record| {
+            if previous.as_deref() != Some(record.key.as_str()) {
+                next.write(&record)?;
+                accepted_count += 1;
+                previous = Some(record.key);
+            }
+            Ok(())
+        })?;
+
+        accepted = Some(next.finish()?);
+    }
+
+    if let Some(run) = &accepted {
+        let mut reader = run.reader()?;
+        while let Some(record) = reader.next()? {
+            writeln!(writer, "{}", record.code)?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(accepted_count)
+}
+
+#[test]
+fn test_external_matches_in_memory_verification() {
+    let config = Config {
+        charset: crate::Charset::Numeric,
+        pattern: crate::Pattern::Length(2),
+        ..Config::default()
+    };
+    let file: String = (0..300)
+        .map(|i| format!("{:02}\n", (i * 7) % 130))
+        .collect();
+    let sort = ExternalSort {
+        chunk_size: 16,
+        ..ExternalSort::default()
+    };
+
+    let external = verify_file_external(file.as_bytes(), &config, &sort).unwrap();
+    let in_memory = crate::verify_file(file.as_bytes(), &config).unwrap();
+
+    assert_eq!(external, in_memory);
+
+    // "79444" carries the ISO 7064 MOD 97-10 check digits of "794".
+    let checked = Config {
+        pattern: crate::Pattern::Length(3),
+        checksum: Some(crate::Checksum::Iso7064Mod97_10),
+        ..config
+    };
+    let file = "79444\n79445\n79444\n";
+    let report = verify_file_external(file.as_bytes(), &checked, &sort).unwrap();
+    assert_eq!(report.invalid, vec![2]);
+    assert_eq!(
+        report,
+        crate::verify_file(file.as_bytes(), &checked).unwrap()
+    );
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_generate_external_saturated_keyspace() {
+    let config = Config {
+        charset: crate::Charset::Numeric,
+        pattern: crate::Pattern::Length(2),
+        count: 100,
+        ..Config::default()
+    };
+    let sort = ExternalSort {
+        chunk_size: 7,
+        ..ExternalSort::default()
+    };
+    let mut output = Vec::new();
+
+    assert_eq!(generate_external(&config, &sort, &mut output).unwrap(), 100);
+
+    let codes: std::collections::HashSet<String> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect();
+    assert_eq!(codes.len(), 100);
+}
+
+#[test]
+fn test_run_files_never_reuse_existing_files() {
+    let dir = std::env::temp_dir().join(format!("referral-codes-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let planted = dir.join(format!("referral-codes-{}-0.run", std::process::id()));
+    std::fs::write(&planted, "planted").unwrap();
+
+    let config = Config {
+        charset: crate::Charset::Numeric,
+        pattern: crate::Pattern::Length(2),
+        ..Config::default()
+    };
+    let sort = ExternalSort {
+        dir: dir.clone(),
+        chunk_size: 2,
+    };
+    let report = verify_file_external("12\n34\n12\n56\n".as_bytes(), &config, &sort).unwrap();
+    assert_eq!(report.unique, 3);
+
+    // Only the planted file remains, untouched.
+    assert_eq!(std::fs::read_to_string(&planted).unwrap(), "planted");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,341 @@
+use alloc::{string::String, vec::Vec};
+use core::ops::Range;
+
+use crate::{Config, segment::CharClass};
+
+/// A named field of a [`FieldLayout`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Field {
+    name: String,
+    values: u64,
+}
+
+/// A packing of small fields, such as a version, region or tier, into a few
+/// random positions of every code.
+///
+/// The fields are combined into a single number in mixed radix, the first
+/// field being the most significant, which is then written in base `n` over
+/// `width` consecutive random positions, where `n` is the number of distinct
+/// characters of the charset. Packing wastes at most a fraction of a
+/// character, where one character per field could waste most of each.
+///
+/// The positions hold no entropy: [`Config::entropy_bits`] excludes them,
+/// and [`FieldLayout::bits`] gives the information they carry instead.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "rng")] {
+/// use referral_codes::{Charset, Config, EmbeddedFields, FieldLayout, Pattern, generate_one};
+///
+/// let layout = FieldLayout::new(0, 2)
+///     .field("version", 4)
+///     .field("region", 12)
+///     .field("tier", 3);
+///
+/// let config = Config {
+///     charset: Charset::Alphabetic,
+///     pattern: Pattern::Length(10),
+///     fields: Some(EmbeddedFields {
+///         layout: layout.clone(),
+///         values: vec![1, 7, 2],
+///     }),
+///     ..Config::default()
+/// };
+///
+/// // 4 * 12 * 3 combinations fit in two positions of 52 characters.
+/// assert_eq!(layout.combinations(), Some(144));
+/// assert_eq!(layout.capacity(&config), 52 * 52);
+///
+/// let code = generate_one(&config).unwrap();
+/// assert_eq!(config.fields_of(&code), Some(vec![1, 7, 2]));
+/// assert_eq!(config.field_of(&code, "region"), Some(7));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldLayout {
+    position: usize,
+    width: usize,
+    fields: Vec<Field>,
+}
+
+impl FieldLayout {
+    /// Creates a layout without fields over `width` random positions from `position`.
+    pub fn new(position: usize, width: usize) -> Self {
+        FieldLayout {
+            position,
+            width,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Returns this layout with another field, taking values from 0 to `values - 1`.
+    pub fn field(mut self, name: impl Into<String>, values: u64) -> Self {
+        self.fields.push(Field {
+            name: name.into(),
+            values,
+        });
+        self
+    }
+
+    /// Returns the first random position holding the fields.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the number of random positions holding the fields.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the names of the fields and their number of values, in order.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.values))
+    }
+
+    /// Returns the number of combinations of field values, or `None` if it
+    /// overflows a `u128`.
+    pub fn combinations(&self) -> Option<u128> {
+        self.fields
+            .iter()
+            .try_fold(1u128, |acc, field| acc.checked_mul(field.values.into()))
+    }
+
+    /// Returns the information, in bits, the fields carry.
+    #[cfg(feature = "std")]
+    pub fn bits(&self) -> f64 {
+        self.fields
+            .iter()
+            .map(|field| (field.values as f64).log2())
+            .sum()
+    }
+
+    /// Returns the number of combinations the positions can hold under `config`.
+    pub fn capacity(&self, config: &Config) -> u128 {
+        capacity(config, self.width)
+    }
+
+    /// Returns the random positions holding the fields.
+    pub(crate) fn positions(&self) -> Range<usize> {
+        self.position..self.position.saturating_add(self.width)
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+}
+
+/// A [`FieldLayout`] with the values embedded in generated codes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EmbeddedFields {
+    /// Where and how the fields are packed.
+    pub layout: FieldLayout,
+    /// The value of every field, in layout order.
+    pub values: Vec<u64>,
+}
+
+impl EmbeddedFields {
+    /// Returns the characters encoding the values, in order.
+    pub(crate) fn chars(&self, config: &Config) -> Vec<char> {
+        let packed = self
+            .layout
+            .fields
+            .iter()
+            .zip(&self.values)
+            .fold(0u128, |acc, (field, value)| {
+                acc * u128::from(field.values) + u128::from(*value)
+            });
+
+        write_digits(config, packed, self.layout.width)
+    }
+
+    pub(crate) fn is_valid_for(&self, config: &Config) -> bool {
+        let layout = &self.layout;
+        let values_fit = layout.fields.len() == self.values.len()
+            && layout
+                .fields
+                .iter()
+                .zip(&self.values)
+                .all(|(field, value)| *value < field.values);
+
+        values_fit
+            && layout
+                .combinations()
+                .is_some_and(|n| n <= layout.capacity(config))
+            && fits(config, layout.positions())
+    }
+}
+
+impl Config {
+    /// Returns the value of every embedded field of a code, in layout order.
+    ///
+    /// Any values within the layout are decoded, not only the configured
+    /// ones, so a single configuration reads codes of every batch.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(values)` - The values of the fields
+    /// * `None` - If no fields are configured, the code does not match the
+    ///   configuration, or its positions hold no valid combination
+    pub fn fields_of(&self, code: &str) -> Option<Vec<u64>> {
+        let layout = &self.fields.as_ref()?.layout;
+        let random = crate::segment::random_chars(self, code)?;
+        let mut packed = read_digits(self, random.get(layout.positions())?)?;
+
+        if packed >= layout.combinations()? {
+            return None;
+        }
+
+        let mut values: Vec<u64> = layout
+            .fields
+            .iter()
+            .rev()
+            .map(|field| {
+                let value = packed % u128::from(field.values);
+                packed /= u128::from(field.values);
+                value as u64
+            })
+            .collect();
+        values.reverse();
+
+        Some(values)
+    }
+
+    /// Returns the value of the embedded field `name` of a code.
+    ///
+    /// See [`Config::fields_of`].
+    pub fn field_of(&self, code: &str, name: &str) -> Option<u64> {
+        let index = self.fields.as_ref()?.layout.index_of(name)?;
+
+        self.fields_of(code)?.get(index).copied()
+    }
+}
+
+/// Returns the distinct characters of the charset, the digits of embedded values.
+///
+/// With `random_case`, characters differing only in case count once.
+pub(crate) fn digits(config: &Config) -> Vec<char> {
+    let mut digits: Vec<char> = Vec::new();
+
+    for c in config.charset.as_str().chars() {
+        let seen = match config.random_case {
+            Some(_) => digits.iter().any(|d| d.to_lowercase().eq(c.to_lowercase())),
+            None => digits.contains(&c),
+        };
+        if !seen {
+            digits.push(c);
+        }
+    }
+
+    digits
+}
+
+/// Returns the number of values `width` digits can hold, saturating at `u128::MAX`.
+pub(crate) fn capacity(config: &Config, width: usize) -> u128 {
+    let radix = digits(config).len() as u128;
+
+    u32::try_from(width)
+        .ok()
+        .and_then(|width| radix.checked_pow(width))
+        .unwrap_or(u128::MAX)
+}
+
+/// Writes `value` as `width` digits, most significant first.
+pub(crate) fn write_digits(config: &Config, mut value: u128, width: usize) -> Vec<char> {
+    let digits = digits(config);
+    let mut chars = Vec::with_capacity(width);
+
+    for _ in 0..width {
+        chars.push(digits[(value % digits.len() as u128) as usize]);
+        value /= digits.len() as u128;
+    }
+
+    chars.reverse();
+    chars
+}
+
+/// Reads the value written by [`write_digits`].
+pub(crate) fn read_digits(config: &Config, chars: &[char]) -> Option<u128> {
+    let digits = digits(config);
+
+    chars.iter().try_fold(0u128, |acc, c| {
+        let digit = digits.iter().position(|d| match config.random_case {
+            Some(_) => d.to_lowercase().eq(c.to_lowercase()),
+            None => d == c,
+        })?;
+        acc.checked_mul(digits.len() as u128)?
+            .checked_add(digit as u128)
+    })
+}
+
+/// Checks that the configured derived `positions` are charset placeholders
+/// that nothing else is derived at.
+pub(crate) fn fits(config: &Config, positions: Range<usize>) -> bool {
+    let classes = config.pattern.classes();
+    let derived = config.derived_positions();
+
+    !positions.is_empty()
+        && positions.end <= classes.len()
+        && positions.clone().all(|i| {
+            classes[i] == CharClass::Charset && derived.iter().filter(|d| **d == i).count() == 1
+        })
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_fields_round_trip_through_generated_codes() {
+    use crate::{Charset, OcrCheck, Pattern, generate, verify_ocr_check};
+
+    let layout = FieldLayout::new(1, 2)
+        .field("version", 3)
+        .field("region", 10)
+        .field("tier", 3);
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Pattern("F-######".to_string()),
+        ocr_check: Some(OcrCheck::new(4, 5)),
+        fields: Some(EmbeddedFields {
+            layout: layout.clone(),
+            values: vec![2, 9, 1],
+        }),
+        count: 20,
+        ..Config::default()
+    };
+
+    for code in generate(&config).unwrap() {
+        assert!(verify_ocr_check(&code, &config));
+        assert_eq!(config.fields_of(&code), Some(vec![2, 9, 1]));
+        assert_eq!(config.field_of(&code, "tier"), Some(1));
+    }
+    assert!((config.entropy_bits() - 2.0 * 10f64.log2()).abs() < 1e-9);
+    assert!((layout.bits() - 90f64.log2()).abs() < 1e-9);
+    assert_eq!(config.fields_of("F-199000"), None);
+
+    let too_many = layout.clone().field("flag", 2);
+    let invalid = [
+        EmbeddedFields {
+            layout: too_many,
+            values: vec![0, 0, 0, 0],
+        },
+        EmbeddedFields {
+            layout: layout.clone(),
+            values: vec![3, 0, 0],
+        },
+        EmbeddedFields {
+            layout: FieldLayout::new(4, 2).field("version", 3),
+            values: vec![0],
+        },
+    ];
+    for fields in invalid {
+        let config = Config {
+            fields: Some(fields),
+            ..config.clone()
+        };
+        assert!(generate(&config).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use sha2::{Digest, Sha256};
+
+#[cfg(test)]
+use crate::{Charset, ChecksumCoverage, Pattern};
+use crate::{
+    Config,
+    canonical::{canonical_charset, canonical_pattern},
+    registry,
+};
+
+impl Config {
+    /// Returns a stable SHA-256 fingerprint of this configuration, for use
+    /// as a cache or idempotency key.
+    ///
+    /// Configurations generating the same codes have the same fingerprint,
+    /// however they are written: the fingerprint is computed over
+    /// [`Config::canonicalize`], except that custom charsets, including
+    /// those of segments, keep their order, since it decides which codes a
+    /// seed generates. Every other
+    /// difference, including the count and [`Config::rng`], changes the
+    /// fingerprint.
+    ///
+    /// The fingerprint hashes the configuration as written in registry
+    /// files, and doesn't change across crate versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, Pattern};
+    ///
+    /// let length = Config::default().with_pattern(Pattern::Length(3));
+    /// let pattern = Config::default().with_pattern(Pattern::Pattern("###".to_string()));
+    ///
+    /// assert_eq!(length.fingerprint(), pattern.fingerprint());
+    /// assert_ne!(length.fingerprint(), length.with_count(2).fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let config = Config {
+            charset: canonical_charset(&self.charset, false),
+            pattern: canonical_pattern(&self.pattern, false),
+            ..self.canonicalize()
+        };
+
+        Sha256::digest(registry::encode_config(&config).as_bytes()).into()
+    }
+}
+
+#[test]
+fn test_fingerprint_ignores_equivalent_spellings() {
+    let config = Config::default().with_pattern(Pattern::Length(4));
+    let equivalent = [
+        config.with_pattern(Pattern::Pattern("####".to_string())),
+        config.with_pattern(Pattern::Template("####".to_string())),
+        config.with_pattern(Pattern::WithPlaceholder("????".to_string(), '?')),
+        Config {
+            charset: Charset::Custom(Charset::Alphanumeric.as_str().to_string()),
+            prefix: Some(String::new()),
+            checksum_coverage: ChecksumCoverage::Full,
+            ..config.clone()
+        },
+    ];
+    for other in &equivalent {
+        assert_eq!(config.fingerprint(), other.fingerprint(), "{other:?}");
+    }
+
+    // Pinned, so fingerprints stay valid keys across releases.
+    let hex: String = config
+        .fingerprint()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    assert_eq!(
+        hex,
+        "56c300887e4e9acb1c076189a80b6c48aaab353f2b0e557822891609f17b5f10"
+    );
+
+    let different = [
+        config.with_pattern(Pattern::Template("AA##".to_string())),
+        config.with_pattern(Pattern::Pattern("##-##".to_string())),
+        config.with_prefix("R"),
+        Config {
+            charset: Charset::Custom("ABC".to_string()),
+            ..config.clone()
+        },
+        Config {
+            charset: Charset::Custom(Charset::Alphanumeric.as_str().chars().rev().collect()),
+            ..config.clone()
+        },
+        Config {
+            rng: crate::RngSource::Fast,
+            ..config.clone()
+        },
+    ];
+    for other in &different {
+        assert_ne!(config.fingerprint(), other.fingerprint(), "{other:?}");
+    }
+
+    let literal_hash = |p: Pattern| config.with_pattern(p).fingerprint();
+    assert_eq!(
+        literal_hash(Pattern::WithPlaceholder("#??".to_string(), '?')),
+        literal_hash(Pattern::WithPlaceholder("#**".to_string(), '*'))
+    );
+
+    let reserved = |prefixes: &[&str]| Config {
+        reserved_prefixes: prefixes.iter().map(|p| p.to_string()).collect(),
+        ..config.clone()
+    };
+    assert_eq!(
+        reserved(&["AB", "CD"]).fingerprint(),
+        reserved(&["CD", "AB", "CD"]).fingerprint()
+    );
+
+    // The same characters in another order generate other codes from a seed.
+    let custom = |charset: &str| Config {
+        charset: Charset::Custom(charset.to_string()),
+        ..config.clone()
+    };
+    assert_ne!(custom("CAB").fingerprint(), custom("ABC").fingerprint());
+}
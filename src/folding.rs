@@ -0,0 +1,232 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::Charset;
+
+/// Folding rules applied by downstream systems when comparing codes.
+///
+/// Partner systems frequently normalize codes before storing or deduplicating
+/// them. Characters that are distinct in a charset may become identical once
+/// such a normalization is applied, which silently merges distinct codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Folding {
+    /// Case-insensitive comparison (`a` and `A` are the same).
+    Case,
+    /// Diacritic-stripping ASCII folding (`n` and `ñ` are the same).
+    Diacritics,
+    /// Both case folding and diacritic stripping.
+    Full,
+}
+
+/// Result of analyzing a charset for characters that collide under a `Folding`.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct CollisionReport {
+    /// Groups of two or more charset characters that fold to the same form,
+    /// in charset order.
+    pub groups: Vec<Vec<char>>,
+    /// Characters that fold to more than one character (e.g. `ß` to `ss`),
+    /// which can make codes of different content fold to the same string.
+    pub expanding: Vec<char>,
+}
+
+impl CollisionReport {
+    /// Returns `true` if no collisions or expanding characters were found.
+    pub fn is_clean(&self) -> bool {
+        self.groups.is_empty() && self.expanding.is_empty()
+    }
+}
+
+const DIACRITICS: &[(char, &str)] = &[
+    ('a', "àáâãäåāăą"),
+    ('A', "ÀÁÂÃÄÅĀĂĄ"),
+    ('c', "çćĉċč"),
+    ('C', "ÇĆĈĊČ"),
+    ('d', "ďđð"),
+    ('D', "ĎĐÐ"),
+    ('e', "èéêëēĕėęě"),
+    ('E', "ÈÉÊËĒĔĖĘĚ"),
+    ('g', "ĝğġģ"),
+    ('G', "ĜĞĠĢ"),
+    ('h', "ĥħ"),
+    ('H', "ĤĦ"),
+    ('i', "ìíîïĩīĭįı"),
+    ('I', "ÌÍÎÏĨĪĬĮİ"),
+    ('j', "ĵ"),
+    ('J', "Ĵ"),
+    ('k', "ķĸ"),
+    ('K', "Ķ"),
+    ('l', "ĺļľŀł"),
+    ('L', "ĹĻĽĿŁ"),
+    ('n', "ñńņňŉŋ"),
+    ('N', "ÑŃŅŇŊ"),
+    ('o', "òóôõöøōŏő"),
+    ('O', "ÒÓÔÕÖØŌŎŐ"),
+    ('r', "ŕŗř"),
+    ('R', "ŔŖŘ"),
+    ('s', "śŝşšſ"),
+    ('S', "ŚŜŞŠ"),
+    ('t', "ţťŧ"),
+    ('T', "ŢŤŦ"),
+    ('u', "ùúûüũūŭůűų"),
+    ('U', "ÙÚÛÜŨŪŬŮŰŲ"),
+    ('w', "ŵ"),
+    ('W', "Ŵ"),
+    ('y', "ýÿŷ"),
+    ('Y', "ÝŶŸ"),
+    ('z', "źżž"),
+    ('Z', "ŹŻŽ"),
+];
+
+const EXPANSIONS: &[(char, &str)] = &[
+    ('ß', "ss"),
+    ('æ', "ae"),
+    ('Æ', "AE"),
+    ('œ', "oe"),
+    ('Œ', "OE"),
+    ('þ', "th"),
+    ('Þ', "TH"),
+    ('Ĳ', "IJ"),
+    ('ĳ', "ij"),
+];
+
+fn strip_diacritics(c: char) -> String {
+    if let Some((base, _)) = DIACRITICS.iter().find(|(_, v)| v.contains(c)) {
+        return base.to_string();
+    }
+
+    if let Some((_, expansion)) = EXPANSIONS.iter().find(|(e, _)| *e == c) {
+        return expansion.to_string();
+    }
+
+    c.to_string()
+}
+
+/// Folds a single character according to the given `Folding`.
+///
+/// The diacritic table covers the Latin-1 Supplement and Latin Extended-A
+/// blocks; characters outside those blocks are only affected by case folding.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Folding, fold};
+///
+/// assert_eq!(fold('Ñ', Folding::Diacritics), "N");
+/// assert_eq!(fold('Ñ', Folding::Full), "n");
+/// assert_eq!(fold('ß', Folding::Diacritics), "ss");
+/// ```
+pub fn fold(c: char, folding: Folding) -> String {
+    match folding {
+        Folding::Case => c.to_lowercase().collect(),
+        Folding::Diacritics => strip_diacritics(c),
+        Folding::Full => strip_diacritics(c).to_lowercase(),
+    }
+}
+
+impl Charset {
+    /// Analyzes this charset for characters that collide under the given folding.
+    ///
+    /// # Arguments
+    ///
+    /// * `folding` - The normalization applied by the downstream system
+    ///
+    /// # Returns
+    ///
+    /// A `CollisionReport` listing colliding groups and expanding characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Folding};
+    ///
+    /// let charset = Charset::Custom("nñxy".to_string());
+    /// let report = charset.collisions(Folding::Diacritics);
+    ///
+    /// assert_eq!(report.groups, vec![vec!['n', 'ñ']]);
+    /// assert!(Charset::Numeric.collisions(Folding::Full).is_clean());
+    /// ```
+    pub fn collisions(&self, folding: Folding) -> CollisionReport {
+        let mut report = CollisionReport::default();
+        let mut keys: Vec<(String, Vec<char>)> = Vec::new();
+
+        for c in self.as_str().chars() {
+            let folded = fold(c, folding);
+
+            if folded.chars().count() > 1 {
+                report.expanding.push(c);
+                continue;
+            }
+
+            match keys.iter_mut().find(|(k, _)| *k == folded) {
+                Some((_, group)) => {
+                    if !group.contains(&c) {
+                        group.push(c)
+                    }
+                }
+                None => keys.push((folded, vec![c])),
+            }
+        }
+
+        report.groups = keys
+            .into_iter()
+            .map(|(_, group)| group)
+            .filter(|group| group.len() > 1)
+            .collect();
+
+        report
+    }
+
+    /// Returns a copy of this charset with colliding characters removed.
+    ///
+    /// Only the first character (in charset order) of each colliding group is
+    /// kept, and expanding characters are dropped entirely, so the resulting
+    /// charset is clean under the given folding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Folding};
+    ///
+    /// let charset = Charset::Custom("nñßxy".to_string());
+    /// let safe = charset.without_collisions(Folding::Diacritics);
+    ///
+    /// assert_eq!(safe.as_str(), "nxy");
+    /// ```
+    pub fn without_collisions(&self, folding: Folding) -> Charset {
+        let report = self.collisions(folding);
+
+        let chars = self
+            .as_str()
+            .chars()
+            .filter(|c| !report.expanding.contains(c))
+            .filter(|c| {
+                report
+                    .groups
+                    .iter()
+                    .all(|group| !group.contains(c) || group[0] == *c)
+            })
+            .collect();
+
+        Charset::Custom(chars)
+    }
+}
+
+#[test]
+fn test_full_folding_collisions() {
+    let charset = Charset::Custom("AaÁbc".to_string());
+
+    let report = charset.collisions(Folding::Full);
+
+    assert_eq!(report.groups, vec![vec!['A', 'a', 'Á']]);
+    assert_eq!(charset.without_collisions(Folding::Full).as_str(), "Abc");
+    assert!(
+        charset
+            .without_collisions(Folding::Full)
+            .collisions(Folding::Full)
+            .is_clean()
+    );
+}
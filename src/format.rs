@@ -0,0 +1,180 @@
+use alloc::vec::Vec;
+
+use crate::{
+    Charset, Config, ReferralCodeError,
+    segment::{self, CharClass},
+};
+
+/// Identifier of a format in a [`FormatRegistry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct FormatId(pub usize);
+
+/// A fixed character identifying the format of a code.
+///
+/// The character at random position `position` (counting only placeholder
+/// characters, like [`crate::OcrCheck`] positions) is not drawn at random but
+/// set to the charset character at index `id`, so the format can be told from
+/// the code alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatTag {
+    /// The random position holding the tag.
+    pub position: usize,
+    /// The identifier encoded by the tag.
+    pub id: FormatId,
+}
+
+impl FormatTag {
+    /// Returns the character encoding the identifier, or `None` if the
+    /// charset has too few characters for it.
+    pub(crate) fn char(&self, charset: &Charset) -> Option<char> {
+        charset.as_str().chars().nth(self.id.0)
+    }
+
+    pub(crate) fn is_valid_for(&self, config: &Config) -> bool {
+        let checks = config.ocr_check.map_or([usize::MAX; 2], |c| c.positions);
+
+        config.pattern.classes().get(self.position) == Some(&CharClass::Charset)
+            && !checks.contains(&self.position)
+            && self.id.0 < config.charset.effective_len()
+    }
+}
+
+/// A set of concurrently issued formats, each tagged with its identifier.
+///
+/// Registering a format reserves one of its random positions for a
+/// [`FormatTag`]. A single redemption endpoint can then route any code to the
+/// format it was issued under with [`FormatRegistry::identify`].
+#[derive(Clone, Debug, Default)]
+pub struct FormatRegistry {
+    formats: Vec<Config>,
+}
+
+impl FormatRegistry {
+    /// Creates an empty format registry.
+    pub fn new() -> Self {
+        FormatRegistry::default()
+    }
+
+    /// Registers a format, tagging it at the given random position.
+    ///
+    /// Formats are identified in registration order, starting at 0. The
+    /// configuration's own `format_tag`, if any, is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The format to register
+    /// * `position` - The random position to hold the tag
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::InvalidFormatTag` if the tag does not fit
+    /// the configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, FormatId, FormatRegistry};
+    ///
+    /// let mut formats = FormatRegistry::new();
+    /// let id = formats.register(Config::default(), 0).unwrap();
+    ///
+    /// assert_eq!(id, FormatId(0));
+    /// assert!(formats.config(id).unwrap().format_tag.is_some());
+    /// ```
+    pub fn register(
+        &mut self,
+        config: Config,
+        position: usize,
+    ) -> Result<FormatId, ReferralCodeError> {
+        let id = FormatId(self.formats.len());
+        let tag = FormatTag { position, id };
+        if !tag.is_valid_for(&config) {
+            return Err(ReferralCodeError::InvalidFormatTag);
+        }
+
+        self.formats.push(Config {
+            format_tag: Some(tag),
+            ..config
+        });
+
+        Ok(id)
+    }
+
+    /// Returns the tagged configuration of a registered format.
+    pub fn config(&self, id: FormatId) -> Option<&Config> {
+        self.formats.get(id.0)
+    }
+
+    /// Returns every tagged configuration, in registration order.
+    pub fn formats(&self) -> &[Config] {
+        &self.formats
+    }
+
+    /// Returns the format `code` was issued under.
+    ///
+    /// Only the layout and the tag are checked; verify check characters with
+    /// the returned format's configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Config, FormatRegistry};
+    ///
+    /// let mut formats = FormatRegistry::new();
+    /// let spring = formats.register(Config::default(), 0).unwrap();
+    /// let summer = formats.register(Config::default(), 0).unwrap();
+    ///
+    /// let code = referral_codes::generate_one(formats.config(summer).unwrap()).unwrap();
+    ///
+    /// assert_eq!(formats.identify(&code), Some(summer));
+    /// assert_ne!(formats.identify(&code), Some(spring));
+    /// assert_eq!(formats.identify("too short"), None);
+    /// # }
+    /// ```
+    pub fn identify(&self, code: &str) -> Option<FormatId> {
+        self.formats
+            .iter()
+            .position(|config| segment::random_chars(config, code).is_some())
+            .map(FormatId)
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_format_tag_is_excluded_from_keyspace() {
+    use crate::{Charset, OcrCheck, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(3),
+        ..Config::default()
+    };
+    let mut formats = FormatRegistry::new();
+    let id = formats.register(config.clone(), 1).unwrap();
+    let tagged = formats.config(id).unwrap();
+
+    assert_eq!(tagged.keyspace(), Some(100));
+    assert!(crate::generate(&tagged.with_count(100)).is_ok());
+    assert!(crate::generate(&tagged.with_count(101)).is_err());
+
+    // A tag beyond the charset matches no code, rather than panicking.
+    let out_of_range = Config {
+        format_tag: Some(FormatTag {
+            position: 0,
+            id: FormatId(10),
+        }),
+        ..config.clone()
+    };
+    assert!(crate::validate("012", &out_of_range).is_err());
+    assert!(crate::generate(&out_of_range).is_err());
+
+    let checked = Config {
+        ocr_check: Some(OcrCheck::new(0, 1)),
+        ..config
+    };
+    assert!(formats.register(checked, 1).is_err());
+}
@@ -0,0 +1,252 @@
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+use crate::{
+    Config,
+    segment::{self, Slot, slots},
+};
+
+/// How codes are written out for people, see [`Config::formatting`].
+///
+/// Formatting only changes how codes read: codes are generated, stored and
+/// compared unformatted. [`Config::format_code`] writes a code the way it
+/// is shown, and [`Config::normalize_code`] turns what someone typed back
+/// into the stored code, which [`crate::validate`] and
+/// [`crate::Verifier`] do before checking it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct Formatting {
+    /// Optional separator written between groups of characters.
+    pub group: Option<Grouping>,
+    /// Optional letter case every letter is written in.
+    pub case: Option<LetterCase>,
+}
+
+/// Groups of characters of a formatted code, like `XXXX-XXXX-XXXX`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Grouping {
+    /// The number of characters of every group but the last one.
+    pub size: usize,
+    /// The character written between groups.
+    ///
+    /// It can't be a character codes hold, so that it can be stripped
+    /// wherever it is typed.
+    pub separator: char,
+}
+
+/// A letter case, see [`Formatting::case`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum LetterCase {
+    /// Upper case letters, like `ABCD`.
+    Upper,
+    /// Lower case letters, like `abcd`.
+    Lower,
+}
+
+impl LetterCase {
+    /// Writes `c` in this case, unless that takes several characters.
+    fn apply(self, c: char) -> char {
+        let cased: Vec<char> = match self {
+            LetterCase::Upper => c.to_uppercase().collect(),
+            LetterCase::Lower => c.to_lowercase().collect(),
+        };
+
+        match cased[..] {
+            [single] => single,
+            _ => c,
+        }
+    }
+}
+
+impl Formatting {
+    /// Returns `true` if this formatting fits the configuration.
+    ///
+    /// Groups must hold at least one character, and their separator must
+    /// be a character no code holds. Without `random_case`, a letter case
+    /// can only be forced if no position holds two letters differing only
+    /// in case, which would write two codes the same.
+    pub(crate) fn is_valid_for(&self, config: &Config) -> bool {
+        let slots = slots(config);
+
+        if let Some(Grouping { size, separator }) = self.group {
+            let held = |slot: &Slot| match slot {
+                Slot::Literal(c) => *c == separator,
+                Slot::Random(class) => class.contains(config, separator),
+                Slot::Check => config
+                    .checksum
+                    .as_ref()
+                    .is_some_and(|checksum| checksum.output_alphabet().contains(separator)),
+            };
+            if size == 0 || slots.iter().any(held) {
+                return false;
+            }
+        }
+
+        if self.case.is_some() && config.random_case.is_none() {
+            return slots.iter().all(|slot| match slot {
+                Slot::Random(class) => {
+                    class.caseless_len(config) == segment::distinct_alphabet(config, *class).len()
+                }
+                _ => true,
+            });
+        }
+
+        true
+    }
+}
+
+impl Config {
+    /// Writes a code the way it is shown, with the configured grouping and
+    /// letter case.
+    ///
+    /// Groups are counted over the whole code, prefix and suffix included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Formatting, Grouping, LetterCase, Pattern};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Custom("abcd1234".to_string()),
+    ///     pattern: Pattern::Length(12),
+    ///     formatting: Formatting {
+    ///         group: Some(Grouping { size: 4, separator: '-' }),
+    ///         case: Some(LetterCase::Upper),
+    ///     },
+    ///     ..Config::default()
+    /// };
+    ///
+    /// assert_eq!(config.format_code("abcd1234dcba"), "ABCD-1234-DCBA");
+    /// assert_eq!(config.normalize_code("abcd 1234-DCba"), "abcd 1234DCba");
+    /// assert_eq!(config.normalize_code("ABCD1234DCBA"), "abcd1234dcba");
+    /// ```
+    pub fn format_code(&self, code: &str) -> String {
+        let Formatting { group, case } = self.formatting;
+        let mut formatted = String::with_capacity(code.len() + code.len() / 2);
+
+        for (i, c) in code.chars().enumerate() {
+            if let Some(Grouping { size, separator }) = group
+                && i > 0
+                && i % size == 0
+            {
+                formatted.push(separator);
+            }
+            formatted.push(case.map_or(c, |case| case.apply(c)));
+        }
+
+        formatted
+    }
+
+    /// Turns a code as typed back into the stored code, undoing
+    /// [`Config::format_code`].
+    ///
+    /// Separators are removed wherever they are, so codes are accepted
+    /// with or without them. With a letter case, every character takes
+    /// the case codes are generated in, matched position by position; a
+    /// code of the wrong length only loses its separators. Other
+    /// mistakes are left for verification to find. Codes that are already
+    /// stored codes are borrowed.
+    pub fn normalize_code<'a>(&self, code: &'a str) -> Cow<'a, str> {
+        let Formatting { group, case } = self.formatting;
+        if case.is_none() && group.is_none_or(|group| !code.contains(group.separator)) {
+            return Cow::Borrowed(code);
+        }
+
+        let stripped: Vec<char> = code
+            .chars()
+            .filter(|c| group.is_none_or(|group| *c != group.separator))
+            .collect();
+
+        let slots = slots(self);
+        if case.is_none() || stripped.len() != slots.len() {
+            return Cow::Owned(stripped.into_iter().collect());
+        }
+
+        let same = |a: &char, b: char| a.to_lowercase().eq(b.to_lowercase());
+        let normalized = slots
+            .iter()
+            .zip(stripped)
+            .map(|(slot, c)| match slot {
+                Slot::Literal(l) if same(l, c) => *l,
+                Slot::Literal(_) => c,
+                Slot::Random(class) => class.canonical(self, c, true).unwrap_or(c),
+                Slot::Check => self
+                    .checksum
+                    .as_ref()
+                    .and_then(|checksum| checksum.output_alphabet().chars().find(|a| same(a, c)))
+                    .unwrap_or(c),
+            })
+            .collect();
+
+        Cow::Owned(normalized)
+    }
+}
+
+#[test]
+fn test_formatting_round_trips_and_rejects_ambiguous_setups() {
+    use crate::{Charset, Checksum, Pattern, ReferralCodeError, check_format, validate};
+
+    let config = Config {
+        charset: Charset::Custom("abcdef0123".to_string()),
+        pattern: Pattern::Pattern("#####".to_string()),
+        prefix: Some("Q".to_string()),
+        checksum: Some(Checksum::Iso7064Mod37_36),
+        formatting: Formatting {
+            group: Some(Grouping {
+                size: 3,
+                separator: '-',
+            }),
+            case: Some(LetterCase::Lower),
+        },
+        ..Config::default()
+    };
+    let code = crate::assemble(&config, "ab0c1".chars().collect());
+    let formatted = config.format_code(&code);
+
+    assert_eq!(formatted.len(), code.len() + 2);
+    assert_eq!(formatted.matches('-').count(), 2);
+    assert_eq!(formatted, formatted.to_lowercase());
+    assert_eq!(config.normalize_code(&formatted), code);
+    assert_eq!(config.normalize_code(&formatted.to_uppercase()), code);
+    assert_eq!(validate(&formatted.to_uppercase(), &config), Ok(()));
+    assert!(validate(&format!("{formatted}a"), &config).is_err());
+
+    let unformatted = Config {
+        formatting: Formatting::default(),
+        ..config.clone()
+    };
+    assert_eq!(unformatted.format_code(&code), code);
+    assert_eq!(unformatted.normalize_code(&formatted), formatted);
+
+    let invalid = |formatting: Formatting, charset: Charset| {
+        matches!(
+            check_format(&Config {
+                formatting,
+                charset,
+                ..config.clone()
+            }),
+            Err(ReferralCodeError::InvalidFormatting)
+        )
+    };
+    let grouped = |size, separator| Formatting {
+        group: Some(Grouping { size, separator }),
+        case: None,
+    };
+    assert!(invalid(grouped(0, '-'), Charset::Numeric));
+    assert!(invalid(grouped(4, 'Q'), Charset::Numeric));
+    assert!(invalid(grouped(4, '1'), Charset::Numeric));
+    assert!(!invalid(grouped(4, ' '), Charset::Numeric));
+    let upper = Formatting {
+        group: None,
+        case: Some(LetterCase::Upper),
+    };
+    assert!(invalid(upper, Charset::Alphanumeric));
+    assert!(!invalid(upper, Charset::CrockfordBase32));
+}
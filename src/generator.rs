@@ -0,0 +1,114 @@
+use crate::{
+    Attempts, Config, ReferralCodeError, check_config, generate_one_with, generate_with,
+    rng::backend::Rng,
+};
+
+/// Generates codes of a configuration with a random number generator of
+/// the caller's choice.
+///
+/// [`crate::generate`] and [`crate::generate_one`] draw from the
+/// [`crate::RngSource`] of the configuration. A `Generator` draws from the
+/// generator it owns instead, whatever [`Config::rng`] says: seed one, such as `StdRng::seed_from_u64`, to
+/// get the same codes on every run in tests, or pick the entropy source of
+/// production. It takes any generator of the `rand` version the crate is
+/// built with, 0.10 with the `generation` feature and 0.9 with
+/// `generation-rand09`, and a seed gives the same codes with either.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "generation")] {
+/// use rand::{SeedableRng, rngs::StdRng};
+/// use referral_codes::{Config, Generator};
+///
+/// let config = Config::default().with_count(3);
+/// let mut first = Generator::new(config.clone(), StdRng::seed_from_u64(42));
+/// let mut second = Generator::new(config, StdRng::seed_from_u64(42));
+///
+/// assert_eq!(first.generate().unwrap(), second.generate().unwrap());
+/// assert_eq!(first.generate_one().unwrap(), second.generate_one().unwrap());
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Generator<R> {
+    config: Config,
+    rng: R,
+}
+
+impl<R: Rng> Generator<R> {
+    /// Creates a generator of codes of `config`, drawing from `rng`.
+    pub fn new(config: Config, rng: R) -> Self {
+        Generator { config, rng }
+    }
+
+    /// Returns the configuration codes are generated from.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the random number generator, in its current state.
+    pub fn into_rng(self) -> R {
+        self.rng
+    }
+
+    /// Generates a single code, as [`crate::generate_one`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`crate::generate_one`].
+    pub fn generate_one(&mut self) -> Result<String, ReferralCodeError> {
+        check_config(&self.config.with_count(1))?;
+
+        let mut attempts = Attempts::new(&self.config, 0, 1);
+        generate_one_with(&self.config, &mut self.rng, &mut attempts)
+            .ok_or_else(|| attempts.exceeded(Vec::new()))
+    }
+
+    /// Generates `config.count` unique codes, as [`crate::generate`] does.
+    ///
+    /// Codes are only unique within one call: call it again for another
+    /// batch, which may repeat codes of the first.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`crate::generate`].
+    pub fn generate(&mut self) -> Result<Vec<String>, ReferralCodeError> {
+        generate_with(&self.config, &mut self.rng)
+    }
+}
+
+#[test]
+fn test_generator_is_reproducible() {
+    use crate::{
+        Batch, Charset, Pattern,
+        rng::{SeedableRng, StdRng},
+    };
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(4),
+        count: 20,
+        ..Config::default()
+    };
+    let mut generator = Generator::new(config.clone(), StdRng::seed_from_u64(7));
+    let codes = generator.generate().unwrap();
+
+    // The same seed gives the same codes as a seeded batch.
+    let batch = Batch::generate_seeded("b", &config, 7).unwrap();
+    assert_eq!(codes, batch.codes);
+    assert_ne!(generator.generate().unwrap(), codes);
+
+    let mut again = Generator::new(config.clone(), StdRng::seed_from_u64(7));
+    again.generate().unwrap();
+    again.generate().unwrap();
+    assert_eq!(
+        generator.generate_one().unwrap(),
+        again.generate_one().unwrap()
+    );
+
+    let mut infeasible = Generator::new(config.with_count(10_001), StdRng::seed_from_u64(7));
+    assert!(matches!(
+        infeasible.generate(),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+}
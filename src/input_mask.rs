@@ -0,0 +1,151 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{CharClass, Pattern, Segment};
+
+/// A syntax of frontend input masks, see [`Pattern::to_input_mask`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MaskSyntax {
+    /// An [imask.js](https://imask.js.org) pattern mask, such as `REF-aa00`,
+    /// also understood by libraries using the same definitions.
+    ///
+    /// `0` accepts a digit, `a` a letter and `*` any character; other
+    /// characters are fixed, escaped with `\` where they would be read as
+    /// definitions.
+    IMask,
+    /// A JSON object of [Cleave.js](https://nosir.github.io/cleave.js/)
+    /// options, such as `{"prefix":"REF-","blocks":[4]}`.
+    ///
+    /// Cleave.js doesn't check characters against their class, and has no
+    /// suffix option: literals after the last random position are left out.
+    Cleave,
+}
+
+impl Pattern {
+    /// Returns an input mask for web forms, so codes are formatted as they
+    /// are typed like they are generated.
+    ///
+    /// Positions drawing from the configured charset accept any character:
+    /// masks can't express arbitrary charsets, so entered codes must still
+    /// be verified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{MaskSyntax, Pattern};
+    ///
+    /// let pattern = Pattern::parse("REF-AA99-##").unwrap();
+    ///
+    /// assert_eq!(pattern.to_input_mask(MaskSyntax::IMask), "REF-aa00-**");
+    /// assert_eq!(
+    ///     pattern.to_input_mask(MaskSyntax::Cleave),
+    ///     r#"{"prefix":"REF-","blocks":[4,2],"delimiters":["-"]}"#
+    /// );
+    /// ```
+    pub fn to_input_mask(&self, syntax: MaskSyntax) -> String {
+        match syntax {
+            MaskSyntax::IMask => self.imask(),
+            MaskSyntax::Cleave => self.cleave(),
+        }
+    }
+
+    fn imask(&self) -> String {
+        let mut mask = String::new();
+
+        for segment in self.segments() {
+            match segment {
+                Segment::Literal(s) => {
+                    for c in s.chars() {
+                        if "0a*[]{}`\\".contains(c) {
+                            mask.push('\\');
+                        }
+                        mask.push(c);
+                    }
+                }
+                Segment::Placeholder(class, n) => {
+                    let definition = match class {
+                        CharClass::Charset | CharClass::Pool(_) => '*',
+                        CharClass::Letter => 'a',
+                        CharClass::Digit => '0',
+                    };
+                    mask.extend(core::iter::repeat_n(definition, n));
+                }
+            }
+        }
+
+        mask
+    }
+
+    fn cleave(&self) -> String {
+        let mut prefix = String::new();
+        let mut blocks: Vec<usize> = Vec::new();
+        let mut delimiters: Vec<&str> = Vec::new();
+        let mut pending = None;
+
+        for segment in self.segments() {
+            match (segment, blocks.last_mut()) {
+                (Segment::Literal(s), None) => prefix.push_str(s),
+                (Segment::Literal(s), Some(_)) => pending = Some(s),
+                (Segment::Placeholder(_, n), Some(last)) if pending.is_none() => *last += n,
+                (Segment::Placeholder(_, n), _) => {
+                    delimiters.extend(pending.take());
+                    blocks.push(n);
+                }
+            }
+        }
+
+        let mut options = Vec::new();
+        if !prefix.is_empty() {
+            options.push(format!("\"prefix\":{}", json_string(&prefix)));
+        }
+        let blocks: Vec<String> = blocks.iter().map(usize::to_string).collect();
+        options.push(format!("\"blocks\":[{}]", blocks.join(",")));
+        if !delimiters.is_empty() {
+            let delimiters: Vec<String> = delimiters.into_iter().map(json_string).collect();
+            options.push(format!("\"delimiters\":[{}]", delimiters.join(",")));
+        }
+
+        format!("{{{}}}", options.join(","))
+    }
+}
+
+/// Quotes a string as a JSON string literal.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[test]
+fn test_input_masks_escape_literals() {
+    let pattern = Pattern::Pattern("a0-##\"#".to_string());
+
+    assert_eq!(pattern.to_input_mask(MaskSyntax::IMask), "\\a\\0-**\"*");
+    assert_eq!(
+        pattern.to_input_mask(MaskSyntax::Cleave),
+        r#"{"prefix":"a0-","blocks":[2,1],"delimiters":["\""]}"#
+    );
+    assert_eq!(
+        Pattern::Length(8).to_input_mask(MaskSyntax::Cleave),
+        r#"{"blocks":[8]}"#
+    );
+    assert_eq!(
+        Pattern::Pattern("##-".to_string()).to_input_mask(MaskSyntax::Cleave),
+        r#"{"blocks":[2]}"#
+    );
+}
@@ -0,0 +1,310 @@
+//! Generation and verification for C, and the languages binding to it.
+//!
+//! With the `ffi` feature, these functions are exported unmangled, so
+//! services in PHP, Go or any language with a C FFI issue and check codes
+//! with the same rules as Rust services. Build the crate as a shared
+//! library with `cargo rustc --release --features ffi --crate-type cdylib`,
+//! and declare:
+//!
+//! ```c
+//! typedef struct {
+//!     const char *pattern; /* '#' marks random characters, NULL for `length` ones */
+//!     size_t length;
+//!     const char *charset; /* a name, such as "numeric", or characters, NULL for alphanumeric */
+//!     const char *prefix;  /* NULL for none */
+//!     const char *suffix;  /* NULL for none */
+//!     size_t count;
+//! } RcConfig;
+//!
+//! typedef struct {
+//!     char **codes;
+//!     size_t len;
+//! } RcStrings;
+//!
+//! int rc_generate(const RcConfig *config, RcStrings *out);
+//! int rc_validate(const RcConfig *config, const char *code);
+//! void rc_free_strings(RcStrings *strings);
+//! ```
+//!
+//! Strings passed in are NUL-terminated UTF-8, and stay owned by the
+//! caller. Codes returned in an `RcStrings` are owned by the library: read
+//! them, copy what must outlive them, and give them back to
+//! `rc_free_strings` exactly once, never to `free`.
+
+use std::{
+    ffi::{CStr, CString, c_char, c_int},
+    ptr,
+};
+
+use crate::{Charset, Config, Pattern, ReferralCodeError, Verifier};
+
+/// Returned on success, or by [`rc_validate`] for a valid code.
+pub const RC_OK: c_int = 0;
+/// Returned by [`rc_validate`] for an invalid code.
+pub const RC_INVALID_CODE: c_int = 1;
+/// Returned for a null pointer or a string that is not UTF-8.
+pub const RC_INVALID_ARGUMENT: c_int = -1;
+/// Returned for a configuration codes can't be generated from.
+pub const RC_INVALID_CONFIG: c_int = -2;
+/// Returned when the configuration can't hold `count` unique codes.
+pub const RC_NON_FEASIBLE: c_int = -3;
+
+/// A configuration of codes, written with C types.
+///
+/// It covers the settings of the CLI; configurations needing more are
+/// better shared as JSON with the `serde` feature.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RcConfig {
+    /// The pattern string, `#` marking random characters, or null for a
+    /// code of `length` random characters.
+    pub pattern: *const c_char,
+    /// The number of random characters, if `pattern` is null.
+    pub length: usize,
+    /// The charset name or characters, or null for alphanumeric.
+    pub charset: *const c_char,
+    /// Text before every code, or null for none.
+    pub prefix: *const c_char,
+    /// Text after every code, or null for none.
+    pub suffix: *const c_char,
+    /// The number of unique codes [`rc_generate`] generates.
+    pub count: usize,
+}
+
+/// Codes returned by [`rc_generate`], to give back to [`rc_free_strings`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct RcStrings {
+    /// `len` NUL-terminated codes.
+    pub codes: *mut *mut c_char,
+    /// The number of codes.
+    pub len: usize,
+}
+
+/// Reads a string the caller owns, or `None` for a null pointer.
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL-terminated string.
+unsafe fn read_str(s: *const c_char) -> Result<Option<String>, c_int> {
+    if s.is_null() {
+        return Ok(None);
+    }
+
+    // SAFETY: the caller guarantees a NUL-terminated string.
+    let s = unsafe { CStr::from_ptr(s) };
+    s.to_str()
+        .map(|s| Some(s.to_string()))
+        .map_err(|_| RC_INVALID_ARGUMENT)
+}
+
+impl RcConfig {
+    /// Returns the configuration this describes.
+    ///
+    /// # Safety
+    ///
+    /// Every string must be null or NUL-terminated.
+    unsafe fn to_config(self) -> Result<Config, c_int> {
+        // SAFETY: the caller guarantees every string.
+        let (pattern, charset, prefix, suffix) = unsafe {
+            (
+                read_str(self.pattern)?,
+                read_str(self.charset)?,
+                read_str(self.prefix)?,
+                read_str(self.suffix)?,
+            )
+        };
+        let charset = match charset {
+            Some(charset) => charset.parse().map_err(|_| RC_INVALID_CONFIG)?,
+            None => Charset::Alphanumeric,
+        };
+
+        Ok(Config {
+            pattern: pattern.map_or(Pattern::Length(self.length), Pattern::Pattern),
+            charset,
+            prefix,
+            suffix,
+            count: self.count,
+            ..Config::default()
+        })
+    }
+}
+
+/// Returns the status reporting a generation error.
+fn status(error: ReferralCodeError) -> c_int {
+    match error {
+        ReferralCodeError::NonFeasibleConfig => RC_NON_FEASIBLE,
+        _ => RC_INVALID_CONFIG,
+    }
+}
+
+/// Generates `config->count` unique codes into `out`.
+///
+/// On success, `out` holds the codes, to give back to [`rc_free_strings`].
+/// On error, it is left empty, and there is nothing to free.
+///
+/// # Returns
+///
+/// * `RC_OK` - If the codes were generated
+/// * `RC_INVALID_ARGUMENT` - If a pointer is null or a string is not UTF-8
+/// * `RC_INVALID_CONFIG` - If codes can't be generated from the configuration
+/// * `RC_NON_FEASIBLE` - If the configuration can't hold `count` unique codes
+///
+/// # Safety
+///
+/// `config` must point to a valid [`RcConfig`], and `out` to writable
+/// memory for an [`RcStrings`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rc_generate(config: *const RcConfig, out: *mut RcStrings) -> c_int {
+    if config.is_null() || out.is_null() {
+        return RC_INVALID_ARGUMENT;
+    }
+    // SAFETY: the caller guarantees both pointers.
+    let (config, out) = unsafe { (&*config, &mut *out) };
+    *out = RcStrings {
+        codes: ptr::null_mut(),
+        len: 0,
+    };
+
+    // SAFETY: the caller guarantees the strings of the configuration.
+    let config = match unsafe { config.to_config() } {
+        Ok(config) => config,
+        Err(status) => return status,
+    };
+    let codes = match crate::generate(&config) {
+        Ok(codes) => codes,
+        Err(error) => return status(error),
+    };
+    // Charsets may hold a NUL, which C strings can't.
+    let Ok(codes) = codes
+        .into_iter()
+        .map(CString::new)
+        .collect::<Result<Vec<_>, _>>()
+    else {
+        return RC_INVALID_CONFIG;
+    };
+
+    let codes: Box<[*mut c_char]> = codes.into_iter().map(CString::into_raw).collect();
+    *out = RcStrings {
+        len: codes.len(),
+        codes: Box::into_raw(codes).cast(),
+    };
+
+    RC_OK
+}
+
+/// Checks a code against a configuration, as [`Verifier::verify`] does.
+///
+/// # Returns
+///
+/// * `RC_OK` - If the code is valid
+/// * `RC_INVALID_CODE` - If the code is not valid
+/// * `RC_INVALID_ARGUMENT` - If a pointer is null or a string is not UTF-8
+/// * `RC_INVALID_CONFIG` - If the charset can't be parsed, such as an
+///   empty one
+///
+/// # Safety
+///
+/// `config` must point to a valid [`RcConfig`], and `code` to a
+/// NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rc_validate(config: *const RcConfig, code: *const c_char) -> c_int {
+    if config.is_null() {
+        return RC_INVALID_ARGUMENT;
+    }
+    // SAFETY: the caller guarantees both pointers.
+    let (config, code) = match unsafe { ((*config).to_config(), read_str(code)) } {
+        (Ok(config), Ok(Some(code))) => (config, code),
+        (Err(status), _) | (_, Err(status)) => return status,
+        (_, Ok(None)) => return RC_INVALID_ARGUMENT,
+    };
+
+    if Verifier::new(config).verify(&code) {
+        RC_OK
+    } else {
+        RC_INVALID_CODE
+    }
+}
+
+/// Frees the codes of an [`RcStrings`] filled by [`rc_generate`], and
+/// leaves it empty.
+///
+/// Null pointers and empty `RcStrings` are ignored, so freeing twice the
+/// same `RcStrings` is harmless, unlike freeing two copies of it.
+///
+/// # Safety
+///
+/// `strings` must be null or point to an [`RcStrings`] filled by
+/// [`rc_generate`], or left empty by it or by this function.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rc_free_strings(strings: *mut RcStrings) {
+    if strings.is_null() {
+        return;
+    }
+    // SAFETY: the caller guarantees the pointer.
+    let strings = unsafe { &mut *strings };
+    if strings.codes.is_null() {
+        return;
+    }
+
+    // SAFETY: the array and every code were allocated by `rc_generate`,
+    // and are only freed here, once, as the pointer is cleared below.
+    let codes = unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(strings.codes, strings.len)) };
+    for code in codes {
+        // SAFETY: as above.
+        drop(unsafe { CString::from_raw(code) });
+    }
+    *strings = RcStrings {
+        codes: ptr::null_mut(),
+        len: 0,
+    };
+}
+
+#[test]
+fn test_c_functions_generate_validate_and_free() {
+    let pattern = c"REF-####";
+    let charset = c"numeric";
+    let config = RcConfig {
+        pattern: pattern.as_ptr(),
+        length: 0,
+        charset: charset.as_ptr(),
+        prefix: ptr::null(),
+        suffix: ptr::null(),
+        count: 5,
+    };
+    let mut out = RcStrings {
+        codes: ptr::null_mut(),
+        len: 0,
+    };
+
+    // SAFETY: every pointer is valid, and `out` is freed once.
+    unsafe {
+        assert_eq!(rc_generate(&config, &mut out), RC_OK);
+        assert_eq!(out.len, 5);
+        let codes = std::slice::from_raw_parts(out.codes, out.len);
+        for &code in codes {
+            assert!(CStr::from_ptr(code).to_str().unwrap().starts_with("REF-"));
+            assert_eq!(rc_validate(&config, code), RC_OK);
+        }
+        assert_eq!(rc_validate(&config, c"REF-12a4".as_ptr()), RC_INVALID_CODE);
+        assert_eq!(rc_validate(&config, ptr::null()), RC_INVALID_ARGUMENT);
+
+        rc_free_strings(&mut out);
+        assert!(out.codes.is_null() && out.len == 0);
+        rc_free_strings(&mut out);
+
+        let infeasible = RcConfig {
+            count: 10_001,
+            ..config
+        };
+        assert_eq!(rc_generate(&infeasible, &mut out), RC_NON_FEASIBLE);
+        assert!(out.codes.is_null());
+        let empty = RcConfig {
+            pattern: ptr::null(),
+            length: 0,
+            ..config
+        };
+        assert_eq!(rc_generate(&empty, &mut out), RC_INVALID_CONFIG);
+        assert_eq!(rc_generate(ptr::null(), &mut out), RC_INVALID_ARGUMENT);
+    }
+}
@@ -0,0 +1,25 @@
+//! Integrations with third-party crates.
+//!
+//! Each integration lives in its own module, behind its own feature, and
+//! builds on the core API only: nothing in the core depends on an
+//! integration, so disabling every integration feature leaves the core build
+//! untouched. Features are named after what they provide rather than after
+//! the crate they pull in, and public items are re-exported from the crate
+//! root, so moving code in or out of this module never changes public paths.
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "parallel")]
+pub(crate) mod rayon;
+#[cfg(feature = "serde")]
+mod serde;
+#[cfg(feature = "voucher-sheet")]
+pub mod sheet;
+#[cfg(feature = "async")]
+pub(crate) mod store;
+#[cfg(feature = "async")]
+pub(crate) mod tokio;
+#[cfg(feature = "verify-layer")]
+pub(crate) mod tower;
+#[cfg(feature = "wasm")]
+pub mod wasm;
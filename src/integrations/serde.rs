@@ -0,0 +1,211 @@
+//! `Serialize` and `Deserialize` for charsets and patterns, written the way
+//! they are typed in service configurations.
+//!
+//! A charset is its name, such as `"alphanumeric"`, or its characters, and
+//! `{"custom": "..."}` for the rare custom charset spelling a name, and
+//! `{"weighted": [["A", 1], ["7", 3], ...]}` for a weighted one. A
+//! pattern is its length, such as `8`, its pattern string, such as
+//! `"REF-####"`, `{"template": "AA-99"}`,
+//! `{"pattern": "REF-???", "placeholder": "?"}` or
+//! `{"segments": [{"pattern": "###-", "charset": "ABC"}, ...]}`. The other
+//! types of a [`crate::Config`] derive their implementations, and fields
+//! left out of a configuration keep their default.
+
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Charset, Pattern};
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum CharsetRepr {
+    Text(String),
+    Custom { custom: String },
+    Weighted { weighted: Vec<(char, u32)> },
+}
+
+impl Serialize for Charset {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let chars = self.as_str().to_string();
+        let repr = match (self.name(), self) {
+            (Some(name), _) => CharsetRepr::Text(name.to_string()),
+            (None, Charset::Weighted(weights)) => CharsetRepr::Weighted {
+                weighted: weights.clone(),
+            },
+            (None, _) if Charset::from_name(&chars).is_some() => {
+                CharsetRepr::Custom { custom: chars }
+            }
+            (None, _) => CharsetRepr::Text(chars),
+        };
+
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Charset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match CharsetRepr::deserialize(deserializer)? {
+            CharsetRepr::Text(text) => Charset::from_name(&text).unwrap_or(Charset::Custom(text)),
+            CharsetRepr::Custom { custom } => Charset::Custom(custom),
+            CharsetRepr::Weighted { weighted } => Charset::Weighted(weighted),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum PatternRepr {
+    Length(usize),
+    Pattern(String),
+    Template { template: String },
+    WithPlaceholder { pattern: String, placeholder: char },
+    Segments { segments: Vec<SegmentRepr> },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SegmentRepr {
+    pattern: Pattern,
+    charset: Charset,
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.clone() {
+            Pattern::Length(n) => PatternRepr::Length(n),
+            Pattern::Pattern(s) => PatternRepr::Pattern(s),
+            Pattern::Template(template) => PatternRepr::Template { template },
+            Pattern::WithPlaceholder(pattern, placeholder) => PatternRepr::WithPlaceholder {
+                pattern,
+                placeholder,
+            },
+            Pattern::Segments(segments) => PatternRepr::Segments {
+                segments: segments
+                    .into_iter()
+                    .map(|(pattern, charset)| SegmentRepr { pattern, charset })
+                    .collect(),
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match PatternRepr::deserialize(deserializer)? {
+            PatternRepr::Length(n) => Pattern::Length(n),
+            PatternRepr::Pattern(s) => Pattern::Pattern(s),
+            PatternRepr::Template { template } => Pattern::Template(template),
+            PatternRepr::WithPlaceholder {
+                pattern,
+                placeholder,
+            } => Pattern::WithPlaceholder(pattern, placeholder),
+            PatternRepr::Segments { segments } => Pattern::Segments(
+                segments
+                    .into_iter()
+                    .map(|segment| (segment.pattern, segment.charset))
+                    .collect(),
+            ),
+        })
+    }
+}
+
+#[test]
+fn test_config_round_trips_through_json() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::{
+        Checksum, Config, EmbeddedFields, Expiry, FieldLayout, FormatId, FormatTag, Formatting,
+        Granularity, Grouping, LetterCase, OcrCheck,
+    };
+
+    let config: Config = serde_json::from_str(
+        r#"{"pattern": "REF-####", "charset": "crockford-base32", "count": 5}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config,
+        Config {
+            pattern: Pattern::Pattern("REF-####".to_string()),
+            charset: Charset::CrockfordBase32,
+            count: 5,
+            ..Config::default()
+        }
+    );
+    assert!(serde_json::from_str::<Config>(r#"{"patern": 8}"#).is_err());
+
+    let epoch = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let configs = [
+        Config::default(),
+        Config {
+            pattern: Pattern::WithPlaceholder("R-???".to_string(), '?'),
+            charset: Charset::Custom("numeric".to_string()),
+            prefix: Some("X".to_string()),
+            ocr_check: Some(OcrCheck::new(0, 2)),
+            checksum: Some(Checksum::LuhnMod36),
+            random_case: Some(0.5),
+            reserved_prefixes: vec!["XR-0".to_string()],
+            blocked_words: vec!["bad".to_string()],
+            ..Config::default()
+        },
+        Config {
+            pattern: Pattern::Template("AA-99-####".to_string()),
+            charset: Charset::Custom("ABC".to_string()),
+            format_tag: Some(FormatTag {
+                position: 4,
+                id: FormatId(1),
+            }),
+            expiry: Some(Expiry {
+                position: 5,
+                width: 2,
+                granularity: Granularity::Week,
+                epoch,
+                expires: epoch + Duration::from_secs(86_400 * 30),
+            }),
+            fields: Some(EmbeddedFields {
+                layout: FieldLayout::new(7, 1).field("region", 3),
+                values: vec![2],
+            }),
+            formatting: Formatting {
+                group: Some(Grouping {
+                    size: 4,
+                    separator: ' ',
+                }),
+                case: Some(LetterCase::Lower),
+            },
+            ..Config::default()
+        },
+        Config {
+            pattern: Pattern::Segments(vec![
+                (Pattern::Pattern("###-".to_string()), Charset::Alphabetic),
+                (
+                    Pattern::Template("99-##".to_string()),
+                    Charset::Custom("XY".to_string()),
+                ),
+            ]),
+            ..Config::default()
+        },
+    ];
+    for config in configs {
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Config>(&json).unwrap(),
+            config,
+            "{json}"
+        );
+    }
+
+    let json = |charset: Charset| serde_json::to_string(&charset).unwrap();
+    assert_eq!(json(Charset::Alphanumeric), r#""alphanumeric""#);
+    assert_eq!(json(Charset::Custom("ABC".to_string())), r#""ABC""#);
+    assert_eq!(
+        json(Charset::Custom("numeric".to_string())),
+        r#"{"custom":"numeric"}"#
+    );
+    let weighted = Charset::Weighted(vec![('A', 1), ('7', 3)]);
+    assert_eq!(json(weighted.clone()), r#"{"weighted":[["A",1],["7",3]]}"#);
+    assert_eq!(
+        serde_json::from_str::<Charset>(&json(weighted.clone())).unwrap(),
+        weighted
+    );
+    assert_eq!(serde_json::to_string(&Pattern::Length(8)).unwrap(), "8");
+}
@@ -0,0 +1,137 @@
+//! Printable voucher sheets.
+//!
+//! Renders a batch of codes as a self-contained HTML page laid out as a grid,
+//! ready to be printed (or printed to PDF) from any browser. With the `qr`
+//! feature, each voucher can also carry a QR code of its code as inline SVG.
+
+/// Layout options for a voucher sheet.
+#[derive(Clone, Debug)]
+pub struct SheetOptions {
+    /// Optional title printed at the top of the sheet.
+    pub title: Option<String>,
+    /// Number of vouchers per row.
+    pub columns: usize,
+    /// Whether to render a QR code alongside each code.
+    #[cfg(feature = "qr")]
+    pub qr: bool,
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        SheetOptions {
+            title: None,
+            columns: 3,
+            #[cfg(feature = "qr")]
+            qr: false,
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(feature = "qr")]
+fn qr_svg(code: &str) -> String {
+    use qrcode::{QrCode, render::svg};
+
+    let Ok(qr) = QrCode::new(code.as_bytes()) else {
+        return String::new();
+    };
+
+    let svg = qr.render::<svg::Color>().min_dimensions(96, 96).build();
+
+    // Drop the XML declaration so the SVG can be inlined in HTML.
+    match svg.find("<svg") {
+        Some(start) => svg[start..].to_string(),
+        None => svg,
+    }
+}
+
+/// Renders codes as a printable HTML voucher sheet.
+///
+/// # Arguments
+///
+/// * `codes` - The codes to print, one voucher each
+/// * `options` - Layout options for the sheet
+///
+/// # Returns
+///
+/// A complete HTML document with one cell per code. Codes are HTML-escaped,
+/// so custom charsets containing markup characters are rendered safely.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::sheet::{SheetOptions, to_html};
+///
+/// let codes = vec!["ABC123".to_string(), "XYZ789".to_string()];
+/// let html = to_html(&codes, &SheetOptions::default());
+///
+/// assert!(html.contains("ABC123"));
+/// assert!(html.starts_with("<!DOCTYPE html>"));
+/// ```
+pub fn to_html<S: AsRef<str>>(codes: &[S], options: &SheetOptions) -> String {
+    let columns = options.columns.max(1);
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+
+    if let Some(title) = &options.title {
+        html.push_str(&format!("<title>{}</title>\n", escape(title)));
+    }
+
+    html.push_str(&format!(
+        "<style>\n\
+         .sheet {{ display: grid; grid-template-columns: repeat({columns}, 1fr); gap: 8mm; }}\n\
+         .voucher {{ border: 1px dashed #888; padding: 6mm; text-align: center; break-inside: avoid; }}\n\
+         .code {{ font-family: monospace; font-size: 14pt; letter-spacing: 0.1em; }}\n\
+         </style>\n</head>\n<body>\n"
+    ));
+
+    if let Some(title) = &options.title {
+        html.push_str(&format!("<h1>{}</h1>\n", escape(title)));
+    }
+
+    html.push_str("<div class=\"sheet\">\n");
+
+    for code in codes {
+        let code = code.as_ref();
+        html.push_str("<div class=\"voucher\">");
+
+        #[cfg(feature = "qr")]
+        if options.qr {
+            html.push_str(&qr_svg(code));
+        }
+
+        html.push_str(&format!(
+            "<div class=\"code\">{}</div></div>\n",
+            escape(code)
+        ));
+    }
+
+    html.push_str("</div>\n</body>\n</html>\n");
+
+    html
+}
+
+#[test]
+fn test_to_html_escapes_codes() {
+    let codes = ["<b>&</b>"];
+
+    let html = to_html(&codes, &SheetOptions::default());
+
+    assert!(html.contains("&lt;b&gt;&amp;&lt;/b&gt;"));
+    assert!(!html.contains("<b>&</b>"));
+}
@@ -0,0 +1,231 @@
+use std::{collections::HashSet, convert::Infallible, error::Error, future::Future};
+
+use crate::{
+    Attempts, Config, ReferralCodeError, check_config, dedup_key, generate_one_with,
+    reservation::RESERVE_ATTEMPTS, rng::rng_for,
+};
+
+/// Number of candidates [`generate_async`] looks up in one round trip, at most.
+const LOOKUP_BATCH: usize = 1_000;
+
+/// A store of issued codes, looked up asynchronously.
+///
+/// Implement this against live storage, such as Redis or a Postgres table,
+/// to generate codes with [`generate_async`] that the store doesn't hold
+/// yet. Override [`AsyncUniquenessStore::are_taken`] to look up many codes
+/// in one round trip, such as with `MGET` or `WHERE code = ANY($1)`.
+///
+/// Looking codes up reserves nothing, so two generators running at once may
+/// return the same free code. Insert issued codes under a unique index, or
+/// use a [`crate::UniquenessStore`] when several generators share a store.
+pub trait AsyncUniquenessStore: Sync {
+    /// The error returned when the store fails, such as a lost connection.
+    type Error: Error + Send + Sync + 'static;
+
+    /// Returns `true` if the store already holds `code`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store failed to tell.
+    fn is_taken(&self, code: &str) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+
+    /// Returns, for each code, whether the store already holds it.
+    ///
+    /// The default looks the codes up one by one with
+    /// [`AsyncUniquenessStore::is_taken`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store failed to tell.
+    fn are_taken(
+        &self,
+        codes: &[String],
+    ) -> impl Future<Output = Result<Vec<bool>, Self::Error>> + Send {
+        async move {
+            let mut taken = Vec::with_capacity(codes.len());
+            for code in codes {
+                taken.push(self.is_taken(code).await?);
+            }
+
+            Ok(taken)
+        }
+    }
+}
+
+impl AsyncUniquenessStore for HashSet<String> {
+    type Error = Infallible;
+
+    async fn is_taken(&self, code: &str) -> Result<bool, Infallible> {
+        Ok(self.contains(code))
+    }
+}
+
+/// Generates `config.count` codes that an asynchronous store doesn't hold.
+///
+/// Candidates are drawn as [`crate::generate`] draws them and looked up in
+/// batches of up to a thousand, with one call to
+/// [`AsyncUniquenessStore::are_taken`] each. With `random_case`, the
+/// lowercased codes are looked up. After 100,000 candidates are taken
+/// without a free one, the store is taken to be full. The returned codes
+/// are not added to the store.
+///
+/// # Errors
+///
+/// Returns the same errors as [`crate::generate`], and also:
+///
+/// * `ReferralCodeError::Store` - If the store fails
+/// * `ReferralCodeError::StoreSaturated` - If the store turned down too many
+///   candidates in a row
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+/// use referral_codes::{Charset, Config, Pattern, generate_async};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Length(2),
+///     count: 10,
+///     ..Config::default()
+/// };
+/// let issued: HashSet<String> = (0..90).map(|n| format!("{n:02}")).collect();
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// let mut codes = runtime.block_on(generate_async(&config, &issued)).unwrap();
+/// codes.sort();
+///
+/// assert_eq!(codes, ["90", "91", "92", "93", "94", "95", "96", "97", "98", "99"]);
+/// ```
+pub async fn generate_async<S: AsyncUniquenessStore + ?Sized>(
+    config: &Config,
+    store: &S,
+) -> Result<Vec<String>, ReferralCodeError> {
+    check_config(config)?;
+
+    let mut seen = HashSet::with_capacity(config.count);
+    let mut codes = Vec::with_capacity(config.count);
+    let mut rejected = 0;
+
+    while codes.len() < config.count {
+        let batch = (config.count - codes.len()).min(LOOKUP_BATCH);
+        let mut attempts = Attempts::new(config, seen.len(), batch);
+        let Some((candidates, keys)) = draw_candidates(config, &mut seen, batch, &mut attempts)
+        else {
+            return Err(attempts.exceeded(codes));
+        };
+        let taken = store
+            .are_taken(&keys)
+            .await
+            .map_err(|e| ReferralCodeError::Store(Box::new(e)))?;
+
+        for (code, taken) in candidates.into_iter().zip(taken) {
+            if !taken {
+                rejected = 0;
+                codes.push(code);
+                continue;
+            }
+
+            rejected += 1;
+            if rejected == RESERVE_ATTEMPTS {
+                return Err(ReferralCodeError::StoreSaturated);
+            }
+        }
+    }
+
+    Ok(codes)
+}
+
+/// Draws `n` codes not drawn before, with their deduplication keys, or
+/// returns `None` once `attempts` is spent.
+///
+/// The generator lives in this function only, so the future of
+/// [`generate_async`] stays `Send`.
+fn draw_candidates(
+    config: &Config,
+    seen: &mut HashSet<String>,
+    n: usize,
+    attempts: &mut Attempts,
+) -> Option<(Vec<String>, Vec<String>)> {
+    let mut rng = rng_for(config);
+    let mut candidates = Vec::with_capacity(n);
+    let mut keys = Vec::with_capacity(n);
+
+    while candidates.len() < n {
+        let code = generate_one_with(config, &mut rng, attempts)?;
+        let key = dedup_key(config, &code).into_owned();
+        if seen.insert(key.clone()) {
+            candidates.push(code);
+            keys.push(key);
+        }
+    }
+
+    Some((candidates, keys))
+}
+
+#[test]
+fn test_generate_async_batches_lookups() {
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use crate::{Charset, Pattern};
+
+    /// A store holding every even number, counting its round trips.
+    struct Evens {
+        lookups: AtomicUsize,
+        fail: Mutex<bool>,
+    }
+
+    impl AsyncUniquenessStore for Evens {
+        type Error = std::io::Error;
+
+        async fn is_taken(&self, code: &str) -> Result<bool, std::io::Error> {
+            Ok(self.are_taken(&[code.to_string()]).await?[0])
+        }
+
+        async fn are_taken(&self, codes: &[String]) -> Result<Vec<bool>, std::io::Error> {
+            if *self.fail.lock().unwrap() {
+                return Err(std::io::Error::other("connection lost"));
+            }
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+
+            Ok(codes
+                .iter()
+                .map(|code| code.parse::<u32>().unwrap() % 2 == 0)
+                .collect())
+        }
+    }
+
+    let store = Evens {
+        lookups: AtomicUsize::new(0),
+        fail: Mutex::new(false),
+    };
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(4),
+        count: 2_000,
+        ..Config::default()
+    };
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let codes = runtime.block_on(generate_async(&config, &store)).unwrap();
+    assert_eq!(codes.len(), 2_000);
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 2_000);
+    assert!(
+        codes
+            .iter()
+            .all(|code| code.parse::<u32>().unwrap() % 2 == 1)
+    );
+    // About half the candidates are free, so a few batches are enough.
+    assert!(store.lookups.load(Ordering::Relaxed) < 20);
+
+    *store.fail.lock().unwrap() = true;
+    assert!(matches!(
+        runtime.block_on(generate_async(&config, &store)),
+        Err(ReferralCodeError::Store(_))
+    ));
+}
@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+
+use tokio::{sync::mpsc::Sender, task::JoinHandle};
+
+use crate::{
+    Attempts, Config, ReferralCodeError, check_config, dedup_key, generate_one_with, rng::rng_for,
+};
+
+/// Spawns a task that generates `config.count` unique codes into a channel.
+///
+/// Codes are sent one at a time as they are generated. With a bounded
+/// channel, the producer waits whenever the channel is full, so a slow
+/// consumer (e.g. one persisting each code) naturally throttles generation
+/// instead of letting codes pile up in memory.
+///
+/// The task stops early if the receiving half of the channel is dropped.
+///
+/// Must be called from within a Tokio runtime.
+///
+/// # Arguments
+///
+/// * `config` - Configuration specifying the pattern, charset, and count of codes to generate
+/// * `sender` - The sending half of the channel the codes are delivered to
+///
+/// # Returns
+///
+/// A handle to the spawned task, which resolves to:
+///
+/// * `Ok(sent)` - The number of codes delivered; less than `config.count`
+///   only if the receiver was dropped
+/// * `Err(ReferralCodeError)` - If the configuration is invalid, with the same
+///   errors as [`crate::generate`]
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, spawn_producer};
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+///
+/// runtime.block_on(async {
+///     let (sender, mut receiver) = tokio::sync::mpsc::channel(16);
+///     let producer = spawn_producer(Config::default().with_count(100), sender);
+///
+///     let mut received = 0;
+///     while let Some(_code) = receiver.recv().await {
+///         received += 1;
+///     }
+///
+///     assert_eq!(producer.await.unwrap().unwrap(), 100);
+///     assert_eq!(received, 100);
+/// });
+/// ```
+pub fn spawn_producer(
+    config: Config,
+    sender: Sender<String>,
+) -> JoinHandle<Result<usize, ReferralCodeError>> {
+    tokio::spawn(async move {
+        check_config(&config)?;
+
+        let mut codes = HashSet::with_capacity(config.count);
+        let mut attempts = Attempts::new(&config, 0, config.count);
+        let mut sent = 0;
+
+        while sent < config.count {
+            // Drawn anew for every code, as the thread-local generator must
+            // not be held across the await below.
+            let Some(code) = generate_one_with(&config, &mut rng_for(&config), &mut attempts)
+            else {
+                // The codes drawn so far were sent already.
+                return Err(attempts.exceeded(Vec::new()));
+            };
+
+            if codes.insert(dedup_key(&config, &code).into_owned()) {
+                if sender.send(code).await.is_err() {
+                    break;
+                }
+                sent += 1;
+            }
+        }
+
+        Ok(sent)
+    })
+}
+
+#[test]
+fn test_spawn_producer_stops_when_receiver_dropped() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let producer = spawn_producer(Config::default().with_count(1_000), sender);
+
+        receiver.recv().await.unwrap();
+        drop(receiver);
+
+        // The code whose send failed once the receiver was gone is not counted.
+        assert_eq!(producer.await.unwrap().unwrap(), 1);
+    });
+}
@@ -0,0 +1,295 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{HeaderName, Request, Response, StatusCode};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{Config, SpecVersion, Verifier};
+
+/// Where a [`VerifyCodeLayer`] finds the code in a request.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum CodeLocation {
+    /// The zero-based segment of the request path, e.g. `1` for `/redeem/{code}`.
+    PathSegment(usize),
+    /// The value of a query parameter.
+    Query(String),
+    /// The value of a header.
+    Header(HeaderName),
+}
+
+/// A code that passed verification, stored in the request extensions.
+///
+/// Handlers behind a [`VerifyCodeLayer`] read it instead of parsing the
+/// request again, e.g. with axum's `Extension<VerifiedCode>` extractor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiedCode(pub String);
+
+/// A tower layer rejecting requests that don't carry a valid code.
+///
+/// The code is read from the configured [`CodeLocation`], percent-decoded
+/// when it comes from the path or query, trimmed of surrounding whitespace
+/// and verified with [`crate::verify_as`], which checks the pattern, format
+/// tag, OCR check and checksum characters. Requests without a valid code get
+/// an empty `400 Bad Request` response without reaching the inner service;
+/// the others reach it with a [`VerifiedCode`] extension. Build the layer
+/// from a [`Verifier`] to also turn down revoked codes and report outcomes
+/// to a [`crate::VerificationObserver`].
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{CodeLocation, Config, VerifyCodeLayer};
+///
+/// let layer = VerifyCodeLayer::new(Config::default(), CodeLocation::PathSegment(1));
+/// ```
+///
+/// With tower's `ServiceBuilder` or axum's `Router::layer`, the layer wraps
+/// every route of a service.
+#[derive(Clone, Debug)]
+pub struct VerifyCodeLayer {
+    verifier: Arc<Verifier>,
+    location: CodeLocation,
+}
+
+impl VerifyCodeLayer {
+    /// Creates a layer verifying codes at `location` against `config`.
+    ///
+    /// Codes are verified by the rules of [`SpecVersion::LATEST`].
+    pub fn new(config: Config, location: CodeLocation) -> Self {
+        Self::from_verifier(Verifier::new(config), location)
+    }
+
+    /// Creates a layer verifying codes at `location` with `verifier`.
+    pub fn from_verifier(verifier: Verifier, location: CodeLocation) -> Self {
+        VerifyCodeLayer {
+            verifier: Arc::new(verifier),
+            location,
+        }
+    }
+
+    /// Returns this layer verifying codes by the rules of `version` instead.
+    pub fn with_spec_version(self, version: SpecVersion) -> Self {
+        let verifier = Arc::unwrap_or_clone(self.verifier).with_spec_version(version);
+
+        VerifyCodeLayer {
+            verifier: Arc::new(verifier),
+            ..self
+        }
+    }
+
+    /// Returns the verified code of a request, if it carries a valid one.
+    fn verify<B>(&self, request: &Request<B>) -> Option<String> {
+        let raw = match &self.location {
+            CodeLocation::PathSegment(i) => percent_decode(
+                request
+                    .uri()
+                    .path()
+                    .trim_start_matches('/')
+                    .split('/')
+                    .nth(*i)?,
+            )?,
+            CodeLocation::Query(name) => request
+                .uri()
+                .query()?
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| percent_decode(key).as_deref() == Some(name))
+                .and_then(|(_, value)| percent_decode(&value.replace('+', " ")))?,
+            CodeLocation::Header(name) => request.headers().get(name)?.to_str().ok()?.to_string(),
+        };
+        let code = raw.trim();
+
+        self.verifier.verify(code).then(|| code.to_string())
+    }
+}
+
+impl<S> Layer<S> for VerifyCodeLayer {
+    type Service = VerifyCode<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        VerifyCode {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// The service produced by [`VerifyCodeLayer`].
+#[derive(Clone, Debug)]
+pub struct VerifyCode<S> {
+    inner: S,
+    layer: VerifyCodeLayer,
+}
+
+impl<S, B, ResBody> Service<Request<B>> for VerifyCode<S>
+where
+    S: Service<Request<B>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        match self.layer.verify(&request) {
+            Some(code) => {
+                request.extensions_mut().insert(VerifiedCode(code));
+                ResponseFuture::Inner {
+                    future: self.inner.call(request),
+                }
+            }
+            None => {
+                let mut response = Response::new(ResBody::default());
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                ResponseFuture::Rejected {
+                    response: Some(response),
+                }
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// The response future of [`VerifyCode`].
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<F, B> {
+        /// The request was forwarded to the inner service.
+        Inner {
+            #[pin]
+            future: F,
+        },
+        /// The request was rejected.
+        Rejected {
+            response: Option<Response<B>>,
+        },
+    }
+}
+
+impl<F, B, E> Future for ResponseFuture<F, B>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Inner { future } => future.poll(cx),
+            ResponseFutureProj::Rejected { response } => Poll::Ready(Ok(response
+                .take()
+                .expect("ResponseFuture polled after completion"))),
+        }
+    }
+}
+
+/// Decodes `%XX` escapes, returning `None` if they are malformed or not UTF-8.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut input = s.bytes();
+
+    while let Some(b) = input.next() {
+        if b != b'%' {
+            bytes.push(b);
+            continue;
+        }
+
+        let hex = [input.next()?, input.next()?];
+        bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+#[test]
+fn test_verify_code_layer() {
+    use std::{convert::Infallible, future::Ready};
+
+    use crate::{Charset, Pattern};
+
+    /// Responds with the verified code.
+    struct Echo;
+
+    impl Service<Request<()>> for Echo {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<String>, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<()>) -> Self::Future {
+            let code = request.extensions().get::<VerifiedCode>().unwrap();
+            std::future::ready(Ok(Response::new(code.0.clone())))
+        }
+    }
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Pattern("R-####".to_string()),
+        ..Config::default()
+    };
+    let respond_with = |layer: VerifyCodeLayer, uri: &str| {
+        let mut service = layer.layer(Echo);
+        let request = Request::builder()
+            .uri(uri)
+            .header("x-code", " R-0042 ")
+            .body(())
+            .unwrap();
+        let mut future = std::pin::pin!(service.call(request));
+        let mut cx = Context::from_waker(std::task::Waker::noop());
+
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(Ok(response)) => (response.status(), response.into_body()),
+            _ => unreachable!(),
+        }
+    };
+    let respond = |location: CodeLocation, uri: &str| {
+        respond_with(VerifyCodeLayer::new(config.clone(), location), uri)
+    };
+
+    assert_eq!(
+        respond(CodeLocation::PathSegment(1), "/redeem/R-1234"),
+        (StatusCode::OK, "R-1234".to_string())
+    );
+    assert_eq!(
+        respond(CodeLocation::PathSegment(1), "/redeem/R-12A4").0,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        respond(
+            CodeLocation::Query("code".to_string()),
+            "/r?x=1&code=R%2D5678"
+        )
+        .1,
+        "R-5678"
+    );
+    assert_eq!(
+        respond(CodeLocation::Header(HeaderName::from_static("x-code")), "/").1,
+        "R-0042"
+    );
+    assert_eq!(
+        respond(CodeLocation::Query("code".to_string()), "/r").0,
+        StatusCode::BAD_REQUEST
+    );
+
+    let revoking = VerifyCodeLayer::from_verifier(
+        Verifier::new(config.clone()).with_revoked(["R-1234"]),
+        CodeLocation::PathSegment(1),
+    );
+    assert_eq!(
+        respond_with(revoking.clone(), "/redeem/R-1234").0,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(respond_with(revoking, "/redeem/R-4321").0, StatusCode::OK);
+}
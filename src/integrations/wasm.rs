@@ -0,0 +1,74 @@
+//! Generation and verification for JavaScript, with `wasm-bindgen`.
+//!
+//! Built for a WebAssembly target, these functions are exported to
+//! JavaScript, so a dashboard previews codes with the same rules as the
+//! server issuing them. Configurations are passed as JSON, written as
+//! with the `serde` feature, and errors are thrown as JavaScript `Error`s.
+//!
+//! ```js
+//! import { generate, validate } from "referral-codes";
+//!
+//! const config = JSON.stringify({ pattern: "REF-####", count: 5 });
+//! const codes = generate(config);
+//! validate(config, codes[0]); // true
+//! ```
+
+use std::error::Error;
+
+use wasm_bindgen::prelude::{JsError, wasm_bindgen};
+
+use crate::{Config, Verifier};
+
+/// Generates `count` unique codes of a configuration given as JSON.
+///
+/// # Errors
+///
+/// Throws if the configuration is not valid JSON of a [`Config`], or with
+/// the errors of [`crate::generate`].
+#[wasm_bindgen]
+pub fn generate(config: &str) -> Result<Vec<String>, JsError> {
+    generate_codes(config).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Returns `true` if a code is valid under a configuration given as JSON,
+/// as [`Verifier::verify`] checks it.
+///
+/// # Errors
+///
+/// Throws if the configuration is not valid JSON of a [`Config`].
+#[wasm_bindgen]
+pub fn validate(config: &str, code: &str) -> Result<bool, JsError> {
+    validate_code(config, code).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn generate_codes(config: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let config: Config = serde_json::from_str(config)?;
+
+    Ok(crate::generate(&config)?)
+}
+
+fn validate_code(config: &str, code: &str) -> Result<bool, Box<dyn Error>> {
+    let config: Config = serde_json::from_str(config)?;
+
+    Ok(Verifier::new(config).verify(code))
+}
+
+#[test]
+fn test_json_configs_generate_and_validate() {
+    let config = r#"{"pattern": "REF-####", "charset": "numeric", "count": 5}"#;
+
+    let codes = generate_codes(config).unwrap();
+    assert_eq!(codes.len(), 5);
+    assert!(codes.iter().all(|code| code.starts_with("REF-")));
+    assert!(validate_code(config, &codes[0]).unwrap());
+    assert!(!validate_code(config, "REF-12a4").unwrap());
+
+    assert_eq!(
+        generate_codes(r#"{"pattern": 1, "charset": "numeric", "count": 11}"#)
+            .unwrap_err()
+            .to_string(),
+        "Non feasible configuration"
+    );
+    assert!(generate_codes(r#"{"patern": 8}"#).is_err());
+    assert!(validate_code("8", "REF-1234").is_err());
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use crate::{
+    Attempts, Config, ReferralCodeError, check_config, dedup_key, generate_one_with, rng::rng_for,
+};
+
+/// Iterator over codes of several configurations, in turn.
+///
+/// Created by [`interleave`].
+#[derive(Clone, Debug)]
+pub struct Interleave {
+    configs: Vec<Config>,
+    remaining: Vec<usize>,
+    attempts: Vec<Attempts>,
+    next: usize,
+    seen: HashSet<String>,
+    /// A configuration with `random_case`, whose deduplication key every
+    /// code is stored under, if any configuration uses it.
+    folding: Option<usize>,
+}
+
+impl Iterator for Interleave {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let len = self.configs.len();
+        let i = (0..len)
+            .map(|offset| (self.next + offset) % len)
+            .find(|i| self.remaining[*i] > 0)?;
+        let config = &self.configs[i];
+        let keyed = self.folding.map_or(config, |k| &self.configs[k]);
+
+        let mut rng = rng_for(config);
+        let code = loop {
+            let Some(candidate) = generate_one_with(config, &mut rng, &mut self.attempts[i]) else {
+                self.remaining.fill(0);
+                return None;
+            };
+
+            if self.seen.insert(dedup_key(keyed, &candidate).into_owned()) {
+                break candidate;
+            }
+        };
+
+        self.remaining[i] -= 1;
+        self.next = (i + 1) % len;
+
+        Some(code)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining.iter().sum();
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Interleave {}
+
+/// Generates codes of several configurations in a single, unique stream.
+///
+/// Codes are yielded round-robin, one per configuration in turn, until each
+/// configuration has yielded its `count` codes; configurations that are
+/// done are skipped. No code is yielded twice across the whole stream. If
+/// any configuration uses `random_case`, codes differing only in case count
+/// as the same code, whichever configuration produced them.
+///
+/// Feasibility is checked conservatively: every configuration must have
+/// room for its own codes plus those of every other configuration whose
+/// format overlaps it, as reported by [`Config::compatible_with`]. The
+/// stream ends early once a configuration has made
+/// [`Config::max_attempts`] draws without enough unique codes.
+///
+/// # Arguments
+///
+/// * `configs` - The configurations to interleave, in turn order
+///
+/// # Errors
+///
+/// Returns the same errors as [`crate::generate`].
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, interleave};
+///
+/// let gold = Config::default().with_prefix("GOLD-").with_count(2);
+/// let silver = Config::default().with_prefix("SILVER-").with_count(4);
+///
+/// let codes: Vec<String> = interleave(&[gold, silver]).unwrap().collect();
+///
+/// assert_eq!(codes.len(), 6);
+/// assert!(codes[0].starts_with("GOLD-") && codes[1].starts_with("SILVER-"));
+/// assert!(codes[2].starts_with("GOLD-") && codes[3].starts_with("SILVER-"));
+/// assert!(codes[4..].iter().all(|code| code.starts_with("SILVER-")));
+/// ```
+pub fn interleave(configs: &[Config]) -> Result<Interleave, ReferralCodeError> {
+    let mut attempts = Vec::with_capacity(configs.len());
+    for (i, config) in configs.iter().enumerate() {
+        let sharing: usize = configs
+            .iter()
+            .enumerate()
+            .filter(|(j, other)| *j != i && !config.compatible_with(other).is_compatible())
+            .map(|(_, other)| other.count)
+            .sum();
+        check_config(&config.with_count(config.count + sharing))?;
+        attempts.push(Attempts::new(config, sharing, config.count));
+    }
+
+    Ok(Interleave {
+        configs: configs.to_vec(),
+        remaining: configs.iter().map(|config| config.count).collect(),
+        attempts,
+        next: 0,
+        seen: HashSet::new(),
+        folding: configs
+            .iter()
+            .position(|config| config.random_case.is_some()),
+    })
+}
+
+#[test]
+fn test_interleave_enforces_global_uniqueness() {
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(2),
+        count: 50,
+        ..Config::default()
+    };
+
+    let codes: Vec<String> = interleave(&[config.clone(), config.clone()])
+        .unwrap()
+        .collect();
+
+    assert_eq!(codes.len(), 100);
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 100);
+    assert!(interleave(&[config.clone(), config.with_count(51)]).is_err());
+    assert_eq!(interleave(&[]).unwrap().count(), 0);
+
+    // An empty keyspace overlaps nothing, not even itself.
+    let empty = Config {
+        charset: Charset::Custom(String::new()),
+        count: 1,
+        ..config
+    };
+    assert!(interleave(&[empty]).is_err());
+}
@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use crate::{
+    Attempts, Config, ReferralCodeError, check_config, dedup_key, generate_one_with,
+    rng::{SourceRng, rng_for},
+};
+
+/// Iterator over unique random codes, see [`Config::iter`].
+struct Codes {
+    config: Config,
+    rng: SourceRng,
+    seen: HashSet<String>,
+    /// Codes left to draw, or `None` if more than a `u128` holds.
+    remaining: Option<u128>,
+    attempts: Attempts,
+}
+
+impl Iterator for Codes {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+
+        loop {
+            let Some(code) = generate_one_with(&self.config, &mut self.rng, &mut self.attempts)
+            else {
+                self.remaining = Some(0);
+                return None;
+            };
+            if self
+                .seen
+                .insert(dedup_key(&self.config, &code).into_owned())
+            {
+                self.remaining = self.remaining.map(|n| n - 1);
+                return Some(code);
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Returns an iterator drawing unique codes on demand.
+    ///
+    /// Codes come one at a time, as [`crate::generate`] would draw them,
+    /// so they can be filtered, taken or written out without first
+    /// collecting a batch. `config.count` is ignored: the iterator ends once
+    /// every code of the keyspace was yielded. It remembers the codes it
+    /// yielded to keep them unique, so memory still grows with the number
+    /// of codes drawn, but no more than the codes themselves take. Drawing
+    /// gets slower close to the end of the keyspace, where most draws were
+    /// already yielded. The iterator also ends once [`Config::max_attempts`]
+    /// draws are made, by default enough to go through the whole keyspace.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`crate::generate_one`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use referral_codes::{Charset, Config, Pattern};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Length(2),
+    ///     ..Config::default()
+    /// };
+    ///
+    /// let sevens: Vec<String> = config
+    ///     .iter()
+    ///     .unwrap()
+    ///     .filter(|code| code.ends_with('7'))
+    ///     .take(5)
+    ///     .collect();
+    /// assert_eq!(sevens.len(), 5);
+    ///
+    /// let all: HashSet<String> = config.iter().unwrap().collect();
+    /// assert_eq!(all.len(), 100);
+    /// ```
+    pub fn iter(&self) -> Result<impl Iterator<Item = String> + use<>, ReferralCodeError> {
+        let config = self.with_count(1);
+        check_config(&config)?;
+
+        let remaining = config.keyspace();
+        let total = remaining.map_or(usize::MAX, |n| n.try_into().unwrap_or(usize::MAX));
+
+        Ok(Codes {
+            remaining,
+            attempts: Attempts::new(&config, 0, total),
+            rng: rng_for(&config),
+            config,
+            seen: HashSet::new(),
+        })
+    }
+}
+
+#[test]
+fn test_iter_yields_unique_codes_until_exhausted() {
+    use crate::{Charset, OcrCheck, Pattern, verify_ocr_check};
+
+    let config = Config {
+        charset: Charset::Custom("ABCD".to_string()),
+        pattern: Pattern::Length(4),
+        ocr_check: Some(OcrCheck::new(1, 3)),
+        reserved_prefixes: vec!["A".to_string()],
+        ..Config::default()
+    };
+
+    let codes: Vec<String> = config.iter().unwrap().collect();
+    assert_eq!(codes.len(), 12);
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 12);
+    assert!(
+        codes
+            .iter()
+            .all(|code| verify_ocr_check(code, &config) && !code.starts_with('A'))
+    );
+
+    let literal = config.with_pattern(Pattern::Pattern("AB".to_string()));
+    assert_eq!(
+        Config {
+            reserved_prefixes: Vec::new(),
+            ocr_check: None,
+            ..literal.clone()
+        }
+        .iter()
+        .unwrap()
+        .collect::<Vec<_>>(),
+        ["AB"]
+    );
+    assert!(matches!(
+        literal.iter().map(|_| ()),
+        Err(ReferralCodeError::InvalidCheckPositions)
+    ));
+}
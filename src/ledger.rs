@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use crate::{Batch, Config, ReferralCodeError};
+
+/// A durable record of which batches have already been produced.
+///
+/// Implement this against your job store (a database table, a key-value
+/// store, ...) to make batch production exactly-once across retries.
+pub trait BatchLedger {
+    /// Returns the digest recorded for `batch_id`, if the batch was produced.
+    fn get(&self, batch_id: &str) -> Option<[u8; 32]>;
+
+    /// Records that `batch_id` was produced with the given digest.
+    fn record(&mut self, batch_id: &str, digest: [u8; 32]);
+}
+
+impl BatchLedger for HashMap<String, [u8; 32]> {
+    fn get(&self, batch_id: &str) -> Option<[u8; 32]> {
+        HashMap::get(self, batch_id).copied()
+    }
+
+    fn record(&mut self, batch_id: &str, digest: [u8; 32]) {
+        self.insert(batch_id.to_string(), digest);
+    }
+}
+
+/// The outcome of [`produce_once`].
+// Outcomes are returned once per call, so boxing the batch would buy nothing.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum LedgerOutcome {
+    /// The batch was generated now and its digest recorded.
+    Produced(Batch),
+    /// The batch had already been produced; this is its recorded digest.
+    AlreadyProduced([u8; 32]),
+}
+
+/// Produces a seeded batch at most once per batch identifier.
+///
+/// If the ledger already holds a digest for `batch_id`, no codes are
+/// generated and the stored digest is returned. Otherwise the batch is
+/// generated with [`Batch::generate_seeded`] and its digest recorded. Because
+/// the batch is seeded, a retry that crashed after recording can still
+/// recreate the exact same codes and check them against the digest.
+///
+/// # Arguments
+///
+/// * `ledger` - The record of produced batches
+/// * `batch_id` - Identifier of the batch
+/// * `config` - Configuration specifying the pattern, charset, and count of codes to generate
+/// * `seed` - Seed for deterministic generation
+///
+/// # Errors
+///
+/// Returns the same errors as [`crate::generate`].
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use referral_codes::{Config, LedgerOutcome, produce_once};
+///
+/// let mut ledger = HashMap::new();
+/// let config = Config::default().with_count(10);
+///
+/// let LedgerOutcome::Produced(batch) = produce_once(&mut ledger, "job-1", &config, 7).unwrap() else {
+///     panic!("first run should produce the batch");
+/// };
+///
+/// match produce_once(&mut ledger, "job-1", &config, 7).unwrap() {
+///     LedgerOutcome::AlreadyProduced(digest) => assert_eq!(digest, batch.digest()),
+///     LedgerOutcome::Produced(_) => panic!("retry should not produce again"),
+/// }
+/// ```
+pub fn produce_once<L: BatchLedger + ?Sized>(
+    ledger: &mut L,
+    batch_id: &str,
+    config: &Config,
+    seed: u64,
+) -> Result<LedgerOutcome, ReferralCodeError> {
+    if let Some(digest) = ledger.get(batch_id) {
+        return Ok(LedgerOutcome::AlreadyProduced(digest));
+    }
+
+    let batch = Batch::generate_seeded(batch_id, config, seed)?;
+    ledger.record(batch_id, batch.digest());
+
+    Ok(LedgerOutcome::Produced(batch))
+}
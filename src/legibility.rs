@@ -0,0 +1,119 @@
+use alloc::vec::Vec;
+
+/// Groups of characters that are easily mistaken for one another on a screen.
+const LOOK_ALIKES: &[&str] = &["0OoQD", "1IlLi|", "2Zz", "5Ss", "6Gb", "8B", "UVuv", "nm"];
+
+/// Groups of letters that are easily mistaken for one another over a poor line.
+const SOUND_ALIKES: &[&str] = &["BDEPTV", "MN", "FS"];
+
+/// How easily a code is read aloud, remembered and written down by a person.
+///
+/// Every component is between 0 and 1. Only letters and digits are scored:
+/// separators are not spoken.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct LegibilityScore {
+    /// The fraction of characters that fall into syllables: letters outside
+    /// runs of more than two consonants or two vowels, and digits, which are
+    /// read one by one.
+    pub pronounceability: f64,
+    /// The fraction of characters that can be mistaken for another character.
+    pub confusable_density: f64,
+    /// The fraction of characters repeating the previous one, which listeners
+    /// tend to drop or double.
+    pub repetition: f64,
+}
+
+impl LegibilityScore {
+    /// Returns the combined score, from 0 (illegible) to 1.
+    pub fn value(&self) -> f64 {
+        self.pronounceability * (1.0 - self.confusable_density) * (1.0 - self.repetition)
+    }
+}
+
+/// Scores how human-friendly a code is.
+///
+/// Use this to keep the most legible codes of a batch destined for spoken
+/// or broadcast promotion, where codes are heard once and typed from memory.
+/// Case is ignored, except for look-alikes such as `l` and `1`.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::score;
+///
+/// let mut codes = vec!["QZ0O1", "HAKAYA", "TAPPEE"];
+/// codes.sort_by(|a, b| score(b).value().total_cmp(&score(a).value()));
+///
+/// assert_eq!(codes, ["HAKAYA", "TAPPEE", "QZ0O1"]);
+/// assert_eq!(score("QZ0O1").confusable_density, 1.0);
+/// assert!(score("TAPPEE").repetition > 0.0);
+/// ```
+pub fn score(code: &str) -> LegibilityScore {
+    let chars: Vec<char> = code.chars().filter(|c| c.is_alphanumeric()).collect();
+    if chars.is_empty() {
+        return LegibilityScore::default();
+    }
+    let len = chars.len() as f64;
+
+    LegibilityScore {
+        pronounceability: 1.0 - unpronounceable(&chars) as f64 / len,
+        confusable_density: chars.iter().filter(|c| is_confusable(**c)).count() as f64 / len,
+        repetition: chars
+            .windows(2)
+            .filter(|pair| same_char(pair[0], pair[1]))
+            .count() as f64
+            / len,
+    }
+}
+
+/// Counts the letters beyond the second of runs of consonants or of vowels.
+fn unpronounceable(chars: &[char]) -> usize {
+    let is_vowel = |c: char| "aeiou".contains(c.to_ascii_lowercase());
+    let mut excess = 0;
+    let mut run = 0;
+
+    for (i, c) in chars.iter().enumerate() {
+        if !c.is_alphabetic() {
+            run = 0;
+            continue;
+        }
+
+        let continues =
+            i > 0 && chars[i - 1].is_alphabetic() && is_vowel(chars[i - 1]) == is_vowel(*c);
+        run = if continues { run + 1 } else { 1 };
+        if run > 2 {
+            excess += 1;
+        }
+    }
+
+    excess
+}
+
+fn is_confusable(c: char) -> bool {
+    LOOK_ALIKES
+        .iter()
+        .chain(SOUND_ALIKES)
+        .any(|group| group.contains(c) || group.contains(c.to_ascii_uppercase()))
+}
+
+fn same_char(a: char, b: char) -> bool {
+    a.to_lowercase().eq(b.to_lowercase())
+}
+
+#[test]
+fn test_score_components() {
+    let clean = score("ka-ha-ya");
+    assert_eq!(clean.pronounceability, 1.0);
+    assert_eq!(clean.repetition, 0.0);
+    assert_eq!(clean.value(), 1.0);
+
+    // "STRK" has two letters past the second consonant, and "S" and "T"
+    // sound like "F" and "D".
+    let cluster = score("STRK");
+    assert_eq!(cluster.pronounceability, 0.5);
+    assert_eq!(cluster.confusable_density, 0.5);
+
+    assert_eq!(score("aaaa").repetition, 0.75);
+    assert_eq!(score("1-1").repetition, 0.5);
+    assert_eq!(score("--"), LegibilityScore::default());
+}
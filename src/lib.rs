@@ -1,28 +1,616 @@
+//! Generation and verification of referral codes.
+//!
+//! Everything is available from the crate root; modules only organize the
+//! source. The crate is layered as follows:
+//!
+//! * The core: [`Charset`], [`Pattern`] and [`Config`] describe a code
+//!   format, [`generate`], [`generate_one`] and a seedable [`Generator`]
+//!   produce codes, and functions such as [`verify_ocr_check`] inspect them.
+//!   A [`VerificationBundle`] carries what verification needs to services
+//!   that can't reach the issuer, and a [`ConfigRegistry`] names formats
+//!   that can change at runtime.
+//! * Batches and uniqueness: [`Batch`], [`Registry`], [`UniquenessStore`] and
+//!   the file verifiers keep codes unique across a batch, a namespace or a
+//!   shared store, and with the `crypto` feature, [`DigestSet`] across runs
+//!   without keeping the codes. The [`bench`](mod@bench) module measures the
+//!   throughput of generation strategies.
+//! * Tamper evidence, with the `crypto` feature: batch digests, Merkle proofs,
+//!   keyed rotation, per-partner salting, codes signed with a
+//!   [`SigningKey`], signed [`Claims`] for links and exactly-once production.
+//! * Integrations with other crates, each behind its own feature.
+//!
+//! # Features
+//!
+//! * `std` (default) - Everything relying on the standard library, see
+//!   [Platform support](#platform-support). Every other feature enables it.
+//! * `generation` (default) - Code generation, which pulls in `rand`, the
+//!   only dependency of a default build. Without it, the crate has no
+//!   dependencies, and only verification, normalization and inspection remain.
+//! * `generation-rand09` - Code generation with `rand` 0.9 instead, for
+//!   projects that can't move to 0.10 yet. Use it with
+//!   `default-features = false`. Seeded generation gives the same codes with
+//!   either version.
+//! * `profanity` - [`Config::with_profanity_filter`], a built-in list of
+//!   offensive words to block.
+//! * `crypto` - Tamper evidence and [`Config::fingerprint`], with `hmac` and `sha2`.
+//! * `serde` - `Serialize` and `Deserialize` for [`Config`] and [`CodeChunk`].
+//!   Charsets are written by name, such as `"alphanumeric"`, or as their
+//!   characters, and `{"custom": "..."}` for a custom charset spelling a
+//!   name. Patterns are written as their length, such as `8`, their pattern
+//!   string, such as `"REF-####"`, `{"template": "AA-99"}`,
+//!   `{"pattern": "REF-???", "placeholder": "?"}` or `{"segments": [...]}`
+//!   pairing patterns with charsets.
+//! * `async` - [`spawn_producer`], a Tokio task streaming codes into a channel,
+//!   and [`generate_async`], checking codes against live storage.
+//! * `parallel` - [`generate_parallel`], drawing large batches on the
+//!   `rayon` thread pool.
+//! * `voucher-sheet` - [`sheet`], printable HTML voucher sheets.
+//! * `qr` - QR codes on voucher sheets, with `qrcode`.
+//! * `verify-layer` - [`VerifyCodeLayer`], a tower layer rejecting requests
+//!   without a valid code, with `http` and `tower`.
+//! * `cli` - The `referral-codes` binary, generating codes as lines, CSV or
+//!   JSON and validating them.
+//! * `wasm` - [`wasm`], generation and verification exported to JavaScript
+//!   with `wasm-bindgen`, taking JSON configurations. Enables `serde`.
+//!
+//! # Platform support
+//!
+//! Without the `std` feature, the crate is `#![no_std]` and only needs
+//! `alloc`, for firmware and other targets checking codes without an
+//! operating system. What remains is the core of formats: [`Charset`],
+//! [`Pattern`], [`Config`] and [`Config::builder`], [`validate`] and
+//! [`Verifier`], checksums and OCR checks, normalization and formatting,
+//! canonical strings and [`enumerate_all`]. Sets of characters and revoked
+//! codes are then `BTreeSet`s rather than `HashSet`s.
+//!
+//! The rest needs `std`:
+//!
+//! * [`Config::expiry`], as expiries are `SystemTime`s, and the entropy
+//!   functions, such as [`Config::entropy_bits`], which need the floating
+//!   point functions of `std`.
+//! * Batches, registries, stores and files, which rely on `std` I/O,
+//!   collections, locks and threads, and [`ReferralCodeError::Io`].
+//! * Generation, which also needs an operating system entropy source for
+//!   [`generate`] and the other functions drawing from the [`RngSource`] of
+//!   their configuration. A [`Generator`] draws from any `rand` generator
+//!   supplied by the caller, but it is built with the same dependencies.
+//!
+//! # Stability
+//!
+//! Minor releases only add to the API, so that long-lived services can
+//! upgrade without changes:
+//!
+//! * Enums that are expected to grow, such as [`ReferralCodeError`],
+//!   [`Charset`], [`Pattern`], [`Checksum`] and [`MigrationIssue`], are
+//!   `#[non_exhaustive]`: match them with a wildcard arm. Enums that answer
+//!   a closed question, such as [`Reservation`], are not.
+//! * Structs with public fields, such as [`Config`], are not
+//!   `#[non_exhaustive]`, which would rule out building them with struct
+//!   update syntax outside the crate. Instead, they only gain fields whose
+//!   default keeps the previous behavior. Build them with
+//!   `..Default::default()`, their constructors or, for [`Config`],
+//!   [`Config::builder`]: a literal spelling out every field can break in a
+//!   minor release.
+//! * Public traits, [`UniquenessStore`], `AsyncUniquenessStore` and
+//!   `BatchLedger`, are meant to be implemented by users, and only gain
+//!   methods with a default implementation. None are sealed.
+//! * Codes and files written by a release remain valid and readable by
+//!   later releases: verification rules are versioned by [`SpecVersion`], and
+//!   registry files and canonical strings carry their own version.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(all(
+    feature = "rng",
+    not(any(feature = "generation", feature = "generation-rand09"))
+))]
+compile_error!("enable `generation` or `generation-rand09` instead of `rng`");
+
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+#[cfg(feature = "rng")]
 use std::collections::HashSet;
 
-use rand::{RngExt, distr::Distribution, seq::IteratorRandom};
-use thiserror::Error;
+/// The set the core keeps characters and codes in, a `HashSet` with `std`
+/// and a `BTreeSet` without.
+#[cfg(feature = "std")]
+pub(crate) type Set<T> = std::collections::HashSet<T>;
+#[cfg(not(feature = "std"))]
+pub(crate) type Set<T> = alloc::collections::BTreeSet<T>;
+
+#[cfg(feature = "rng")]
+use bounded::Attempts;
+#[cfg(feature = "rng")]
+use rng::{CodeRng, backend::distr::Distribution, rng_for};
+
+// Core: formats, generation and verification of single codes.
+mod blocklist;
+#[cfg(feature = "std")]
+mod bloom;
+mod builder;
+#[cfg(feature = "std")]
+mod bundle;
+mod canonical;
+mod charset_file;
+mod checksum;
+mod classifier;
+mod code;
+mod compat;
+mod compatibility;
+#[cfg(feature = "std")]
+mod config_registry;
+mod entropy;
+mod enumerate;
+#[cfg(feature = "std")]
+mod expiry;
+mod fields;
+#[cfg(feature = "crypto")]
+mod fingerprint;
+mod folding;
+mod format;
+mod formatting;
+#[cfg(feature = "rng")]
+mod generator;
+mod input_mask;
+#[cfg(feature = "rng")]
+mod iter;
+mod legibility;
+mod link;
+mod lint;
+mod market;
+mod medium;
+mod migration;
+mod ocr;
+mod ordering;
+mod pattern_macro;
+mod repeats;
+mod reserved;
+#[cfg(feature = "rng")]
+mod rng;
+mod rng_source;
+mod segment;
+mod spec_version;
+mod untrusted;
+mod validation;
+#[cfg(feature = "rng")]
+mod vectors;
+mod verifier;
+
+// Batches and uniqueness across codes.
+#[cfg(feature = "rng")]
+mod balanced;
+#[cfg(feature = "std")]
+mod batch;
+#[cfg(feature = "rng")]
+pub mod bench;
+#[cfg(feature = "rng")]
+mod bounded;
+#[cfg(feature = "rng")]
+mod budget;
+#[cfg(feature = "crypto")]
+mod chunk;
+#[cfg(feature = "crypto")]
+mod digest_set;
+#[cfg(feature = "rng")]
+mod estimate;
+#[cfg(feature = "std")]
+mod external;
+#[cfg(feature = "rng")]
+mod interleave;
+#[cfg(feature = "rng")]
+mod more;
+#[cfg(feature = "rng")]
+mod rate_limit;
+#[cfg(feature = "std")]
+mod registry;
+#[cfg(feature = "std")]
+mod reservation;
+#[cfg(feature = "std")]
+mod sample;
+#[cfg(feature = "rng")]
+mod saturation;
+#[cfg(feature = "rng")]
+mod scoped;
+#[cfg(feature = "crypto")]
+mod sequence;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "bloom")]
+mod stream;
+#[cfg(feature = "rng")]
+mod stress;
+#[cfg(feature = "std")]
+mod verify_file;
+
+// Tamper evidence and auditing.
+#[cfg(feature = "crypto")]
+mod claims;
+#[cfg(all(feature = "crypto", feature = "rng"))]
+mod ledger;
+#[cfg(feature = "crypto")]
+mod merkle;
+#[cfg(all(feature = "crypto", feature = "rng"))]
+mod rotation;
+#[cfg(feature = "crypto")]
+mod salt;
+#[cfg(feature = "crypto")]
+mod signed;
+
+mod integrations;
+
+pub use builder::ConfigBuilder;
+#[cfg(feature = "std")]
+pub use bundle::VerificationBundle;
+pub use checksum::{Checksum, ChecksumCoverage, verify_checksum};
+pub use classifier::Classifier;
+pub use code::Code;
+#[cfg(feature = "rng")]
+pub use code::{GeneratedCode, generate_code, generate_codes};
+pub use compat::VoucherCodesOptions;
+pub use compatibility::CompatibilityReport;
+#[cfg(feature = "std")]
+pub use config_registry::{ConfigRegistry, ConfigSet};
+pub use enumerate::enumerate_all;
+#[cfg(feature = "std")]
+pub use expiry::{Expiry, Granularity};
+pub use fields::{EmbeddedFields, FieldLayout};
+pub use folding::{CollisionReport, Folding, fold};
+pub use format::{FormatId, FormatRegistry, FormatTag};
+pub use formatting::{Formatting, Grouping, LetterCase};
+#[cfg(feature = "rng")]
+pub use generator::Generator;
+pub use input_mask::MaskSyntax;
+pub use legibility::{LegibilityScore, score};
+pub use link::{Placement, ReferralLink};
+pub use lint::{AmbiguousLiteral, LintReport};
+pub use market::Markets;
+pub use medium::{Medium, MediumReport, QrEcLevel, QrSize};
+pub use migration::{MigrationIssue, MigrationReport, check_migration};
+pub use ocr::{OcrCheck, verify_ocr_check};
+pub use ordering::{compare_codes, sort_codes};
+#[doc(hidden)]
+pub use pattern_macro::__check_pattern_literal;
+pub use rng_source::RngSource;
+pub use segment::{CharClass, Segment};
+pub use spec_version::{SpecVersion, verify_as};
+pub use untrusted::Limits;
+pub use validation::{ValidationError, validate};
+#[cfg(feature = "rng")]
+pub use vectors::{TestVector, VectorKind, test_vectors};
+pub use verifier::{VerificationObserver, Verifier, VerifyOutcome};
+
+#[cfg(feature = "rng")]
+pub use balanced::generate_balanced;
+#[cfg(feature = "std")]
+pub use batch::{Assignment, Batch, Provenance, RngPolicy, Strategy};
+#[cfg(feature = "rng")]
+pub use bounded::generate_bounded;
+#[cfg(feature = "crypto")]
+pub use chunk::CodeChunk;
+#[cfg(feature = "crypto")]
+pub use digest_set::DigestSet;
+#[cfg(feature = "rng")]
+pub use estimate::{Estimate, Feasibility};
+#[cfg(feature = "rng")]
+pub use external::generate_external;
+#[cfg(feature = "std")]
+pub use external::{ExternalSort, verify_file_external};
+#[cfg(feature = "rng")]
+pub use interleave::{Interleave, interleave};
+#[cfg(feature = "rng")]
+pub use more::generate_more;
+#[cfg(feature = "rng")]
+pub use rate_limit::{IssuedCode, RateLimitedIssuer};
+#[cfg(feature = "std")]
+pub use registry::Registry;
+#[cfg(feature = "std")]
+pub use reservation::{Reservation, UniquenessStore};
+#[cfg(feature = "rng")]
+pub use reservation::{generate_reserved, issue_one};
+#[cfg(feature = "std")]
+pub use sample::MaskPolicy;
+#[cfg(feature = "rng")]
+pub use saturation::Saturation;
+#[cfg(feature = "rng")]
+pub use scoped::generate_scoped;
+#[cfg(feature = "crypto")]
+pub use sequence::Sequence;
+#[cfg(feature = "std")]
+pub use stats::BatchStats;
+#[cfg(feature = "bloom")]
+pub use stream::{StreamReport, generate_to_writer};
+#[cfg(feature = "rng")]
+pub use stress::{StressReport, stress};
+#[cfg(feature = "std")]
+pub use verify_file::{Duplicate, FileReport, verify_file};
+
+#[cfg(feature = "crypto")]
+pub use claims::Claims;
+#[cfg(all(feature = "crypto", feature = "rng"))]
+pub use ledger::{BatchLedger, LedgerOutcome, produce_once};
+#[cfg(all(feature = "crypto", feature = "rng"))]
+pub use merkle::respond_challenge;
+#[cfg(feature = "crypto")]
+pub use merkle::{MembershipProof, MerkleProof, verify_membership, verify_proof};
+#[cfg(all(feature = "crypto", feature = "rng"))]
+pub use rotation::{Rotation, rotate};
+#[cfg(feature = "crypto")]
+pub use salt::Salt;
+#[cfg(all(feature = "crypto", feature = "rng"))]
+pub use signed::generate_signed;
+#[cfg(feature = "crypto")]
+pub use signed::{SigningKey, verify_signature};
+
+#[cfg(feature = "ffi")]
+pub use integrations::ffi;
+#[cfg(feature = "parallel")]
+pub use integrations::rayon::generate_parallel;
+#[cfg(feature = "voucher-sheet")]
+pub use integrations::sheet;
+#[cfg(feature = "async")]
+pub use integrations::store::{AsyncUniquenessStore, generate_async};
+#[cfg(feature = "async")]
+pub use integrations::tokio::spawn_producer;
+#[cfg(feature = "verify-layer")]
+pub use integrations::tower::{
+    CodeLocation, ResponseFuture, VerifiedCode, VerifyCode, VerifyCodeLayer,
+};
+#[cfg(feature = "wasm")]
+pub use integrations::wasm;
 
 /// Error type for referral code generation operations.
 ///
 /// This enum represents errors that can occur when generating referral codes,
 /// such as when the configuration is not feasible for the requested number of codes.
-#[derive(Error, Debug)]
+#[derive(Debug)]
+#[non_exhaustive]
 pub enum ReferralCodeError {
     /// Indicates that the configuration cannot generate the requested number of unique codes.
     ///
     /// This error occurs when the combination of charset size and pattern length
     /// does not provide enough possible combinations to generate the requested count
     /// of unique codes.
-    #[error("Non feasible configuration")]
     NonFeasibleConfig,
+    /// Indicates that several codes were requested from a format without random positions.
+    ///
+    /// A pattern made only of literals, or whose random positions are all
+    /// check or format tag characters, has a single code. Add random
+    /// positions to the pattern rather than enlarging the charset.
+    NoRandomPositions,
+    /// Indicates that the OCR check positions do not fit the pattern.
+    ///
+    /// Both positions must be distinct, smaller than the pattern size, and
+    /// refer to placeholders drawing from the configured charset.
+    InvalidCheckPositions,
+    /// Indicates that a format tag does not fit the configuration.
+    ///
+    /// The tag position must be a placeholder drawing from the configured
+    /// charset that is not an OCR check position, and the charset must have
+    /// more characters than the format identifier.
+    InvalidFormatTag,
+    /// Indicates that the expiry does not fit the configuration.
+    ///
+    /// Expiry positions must be charset placeholders that hold nothing else
+    /// derived, and the expiry must be after the epoch and within the units
+    /// its width can count.
+    InvalidExpiry,
+    /// Indicates that the embedded fields do not fit the configuration.
+    ///
+    /// Field positions must be charset placeholders that hold nothing else
+    /// derived, every field must have a value below its number of values,
+    /// and all combinations must fit in the positions.
+    InvalidFields,
+    /// Indicates that the formatting does not fit the configuration.
+    ///
+    /// Groups must hold at least one character and be separated by a
+    /// character no code holds, and a letter case can't be forced on
+    /// positions holding letters that differ only in case, unless
+    /// `random_case` is set.
+    InvalidFormatting,
+    /// Indicates that a market has the same code as the contained, already
+    /// registered market.
+    ///
+    /// Give [`Markets`] a wider code, or rename one of the markets.
+    MarketCollision(String),
+    /// Indicates that a probability is not between 0 and 1.
+    InvalidProbability,
+    /// Indicates that the checksum cannot be computed over the random characters.
+    ///
+    /// Every character the checksum covers, as set by [`ChecksumCoverage`],
+    /// must belong to the input alphabet of the configured [`Checksum`].
+    IncompatibleChecksum,
+    /// Indicates that a pattern string is empty.
+    EmptyPattern,
+    /// Indicates that a custom charset is empty.
+    EmptyCharset,
+    /// Indicates that a custom charset lists the contained character more
+    /// than once.
+    ///
+    /// Repeating a character draws it more often without making room for
+    /// more codes. Use a [`Charset::Weighted`] charset to draw some
+    /// characters more often than others.
+    DuplicateCharsetChars(char),
+    /// Indicates that a custom charset contains the contained character,
+    /// which is the placeholder of the pattern.
+    ///
+    /// Codes holding the placeholder can't be told apart from patterns,
+    /// for instance in registry files or input masks. Remove the character
+    /// from the charset, or pick another placeholder with
+    /// [`Pattern::WithPlaceholder`].
+    PlaceholderInCharset(char),
+    /// Indicates that a segment of a [`Pattern::Segments`] is made of
+    /// segments itself.
+    NestedSegments,
+    /// Indicates that a canonical charset or pattern string is malformed.
+    ///
+    /// This includes strings written in a version this crate does not know.
+    InvalidSerialization,
+    /// Indicates that a line of a charset file is malformed.
+    InvalidCharsetFile {
+        /// One-based number of the offending line.
+        line: usize,
+        /// What is wrong with the line.
+        reason: &'static str,
+    },
+    /// Indicates that an untrusted input is larger than its [`Limits`] allow.
+    TooLarge {
+        /// The input that is too large.
+        field: &'static str,
+        /// The configured limit.
+        limit: usize,
+    },
+    /// Indicates that an untrusted input contains a character it may not contain.
+    ///
+    /// Control characters are never accepted, and charsets may neither
+    /// contain whitespace nor repeat a character.
+    InvalidCharacter(char),
+    /// Indicates that a base URL can't carry a code, see [`ReferralLink::build`].
+    InvalidUrl,
+    /// Indicates that a rate limit lets no attempt through, as a burst of
+    /// zero or a rate that is not positive does.
+    InvalidRateLimit,
+    /// Indicates that too many codes were requested in too short a time.
+    RateLimited {
+        /// How long to wait before a new attempt can be made.
+        retry_after: core::time::Duration,
+    },
+    /// Indicates that reading or writing codes failed.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// Indicates that a [`UniquenessStore`] failed.
+    Store(Box<dyn core::error::Error + Send + Sync>),
+    /// Indicates that a [`UniquenessStore`] turned down too many candidates
+    /// in a row, as a store with no free codes left does.
+    StoreSaturated,
+    /// Indicates that generation ran out of draws before it had enough
+    /// unique codes, see [`Config::max_attempts`].
+    CollisionBudgetExceeded {
+        /// The unique codes drawn, in generation order.
+        ///
+        /// Empty where codes are not kept in memory, as they are written
+        /// out by [`generate_to_writer`] or [`generate_external`], or sent
+        /// by [`spawn_producer`].
+        generated: Vec<String>,
+        /// The number of draws made.
+        attempts: usize,
+    },
+    /// Indicates that a batch takes more of the keyspace than allowed, see
+    /// [`Feasibility::check`].
+    Saturated {
+        /// The requested fraction of the keyspace.
+        saturation: f64,
+    },
+    /// Indicates that no share of the keyspace can be reserved by its first
+    /// character, see [`Config::with_reserved_fraction`].
+    NoReservablePosition,
+    /// Indicates that a code or position is not part of the batch.
+    NotInBatch,
+    /// Indicates that a batch with the same identifier already exists.
+    DuplicateBatch,
+    /// Indicates that a regenerated batch does not match its recorded digest.
+    #[cfg(feature = "crypto")]
+    DigestMismatch,
+    /// Indicates that a [`SigningKey`] signs no character, or more than
+    /// the freely drawn characters of the format.
+    #[cfg(feature = "crypto")]
+    InvalidSignatureLength,
+    /// Indicates that a format has more possible codes than a [`Sequence`]
+    /// counts, which is 2^128.
+    #[cfg(feature = "crypto")]
+    KeyspaceTooLarge,
+}
+
+impl fmt::Display for ReferralCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFeasibleConfig => f.write_str("Non feasible configuration"),
+            Self::NoRandomPositions => {
+                f.write_str("No random positions to generate more than one code")
+            }
+            Self::InvalidCheckPositions => f.write_str("Invalid OCR check positions"),
+            Self::InvalidFormatTag => f.write_str("Invalid format tag"),
+            Self::InvalidExpiry => f.write_str("Invalid expiry"),
+            Self::InvalidFields => f.write_str("Invalid embedded fields"),
+            Self::InvalidFormatting => f.write_str("Invalid formatting"),
+            Self::MarketCollision(market) => write!(f, "Market code already taken by {market}"),
+            Self::InvalidProbability => f.write_str("Invalid probability"),
+            Self::IncompatibleChecksum => f.write_str("Checksum incompatible with the code format"),
+            Self::EmptyPattern => f.write_str("Empty pattern"),
+            Self::EmptyCharset => f.write_str("Empty charset"),
+            Self::DuplicateCharsetChars(c) => write!(f, "Charset lists {c:?} more than once"),
+            Self::PlaceholderInCharset(c) => write!(f, "Charset contains the placeholder {c:?}"),
+            Self::NestedSegments => f.write_str("Pattern segments made of segments"),
+            Self::InvalidSerialization => f.write_str("Invalid canonical serialization"),
+            Self::InvalidCharsetFile { line, reason } => {
+                write!(f, "Invalid charset file at line {line}: {reason}")
+            }
+            Self::TooLarge { field, limit } => write!(f, "{field} exceeds the limit of {limit}"),
+            Self::InvalidCharacter(c) => write!(f, "Invalid character {c:?}"),
+            Self::InvalidUrl => f.write_str("Invalid base URL"),
+            Self::InvalidRateLimit => f.write_str("Invalid rate limit"),
+            Self::RateLimited { retry_after } => {
+                write!(f, "Rate limited, retry after {retry_after:?}")
+            }
+            #[cfg(feature = "std")]
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::Store(e) => write!(f, "Uniqueness store error: {e}"),
+            Self::StoreSaturated => f.write_str("Uniqueness store saturated"),
+            Self::CollisionBudgetExceeded {
+                generated,
+                attempts,
+            } => write!(
+                f,
+                "Collision budget exceeded: {} unique codes after {attempts} attempts",
+                generated.len()
+            ),
+            Self::Saturated { saturation } => {
+                write!(f, "Batch takes {saturation} of the keyspace")
+            }
+            Self::NoReservablePosition => f.write_str("No position to reserve codes by"),
+            Self::NotInBatch => f.write_str("Not in batch"),
+            Self::DuplicateBatch => f.write_str("Duplicate batch"),
+            #[cfg(feature = "crypto")]
+            Self::DigestMismatch => f.write_str("Batch digest mismatch"),
+            #[cfg(feature = "crypto")]
+            Self::InvalidSignatureLength => f.write_str("Invalid signature length"),
+            #[cfg(feature = "crypto")]
+            Self::KeyspaceTooLarge => f.write_str("Keyspace too large"),
+        }
+    }
+}
+
+impl core::error::Error for ReferralCodeError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(e) => Some(e),
+            Self::Store(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for ReferralCodeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
 }
 
 /// Character set used for generating referral codes.
 ///
 /// Defines the set of characters that can be used when generating codes.
 /// The charset determines the available character pool for random selection.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Charset {
     /// Numeric characters only: 0-9 (10 characters).
     Numeric,
@@ -30,11 +618,33 @@ pub enum Charset {
     Alphabetic,
     /// Alphanumeric characters: a-z, A-Z, and 0-9 (62 characters).
     Alphanumeric,
+    /// Alphanumeric characters without those easily mistaken for another:
+    /// a-z without `l` and `o`, A-Z without `I` and `O`, and 2-9
+    /// (56 characters).
+    ///
+    /// Codes drawn from it can be read out over the phone or copied by hand
+    /// without mixing up `0` and `O`, or `1`, `l` and `I`.
+    AlphanumericUnambiguous,
+    /// Crockford's Base32 alphabet: 0-9 and A-Z without `I`, `L`, `O` and
+    /// `U` (32 characters).
+    ///
+    /// It has a single letter case and no letter that reads like a digit.
+    CrockfordBase32,
     /// Custom character set specified as a string.
     ///
     /// The string can contain any characters that should be used for code generation.
-    /// Characters will be selected randomly from this string.
+    /// Characters will be selected randomly from this string. It may not
+    /// contain the placeholder of the pattern, `#` unless set otherwise by
+    /// [`Pattern::WithPlaceholder`].
     Custom(String),
+    /// Custom character set drawing each character in proportion to its
+    /// weight.
+    ///
+    /// A character of weight 3 is drawn three times as often as one of
+    /// weight 1, and characters of weight 0 are never drawn. Every other
+    /// character counts once in the keyspace: weights lower the entropy of
+    /// codes, not their number. [`Charset::weighted`] checks the weights.
+    Weighted(Vec<(char, u32)>),
 }
 
 impl Charset {
@@ -46,7 +656,10 @@ impl Charset {
     /// - `Numeric`: 10
     /// - `Alphabetic`: 52
     /// - `Alphanumeric`: 62
-    /// - `Custom(s)`: length of the custom string
+    /// - `AlphanumericUnambiguous`: 56
+    /// - `CrockfordBase32`: 32
+    /// - `Custom(s)`: number of characters of the custom string
+    /// - `Weighted(weights)`: number of characters of non-zero weight
     ///
     /// # Examples
     ///
@@ -56,30 +669,285 @@ impl Charset {
     /// assert_eq!(Charset::Numeric.len(), 10);
     /// assert_eq!(Charset::Alphanumeric.len(), 62);
     /// assert_eq!(Charset::Custom("ABC".to_string()).len(), 3);
+    /// assert_eq!(Charset::Custom("äöü".to_string()).len(), 3);
+    /// assert_eq!(Charset::Weighted(vec![('A', 1), ('7', 3)]).len(), 2);
     /// ```
     pub fn len(&self) -> usize {
         match self {
             Self::Numeric => 10,
             Self::Alphabetic => 52,
             Self::Alphanumeric => 62,
-            Self::Custom(s) => s.len(),
+            Self::AlphanumericUnambiguous => 56,
+            Self::CrockfordBase32 => 32,
+            Self::Custom(s) => s.chars().count(),
+            Self::Weighted(weights) => weights.iter().filter(|(_, w)| *w > 0).count(),
         }
     }
+
+    /// Returns `true` if this charset contains no characters.
+    ///
+    /// Only `Custom` charsets can be empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// assert!(!Charset::Numeric.is_empty());
+    /// assert!(Charset::Custom(String::new()).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the characters of this charset, in canonical order.
+    ///
+    /// The canonical order is the order in which characters are listed in the
+    /// charset: lowercase letters, then uppercase letters, then digits for the
+    /// built-in charsets, except `CrockfordBase32` which lists digits first,
+    /// as its alphabet does, the string order for `Custom` charsets and the
+    /// order of the weights, without those of weight 0, for `Weighted` ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// assert_eq!(Charset::Numeric.as_str(), "0123456789");
+    /// assert_eq!(Charset::Custom("XYZ".to_string()).as_str(), "XYZ");
+    /// assert_eq!(Charset::Weighted(vec![('A', 1), ('B', 0), ('7', 3)]).as_str(), "A7");
+    /// ```
+    pub fn as_str(&self) -> Cow<'_, str> {
+        match self.unweighted() {
+            Some(chars) => Cow::Borrowed(chars),
+            None => self.weights().map(|(c, _)| c).collect(),
+        }
+    }
+
+    /// Returns the characters of this charset, unless it is weighted.
+    fn unweighted(&self) -> Option<&str> {
+        Some(match self {
+            Self::Numeric => "0123456789",
+            Self::Alphabetic => "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            Self::Alphanumeric => "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789",
+            Self::AlphanumericUnambiguous => {
+                "abcdefghijkmnpqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789"
+            }
+            Self::CrockfordBase32 => "0123456789ABCDEFGHJKMNPQRSTVWXYZ",
+            Self::Custom(s) => s,
+            Self::Weighted(_) => return None,
+        })
+    }
+
+    /// Returns the characters of this charset, in canonical order, with how
+    /// many times as often as a character of weight 1 each is drawn.
+    pub(crate) fn weights(&self) -> impl Iterator<Item = (char, u32)> + '_ {
+        let weights = match self {
+            Self::Weighted(weights) => &weights[..],
+            _ => &[],
+        };
+
+        self.unweighted()
+            .unwrap_or("")
+            .chars()
+            .map(|c| (c, 1))
+            .chain(weights.iter().copied().filter(|(_, w)| *w > 0))
+    }
+
+    /// Returns the charset of the given characters, each kept once in the
+    /// order they first appear, named if a named charset lists them in
+    /// that order.
+    fn from_chars(chars: impl IntoIterator<Item = char>) -> Charset {
+        let mut seen = String::new();
+        for c in chars {
+            if !seen.contains(c) {
+                seen.push(c);
+            }
+        }
+
+        canonical::canonical_charset(&Charset::Custom(seen), false)
+    }
+
+    /// Returns a copy of this charset without the characters of `chars`.
+    ///
+    /// The remaining characters keep their order, and are each kept once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// let charset = Charset::CrockfordBase32.exclude("0123456789");
+    ///
+    /// assert_eq!(charset.as_str(), "ABCDEFGHJKMNPQRSTVWXYZ");
+    /// assert_eq!(Charset::Numeric.exclude("13579").as_str(), "02468");
+    /// ```
+    pub fn exclude(&self, chars: &str) -> Charset {
+        Charset::from_chars(self.as_str().chars().filter(|c| !chars.contains(*c)))
+    }
+
+    /// Returns the characters of this charset followed by those of `other`
+    /// it lacks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// let charset = Charset::Alphabetic.union(&Charset::Numeric);
+    ///
+    /// assert_eq!(charset, Charset::Alphanumeric);
+    /// assert_eq!(Charset::Numeric.union(&Charset::Custom("19X".to_string())).len(), 11);
+    /// ```
+    pub fn union(&self, other: &Charset) -> Charset {
+        Charset::from_chars(self.as_str().chars().chain(other.as_str().chars()))
+    }
+
+    /// Returns this charset with its letters in uppercase, each kept once.
+    ///
+    /// Letters whose uppercase takes several characters, such as `ß`, are
+    /// kept as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// let charset = Charset::Alphanumeric.uppercase();
+    ///
+    /// assert_eq!(charset.as_str(), "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789");
+    /// assert_eq!(charset.exclude("AEIOU").len(), 31);
+    /// ```
+    pub fn uppercase(&self) -> Charset {
+        Charset::from_chars(
+            self.as_str()
+                .chars()
+                .map(|c| single(c.to_uppercase()).unwrap_or(c)),
+        )
+    }
+
+    /// Returns this charset with its letters in lowercase, each kept once.
+    ///
+    /// Letters whose lowercase takes several characters, such as `İ`, are
+    /// kept as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// assert_eq!(Charset::Alphabetic.lowercase().len(), 26);
+    /// ```
+    pub fn lowercase(&self) -> Charset {
+        Charset::from_chars(
+            self.as_str()
+                .chars()
+                .map(|c| single(c.to_lowercase()).unwrap_or(c)),
+        )
+    }
+
+    /// Returns the position of `c` in this charset's canonical order.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(index)` - The zero-based index of the character
+    /// * `None` - If the character is not part of the charset
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Charset;
+    ///
+    /// assert_eq!(Charset::Numeric.index_of('7'), Some(7));
+    /// assert_eq!(Charset::Alphanumeric.index_of('0'), Some(52));
+    /// assert_eq!(Charset::Numeric.index_of('a'), None);
+    /// ```
+    pub fn index_of(&self, c: char) -> Option<usize> {
+        self.as_str().chars().position(|x| x == c)
+    }
+}
+
+/// Returns the only character of `chars`, or `None` if there are several.
+fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
 }
 
+/// Draws each character of the charset with equal probability, or in
+/// proportion to its weight for a `Weighted` charset.
+///
+/// Indices are drawn with `random_range`, which rejects out-of-range draws
+/// instead of reducing them modulo the charset size, so sampling is free of
+/// modulo bias whatever the size. Characters repeated in a custom charset are
+/// proportionally more likely.
+///
+/// # Panics
+///
+/// Panics if the charset is empty.
+#[cfg(feature = "rng")]
 impl Distribution<char> for Charset {
-    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> char {
+    fn sample<R: rng::backend::Rng + ?Sized>(&self, rng: &mut R) -> char {
+        Alphabet::new(self).sample(rng)
+    }
+}
+
+/// The characters of a charset, laid out to be drawn by index.
+///
+/// ASCII alphabets are their bytes, and others are decoded once, so codes
+/// drawing several characters of a non-ASCII charset don't walk the string
+/// for each of them. Weighted charsets keep, for every character, the sum
+/// of the weights up to its own.
+#[cfg(feature = "rng")]
+pub(crate) enum Alphabet<'a> {
+    Ascii(&'a [u8]),
+    Chars(Vec<char>),
+    Weighted(Vec<(usize, char)>),
+}
+
+#[cfg(feature = "rng")]
+impl<'a> Alphabet<'a> {
+    pub(crate) fn new(charset: &'a Charset) -> Self {
+        match charset.unweighted() {
+            Some(alphabet) if alphabet.is_ascii() => Alphabet::Ascii(alphabet.as_bytes()),
+            Some(alphabet) => Alphabet::Chars(alphabet.chars().collect()),
+            None => {
+                let mut total: usize = 0;
+                Alphabet::Weighted(
+                    charset
+                        .weights()
+                        .map(|(c, w)| {
+                            total = total.saturating_add(w as usize);
+                            (total, c)
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    /// Returns the number of bytes a drawn character takes at most.
+    fn width(&self) -> usize {
         match self {
-            Self::Numeric => "0123456789".chars().choose(rng).unwrap(),
-            Self::Alphabetic => "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ"
-                .chars()
-                .choose(rng)
-                .unwrap(),
-            Self::Alphanumeric => "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789"
-                .chars()
-                .choose(rng)
-                .unwrap(),
-            Self::Custom(s) => s.chars().choose(rng).unwrap(),
+            Alphabet::Ascii(_) => 1,
+            Alphabet::Chars(_) | Alphabet::Weighted(_) => 4,
+        }
+    }
+
+    /// Draws a character, consuming the generator like an index below the
+    /// number of characters, or below the total weight of a weighted
+    /// charset.
+    ///
+    /// A weighted charset is drawn from as the custom charset repeating
+    /// each character as many times as its weight would be.
+    pub(crate) fn sample<R: CodeRng + ?Sized>(&self, rng: &mut R) -> char {
+        match self {
+            Alphabet::Ascii(bytes) => char::from(bytes[rng.index(bytes.len())]),
+            Alphabet::Chars(chars) => chars[rng.index(chars.len())],
+            Alphabet::Weighted(ends) => {
+                let total = ends.last().map_or(0, |(end, _)| *end);
+                let i = rng.index(total);
+                ends[ends.partition_point(|(end, _)| *end <= i)].1
+            }
         }
     }
 }
@@ -88,7 +956,8 @@ impl Distribution<char> for Charset {
 ///
 /// Defines the structure and length of generated codes. Patterns can be specified
 /// as a simple length or as a template string with placeholders.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Pattern {
     /// Generate a code of the specified length using all random characters.
     ///
@@ -106,6 +975,49 @@ pub enum Pattern {
     /// - `"ABC###"` generates codes like "ABC123"
     /// - `"###-###"` generates codes like "123-123"
     Pattern(String),
+    /// Generate a code following a shorthand template with typed placeholders.
+    ///
+    /// The template string can contain:
+    /// - `#` characters: replaced with random characters from the charset
+    /// - `A` characters: replaced with random letters (a-z, A-Z)
+    /// - `9` characters: replaced with random digits (0-9)
+    /// - `\#`, `\A`, `\9` and `\\`: a literal `#`, `A`, `9` or backslash
+    /// - Any other characters: included literally in the generated code
+    ///
+    /// Letter and digit placeholders ignore the configured charset. This is
+    /// the syntax used by the popular JavaScript voucher code libraries; use
+    /// [`Pattern::parse`] to build one.
+    ///
+    /// # Examples
+    ///
+    /// - `"AA-99-##"` generates codes like "Qe-42-x7"
+    /// - `"\\A\\9-99"` generates codes like "A9-42"
+    Template(String),
+    /// Generate a code following a pattern string with a custom placeholder.
+    ///
+    /// This works like [`Pattern::Pattern`], except that the given character
+    /// marks random positions instead of `#`, which is then an ordinary
+    /// literal. Use it for templates that need a literal `#`.
+    ///
+    /// # Examples
+    ///
+    /// - `("#??-??", '?')` generates codes like "#1A-2B"
+    WithPlaceholder(String, char),
+    /// Generate a code from consecutive patterns, each drawing from its own
+    /// charset.
+    ///
+    /// The charset placeholders of every segment, such as `#`, draw from
+    /// the charset paired with it instead of the configured one, while the
+    /// letter and digit placeholders of templates keep their meaning. A
+    /// segment can't itself be made of segments, and derived characters,
+    /// such as OCR check characters or format tags, can't be placed in
+    /// segments.
+    ///
+    /// # Examples
+    ///
+    /// - `[("###-", uppercase letters), ("####-", digits), ("##", "XYZ")]`
+    ///   generates codes like "QKD-4205-YX"
+    Segments(Vec<(Pattern, Charset)>),
 }
 
 impl Pattern {
@@ -131,13 +1043,50 @@ impl Pattern {
         match self {
             Self::Length(u) => *u,
             Self::Pattern(s) => s.chars().filter(|p| p == &'#').count(),
+            Self::Template(_) => self.classes().len(),
+            Self::WithPlaceholder(s, placeholder) => s.chars().filter(|p| p == placeholder).count(),
+            Self::Segments(segments) => segments.iter().map(|(pattern, _)| pattern.size()).sum(),
         }
     }
 
+    /// Parses a shorthand template such as `"AA-99-##"`.
+    ///
+    /// See [`Pattern::Template`] for the placeholder syntax.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The shorthand template string
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Pattern::Template)` - The parsed pattern
+    /// * `Err(ReferralCodeError::EmptyPattern)` - If the template is empty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Pattern;
+    ///
+    /// let pattern = Pattern::parse("AA-99-##").unwrap();
+    /// assert_eq!(pattern.size(), 6);
+    /// assert!(Pattern::parse("").is_err());
+    /// ```
+    pub fn parse(template: &str) -> Result<Self, ReferralCodeError> {
+        if template.is_empty() {
+            return Err(ReferralCodeError::EmptyPattern);
+        }
+
+        Ok(Self::Template(template.to_string()))
+    }
+
     /// Returns the pattern string representation.
     ///
     /// For `Length(n)`, this returns a string of `n` `#` characters.
-    /// For `Pattern(s)`, this returns the pattern string as-is.
+    /// For `Pattern(s)` and `Template(s)`, this returns the pattern string as-is.
+    /// For `WithPlaceholder(s, _)`, this also returns the pattern string as-is,
+    /// where the placeholder rather than `#` represents random positions.
+    /// For `Segments`, this returns the pattern strings of the segments, one
+    /// after the other.
     ///
     /// # Returns
     ///
@@ -153,8 +1102,20 @@ impl Pattern {
     /// ```
     pub fn pattern(&self) -> String {
         match self {
-            Self::Length(size) => std::iter::repeat('#').take(*size).collect::<String>(),
-            Self::Pattern(s) => s.clone(),
+            Self::Length(size) => "#".repeat(*size),
+            Self::Pattern(s) | Self::Template(s) | Self::WithPlaceholder(s, _) => s.clone(),
+            Self::Segments(segments) => segments
+                .iter()
+                .map(|(pattern, _)| pattern.pattern())
+                .collect(),
+        }
+    }
+
+    /// Returns the charset of the `i`-th segment of a [`Pattern::Segments`].
+    pub(crate) fn pool(&self, i: usize) -> Option<&Charset> {
+        match self {
+            Self::Segments(segments) => segments.get(i).map(|(_, charset)| charset),
+            _ => None,
         }
     }
 }
@@ -162,7 +1123,16 @@ impl Pattern {
 /// Configuration for generating referral codes.
 ///
 /// Specifies all parameters needed to generate one or more unique referral codes.
-#[derive(Clone)]
+///
+/// With the `serde` feature, configurations implement `Serialize` and
+/// `Deserialize`. Fields left out keep their default, and unknown fields
+/// are rejected, so that a misspelled setting isn't silently ignored.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct Config {
     /// The pattern that defines the structure and length of generated codes.
     pub pattern: Pattern,
@@ -170,6 +1140,81 @@ pub struct Config {
     pub count: usize,
     /// The character set to use when generating random characters.
     pub charset: Charset,
+    /// An optional literal prepended to every generated code.
+    pub prefix: Option<String>,
+    /// An optional literal appended to every generated code.
+    pub suffix: Option<String>,
+    /// Optional OCR check characters interleaved into the random positions.
+    pub ocr_check: Option<OcrCheck>,
+    /// Optional format tag identifying the format from a fixed position.
+    ///
+    /// See [`FormatRegistry`].
+    pub format_tag: Option<FormatTag>,
+    /// Optional expiry date embedded in some of the random positions.
+    ///
+    /// Expiries are moments of the system clock, so they need the `std`
+    /// feature.
+    #[cfg(feature = "std")]
+    pub expiry: Option<Expiry>,
+    /// Optional fields, such as a version or region, embedded in some of the random positions.
+    pub fields: Option<EmbeddedFields>,
+    /// Optional standard check characters appended after the pattern.
+    pub checksum: Option<Checksum>,
+    /// The characters `checksum` is computed over.
+    pub checksum_coverage: ChecksumCoverage,
+    /// Optional probability that each letter at a random position is uppercase.
+    ///
+    /// When set, the case of every letter drawn for a random position is
+    /// chosen at random, independently of the charset, for visually varied
+    /// codes. Codes are then treated case-insensitively: two codes differing
+    /// only in case are duplicates, verification accepts either case, and the
+    /// keyspace counts each letter once regardless of case.
+    pub random_case: Option<f64>,
+    /// Prefixes that no generated code may begin with.
+    ///
+    /// Use this to keep curated codes, such as partner vanity codes, apart
+    /// from random ones: a code beginning with a reserved prefix is never
+    /// generated. Prefixes are matched against the whole code, so
+    /// `config.prefix` counts toward them.
+    pub reserved_prefixes: Vec<String>,
+    /// Whether adjacent random characters must differ.
+    ///
+    /// When set, no drawn character equals the drawn character right before
+    /// it, which keeps codes like "AA7Q" that are easy to misread or mishear
+    /// from being generated. Literals and derived characters, such as check
+    /// characters, separate runs and are not constrained. The keyspace
+    /// counts repeat-free codes exactly, so a tiny charset that can't fill
+    /// the pattern is rejected with `NonFeasibleConfig` up front.
+    /// [`Config::entropy_bits`] does not account for the constraint.
+    pub no_repeats: bool,
+    /// Words that no generated code may contain, in any letter case.
+    ///
+    /// Use this to keep offensive words out of random codes, see also
+    /// [`Config::with_profanity_filter`] with the `profanity` feature.
+    /// Words are matched against the whole code, so `config.prefix` and
+    /// literals count toward them. The keyspace subtracts an upper bound of
+    /// the codes containing them, so `NonFeasibleConfig` may be returned
+    /// for counts slightly below the number of codes actually left.
+    pub blocked_words: Vec<String>,
+    /// How codes are written out for people, such as in groups of four.
+    ///
+    /// Generated codes are not formatted: see [`Config::format_code`] and
+    /// [`Config::normalize_code`].
+    pub formatting: Formatting,
+    /// Where the random characters of codes come from.
+    ///
+    /// Defaults to a cryptographically secure generator, see [`RngSource`].
+    pub rng: RngSource,
+    /// The number of codes generation draws before giving up with
+    /// `CollisionBudgetExceeded`, or `None` for a default.
+    ///
+    /// Draws that are duplicates or that the configuration turns down,
+    /// such as codes beginning with a reserved prefix, count too. The
+    /// default is ten draws per code, at least a thousand, and four times
+    /// the draws expected when codes fill most of the keyspace, so it only
+    /// runs out when the keyspace holds far fewer codes than
+    /// [`Config::keyspace`] reports, as with many blocked words.
+    pub max_attempts: Option<usize>,
 }
 
 impl Default for Config {
@@ -178,7 +1223,186 @@ impl Default for Config {
             pattern: Pattern::Length(8),
             count: 1,
             charset: Charset::Alphanumeric,
+            prefix: None,
+            suffix: None,
+            ocr_check: None,
+            format_tag: None,
+            #[cfg(feature = "std")]
+            expiry: None,
+            fields: None,
+            checksum: None,
+            checksum_coverage: ChecksumCoverage::Random,
+            random_case: None,
+            reserved_prefixes: Vec::new(),
+            no_repeats: false,
+            blocked_words: Vec::new(),
+            formatting: Formatting::default(),
+            rng: RngSource::Secure,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Config {
+    /// Returns a copy of this configuration with a different count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Config;
+    ///
+    /// let config = Config::default().with_count(10);
+    /// assert_eq!(config.count, 10);
+    /// ```
+    pub fn with_count(&self, count: usize) -> Self {
+        Config {
+            count,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of this configuration with a different pattern.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, Pattern};
+    ///
+    /// let config = Config::default().with_pattern(Pattern::Length(12));
+    /// assert_eq!(config.pattern.size(), 12);
+    /// ```
+    pub fn with_pattern(&self, pattern: Pattern) -> Self {
+        Config {
+            pattern,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of this configuration with a different prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, verify_ocr_check};
+    ///
+    /// let config = Config::default().with_prefix("SUMMER-");
+    /// assert!(verify_ocr_check("SUMMER-AbC123xY", &config));
+    /// assert!(!verify_ocr_check("AbC123xY", &config));
+    /// ```
+    pub fn with_prefix(&self, prefix: impl Into<String>) -> Self {
+        Config {
+            prefix: Some(prefix.into()),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of this configuration with a different suffix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, verify_ocr_check};
+    ///
+    /// let config = Config::default().with_suffix("-EU");
+    /// assert!(verify_ocr_check("AbC123xY-EU", &config));
+    /// assert!(!verify_ocr_check("AbC123xY", &config));
+    /// ```
+    pub fn with_suffix(&self, suffix: impl Into<String>) -> Self {
+        Config {
+            suffix: Some(suffix.into()),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of this configuration with one more random position.
+    ///
+    /// The position is appended to the pattern, so OCR check positions keep
+    /// pointing at the same characters. Segments grow their last segment.
+    #[cfg(feature = "rng")]
+    pub(crate) fn with_extra_position(&self) -> Self {
+        fn grown(pattern: &Pattern) -> Pattern {
+            match pattern {
+                Pattern::Length(n) => Pattern::Length(n + 1),
+                Pattern::Pattern(s) => Pattern::Pattern(format!("{s}#")),
+                Pattern::Template(s) => Pattern::Template(format!("{s}#")),
+                Pattern::WithPlaceholder(s, p) => Pattern::WithPlaceholder(format!("{s}{p}"), *p),
+                Pattern::Segments(segments) => {
+                    let mut segments = segments.clone();
+                    if let Some((last, _)) = segments.last_mut() {
+                        *last = grown(last);
+                    }
+                    Pattern::Segments(segments)
+                }
+            }
+        }
+
+        self.with_pattern(grown(&self.pattern))
+    }
+
+    /// Returns the random positions whose character is derived rather than drawn.
+    pub(crate) fn derived_positions(&self) -> Vec<usize> {
+        let checks = self.ocr_check.iter().flat_map(|c| c.positions);
+        let tag = self.format_tag.iter().map(|t| t.position);
+        #[cfg(feature = "std")]
+        let expiry = self.expiry.iter().flat_map(|e| e.positions());
+        #[cfg(not(feature = "std"))]
+        let expiry = core::iter::empty();
+        let fields = self.fields.iter().flat_map(|f| f.layout.positions());
+
+        checks.chain(tag).chain(expiry).chain(fields).collect()
+    }
+
+    /// Returns the number of possible codes, or `None` if it overflows a `u128`.
+    ///
+    /// Positions reserved for check characters, a format tag, an expiry or
+    /// embedded fields do not contribute, and codes beginning with a reserved
+    /// prefix, containing a blocked word or, with `no_repeats`, repeating a
+    /// character are not counted. Reserved prefixes and blocked words are
+    /// counted with an upper bound, so the keyspace may be slightly
+    /// underestimated when they overlap. Generation refuses batches larger
+    /// than this, and [`Config::entropy_bits`] gives the size of formats
+    /// too large for a `u128`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Pattern("REF-####".to_string()),
+    ///     ..Config::default()
+    /// };
+    /// assert_eq!(config.keyspace(), Some(10_000));
+    ///
+    /// let huge = config.with_pattern(Pattern::Length(40));
+    /// assert_eq!(huge.keyspace(), None);
+    /// # #[cfg(feature = "std")]
+    /// assert!(huge.entropy_bits() > 128.0);
+    /// ```
+    pub fn keyspace(&self) -> Option<u128> {
+        let excluded = self.reserved_count().saturating_add(self.blocked_count());
+        if self.no_repeats {
+            return self
+                .repeat_free_count()
+                .map(|keyspace| keyspace.saturating_sub(excluded));
         }
+
+        let checks = self.derived_positions();
+
+        self.pattern
+            .classes()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !checks.contains(i))
+            .try_fold(1u128, |acc, (_, class)| {
+                let len = match self.random_case {
+                    Some(_) => class.caseless_len(self),
+                    None => class.len(self),
+                };
+                acc.checked_mul(len as u128)
+            })
+            .map(|keyspace| keyspace.saturating_sub(excluded))
     }
 }
 
@@ -186,7 +1410,12 @@ impl Default for Config {
 ///
 /// This function generates one code by replacing `#` characters in the pattern
 /// with random characters from the specified charset, while preserving any
-/// literal characters in the pattern.
+/// literal characters in the pattern. The configured prefix and suffix, if
+/// any, are added verbatim.
+///
+/// The configuration is validated on every call, so any `Config` value,
+/// including one built from customer input, yields either a code or an
+/// error. `config.count` is ignored.
 ///
 /// # Arguments
 ///
@@ -194,7 +1423,9 @@ impl Default for Config {
 ///
 /// # Returns
 ///
-/// A single referral code string generated according to the configuration.
+/// * `Ok(code)` - A single referral code generated according to the configuration
+/// * `Err(ReferralCodeError)` - The same errors as [`generate`] for a count
+///   of one, e.g. `NonFeasibleConfig` for an empty charset
 ///
 /// # Examples
 ///
@@ -205,39 +1436,296 @@ impl Default for Config {
 ///     pattern: Pattern::Length(8),
 ///     count: 1,
 ///     charset: Charset::Alphanumeric,
+///     ..Config::default()
 /// };
 ///
-/// let code = referral_codes::generate_one(&config);
+/// let code = referral_codes::generate_one(&config).unwrap();
 /// assert_eq!(code.len(), 8);
+///
+/// let empty = Config {
+///     charset: Charset::Custom(String::new()),
+///     ..config
+/// };
+/// assert!(referral_codes::generate_one(&empty).is_err());
 /// ```
-pub fn generate_one(config: &Config) -> String {
-    let mut rng = rand::rng();
+#[cfg(feature = "rng")]
+pub fn generate_one(config: &Config) -> Result<String, ReferralCodeError> {
+    check_config(&config.with_count(1))?;
+
+    let mut attempts = Attempts::new(config, 0, 1);
+    generate_one_with(config, &mut rng_for(config), &mut attempts)
+        .ok_or_else(|| attempts.exceeded(Vec::new()))
+}
+
+/// Draws a code the configuration accepts, or returns `None` once
+/// `attempts` is spent.
+#[cfg(feature = "rng")]
+pub(crate) fn generate_one_with<R: CodeRng + ?Sized>(
+    config: &Config,
+    rng: &mut R,
+    attempts: &mut Attempts,
+) -> Option<String> {
+    while attempts.take() {
+        let code = draw(config, rng);
+        if config.accepts(&code) {
+            return Some(code);
+        }
+    }
 
-    let mut result = "".to_string();
+    None
+}
+
+impl Config {
+    /// Returns `true` if a drawn code satisfies the constraints drawing
+    /// ignores: reserved prefixes, blocked words and `no_repeats`.
+    pub(crate) fn accepts(&self, code: &str) -> bool {
+        !self.is_reserved(code) && !self.is_blocked(code) && !self.has_repeats(code)
+    }
+
+    /// Returns this configuration without the constraints of [`Config::accepts`].
+    #[cfg(feature = "rng")]
+    pub(crate) fn unconstrained(&self) -> Config {
+        Config {
+            reserved_prefixes: Vec::new(),
+            no_repeats: false,
+            blocked_words: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Draws a code without regard to reserved prefixes or `no_repeats`.
+#[cfg(feature = "rng")]
+pub(crate) fn draw<R: CodeRng + ?Sized>(config: &Config, rng: &mut R) -> String {
+    match config.pattern {
+        Pattern::Length(n)
+            if config.derived_positions().is_empty()
+                && config.random_case.is_none()
+                && config.checksum.is_none() =>
+        {
+            draw_length(config, n, rng)
+        }
+        _ => draw_segments(config, rng),
+    }
+}
+
+/// Draws a code of `n` random characters between the prefix and suffix.
+///
+/// This is the common case of [`draw`], without expanding the pattern into
+/// segments or collecting the random characters first. It consumes the
+/// random generator exactly like [`draw_segments`], so seeded batches don't
+/// depend on which one is taken.
+#[cfg(feature = "rng")]
+fn draw_length<R: CodeRng + ?Sized>(config: &Config, n: usize, rng: &mut R) -> String {
+    let prefix = config.prefix.as_deref().unwrap_or("");
+    let suffix = config.suffix.as_deref().unwrap_or("");
+    let alphabet = Alphabet::new(&config.charset);
+
+    let mut code = String::with_capacity(prefix.len() + n * alphabet.width() + suffix.len());
+    code.push_str(prefix);
+    for _ in 0..n {
+        code.push(alphabet.sample(rng));
+    }
+    code.push_str(suffix);
+
+    code
+}
+
+/// Draws a code of any format.
+#[cfg(feature = "rng")]
+fn draw_segments<R: CodeRng + ?Sized>(config: &Config, rng: &mut R) -> String {
+    let derived = config.derived_positions();
+    let charset = Alphabet::new(&config.charset);
+    let pools: Vec<Alphabet> = match &config.pattern {
+        Pattern::Segments(segments) => segments
+            .iter()
+            .map(|(_, charset)| Alphabet::new(charset))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let free = config
+        .pattern
+        .classes()
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !derived.contains(i))
+        .map(|(_, class)| match class {
+            CharClass::Charset => charset.sample(rng),
+            CharClass::Pool(i) => pools[i].sample(rng),
+            class => class.sample(config, rng),
+        });
+    let mut random = segment::with_derived(config, free);
+    apply_random_case(config, &mut random, rng);
+
+    assemble(config, random)
+}
+
+/// Flips the letters of the random positions to upper or lower case, if
+/// the configuration has `random_case`.
+#[cfg(feature = "rng")]
+pub(crate) fn apply_random_case<R: CodeRng + ?Sized>(
+    config: &Config,
+    random: &mut [char],
+    rng: &mut R,
+) {
+    if let Some(p) = config.random_case {
+        for c in random.iter_mut().filter(|c| c.is_alphabetic()) {
+            let cased: String = if rng.chance(p) {
+                c.to_uppercase().collect()
+            } else {
+                c.to_lowercase().collect()
+            };
+
+            if let [single] = cased.chars().collect::<Vec<_>>()[..] {
+                *c = single;
+            }
+        }
+    }
+}
+
+/// Builds a code from the characters of all of its random positions.
+///
+/// The prefix, literals, checksum and suffix are added around them.
+pub(crate) fn assemble(config: &Config, random: Vec<char>) -> String {
+    let covered: String = random.iter().collect();
+    let mut random = random.into_iter();
+    let mut result = config.prefix.clone().unwrap_or_default();
 
-    for p in config.pattern.pattern().chars() {
-        if p == '#' {
-            result.push(rng.sample(&config.charset));
-        } else {
-            result.push(p)
+    for segment in config.pattern.segments() {
+        match segment {
+            Segment::Literal(s) => result.push_str(s),
+            Segment::Placeholder(_, n) => result.extend(random.by_ref().take(n)),
         }
     }
 
+    if let Some(checksum) = &config.checksum {
+        let data = match config.checksum_coverage {
+            ChecksumCoverage::Random => &covered,
+            ChecksumCoverage::Full => &result,
+        };
+        // `check_format` rejects checksums that can't cover these characters.
+        let check = checksum
+            .compute(data)
+            .expect("checksum incompatible with the charset");
+        result.push_str(&check);
+    }
+
+    if let Some(suffix) = &config.suffix {
+        result.push_str(suffix);
+    }
+
     result
 }
 
-fn is_feasible(config: &Config) -> bool {
+#[cfg(feature = "rng")]
+pub(crate) fn is_feasible(config: &Config) -> bool {
     config
-        .charset
-        .len()
-        .pow(u32::try_from(config.pattern.size()).unwrap())
-        >= config.count
+        .keyspace()
+        .is_none_or(|keyspace| keyspace >= config.count as u128)
+}
+
+/// Returns the form of a code used to detect duplicates.
+///
+/// With `random_case`, codes that differ only in letter case are duplicates.
+/// Every issuer records and compares this form, never the raw code, so that
+/// two codes normalizing to the same form can't both be issued.
+pub(crate) fn dedup_key<'a>(config: &Config, code: &'a str) -> Cow<'a, str> {
+    match config.random_case {
+        Some(_) => Cow::Owned(code.to_lowercase()),
+        None => Cow::Borrowed(code),
+    }
+}
+
+/// Checks that a configuration can produce `config.count` unique codes.
+#[cfg(feature = "rng")]
+pub(crate) fn check_config(config: &Config) -> Result<(), ReferralCodeError> {
+    check_format(config)?;
+
+    if config.count > 1 && config.pattern.size() == config.derived_positions().len() {
+        return Err(ReferralCodeError::NoRandomPositions);
+    }
+
+    if !is_feasible(config) {
+        return Err(ReferralCodeError::NonFeasibleConfig);
+    }
+
+    Ok(())
+}
+
+/// Checks that the parts of a configuration fit together, regardless of `count`.
+pub(crate) fn check_format(config: &Config) -> Result<(), ReferralCodeError> {
+    let placeholder = |pattern: &Pattern| match pattern {
+        Pattern::WithPlaceholder(_, placeholder) => *placeholder,
+        _ => '#',
+    };
+    let pools = match &config.pattern {
+        Pattern::Segments(segments) => segments.iter().map(|(p, c)| (p, c)).collect(),
+        pattern => vec![(pattern, &config.charset)],
+    };
+    for (pattern, charset) in pools {
+        if let Pattern::Segments(_) = pattern {
+            return Err(ReferralCodeError::NestedSegments);
+        }
+        if charset.as_str().contains(placeholder(pattern)) {
+            return Err(ReferralCodeError::PlaceholderInCharset(placeholder(
+                pattern,
+            )));
+        }
+    }
+
+    if let Some(check) = &config.ocr_check
+        && !check.is_valid_for(&config.pattern)
+    {
+        return Err(ReferralCodeError::InvalidCheckPositions);
+    }
+
+    if let Some(tag) = &config.format_tag
+        && !tag.is_valid_for(config)
+    {
+        return Err(ReferralCodeError::InvalidFormatTag);
+    }
+
+    #[cfg(feature = "std")]
+    if let Some(expiry) = &config.expiry
+        && !expiry.is_valid_for(config)
+    {
+        return Err(ReferralCodeError::InvalidExpiry);
+    }
+
+    if let Some(fields) = &config.fields
+        && !fields.is_valid_for(config)
+    {
+        return Err(ReferralCodeError::InvalidFields);
+    }
+
+    if !config.formatting.is_valid_for(config) {
+        return Err(ReferralCodeError::InvalidFormatting);
+    }
+
+    if let Some(checksum) = &config.checksum
+        && !checksum.is_valid_for(config)
+    {
+        return Err(ReferralCodeError::IncompatibleChecksum);
+    }
+
+    if let Some(p) = config.random_case
+        && !(0.0..=1.0).contains(&p)
+    {
+        return Err(ReferralCodeError::InvalidProbability);
+    }
+
+    Ok(())
 }
 
 /// Generates multiple unique referral codes according to the given configuration.
 ///
 /// This function generates the specified number of unique codes by repeatedly
-/// calling `generate_one` until enough unique codes have been generated.
+/// drawing single codes until enough unique codes have been generated.
+///
+/// Codes are returned in the order they were drawn, duplicates dropped
+/// where they came up, so that the `i`-th code can be paired with the
+/// `i`-th of a list of IDs. Sort them with [`sort_codes`] for exports that
+/// must diff cleanly.
 ///
 /// # Arguments
 ///
@@ -249,6 +1737,22 @@ fn is_feasible(config: &Config) -> bool {
 /// * `Err(ReferralCodeError::NonFeasibleConfig)` - If the configuration cannot generate
 ///   the requested number of unique codes (i.e., the charset size raised to the power
 ///   of the pattern size is less than the requested count)
+/// * `Err(ReferralCodeError::InvalidCheckPositions)` - If the OCR check positions
+///   do not fit the pattern
+/// * `Err(ReferralCodeError::InvalidFormatTag)` - If the format tag does not
+///   fit the configuration
+/// * `Err(ReferralCodeError::InvalidExpiry)` - If the expiry does not fit the
+///   configuration
+/// * `Err(ReferralCodeError::InvalidFields)` - If the embedded fields do not
+///   fit the configuration
+/// * `Err(ReferralCodeError::InvalidFormatting)` - If the formatting does
+///   not fit the configuration
+/// * `Err(ReferralCodeError::IncompatibleChecksum)` - If the checksum cannot
+///   be computed over the characters it covers
+/// * `Err(ReferralCodeError::InvalidProbability)` - If `random_case` is not
+///   between 0 and 1
+/// * `Err(ReferralCodeError::CollisionBudgetExceeded)` - If
+///   [`Config::max_attempts`] draws did not give enough unique codes
 ///
 /// # Examples
 ///
@@ -259,11 +1763,15 @@ fn is_feasible(config: &Config) -> bool {
 ///     pattern: Pattern::Length(8),
 ///     count: 5,
 ///     charset: Charset::Alphanumeric,
+///     ..Config::default()
 /// };
 ///
-/// let codes = referral_codes::generate(&config).unwrap();
+/// let mut codes = referral_codes::generate(&config).unwrap();
 /// assert_eq!(codes.len(), 5);
 /// assert_eq!(codes[0].len(), 8);
+///
+/// referral_codes::sort_codes(&config, &mut codes);
+/// assert!(codes.is_sorted_by(|a, b| referral_codes::compare_codes(&config, a, b).is_le()));
 /// ```
 ///
 /// # Errors
@@ -272,26 +1780,43 @@ fn is_feasible(config: &Config) -> bool {
 /// generate the requested number of unique codes. For example, requesting 100
 /// unique codes with a pattern size of 1 and a charset of 62 characters
 /// (which only provides 62 possible combinations).
+#[cfg(feature = "rng")]
 pub fn generate(config: &Config) -> Result<Vec<String>, ReferralCodeError> {
-    if !is_feasible(config) {
-        return Err(ReferralCodeError::NonFeasibleConfig);
-    }
+    generate_with(config, &mut rng_for(config))
+}
+
+/// Generates unique codes with the given RNG, in generation order.
+#[cfg(feature = "rng")]
+pub(crate) fn generate_with<R: CodeRng + ?Sized>(
+    config: &Config,
+    rng: &mut R,
+) -> Result<Vec<String>, ReferralCodeError> {
+    check_config(config)?;
 
-    let mut codes = HashSet::new();
+    let mut seen = HashSet::with_capacity(config.count);
+    let mut codes = Vec::with_capacity(config.count);
+    let mut attempts = Attempts::new(config, 0, config.count);
 
     while codes.len() < config.count {
-        codes.insert(generate_one(config));
+        let Some(code) = generate_one_with(config, rng, &mut attempts) else {
+            return Err(attempts.exceeded(codes));
+        };
+        if seen.insert(dedup_key(config, &code).into_owned()) {
+            codes.push(code);
+        }
     }
 
-    Ok(codes.into_iter().collect())
+    Ok(codes)
 }
 
+#[cfg(feature = "rng")]
 #[test]
 fn test_generate() {
     let config = Config {
         charset: Charset::Alphanumeric,
         count: 3,
         pattern: Pattern::Length(8),
+        ..Config::default()
     };
 
     let result = generate(&config).unwrap();
@@ -299,15 +1824,467 @@ fn test_generate() {
     assert_eq!(3, result.len());
 }
 
+#[cfg(feature = "rng")]
+#[test]
+fn test_generate_keeps_draw_order() {
+    use rng::{SeedableRng, StdRng};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(2),
+        count: 60,
+        ..Config::default()
+    };
+    let codes = generate_with(&config, &mut StdRng::seed_from_u64(3)).unwrap();
+
+    // Drawing one code at a time from the same seed gives the same order.
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut attempts = Attempts::new(&config, 0, 60);
+    let mut drawn: Vec<String> = Vec::new();
+    while drawn.len() < 60 {
+        let code = generate_one_with(&config, &mut rng, &mut attempts).unwrap();
+        if !drawn.contains(&code) {
+            drawn.push(code);
+        }
+    }
+    assert_eq!(codes, drawn);
+}
+
+#[cfg(feature = "rng")]
 #[test]
 fn test_fail_generate() {
     let config = Config {
         charset: Charset::Alphanumeric,
         count: 100,
         pattern: Pattern::Length(1),
+        ..Config::default()
     };
 
     let result = generate(&config);
 
     assert!(result.is_err())
 }
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_generate_gives_up_after_max_attempts() {
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(3),
+        count: 1_000,
+        ..Config::default()
+    };
+
+    // The default budget covers filling the whole keyspace.
+    assert_eq!(generate(&config).unwrap().len(), 1_000);
+
+    let limited = Config {
+        max_attempts: Some(10),
+        ..config
+    };
+    let Err(ReferralCodeError::CollisionBudgetExceeded {
+        generated,
+        attempts,
+    }) = generate(&limited)
+    else {
+        panic!("expected the budget to run out");
+    };
+    assert_eq!(attempts, 10);
+    assert!(!generated.is_empty() && generated.len() <= 10);
+    assert!(matches!(
+        generate_one(&Config {
+            max_attempts: Some(0),
+            ..limited
+        }),
+        Err(ReferralCodeError::CollisionBudgetExceeded { attempts: 0, .. })
+    ));
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_with_prefix() {
+    let config = Config::default().with_count(5).with_prefix("REF-");
+
+    let result = generate(&config).unwrap();
+
+    assert_eq!(5, result.len());
+    assert!(
+        result
+            .iter()
+            .all(|c| c.starts_with("REF-") && c.len() == 12)
+    );
+
+    // Only the random part counts toward feasibility.
+    let digits = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(2),
+        count: 100,
+        ..Config::default()
+    }
+    .with_prefix("SPRING-")
+    .with_suffix("-2024");
+    let codes = generate(&digits).unwrap();
+    assert!(codes.iter().all(|c| c.len() == 14 && c.ends_with("-2024")));
+    assert!(generate(&digits.with_count(101)).is_err());
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_generate_template() {
+    let config = Config {
+        charset: Charset::Custom("x".to_string()),
+        pattern: Pattern::parse("AA-99-#").unwrap(),
+        count: 2,
+        ..Config::default()
+    };
+
+    let result = generate(&config).unwrap();
+
+    for code in result {
+        let chars: Vec<char> = code.chars().collect();
+        assert!(chars[..2].iter().all(char::is_ascii_alphabetic));
+        assert_eq!(chars[2], '-');
+        assert!(chars[3..5].iter().all(char::is_ascii_digit));
+        assert_eq!(&code[5..], "-x");
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_random_case_dedup_is_case_insensitive() {
+    let config = Config {
+        charset: Charset::Custom("AB".to_string()),
+        pattern: Pattern::Length(2),
+        count: 4,
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+
+    let codes = generate(&config).unwrap();
+    let folded: HashSet<String> = codes.iter().map(|c| c.to_uppercase()).collect();
+
+    assert_eq!(folded.len(), 4);
+    assert!(generate(&config.with_count(5)).is_err());
+    assert!(
+        generate(&Config {
+            random_case: Some(1.5),
+            ..config
+        })
+        .is_err()
+    );
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_random_case_issuers_never_issue_both_cases() {
+    use std::sync::Mutex;
+
+    let config = Config {
+        charset: Charset::Custom("ABC1".to_string()),
+        pattern: Pattern::Length(4),
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+    let folded = |codes: &[String]| -> HashSet<String> {
+        codes.iter().map(|code| code.to_lowercase()).collect()
+    };
+
+    let store = Mutex::new(HashSet::from(["abc1".to_string()]));
+    let reserved = generate_reserved(&config.with_count(255), &store).unwrap();
+    assert_eq!(folded(&reserved).len(), 255);
+    assert!(!folded(&reserved).contains("abc1"));
+
+    let mut batch = Batch::generate("batch", &config.with_count(200)).unwrap();
+    batch.extend(&config, 56).unwrap();
+    assert_eq!(folded(&batch.codes).len(), 256);
+    assert!(batch.extend(&config, 1).is_err());
+
+    let mut registry = Registry::new("cases");
+    registry.generate("one", &config.with_count(128)).unwrap();
+    registry.generate("two", &config.with_count(128)).unwrap();
+    let issued: Vec<String> = registry
+        .batches()
+        .iter()
+        .flat_map(|batch| batch.codes.clone())
+        .collect();
+    assert_eq!(folded(&issued).len(), 256);
+    assert!(registry.generate("three", &config).is_err());
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_generate_one_rejects_invalid_configs() {
+    let invalid = [
+        Config {
+            ocr_check: Some(OcrCheck::new(0, 8)),
+            ..Config::default()
+        },
+        Config {
+            pattern: Pattern::Pattern("AB-".to_string()),
+            checksum: Some(Checksum::Iso7064Mod97_10),
+            checksum_coverage: ChecksumCoverage::Full,
+            ..Config::default()
+        },
+        Config {
+            random_case: Some(f64::NAN),
+            ..Config::default()
+        },
+        Config {
+            charset: Charset::Custom("a".to_string()),
+            reserved_prefixes: vec!["a".to_string()],
+            ..Config::default()
+        },
+    ];
+
+    for config in &invalid {
+        assert!(generate_one(config).is_err());
+    }
+    assert!(generate_one(&Config::default().with_count(0)).is_ok());
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_charset_may_not_contain_the_placeholder() {
+    let config = Config {
+        charset: Charset::Custom("AB#".to_string()),
+        pattern: Pattern::Pattern("X-##".to_string()),
+        ..Config::default()
+    };
+    assert!(matches!(
+        generate(&config),
+        Err(ReferralCodeError::PlaceholderInCharset('#'))
+    ));
+    assert!(matches!(
+        generate(&config.with_pattern(Pattern::Length(4))),
+        Err(ReferralCodeError::PlaceholderInCharset('#'))
+    ));
+
+    // With another placeholder, `#` is an ordinary character.
+    let custom = config.with_pattern(Pattern::WithPlaceholder("X-??".to_string(), '?'));
+    assert_eq!(generate(&custom).unwrap().len(), 1);
+    assert!(matches!(
+        generate(&Config {
+            charset: Charset::Custom("AB?".to_string()),
+            ..custom
+        }),
+        Err(ReferralCodeError::PlaceholderInCharset('?'))
+    ));
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_charset_sampling_is_uniform() {
+    use crate::rng::{SeedableRng, StdRng};
+
+    // Seven characters, so a modulo reduction of random bytes would be biased.
+    for charset in [
+        Charset::Custom("ABCDEFG".to_string()),
+        Charset::Custom("ÀÉÎÕÜÇÑ".to_string()),
+    ] {
+        let mut rng = StdRng::seed_from_u64(246);
+        let mut counts = std::collections::HashMap::new();
+        let samples = 70_000;
+
+        for _ in 0..samples {
+            *counts.entry(charset.sample(&mut rng)).or_insert(0u32) += 1;
+        }
+
+        let expected = f64::from(samples) / 7.0;
+        let chi_squared: f64 = counts
+            .values()
+            .map(|n| (f64::from(*n) - expected).powi(2) / expected)
+            .sum();
+
+        assert_eq!(counts.len(), 7);
+        // The 99.9th percentile of the chi-squared distribution with 6 degrees of freedom.
+        assert!(chi_squared < 22.46, "chi-squared {chi_squared}");
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_weighted_charsets_draw_like_repeated_characters() {
+    let weighted = Config {
+        charset: Charset::Weighted(vec![('A', 1), ('B', 0), ('7', 3)]),
+        pattern: Pattern::Length(2),
+        count: 4,
+        ..Config::default()
+    };
+    let repeated = Config {
+        charset: Charset::Custom("A777".to_string()),
+        ..weighted.clone()
+    };
+
+    // Weights hold as many codes as the distinct characters, not 4 × 4.
+    assert_eq!(weighted.keyspace(), Some(4));
+    assert!(matches!(
+        generate(&weighted.with_count(5)),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+    assert_eq!(weighted.entropy_bits(), repeated.entropy_bits());
+    assert!(weighted.check_untrusted(&Limits::default()).is_ok());
+    assert_eq!(
+        Batch::generate_seeded("w", &weighted, 7).unwrap().codes,
+        Batch::generate_seeded("w", &repeated, 7).unwrap().codes
+    );
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_literal_pattern_has_a_single_code() {
+    let config = Config {
+        pattern: Pattern::Pattern("WELCOME".to_string()),
+        ..Config::default()
+    }
+    .with_prefix("VIP-");
+
+    assert_eq!(generate(&config).unwrap(), vec!["VIP-WELCOME"]);
+    assert!(matches!(
+        generate(&config.with_count(2)),
+        Err(ReferralCodeError::NoRandomPositions)
+    ));
+
+    let checks_only = Config {
+        pattern: Pattern::Length(2),
+        ocr_check: Some(OcrCheck::new(0, 1)),
+        count: 2,
+        ..Config::default()
+    };
+    assert!(matches!(
+        generate(&checks_only),
+        Err(ReferralCodeError::NoRandomPositions)
+    ));
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_length_fast_path_matches_segments() {
+    use crate::rng::{SeedableRng, StdRng};
+
+    let configs = [
+        Config::default(),
+        Config::default().with_prefix("ÄB-"),
+        Config {
+            charset: Charset::Custom("αβγδ".to_string()),
+            suffix: Some("-Z".to_string()),
+            ..Config::default()
+        },
+    ];
+
+    for config in configs {
+        for seed in 0..20 {
+            assert_eq!(
+                draw(&config, &mut StdRng::seed_from_u64(seed)),
+                draw_segments(&config, &mut StdRng::seed_from_u64(seed))
+            );
+        }
+    }
+}
+
+/// Counts the heap allocations made by each test thread.
+#[cfg(test)]
+struct CountingAllocator;
+
+#[cfg(test)]
+thread_local! {
+    static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+// SAFETY: every call is forwarded unchanged to the system allocator.
+#[cfg(test)]
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        unsafe { std::alloc::System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        unsafe { std::alloc::System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        unsafe { std::alloc::System.realloc(ptr, layout, size) }
+    }
+}
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_verification_does_not_allocate() {
+    let config = Config {
+        pattern: Pattern::parse("AA99#####").unwrap(),
+        charset: Charset::Alphanumeric,
+        prefix: Some("REF".to_string()),
+        ocr_check: Some(OcrCheck::new(4, 6)),
+        format_tag: Some(FormatTag {
+            position: 5,
+            id: FormatId(3),
+        }),
+        checksum: Some(Checksum::Iso7064Mod37_36),
+        checksum_coverage: ChecksumCoverage::Full,
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+    let code = assemble(&config, segment::with_derived(&config, "xY12ab".chars()));
+    let tampered = code.replacen("xY1", "xY7", 1);
+
+    let before = ALLOCATIONS.with(|n| n.get());
+    let verified = verify_as(&code, &config, SpecVersion::LATEST)
+        && verify_ocr_check(&code, &config)
+        && verify_checksum(&code, &config)
+        && !verify_as(&tampered, &config, SpecVersion::LATEST)
+        && !verify_as("REF", &config, SpecVersion::LATEST);
+    let allocations = ALLOCATIONS.with(|n| n.get()) - before;
+
+    assert!(verified);
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn test_unambiguous_charsets() {
+    for charset in [Charset::AlphanumericUnambiguous, Charset::CrockfordBase32] {
+        let chars: std::collections::HashSet<char> = charset.as_str().chars().collect();
+        assert_eq!(chars.len(), charset.len());
+        assert!(!charset.as_str().contains(['O', 'o', 'I', 'l']));
+    }
+    assert!(
+        !Charset::AlphanumericUnambiguous
+            .as_str()
+            .contains(['0', '1'])
+    );
+    assert_eq!(
+        Charset::Alphanumeric.exclude("0Oo1lI"),
+        Charset::AlphanumericUnambiguous.exclude("")
+    );
+
+    // Fingerprints of configurations spelling the characters out are kept.
+    let config = Config {
+        charset: Charset::CrockfordBase32,
+        ..Config::default()
+    };
+    assert_eq!(
+        config.canonicalize().charset,
+        Charset::Custom(Charset::CrockfordBase32.as_str().to_string())
+    );
+}
+
+#[test]
+fn test_charset_combinators_deduplicate() {
+    let custom = Charset::Custom("aAäßİ1a".to_string());
+    assert_eq!(custom.len(), 7);
+    assert_eq!(custom.uppercase(), Charset::Custom("AÄßİ1".to_string()));
+    assert_eq!(custom.lowercase(), Charset::Custom("aäßİ1".to_string()));
+    assert_eq!(custom.exclude("").as_str(), "aAäßİ1");
+    assert_eq!(
+        Charset::Numeric.union(&Charset::Alphabetic),
+        Charset::Custom(format!("0123456789{}", Charset::Alphabetic.as_str()))
+    );
+    assert_eq!(
+        Charset::Alphanumeric.exclude("").union(&Charset::Numeric),
+        Charset::Alphanumeric
+    );
+    assert_eq!(Charset::Alphabetic.uppercase().lowercase().len(), 26);
+}
@@ -0,0 +1,190 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::fmt;
+
+use crate::{Medium, ReferralCodeError};
+
+/// Where a [`ReferralLink`] carries its code.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Placement {
+    /// A segment appended to the path, as in `https://example.com/r/{code}`.
+    PathSegment,
+    /// A query parameter of the given name, as in `https://example.com/?ref={code}`.
+    Query(String),
+    /// The fragment, as in `https://example.com/#{code}`, which browsers
+    /// don't send to servers.
+    Fragment,
+}
+
+/// A URL carrying a referral code.
+///
+/// Only the code and the query parameter name are percent-encoded: the base
+/// URL is taken as already encoded, so building a link never encodes
+/// anything twice. Characters that [`Medium::UrlPathSafe`] allows are kept
+/// as they are, everything else is encoded as UTF-8, including `+`, which
+/// query strings could read as a space.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Placement, ReferralLink};
+///
+/// let url = "https://example.com/join?utm_source=mail#top";
+///
+/// assert_eq!(
+///     ReferralLink::build(url, "AB+12/é", Placement::PathSegment).unwrap().as_str(),
+///     "https://example.com/join/AB%2B12%2F%C3%A9?utm_source=mail#top"
+/// );
+/// assert_eq!(
+///     ReferralLink::build(url, "AB12", Placement::Query("ref".to_string())).unwrap().as_str(),
+///     "https://example.com/join?utm_source=mail&ref=AB12#top"
+/// );
+/// assert!(ReferralLink::build(url, "AB12", Placement::Fragment).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ReferralLink {
+    url: String,
+}
+
+impl ReferralLink {
+    /// Builds a link to `base_url` carrying `code` at `placement`.
+    ///
+    /// The query and fragment of the base URL are kept. A path segment goes
+    /// after the last segment of the path, a query parameter after the
+    /// other parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::InvalidUrl` if `base_url` is empty,
+    /// contains a character that must be percent-encoded, such as a space
+    /// or a non-ASCII character, or already has a fragment when the code
+    /// goes in the fragment.
+    pub fn build(
+        base_url: &str,
+        code: &str,
+        placement: Placement,
+    ) -> Result<Self, ReferralCodeError> {
+        if base_url.is_empty() || !base_url.chars().all(|c| c.is_ascii_graphic()) {
+            return Err(ReferralCodeError::InvalidUrl);
+        }
+
+        let (rest, fragment) = match base_url.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (base_url, None),
+        };
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+        let code = percent_encode(code);
+
+        let url = match placement {
+            Placement::PathSegment => {
+                let separator = if path.ends_with('/') { "" } else { "/" };
+                join(&format!("{path}{separator}{code}"), query, fragment)
+            }
+            Placement::Query(name) => {
+                let parameter = format!("{}={code}", percent_encode(&name));
+                let query = match query {
+                    Some("") | None => parameter,
+                    Some(query) => format!("{query}&{parameter}"),
+                };
+                join(path, Some(&query), fragment)
+            }
+            Placement::Fragment if fragment.is_some() => {
+                return Err(ReferralCodeError::InvalidUrl);
+            }
+            Placement::Fragment => join(path, query, Some(&code)),
+        };
+
+        Ok(ReferralLink { url })
+    }
+
+    /// Returns the URL.
+    pub fn as_str(&self) -> &str {
+        &self.url
+    }
+}
+
+impl fmt::Display for ReferralLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.url)
+    }
+}
+
+impl From<ReferralLink> for String {
+    fn from(link: ReferralLink) -> Self {
+        link.url
+    }
+}
+
+fn join(path: &str, query: Option<&str>, fragment: Option<&str>) -> String {
+    let mut url = path.to_string();
+    if let Some(query) = query {
+        url.push('?');
+        url.push_str(query);
+    }
+    if let Some(fragment) = fragment {
+        url.push('#');
+        url.push_str(fragment);
+    }
+
+    url
+}
+
+/// Percent-encodes every character that isn't safe in a URL path segment.
+pub(crate) fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        if Medium::UrlPathSafe.allows(c) {
+            encoded.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for b in c.encode_utf8(&mut bytes).bytes() {
+                encoded.push_str(&format!("%{b:02X}"));
+            }
+        }
+    }
+
+    encoded
+}
+
+#[test]
+fn test_referral_links_encode_codes_once() {
+    let link = |base: &str, code: &str, placement: Placement| {
+        ReferralLink::build(base, code, placement)
+            .map(String::from)
+            .ok()
+    };
+
+    assert_eq!(
+        link("https://x.co/r/", "A~b-9", Placement::PathSegment).as_deref(),
+        Some("https://x.co/r/A~b-9")
+    );
+    assert_eq!(
+        link("https://x.co", "a b%", Placement::PathSegment).as_deref(),
+        Some("https://x.co/a%20b%25")
+    );
+    assert_eq!(
+        link(
+            "https://x.co/?",
+            "A&B=C",
+            Placement::Query("r f".to_string())
+        )
+        .as_deref(),
+        Some("https://x.co/?r%20f=A%26B%3DC")
+    );
+    assert_eq!(
+        link("https://x.co/a%2Fb?q=1", "#1", Placement::Fragment).as_deref(),
+        Some("https://x.co/a%2Fb?q=1#%231")
+    );
+
+    for base in ["", "https://x.co/a b", "https://x.co/é"] {
+        assert_eq!(link(base, "A", Placement::PathSegment), None, "{base:?}");
+    }
+    assert_eq!(link("https://x.co/#", "A", Placement::Fragment), None);
+}
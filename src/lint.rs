@@ -0,0 +1,141 @@
+use alloc::vec::Vec;
+
+use crate::{
+    Config,
+    segment::{Slot, slots},
+};
+
+/// A literal character that a neighbouring random position can also hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AmbiguousLiteral {
+    /// Zero-based character position in the full code.
+    pub position: usize,
+    /// The literal character.
+    pub literal: char,
+}
+
+/// Warnings about a configuration whose codes are valid but easy to misread.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct LintReport {
+    /// Literals next to random positions that can hold the same character,
+    /// in order of position.
+    pub ambiguous_literals: Vec<AmbiguousLiteral>,
+}
+
+impl LintReport {
+    /// Returns `true` if nothing was flagged.
+    pub fn is_clean(&self) -> bool {
+        self.ambiguous_literals.is_empty()
+    }
+}
+
+impl Config {
+    /// Flags layout choices that make codes hard to split into their parts.
+    ///
+    /// Verification is never affected, since every position of a code holds
+    /// either a literal or a random character. But a literal of the prefix,
+    /// pattern or suffix that the random or check position next to it could
+    /// also hold can't be told apart from it without the configuration: in
+    /// `XX##` over an alphanumeric charset, a code like `XXX4` doesn't show
+    /// where the literal ends, so tools splitting codes by what they look
+    /// like mis-parse them. With `random_case`, letters match in either case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{AmbiguousLiteral, Config, Pattern};
+    ///
+    /// let ambiguous = Config::default().with_pattern(Pattern::Pattern("XX####".to_string()));
+    /// let separated = Config::default().with_pattern(Pattern::Pattern("XX-####".to_string()));
+    ///
+    /// assert_eq!(
+    ///     ambiguous.lint().ambiguous_literals,
+    ///     [AmbiguousLiteral { position: 1, literal: 'X' }]
+    /// );
+    /// assert!(separated.lint().is_clean());
+    /// ```
+    pub fn lint(&self) -> LintReport {
+        let slots = slots(self);
+        let ignore_case = self.random_case.is_some();
+        let same =
+            |a: char, b: char| a == b || ignore_case && a.to_lowercase().eq(b.to_lowercase());
+        let holds = |slot: &Slot, literal: char| match slot {
+            Slot::Literal(_) => false,
+            Slot::Random(class) => class.alphabet(self).chars().any(|c| same(c, literal)),
+            Slot::Check => self.checksum.as_ref().is_some_and(|checksum| {
+                checksum.output_alphabet().chars().any(|c| same(c, literal))
+            }),
+        };
+
+        let ambiguous_literals = slots
+            .iter()
+            .enumerate()
+            .filter_map(|(position, slot)| match slot {
+                Slot::Literal(literal) => {
+                    let neighbours = [position.checked_sub(1), Some(position + 1)];
+                    neighbours
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|i| slots.get(i))
+                        .any(|neighbour| holds(neighbour, *literal))
+                        .then_some(AmbiguousLiteral {
+                            position,
+                            literal: *literal,
+                        })
+                }
+                _ => None,
+            })
+            .collect();
+
+        LintReport { ambiguous_literals }
+    }
+}
+
+#[test]
+fn test_lint_flags_literals_next_to_matching_positions() {
+    use crate::{Charset, Checksum, Pattern};
+
+    let positions = |config: &Config| -> Vec<usize> {
+        config
+            .lint()
+            .ambiguous_literals
+            .iter()
+            .map(|a| a.position)
+            .collect()
+    };
+    let numeric = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Pattern("1#-#".to_string()),
+        ..Config::default()
+    };
+
+    assert_eq!(positions(&numeric), [0]);
+    assert_eq!(positions(&numeric.with_prefix("9")), [1]);
+    assert_eq!(
+        positions(&numeric.with_pattern(Pattern::Pattern("#1".to_string()))),
+        [1]
+    );
+    assert_eq!(
+        positions(&Config {
+            suffix: Some("X".to_string()),
+            checksum: Some(Checksum::Iso7064Mod11_2),
+            ..numeric.clone()
+        }),
+        [0, 5]
+    );
+
+    // Only letters of the same case match, unless codes have random case.
+    let alphabetic = Config {
+        charset: Charset::Custom("ABC".to_string()),
+        pattern: Pattern::Pattern("a#".to_string()),
+        ..Config::default()
+    };
+    assert!(alphabetic.lint().is_clean());
+    assert_eq!(
+        positions(&Config {
+            random_case: Some(0.5),
+            ..alphabetic
+        }),
+        [0]
+    );
+}
@@ -0,0 +1,215 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{Config, FieldLayout, ReferralCodeError, fields};
+
+/// Deterministic short codes for markets, such as regions or countries.
+///
+/// Every market identifier maps to a fixed value below [`Markets::values`],
+/// written as `width` charset characters. The value is derived from the
+/// identifier alone, with 64-bit FNV-1a, so every service computes the same
+/// code for a market without sharing state, and redemptions can be routed
+/// by region without a database lookup. The derivation will not change
+/// between versions.
+///
+/// Use the code as a [`Config::suffix`] per market, or embed the value as a
+/// field of a [`FieldLayout`] with [`Markets::field`]. Registering a market
+/// fails if its value is already taken by another market, so all
+/// registered markets can be told apart.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, Markets};
+///
+/// let config = Config {
+///     charset: Charset::Alphabetic,
+///     ..Config::default()
+/// };
+/// let mut markets = Markets::new(&config, 2);
+///
+/// let fr = markets.register("fr").unwrap();
+/// let de = markets.register("de").unwrap();
+///
+/// assert_eq!(fr.chars().count(), 2);
+/// assert_ne!(fr, de);
+/// assert_eq!(markets.market_of(&format!("ABCDEFGH{de}")), Some("de"));
+/// assert_eq!(Markets::new(&config, 2).register("fr").unwrap(), fr);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Markets {
+    config: Config,
+    width: usize,
+    markets: Vec<(String, u64)>,
+}
+
+impl Markets {
+    /// Creates an empty set of markets with codes of `width` characters of
+    /// `config`'s charset.
+    pub fn new(config: &Config, width: usize) -> Self {
+        Markets {
+            config: Config {
+                charset: config.charset.clone(),
+                random_case: config.random_case,
+                ..Config::default()
+            },
+            width,
+            markets: Vec::new(),
+        }
+    }
+
+    /// Returns the number of values market codes can take.
+    pub fn values(&self) -> u64 {
+        fields::capacity(&self.config, self.width)
+            .try_into()
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Registers a market, returning its code.
+    ///
+    /// Registering a market again returns the same code.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::MarketCollision` with the other market if
+    /// it already has the same code, and `ReferralCodeError::EmptyCharset` if
+    /// market codes can't be written because the charset is empty.
+    pub fn register(&mut self, market: &str) -> Result<String, ReferralCodeError> {
+        if self.values() == 0 {
+            return Err(ReferralCodeError::EmptyCharset);
+        }
+        let value = self.derive(market);
+
+        match self.markets.iter().find(|(_, v)| *v == value) {
+            Some((other, _)) if other != market => {
+                return Err(ReferralCodeError::MarketCollision(other.clone()));
+            }
+            Some(_) => {}
+            None => self.markets.push((market.to_string(), value)),
+        }
+
+        Ok(self.encode(value))
+    }
+
+    /// Returns the code of a registered market.
+    pub fn code(&self, market: &str) -> Option<String> {
+        self.value(market).map(|value| self.encode(value))
+    }
+
+    /// Returns the value of a registered market, as embedded in a field.
+    pub fn value(&self, market: &str) -> Option<u64> {
+        self.markets
+            .iter()
+            .find(|(m, _)| m == market)
+            .map(|(_, value)| *value)
+    }
+
+    /// Returns the registered market of a field value.
+    pub fn market(&self, value: u64) -> Option<&str> {
+        self.markets
+            .iter()
+            .find(|(_, v)| *v == value)
+            .map(|(market, _)| market.as_str())
+    }
+
+    /// Returns the registered market whose code ends `code`.
+    pub fn market_of(&self, code: &str) -> Option<&str> {
+        let chars: Vec<char> = code.chars().collect();
+        let suffix = chars.get(chars.len().checked_sub(self.width)?..)?;
+
+        self.market(fields::read_digits(&self.config, suffix)?.try_into().ok()?)
+    }
+
+    /// Returns `layout` with a field named `name` holding market values.
+    ///
+    /// Set the field to [`Markets::value`] in
+    /// [`crate::EmbeddedFields::values`], and read it back with
+    /// [`Markets::market`] on the value returned by [`Config::field_of`].
+    pub fn field(&self, layout: FieldLayout, name: impl Into<String>) -> FieldLayout {
+        layout.field(name, self.values())
+    }
+
+    fn derive(&self, market: &str) -> u64 {
+        let hash = market.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+
+        hash % self.values()
+    }
+
+    fn encode(&self, value: u64) -> String {
+        fields::write_digits(&self.config, value.into(), self.width)
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_markets_route_codes() {
+    use crate::{Charset, EmbeddedFields, Pattern, generate_one};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(6),
+        ..Config::default()
+    };
+    let mut markets = Markets::new(&config, 1);
+
+    // Eleven markets cannot all have distinct digits.
+    let registered: Vec<String> = (0..11)
+        .map(|i| format!("market-{i}"))
+        .filter(|market| markets.register(market).is_ok())
+        .collect();
+    assert!(registered.len() < 11);
+
+    let market = &registered[0];
+    assert_eq!(markets.register(market).ok(), markets.code(market));
+    let routed = Config {
+        fields: Some(EmbeddedFields {
+            layout: markets.field(FieldLayout::new(0, 1), "market"),
+            values: vec![markets.value(market).unwrap()],
+        }),
+        suffix: markets.code(market),
+        ..config
+    };
+    let code = generate_one(&routed).unwrap();
+
+    assert_eq!(markets.market_of(&code), Some(market.as_str()));
+    let value = routed.field_of(&code, "market").unwrap();
+    assert_eq!(markets.market(value), Some(market.as_str()));
+}
+
+#[test]
+fn test_markets_of_degenerate_charsets() {
+    use crate::{Charset, ReferralCodeError};
+
+    let unary = Config {
+        charset: Charset::Custom("A".to_string()),
+        ..Config::default()
+    };
+    let mut markets = Markets::new(&unary, 3);
+    assert_eq!(markets.values(), 1);
+    assert_eq!(markets.register("fr").unwrap(), "AAA");
+    assert!(matches!(
+        markets.register("de"),
+        Err(ReferralCodeError::MarketCollision(other)) if other == "fr"
+    ));
+    assert_eq!(markets.market_of("XYZAAA"), Some("fr"));
+
+    let mut unwritten = Markets::new(&unary, 0);
+    assert_eq!(unwritten.register("fr").unwrap(), "");
+
+    let empty = Config {
+        charset: Charset::Custom(String::new()),
+        ..Config::default()
+    };
+    let mut markets = Markets::new(&empty, 2);
+    assert_eq!(markets.values(), 0);
+    assert!(matches!(
+        markets.register("fr"),
+        Err(ReferralCodeError::EmptyCharset)
+    ));
+}
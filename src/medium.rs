@@ -0,0 +1,344 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{Charset, Config};
+
+/// The 45 characters of the QR code alphanumeric mode.
+const QR_ALPHANUMERIC: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// A medium codes are distributed through, restricting the characters they
+/// can safely contain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Medium {
+    /// Email bodies and subjects.
+    ///
+    /// ASCII letters, digits, `-`, `_` and `.` only: anything else may be
+    /// encoded, auto-linked or broken across lines by mail clients.
+    EmailSafe,
+    /// A segment of a URL path, without percent-encoding.
+    ///
+    /// The unreserved characters of RFC 3986: ASCII letters, digits, `-`,
+    /// `.`, `_` and `~`.
+    UrlPathSafe,
+    /// The alphanumeric mode of QR codes.
+    ///
+    /// Digits, uppercase letters and ` $%*+-./:`, which QR codes encode in
+    /// 5.5 bits per character instead of 8.
+    QrAlnumMode,
+}
+
+impl Medium {
+    /// Returns `true` if `c` is safe in this medium.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Medium;
+    ///
+    /// assert!(Medium::UrlPathSafe.allows('~'));
+    /// assert!(!Medium::EmailSafe.allows('~'));
+    /// assert!(!Medium::QrAlnumMode.allows('a'));
+    /// ```
+    pub fn allows(&self, c: char) -> bool {
+        match self {
+            Self::EmailSafe => c.is_ascii_alphanumeric() || "-_.".contains(c),
+            Self::UrlPathSafe => c.is_ascii_alphanumeric() || "-._~".contains(c),
+            Self::QrAlnumMode => QR_ALPHANUMERIC.contains(c),
+        }
+    }
+
+    /// Returns a charset of random characters for this medium.
+    ///
+    /// Punctuation is left out even where the medium allows it, so codes
+    /// don't begin or end with characters that readers or link detectors
+    /// tend to drop.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Medium;
+    ///
+    /// assert_eq!(Medium::QrAlnumMode.charset().len(), 36);
+    /// ```
+    pub fn charset(&self) -> Charset {
+        match self {
+            Self::EmailSafe | Self::UrlPathSafe => Charset::Alphanumeric,
+            Self::QrAlnumMode => Charset::Custom(QR_ALPHANUMERIC[..36].to_string()),
+        }
+    }
+}
+
+/// The characters of a configuration that are not safe in a [`Medium`].
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct MediumReport {
+    /// Unsafe characters codes can contain, in order of first appearance in
+    /// the charset, prefix, pattern literals, check characters and suffix.
+    pub unsafe_chars: Vec<char>,
+}
+
+impl MediumReport {
+    /// Returns `true` if every code of the configuration is safe in the medium.
+    pub fn is_safe(&self) -> bool {
+        self.unsafe_chars.is_empty()
+    }
+}
+
+impl Charset {
+    /// Returns a copy of this charset without the characters unsafe in `medium`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Medium};
+    ///
+    /// let charset = Charset::Custom("AB+c~".to_string());
+    ///
+    /// assert_eq!(charset.restricted_to(Medium::QrAlnumMode).as_str(), "AB+");
+    /// ```
+    pub fn restricted_to(&self, medium: Medium) -> Charset {
+        Charset::Custom(
+            self.as_str()
+                .chars()
+                .filter(|c| medium.allows(*c))
+                .collect(),
+        )
+    }
+}
+
+impl Config {
+    /// Checks that every code of this configuration is safe in `medium`.
+    ///
+    /// Random characters, literals, the prefix and suffix and check
+    /// characters are all checked. With `random_case`, letters are checked
+    /// in both cases, so random case is unsafe in [`Medium::QrAlnumMode`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, Medium, Pattern};
+    ///
+    /// let config = Config {
+    ///     charset: Medium::QrAlnumMode.charset(),
+    ///     pattern: Pattern::Pattern("REF-####".to_string()),
+    ///     ..Config::default()
+    /// };
+    ///
+    /// assert!(config.check_medium(Medium::QrAlnumMode).is_safe());
+    /// assert_eq!(
+    ///     config.with_prefix("#").check_medium(Medium::QrAlnumMode).unsafe_chars,
+    ///     vec!['#']
+    /// );
+    /// ```
+    pub fn check_medium(&self, medium: Medium) -> MediumReport {
+        let classes = self.pattern.classes();
+        let random = classes
+            .iter()
+            .flat_map(|class| class.alphabet(self).chars().collect::<Vec<_>>());
+        let cased = random.flat_map(|c| match self.random_case {
+            Some(_) => c.to_lowercase().chain(c.to_uppercase()).collect(),
+            None => vec![c],
+        });
+        let literals: String = self
+            .pattern
+            .chars()
+            .filter(|(_, class)| class.is_none())
+            .map(|(p, _)| p)
+            .collect();
+        let checks = self
+            .checksum
+            .iter()
+            .flat_map(|c| c.output_alphabet().chars());
+
+        let mut report = MediumReport::default();
+        let all = cased
+            .chain(self.prefix.iter().flat_map(|s| s.chars()))
+            .chain(literals.chars())
+            .chain(checks)
+            .chain(self.suffix.iter().flat_map(|s| s.chars()));
+        for c in all {
+            if !medium.allows(c) && !report.unsafe_chars.contains(&c) {
+                report.unsafe_chars.push(c);
+            }
+        }
+
+        report
+    }
+}
+
+/// Data codewords of QR versions 1 to 10, at error correction levels L, M, Q and H.
+const QR_DATA_CODEWORDS: [[usize; 4]; 10] = [
+    [19, 16, 13, 9],
+    [34, 28, 22, 16],
+    [55, 44, 34, 26],
+    [80, 64, 48, 36],
+    [108, 86, 62, 46],
+    [136, 108, 76, 60],
+    [156, 124, 88, 66],
+    [194, 154, 110, 86],
+    [232, 182, 132, 100],
+    [274, 216, 154, 122],
+];
+
+/// The error correction level of a QR code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QrEcLevel {
+    /// Recovers about 7% of the symbol.
+    L,
+    /// Recovers about 15% of the symbol.
+    M,
+    /// Recovers about 25% of the symbol.
+    Q,
+    /// Recovers about 30% of the symbol.
+    H,
+}
+
+/// The smallest QR symbol holding a code in alphanumeric mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QrSize {
+    /// The QR version, from 1 to 10.
+    pub version: u8,
+    /// The number of modules on each side of the symbol, quiet zone excluded.
+    pub modules: usize,
+}
+
+impl Config {
+    /// Returns a copy of this configuration drawing only characters safe in `medium`.
+    ///
+    /// The charset is restricted with [`Charset::restricted_to`], and for
+    /// [`Medium::QrAlnumMode`], which has no lowercase letters, `random_case`
+    /// is cleared. Literals, prefix and suffix are kept: check them with
+    /// [`Config::check_medium`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, Medium};
+    ///
+    /// let config = Config::default().with_medium(Medium::QrAlnumMode);
+    ///
+    /// assert_eq!(config.charset.len(), 36);
+    /// assert!(config.check_medium(Medium::QrAlnumMode).is_safe());
+    /// ```
+    pub fn with_medium(&self, medium: Medium) -> Config {
+        Config {
+            charset: self.charset.restricted_to(medium),
+            random_case: match medium {
+                Medium::QrAlnumMode => None,
+                _ => self.random_case,
+            },
+            ..self.clone()
+        }
+    }
+
+    /// Returns the smallest QR symbol holding a code of this configuration.
+    ///
+    /// Codes are assumed to be encoded alone, in alphanumeric mode, which
+    /// takes 11 bits per pair of characters instead of 16 in byte mode.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(size)` - The version and size of the smallest symbol
+    /// * `None` - If codes contain characters outside the QR alphanumeric
+    ///   set, or don't fit in a version 10 symbol
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, Medium, Pattern, QrEcLevel};
+    ///
+    /// let config = Config::default()
+    ///     .with_medium(Medium::QrAlnumMode)
+    ///     .with_pattern(Pattern::Length(20));
+    ///
+    /// assert_eq!(config.qr_size(QrEcLevel::L).unwrap().version, 1);
+    /// assert_eq!(config.qr_size(QrEcLevel::H).unwrap().modules, 25);
+    /// assert_eq!(Config::default().qr_size(QrEcLevel::L), None);
+    /// ```
+    pub fn qr_size(&self, level: QrEcLevel) -> Option<QrSize> {
+        if !self.check_medium(Medium::QrAlnumMode).is_safe() {
+            return None;
+        }
+
+        let len = crate::segment::slots(self).len();
+        let data_bits = 11 * (len / 2) + 6 * (len % 2);
+
+        (1..=QR_DATA_CODEWORDS.len())
+            .find(|&version| {
+                let count_bits = if version < 10 { 9 } else { 11 };
+                let capacity = QR_DATA_CODEWORDS[version - 1][level as usize] * 8;
+                len < 1 << count_bits && 4 + count_bits + data_bits <= capacity
+            })
+            .map(|version| QrSize {
+                version: version as u8,
+                modules: 17 + 4 * version,
+            })
+    }
+}
+
+#[test]
+fn test_qr_capacities_match_the_standard() {
+    let capacity = |version: usize, level: QrEcLevel| {
+        let count_bits = if version < 10 { 9 } else { 11 };
+        let bits = QR_DATA_CODEWORDS[version - 1][level as usize] * 8 - 4 - count_bits;
+        2 * (bits / 11) + usize::from(bits % 11 >= 6)
+    };
+
+    assert_eq!(capacity(1, QrEcLevel::L), 25);
+    assert_eq!(capacity(1, QrEcLevel::H), 10);
+    assert_eq!(capacity(4, QrEcLevel::M), 90);
+    assert_eq!(capacity(10, QrEcLevel::L), 395);
+    assert_eq!(capacity(10, QrEcLevel::H), 174);
+
+    let config = |len| {
+        Config::default()
+            .with_medium(Medium::QrAlnumMode)
+            .with_pattern(crate::Pattern::Length(len))
+    };
+    assert_eq!(config(25).qr_size(QrEcLevel::L).unwrap().version, 1);
+    assert_eq!(config(26).qr_size(QrEcLevel::L).unwrap().version, 2);
+    assert_eq!(config(174).qr_size(QrEcLevel::H).unwrap().version, 10);
+    assert_eq!(config(175).qr_size(QrEcLevel::H), None);
+}
+
+#[test]
+fn test_check_medium_covers_every_part_of_a_code() {
+    use crate::{Checksum, Pattern};
+
+    let config = Config {
+        charset: Medium::QrAlnumMode.charset(),
+        pattern: Pattern::Pattern("A_##".to_string()),
+        prefix: Some("Q:".to_string()),
+        suffix: Some("~".to_string()),
+        checksum: Some(Checksum::Iso7064Mod37_2),
+        ..Config::default()
+    };
+
+    assert_eq!(
+        config.check_medium(Medium::QrAlnumMode).unsafe_chars,
+        vec!['_', '~']
+    );
+    assert_eq!(
+        config.check_medium(Medium::UrlPathSafe).unsafe_chars,
+        vec![':', '*']
+    );
+
+    let random_case = Config {
+        random_case: Some(0.5),
+        ..Config::default().with_pattern(Pattern::Length(2))
+    };
+    assert!(random_case.check_medium(Medium::EmailSafe).is_safe());
+    assert_eq!(
+        Config {
+            charset: Medium::QrAlnumMode.charset(),
+            ..random_case
+        }
+        .check_medium(Medium::QrAlnumMode)
+        .unsafe_chars
+        .len(),
+        26
+    );
+}
@@ -0,0 +1,442 @@
+use sha2::{Digest, Sha256};
+
+use crate::Batch;
+#[cfg(feature = "rng")]
+use crate::{Config, ReferralCodeError};
+
+fn leaf_hash(code: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0]);
+    hasher.update(code.as_bytes());
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([1]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Reduces one level of the tree; an unpaired last node is promoted as-is.
+fn parent_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let (pairs, unpaired) = level.as_chunks::<2>();
+
+    pairs
+        .iter()
+        .map(|[left, right]| node_hash(left, right))
+        .chain(unpaired.iter().copied())
+        .collect()
+}
+
+/// A Merkle tree over the sorted, deduplicated codes of a batch.
+struct MerkleTree {
+    leaves: Vec<String>,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    fn new(codes: &[String]) -> Self {
+        let mut leaves = codes.to_vec();
+        leaves.sort();
+        leaves.dedup();
+
+        let mut levels = vec![leaves.iter().map(|c| leaf_hash(c)).collect::<Vec<_>>()];
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let next = parent_level(level);
+            levels.push(next);
+        }
+
+        MerkleTree { leaves, levels }
+    }
+
+    fn root(&self) -> [u8; 32] {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(root) => *root,
+            None => Sha256::digest([]).into(),
+        }
+    }
+
+    fn proof(&self, index: usize) -> MerkleProof {
+        let mut siblings = Vec::new();
+        let mut i = index;
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(i ^ 1) {
+                siblings.push(*sibling);
+            }
+            i /= 2;
+        }
+
+        MerkleProof {
+            code: self.leaves[index].clone(),
+            index,
+            leaf_count: self.leaves.len(),
+            siblings,
+        }
+    }
+}
+
+/// Proof that a code is a leaf at a given position of a batch's Merkle tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof {
+    /// The code the proof is for.
+    pub code: String,
+    /// Position of the code among the batch's sorted codes.
+    pub index: usize,
+    /// Number of distinct codes in the batch.
+    pub leaf_count: usize,
+    /// Sibling hashes from the leaf up to the root.
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    /// Encodes the proof compactly, without the code itself.
+    ///
+    /// The layout is the index and leaf count as little-endian `u64`s,
+    /// followed by the sibling hashes. This is what an offline verifier needs
+    /// next to the code, e.g. printed as a QR code on the voucher.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + 32 * self.siblings.len());
+        bytes.extend((self.index as u64).to_le_bytes());
+        bytes.extend((self.leaf_count as u64).to_le_bytes());
+        for sibling in &self.siblings {
+            bytes.extend(sibling);
+        }
+        bytes
+    }
+
+    /// Decodes a proof produced by [`MerkleProof::to_bytes`] for the given code.
+    ///
+    /// Returns `None` if the bytes are not a well-formed encoding.
+    pub fn from_bytes(code: &str, bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 16 || !(bytes.len() - 16).is_multiple_of(32) {
+            return None;
+        }
+
+        let index = u64::from_le_bytes(bytes[0..8].try_into().ok()?);
+        let leaf_count = u64::from_le_bytes(bytes[8..16].try_into().ok()?);
+
+        Some(MerkleProof {
+            code: code.to_string(),
+            index: usize::try_from(index).ok()?,
+            leaf_count: usize::try_from(leaf_count).ok()?,
+            siblings: bytes[16..].as_chunks::<32>().0.to_vec(),
+        })
+    }
+
+    /// Recomputes the root this proof leads to, if the proof is well-formed.
+    fn root(&self) -> Option<[u8; 32]> {
+        if self.index >= self.leaf_count {
+            return None;
+        }
+
+        let mut hash = leaf_hash(&self.code);
+        let mut siblings = self.siblings.iter();
+        let (mut i, mut width) = (self.index, self.leaf_count);
+
+        while width > 1 {
+            if i % 2 == 1 {
+                hash = node_hash(siblings.next()?, &hash);
+            } else if i + 1 < width {
+                hash = node_hash(&hash, siblings.next()?);
+            }
+            i /= 2;
+            width = width.div_ceil(2);
+        }
+
+        siblings.next().is_none().then_some(hash)
+    }
+}
+
+/// Answer to a membership challenge for a single code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MembershipProof {
+    /// The code is part of the batch.
+    Included(MerkleProof),
+    /// The code is not part of the batch.
+    ///
+    /// The proof consists of the neighbouring codes that would surround it in
+    /// sorted order; `None` means the code would sort before the first (or
+    /// after the last) code of the batch.
+    Excluded {
+        /// Proof for the greatest batch code smaller than the challenged code.
+        lower: Option<MerkleProof>,
+        /// Proof for the smallest batch code greater than the challenged code.
+        upper: Option<MerkleProof>,
+    },
+}
+
+impl Batch {
+    /// Returns the root of the Merkle tree over the batch's sorted codes.
+    ///
+    /// The root is a 32-byte commitment to the batch contents that can be
+    /// shared with partners; membership of any code can later be proven
+    /// against it without revealing the rest of the batch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Batch, Config};
+    ///
+    /// let config = Config::default().with_count(10);
+    /// let batch = Batch::generate_seeded("spring", &config, 42).unwrap();
+    ///
+    /// assert_eq!(batch.merkle_root(), Batch::generate_seeded("spring", &config, 42).unwrap().merkle_root());
+    /// # }
+    /// ```
+    pub fn merkle_root(&self) -> [u8; 32] {
+        MerkleTree::new(&self.codes).root()
+    }
+
+    /// Proves that `code` is part of the batch.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(proof)` - An inclusion proof, checkable with [`verify_proof`]
+    /// * `None` - If the code is not part of the batch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Batch, Config, verify_proof};
+    ///
+    /// let batch = Batch::generate("spring", &Config::default().with_count(10)).unwrap();
+    /// let root = batch.merkle_root();
+    ///
+    /// let proof = batch.prove(&batch.codes[0]).unwrap();
+    /// assert!(verify_proof(&root, &batch.codes[0], &proof));
+    /// assert!(batch.prove("not-a-code").is_none());
+    /// # }
+    /// ```
+    pub fn prove(&self, code: &str) -> Option<MerkleProof> {
+        match self.challenge(code) {
+            MembershipProof::Included(proof) => Some(proof),
+            MembershipProof::Excluded { .. } => None,
+        }
+    }
+
+    /// Answers a membership challenge for `code`.
+    ///
+    /// Returns an inclusion proof if the code is part of the batch, or the
+    /// proofs of its two sorted neighbours otherwise. Either answer can be
+    /// checked with [`verify_membership`] knowing only the Merkle root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Batch, Config, MembershipProof, verify_membership};
+    ///
+    /// let batch = Batch::generate("spring", &Config::default().with_count(10)).unwrap();
+    /// let root = batch.merkle_root();
+    ///
+    /// let proof = batch.challenge(&batch.codes[3]);
+    /// assert!(matches!(proof, MembershipProof::Included(_)));
+    /// assert_eq!(verify_membership(&root, &batch.codes[3], &proof), Some(true));
+    ///
+    /// let proof = batch.challenge("not-a-code");
+    /// assert_eq!(verify_membership(&root, "not-a-code", &proof), Some(false));
+    /// # }
+    /// ```
+    pub fn challenge(&self, code: &str) -> MembershipProof {
+        let tree = MerkleTree::new(&self.codes);
+
+        match tree.leaves.binary_search_by(|leaf| leaf.as_str().cmp(code)) {
+            Ok(index) => MembershipProof::Included(tree.proof(index)),
+            Err(index) => MembershipProof::Excluded {
+                lower: index.checked_sub(1).map(|i| tree.proof(i)),
+                upper: (index < tree.leaves.len()).then(|| tree.proof(index)),
+            },
+        }
+    }
+}
+
+/// Verifies that a code is part of a batch, knowing only the batch's Merkle root.
+///
+/// # Arguments
+///
+/// * `root` - The Merkle root of the batch
+/// * `code` - The code being checked
+/// * `proof` - The inclusion proof, from [`Batch::prove`]
+///
+/// # Returns
+///
+/// `true` if the proof is valid for this code and root.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "rng")] {
+/// use referral_codes::{Batch, Config, MerkleProof, verify_proof};
+///
+/// let batch = Batch::generate("spring", &Config::default().with_count(10)).unwrap();
+/// let root = batch.merkle_root();
+/// let bytes = batch.prove(&batch.codes[0]).unwrap().to_bytes();
+///
+/// // Offline, with only the root, the code and the encoded proof:
+/// let proof = MerkleProof::from_bytes(&batch.codes[0], &bytes).unwrap();
+/// assert!(verify_proof(&root, &batch.codes[0], &proof));
+/// # }
+/// ```
+pub fn verify_proof(root: &[u8; 32], code: &str, proof: &MerkleProof) -> bool {
+    proof.code == code && proof.root() == Some(*root)
+}
+
+/// Verifies an answer to a membership challenge against a Merkle root.
+///
+/// # Arguments
+///
+/// * `root` - The Merkle root of the batch, as committed to by the issuer
+/// * `code` - The challenged code
+/// * `proof` - The issuer's answer, from [`Batch::challenge`]
+///
+/// # Returns
+///
+/// * `Some(true)` - The proof shows the code is part of the batch
+/// * `Some(false)` - The proof shows the code is not part of the batch
+/// * `None` - The proof is invalid for this root and code
+pub fn verify_membership(root: &[u8; 32], code: &str, proof: &MembershipProof) -> Option<bool> {
+    match proof {
+        MembershipProof::Included(proof) => verify_proof(root, code, proof).then_some(true),
+        MembershipProof::Excluded { lower, upper } => {
+            let leaf_count = lower
+                .as_ref()
+                .or(upper.as_ref())
+                .map_or(0, |p| p.leaf_count);
+
+            for p in [lower, upper].into_iter().flatten() {
+                if p.leaf_count != leaf_count || p.root()? != *root {
+                    return None;
+                }
+            }
+
+            let adjacent = match (lower, upper) {
+                (Some(l), Some(u)) => {
+                    l.index + 1 == u.index && l.code.as_str() < code && code < u.code.as_str()
+                }
+                (Some(l), None) => l.index + 1 == leaf_count && l.code.as_str() < code,
+                (None, Some(u)) => u.index == 0 && code < u.code.as_str(),
+                (None, None) => *root == MerkleTree::new(&[]).root(),
+            };
+
+            adjacent.then_some(false)
+        }
+    }
+}
+
+/// Regenerates a seeded batch and answers a membership challenge for it.
+///
+/// This lets the holder of a batch's seed answer disputes about a code without
+/// keeping the batch around: the batch is regenerated, checked against the
+/// Merkle root that was shared with the partner, and challenged.
+///
+/// # Arguments
+///
+/// * `id` - Identifier of the batch
+/// * `config` - The configuration the batch was generated with
+/// * `seed` - The seed the batch was generated with
+/// * `root` - The Merkle root shared when the batch was issued
+/// * `code` - The challenged code
+///
+/// # Errors
+///
+/// Returns the same errors as [`crate::generate`], or
+/// `ReferralCodeError::DigestMismatch` if the regenerated batch does not
+/// match `root`.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Batch, Config, respond_challenge, verify_membership};
+///
+/// let config = Config::default().with_count(10);
+/// let batch = Batch::generate_seeded("spring", &config, 42).unwrap();
+/// let root = batch.merkle_root();
+///
+/// let proof = respond_challenge("spring", &config, 42, &root, &batch.codes[0]).unwrap();
+/// assert_eq!(verify_membership(&root, &batch.codes[0], &proof), Some(true));
+/// ```
+#[cfg(feature = "rng")]
+pub fn respond_challenge(
+    id: &str,
+    config: &Config,
+    seed: u64,
+    root: &[u8; 32],
+    code: &str,
+) -> Result<MembershipProof, ReferralCodeError> {
+    let batch = Batch::generate_seeded(id, config, seed)?;
+
+    if batch.merkle_root() != *root {
+        return Err(ReferralCodeError::DigestMismatch);
+    }
+
+    Ok(batch.challenge(code))
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_membership_proofs_for_every_position() {
+    for count in 1..=9 {
+        let batch = Batch::generate("test", &Config::default().with_count(count)).unwrap();
+        let root = batch.merkle_root();
+
+        for code in &batch.codes {
+            assert_eq!(
+                verify_membership(&root, code, &batch.challenge(code)),
+                Some(true)
+            );
+
+            let absent = format!("{code}!");
+            assert_eq!(
+                verify_membership(&root, &absent, &batch.challenge(&absent)),
+                Some(false)
+            );
+        }
+
+        for absent in ["", "~~~~~~~~~"] {
+            assert_eq!(
+                verify_membership(&root, absent, &batch.challenge(absent)),
+                Some(false)
+            );
+        }
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_proof_bytes_round_trip() {
+    let batch = Batch::generate("test", &Config::default().with_count(7)).unwrap();
+    let root = batch.merkle_root();
+
+    for code in &batch.codes {
+        let bytes = batch.prove(code).unwrap().to_bytes();
+        let proof = MerkleProof::from_bytes(code, &bytes).unwrap();
+
+        assert!(verify_proof(&root, code, &proof));
+        assert!(MerkleProof::from_bytes(code, &bytes[1..]).is_none());
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_forged_proofs_are_rejected() {
+    let batch = Batch::generate("test", &Config::default().with_count(5)).unwrap();
+    let root = batch.merkle_root();
+
+    // A genuine code's proof cannot be reused for another code.
+    let proof = batch.challenge(&batch.codes[0]);
+    assert_eq!(verify_membership(&root, "forged", &proof), None);
+
+    // Non-adjacent neighbours cannot hide a code that is in the batch.
+    let mut sorted = batch.codes.clone();
+    sorted.sort();
+    let forged = MembershipProof::Excluded {
+        lower: Some(MerkleTree::new(&batch.codes).proof(0)),
+        upper: Some(MerkleTree::new(&batch.codes).proof(2)),
+    };
+    assert_eq!(verify_membership(&root, &sorted[1], &forged), None);
+}
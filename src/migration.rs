@@ -0,0 +1,193 @@
+use alloc::vec::Vec;
+
+use crate::{
+    Config,
+    segment::{CharClass, Slot, slots},
+};
+
+/// A reason why codes issued under one configuration may not verify under another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MigrationIssue {
+    /// Characters of the old charset that the new charset does not contain.
+    MissingCharacters(Vec<char>),
+    /// The total code length (prefix and suffix included) differs.
+    LengthChanged {
+        /// Length of codes under the old configuration.
+        old: usize,
+        /// Length of codes under the new configuration.
+        new: usize,
+    },
+    /// A position that no longer accepts what old codes contain there.
+    ///
+    /// This happens when a random position becomes a literal, when a literal
+    /// changes, or when a literal becomes a random position whose charset
+    /// doesn't contain the old literal.
+    PositionChanged {
+        /// Zero-based character position in the full code.
+        position: usize,
+    },
+    /// The format tag differs, so codes would be identified as another format.
+    FormatTagChanged,
+    /// The check characters would be computed differently.
+    ///
+    /// Check characters depend on the check positions, the charset order and
+    /// the placement of random positions, all of which must stay the same.
+    ChecksumChanged,
+}
+
+/// The result of comparing an old configuration with a new one.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct MigrationReport {
+    /// Every incompatibility that was found.
+    pub issues: Vec<MigrationIssue>,
+}
+
+impl MigrationReport {
+    /// Returns `true` if every previously issued code remains valid.
+    pub fn is_compatible(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Checks whether codes issued under `old` remain valid under `new`.
+///
+/// Use this before changing a live format to make sure previously issued
+/// codes will not be invalidated: the new charset must be a superset of the
+/// old one, the code layout must accept every old code position by position,
+/// and check characters must be computed identically.
+///
+/// # Arguments
+///
+/// * `old` - The configuration codes were issued under
+/// * `new` - The configuration that will be used from now on
+///
+/// # Returns
+///
+/// A `MigrationReport` listing every incompatibility found.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, MigrationIssue, check_migration};
+///
+/// let old = Config {
+///     charset: Charset::Numeric,
+///     ..Config::default()
+/// };
+/// let wider = Config {
+///     charset: Charset::Alphanumeric,
+///     ..Config::default()
+/// };
+///
+/// assert!(check_migration(&old, &wider).is_compatible());
+/// assert_eq!(
+///     check_migration(&wider, &old).issues[0],
+///     MigrationIssue::MissingCharacters(('a'..='z').chain('A'..='Z').collect()),
+/// );
+/// ```
+pub fn check_migration(old: &Config, new: &Config) -> MigrationReport {
+    let mut report = MigrationReport::default();
+
+    let missing: Vec<char> = old
+        .charset
+        .as_str()
+        .chars()
+        .filter(|c| new.charset.index_of(*c).is_none())
+        .collect();
+    if !missing.is_empty() {
+        report
+            .issues
+            .push(MigrationIssue::MissingCharacters(missing));
+    }
+
+    let old_template = slots(old);
+    let new_template = slots(new);
+
+    if old_template.len() != new_template.len() {
+        report.issues.push(MigrationIssue::LengthChanged {
+            old: old_template.len(),
+            new: new_template.len(),
+        });
+    } else {
+        for (position, (o, n)) in old_template.iter().zip(&new_template).enumerate() {
+            let accepted = match (o, n) {
+                // Charset changes are reported once as missing characters.
+                (Slot::Random(CharClass::Charset), Slot::Random(CharClass::Charset)) => true,
+                (Slot::Random(o), Slot::Random(n)) => {
+                    o.alphabet(old).chars().all(|c| n.contains(new, c))
+                }
+                (Slot::Random(_), Slot::Literal(_)) => false,
+                (Slot::Literal(o), Slot::Literal(n)) => o == n,
+                (Slot::Literal(o), Slot::Random(n)) => n.contains(new, *o),
+                // Checksum changes are reported once as a checksum change.
+                (Slot::Check, Slot::Check) => true,
+                (Slot::Check, _) | (_, Slot::Check) => false,
+            };
+
+            if !accepted {
+                report
+                    .issues
+                    .push(MigrationIssue::PositionChanged { position });
+            }
+        }
+    }
+
+    if old.format_tag != new.format_tag {
+        report.issues.push(MigrationIssue::FormatTagChanged);
+    }
+
+    if old.checksum != new.checksum
+        || old.checksum.is_some() && old.checksum_coverage != new.checksum_coverage
+    {
+        report.issues.push(MigrationIssue::ChecksumChanged);
+    } else if old.ocr_check.is_some() || new.ocr_check.is_some() {
+        let random = |slot: &Slot| matches!(slot, Slot::Random(_));
+        let same_layout = old_template
+            .iter()
+            .map(random)
+            .eq(new_template.iter().map(random));
+
+        if old.ocr_check != new.ocr_check
+            || old.charset.as_str() != new.charset.as_str()
+            || !same_layout
+        {
+            report.issues.push(MigrationIssue::ChecksumChanged);
+        }
+    }
+
+    report
+}
+
+#[test]
+fn test_check_migration_layout() {
+    use crate::{OcrCheck, Pattern};
+
+    let old = Config {
+        pattern: Pattern::Pattern("AB-####".to_string()),
+        ..Config::default()
+    };
+    let moved_prefix = Config {
+        prefix: Some("AB".to_string()),
+        pattern: Pattern::Pattern("-####".to_string()),
+        ..Config::default()
+    };
+    let changed_literal = Config {
+        pattern: Pattern::Pattern("AB_####".to_string()),
+        ..Config::default()
+    };
+    let with_check = Config {
+        ocr_check: Some(OcrCheck::new(0, 1)),
+        ..old.clone()
+    };
+
+    assert!(check_migration(&old, &moved_prefix).is_compatible());
+    assert_eq!(
+        check_migration(&old, &changed_literal).issues,
+        vec![MigrationIssue::PositionChanged { position: 2 }]
+    );
+    assert_eq!(
+        check_migration(&old, &with_check).issues,
+        vec![MigrationIssue::ChecksumChanged]
+    );
+}
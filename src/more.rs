@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use crate::{
+    Attempts, Config, ReferralCodeError, check_config, dedup_key, generate_one_with, rng::rng_for,
+    segment,
+};
+
+/// Generates `config.count` codes that are unique together with existing ones.
+///
+/// This tops up a campaign whose codes are kept elsewhere, such as in a
+/// database: none of the new codes is in `existing`, and with
+/// `random_case`, none differs from an existing code only in case. Existing
+/// codes of the format take part of its keyspace, so the keyspace must
+/// hold them plus `config.count` new codes. Existing codes of other
+/// formats, or that the configuration would never generate, are ignored.
+///
+/// # Errors
+///
+/// * `ReferralCodeError::NonFeasibleConfig` - If the keyspace can't hold
+///   the existing codes and `config.count` more
+/// * Any other error of [`crate::generate`]
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+/// use referral_codes::{Charset, Config, Pattern, generate, generate_more};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Length(2),
+///     count: 60,
+///     ..Config::default()
+/// };
+///
+/// let issued: HashSet<String> = generate(&config).unwrap().into_iter().collect();
+/// let more = generate_more(&config.with_count(40), &issued).unwrap();
+///
+/// assert!(more.iter().all(|code| !issued.contains(code)));
+/// assert!(generate_more(&config.with_count(41), &issued).is_err());
+/// ```
+pub fn generate_more(
+    config: &Config,
+    existing: &HashSet<String>,
+) -> Result<Vec<String>, ReferralCodeError> {
+    let mut taken: HashSet<String> = existing
+        .iter()
+        .filter(|code| segment::random_chars(config, code).is_some() && config.accepts(code))
+        .map(|code| dedup_key(config, code).into_owned())
+        .collect();
+    check_config(&config.with_count(taken.len().saturating_add(config.count)))?;
+
+    let mut rng = rng_for(config);
+    let mut attempts = Attempts::new(config, taken.len(), config.count);
+    let mut codes = Vec::with_capacity(config.count);
+
+    while codes.len() < config.count {
+        let Some(code) = generate_one_with(config, &mut rng, &mut attempts) else {
+            return Err(attempts.exceeded(codes));
+        };
+        if taken.insert(dedup_key(config, &code).into_owned()) {
+            codes.push(code);
+        }
+    }
+
+    Ok(codes)
+}
+
+#[test]
+fn test_generate_more_avoids_existing_codes() {
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Custom("ABCabc".to_string()),
+        pattern: Pattern::Pattern("X-##".to_string()),
+        random_case: Some(0.5),
+        reserved_prefixes: vec!["X-C".to_string()],
+        ..Config::default()
+    };
+
+    // Of the 3 * 3 caseless codes, "X-C" reserves 3 and the existing codes
+    // take 2: "X-CA" is reserved, and "Y-AA" and "X-ABC" don't fit.
+    let existing: HashSet<String> = ["X-AB", "X-ab", "X-bC", "X-CA", "Y-AA", "X-ABC"]
+        .map(String::from)
+        .into();
+
+    let more = generate_more(&config.with_count(4), &existing).unwrap();
+    let mut all: Vec<String> = more
+        .iter()
+        .chain(["X-ab", "X-bc"].map(String::from).iter())
+        .map(|code| code.to_lowercase())
+        .collect();
+    all.sort();
+    assert_eq!(all, ["x-aa", "x-ab", "x-ac", "x-ba", "x-bb", "x-bc"]);
+
+    assert!(matches!(
+        generate_more(&config.with_count(5), &existing),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+    assert!(
+        generate_more(&config.with_count(0), &existing)
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,179 @@
+use alloc::vec::Vec;
+
+use crate::{
+    Charset, Config, Pattern,
+    segment::{self, CharClass},
+};
+
+/// Two interleaved check characters for OCR-scanned codes.
+///
+/// When set on a [`Config`], two of the pattern's random positions are used
+/// for check characters computed over the remaining random characters:
+///
+/// - the first is the sum of the charset indices, modulo the charset size,
+///   which detects any single misread character;
+/// - the second is the position-weighted sum of the indices, modulo the
+///   charset size, which additionally detects most adjacent transpositions.
+///
+/// Positions are zero-based indices into the pattern's random positions
+/// (i.e. counting only placeholder characters), and must be distinct,
+/// smaller than `Pattern::size()`, and refer to `#` placeholders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OcrCheck {
+    /// The random positions holding the two check characters.
+    pub positions: [usize; 2],
+}
+
+impl OcrCheck {
+    /// Creates an `OcrCheck` placing the check characters at the given positions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::OcrCheck;
+    ///
+    /// let check = OcrCheck::new(2, 5);
+    /// assert_eq!(check.positions, [2, 5]);
+    /// ```
+    pub fn new(first: usize, second: usize) -> Self {
+        OcrCheck {
+            positions: [first, second],
+        }
+    }
+
+    pub(crate) fn is_valid_for(&self, pattern: &Pattern) -> bool {
+        let classes = pattern.classes();
+        let [a, b] = self.positions;
+
+        a != b
+            && [a, b]
+                .iter()
+                .all(|p| classes.get(*p) == Some(&CharClass::Charset))
+    }
+
+    /// Returns the two check characters of `data`, or `None` for an empty
+    /// charset, which has none.
+    fn check_chars<'a>(
+        &self,
+        charset: &Charset,
+        data: impl IntoIterator<Item = &'a char>,
+    ) -> Option<[char; 2]> {
+        let chars = charset.as_str();
+        let n = chars.chars().count();
+        let (sum, weighted) = data
+            .into_iter()
+            .filter_map(|c| charset.index_of(*c))
+            .enumerate()
+            .fold((0, 0), |(sum, weighted), (i, v)| {
+                ((sum + v) % n, (weighted + (i + 1) * v) % n)
+            });
+
+        let at = |i: usize| chars.chars().nth(i);
+        Some([at(sum)?, at(weighted)?])
+    }
+
+    /// Inserts the check characters into the random data of a code.
+    pub(crate) fn insert(&self, charset: &Charset, data: &mut Vec<char>) {
+        let Some(checks) = self.check_chars(charset, data.iter()) else {
+            return;
+        };
+        let mut placed = [
+            (self.positions[0], checks[0]),
+            (self.positions[1], checks[1]),
+        ];
+        placed.sort();
+
+        for (position, c) in placed {
+            data.insert(position, c);
+        }
+    }
+
+    /// Checks that the random characters of a code carry valid check characters.
+    pub(crate) fn verify(&self, charset: &Charset, random: &[char]) -> bool {
+        let [a, b] = self.positions;
+        if a == b || a >= random.len() || b >= random.len() {
+            return false;
+        }
+
+        let data = random
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.positions.contains(i))
+            .map(|(_, c)| c);
+        let Some([first, second]) = self.check_chars(charset, data) else {
+            return false;
+        };
+
+        random[self.positions[0]] == first && random[self.positions[1]] == second
+    }
+}
+
+/// Verifies that a code matches the configuration and its OCR check characters.
+///
+/// The code is decomposed using the configuration's prefix and pattern; the
+/// check characters are then recomputed over the remaining random characters.
+///
+/// # Arguments
+///
+/// * `code` - The code to verify, as scanned or typed
+/// * `config` - The configuration the code was generated with
+///
+/// # Returns
+///
+/// `true` if the code matches the pattern and, when `config.ocr_check` is
+/// set, both check characters are correct.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "rng")] {
+/// use referral_codes::{Config, OcrCheck, Pattern, verify_ocr_check};
+///
+/// let config = Config {
+///     pattern: Pattern::Pattern("####-####".to_string()),
+///     ocr_check: Some(OcrCheck::new(3, 7)),
+///     ..Config::default()
+/// };
+///
+/// let code = referral_codes::generate_one(&config).unwrap();
+/// assert!(verify_ocr_check(&code, &config));
+/// # }
+/// ```
+pub fn verify_ocr_check(code: &str, config: &Config) -> bool {
+    let Some(random) = segment::random_chars(config, code) else {
+        return false;
+    };
+
+    match &config.ocr_check {
+        Some(check) => check.verify(&config.charset, &random),
+        None => true,
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_ocr_check_detects_single_substitution() {
+    for (charset, a, b) in [
+        (Charset::Numeric, '0', '1'),
+        // Multi-byte characters are counted once each.
+        (Charset::Custom("ÀÉÎ".to_string()), 'À', 'É'),
+    ] {
+        let config = Config {
+            charset,
+            pattern: crate::Pattern::Length(10),
+            ocr_check: Some(OcrCheck::new(0, 9)),
+            ..Config::default()
+        };
+
+        let code = crate::generate_one(&config).unwrap();
+        assert!(verify_ocr_check(&code, &config));
+
+        for i in 0..code.chars().count() {
+            let mut chars: Vec<char> = code.chars().collect();
+            chars[i] = if chars[i] == a { b } else { a };
+            let tampered: String = chars.into_iter().collect();
+            assert!(!verify_ocr_check(&tampered, &config));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use alloc::string::String;
+use core::cmp::Ordering;
+
+use crate::Config;
+
+/// Compares two codes using the canonical ordering of the given configuration.
+///
+/// Characters are compared by their index in the configured charset rather than
+/// by their raw byte value, so codes sort "numerically" within the alphabet.
+/// Characters outside the charset (such as prefixes or pattern literals) sort
+/// after every charset character, ordered among themselves by code point.
+/// When one code is a prefix of the other, the shorter code sorts first.
+///
+/// # Arguments
+///
+/// * `config` - Configuration whose charset defines the ordering
+/// * `a` - The first code
+/// * `b` - The second code
+///
+/// # Returns
+///
+/// The `Ordering` of `a` relative to `b`.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use referral_codes::{Charset, Config, compare_codes};
+///
+/// let config = Config {
+///     charset: Charset::Custom("ZYX".to_string()),
+///     ..Config::default()
+/// };
+///
+/// // 'Z' comes first in the charset, even though 'X' < 'Z' in ASCII.
+/// assert_eq!(compare_codes(&config, "ZZ", "XX"), Ordering::Less);
+/// ```
+pub fn compare_codes(config: &Config, a: &str, b: &str) -> Ordering {
+    let key = |c: char| match config.charset.index_of(c) {
+        Some(index) => (0, index),
+        None => (1, c as usize),
+    };
+
+    a.chars().map(key).cmp(b.chars().map(key))
+}
+
+/// Sorts codes in place using the canonical ordering of the given configuration.
+///
+/// See [`compare_codes`] for the ordering rules.
+///
+/// # Arguments
+///
+/// * `config` - Configuration whose charset defines the ordering
+/// * `codes` - The codes to sort
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, sort_codes};
+///
+/// let config = Config {
+///     charset: Charset::Alphanumeric,
+///     ..Config::default()
+/// };
+///
+/// let mut codes = vec!["a0".to_string(), "Ab".to_string(), "aB".to_string()];
+/// sort_codes(&config, &mut codes);
+///
+/// assert_eq!(codes, vec!["aB", "a0", "Ab"]);
+/// ```
+pub fn sort_codes(config: &Config, codes: &mut [String]) {
+    codes.sort_by(|a, b| compare_codes(config, a, b));
+}
+
+#[test]
+fn test_sort_codes() {
+    let config = Config {
+        charset: crate::Charset::Numeric,
+        prefix: Some("REF-".to_string()),
+        ..Config::default()
+    };
+
+    let mut codes = vec![
+        "REF-91".to_string(),
+        "REF-19".to_string(),
+        "REF-10".to_string(),
+    ];
+    sort_codes(&config, &mut codes);
+
+    assert_eq!(codes, vec!["REF-10", "REF-19", "REF-91"]);
+}
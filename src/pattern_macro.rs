@@ -0,0 +1,100 @@
+/// Builds a [`Pattern`] from a string literal checked at compile time.
+///
+/// `pattern!("REF-####")` gives a [`Pattern::Pattern`], and
+/// `pattern!(template "AA-99-##")` a [`Pattern::Template`]. Hard-coded
+/// patterns are checked when the crate using them builds, rather than
+/// when codes are generated: a pattern can't be empty or lack random
+/// positions, and a template can't hold a backslash that escapes nothing,
+/// which [`Pattern::Template`] would otherwise keep as a literal.
+///
+/// [`Pattern`]: crate::Pattern
+/// [`Pattern::Pattern`]: crate::Pattern::Pattern
+/// [`Pattern::Template`]: crate::Pattern::Template
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Pattern, pattern};
+///
+/// assert_eq!(pattern!("REF-####"), Pattern::Pattern("REF-####".to_string()));
+/// assert_eq!(pattern!(template r"\A-99").size(), 2);
+/// ```
+///
+/// Mistakes don't build:
+///
+/// ```compile_fail
+/// let pattern = referral_codes::pattern!("REF-");
+/// ```
+///
+/// ```compile_fail
+/// let pattern = referral_codes::pattern!(template r"AA-\B");
+/// ```
+#[macro_export]
+macro_rules! pattern {
+    (template $template:literal) => {{
+        const _: () = if let Err(error) = $crate::__check_pattern_literal($template, true) {
+            panic!("{}", error)
+        };
+        $crate::Pattern::Template(::core::convert::From::from($template))
+    }};
+    ($pattern:literal) => {{
+        const _: () = if let Err(error) = $crate::__check_pattern_literal($pattern, false) {
+            panic!("{}", error)
+        };
+        $crate::Pattern::Pattern(::core::convert::From::from($pattern))
+    }};
+}
+
+/// Checks a pattern string for [`pattern!`], with the syntax of templates
+/// if `template` is set.
+#[doc(hidden)]
+pub const fn __check_pattern_literal(s: &str, template: bool) -> Result<(), &'static str> {
+    // Every character checked is ASCII, which never appears within the
+    // bytes of other characters.
+    let bytes = s.as_bytes();
+    if bytes.is_empty() {
+        return Err("empty pattern");
+    }
+
+    let mut random = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if template => {
+                if i + 1 == bytes.len() || !matches!(bytes[i + 1], b'#' | b'A' | b'9' | b'\\') {
+                    return Err("unbalanced escape: `\\` must escape `#`, `A`, `9` or `\\`");
+                }
+                i += 1;
+            }
+            b'#' => random += 1,
+            b'A' | b'9' if template => random += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if random == 0 {
+        return Err("pattern without random positions");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_pattern_literals_are_checked() {
+    use crate::Pattern;
+
+    assert_eq!(pattern!("X-##"), Pattern::Pattern("X-##".to_string()));
+    assert_eq!(
+        pattern!(template r"\#\\-A9"),
+        Pattern::Template(r"\#\\-A9".to_string())
+    );
+
+    assert_eq!(__check_pattern_literal("", false), Err("empty pattern"));
+    assert!(__check_pattern_literal("REF-", false).is_err());
+    assert!(__check_pattern_literal("AA-99", false).is_err());
+    assert!(__check_pattern_literal(r"\A\9", true).is_err());
+    assert!(__check_pattern_literal(r"AA\", true).is_err());
+    assert!(__check_pattern_literal(r"A\B", true).is_err());
+    assert_eq!(__check_pattern_literal(r"é\##", false), Ok(()));
+}
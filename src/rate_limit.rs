@@ -0,0 +1,289 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    Attempts, Code, Config, ReferralCodeError, Reservation, UniquenessStore, check_config,
+    dedup_key, generate_one_with, reservation::reserve, rng::rng_for,
+};
+
+/// A token bucket, refilled continuously up to its capacity.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/// The order and time codes were issued in.
+#[derive(Debug)]
+struct Clock {
+    sequence: u64,
+    last: Option<SystemTime>,
+    monotonic: bool,
+}
+
+impl Clock {
+    /// Returns the sequence number and time of the next issued code.
+    fn tick(&mut self, now: SystemTime) -> (u64, SystemTime) {
+        let at = match self.last {
+            Some(last) if self.monotonic && now <= last => last + Duration::from_nanos(1),
+            _ => now,
+        };
+        let sequence = self.sequence;
+
+        self.sequence += 1;
+        self.last = Some(at);
+        (sequence, at)
+    }
+}
+
+/// A code issued by a [`RateLimitedIssuer`], with its issuance order and time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IssuedCode {
+    /// The issued code.
+    pub code: Code,
+    /// The position of the code in the issuer's issuance order, from 0.
+    pub sequence: u64,
+    /// The time the code was reserved.
+    pub issued_at: SystemTime,
+}
+
+/// Issues codes on demand from a shared store, at a bounded rate.
+///
+/// Put this behind public endpoints such as "get my referral code": every
+/// attempt to reserve a code costs a token, so callers can neither drain the
+/// keyspace nor flood the uniqueness store faster than the configured rate.
+/// Up to `burst` attempts can be made at once, and tokens come back at
+/// `per_second` per second.
+///
+/// The issuer can be shared between threads; the bucket is global to it.
+#[derive(Debug)]
+pub struct RateLimitedIssuer<S> {
+    config: Config,
+    store: S,
+    burst: f64,
+    per_second: f64,
+    bucket: Mutex<Bucket>,
+    clock: Mutex<Clock>,
+}
+
+impl<S: UniquenessStore> RateLimitedIssuer<S> {
+    /// Creates an issuer whose bucket starts full.
+    ///
+    /// `config.count` is ignored: codes are issued one at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration specifying the pattern and charset of issued codes
+    /// * `store` - The store codes are reserved in
+    /// * `burst` - The number of attempts that can be made at once
+    /// * `per_second` - The number of attempts regained per second
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::InvalidRateLimit` - If `burst` is zero or
+    ///   `per_second` is not positive
+    /// * Any other error of [`crate::generate`] for a single code
+    pub fn new(
+        config: Config,
+        store: S,
+        burst: usize,
+        per_second: f64,
+    ) -> Result<Self, ReferralCodeError> {
+        if burst == 0 || per_second.is_nan() || per_second <= 0.0 {
+            return Err(ReferralCodeError::InvalidRateLimit);
+        }
+        check_config(&config.with_count(1))?;
+
+        Ok(RateLimitedIssuer {
+            config,
+            store,
+            burst: burst as f64,
+            per_second,
+            bucket: Mutex::new(Bucket {
+                tokens: burst as f64,
+                last: Instant::now(),
+            }),
+            clock: Mutex::new(Clock {
+                sequence: 0,
+                last: None,
+                monotonic: false,
+            }),
+        })
+    }
+
+    /// Makes the timestamps of [`RateLimitedIssuer::issue_recorded`] strictly
+    /// increasing in issuance order.
+    ///
+    /// Codes issued within the same clock tick, or after the system clock
+    /// was set back, are stamped a nanosecond after the previous code, so
+    /// timestamps order codes like their sequence numbers do. Analytics
+    /// joining on timestamps then never see two codes issued at once.
+    pub fn with_monotonic_timestamps(self) -> Self {
+        self.clock
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .monotonic = true;
+        self
+    }
+
+    /// Returns the store codes are reserved in.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Issues a fresh code, reserved in the store.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::RateLimited` if the bucket ran out of
+    /// tokens before a free code was found, and `ReferralCodeError::Store` if
+    /// the store fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{collections::HashSet, sync::Mutex};
+    /// use referral_codes::{Config, RateLimitedIssuer, ReferralCodeError};
+    ///
+    /// let issuer = RateLimitedIssuer::new(Config::default(), Mutex::new(HashSet::new()), 2, 0.1)
+    ///     .unwrap();
+    ///
+    /// assert!(issuer.issue().is_ok());
+    /// assert!(issuer.issue().is_ok());
+    /// assert!(matches!(issuer.issue(), Err(ReferralCodeError::RateLimited { .. })));
+    /// ```
+    pub fn issue(&self) -> Result<String, ReferralCodeError> {
+        self.issue_recorded()
+            .map(|issued| issued.code.into_string())
+    }
+
+    /// Issues a fresh code, reserved in the store, along with its issuance
+    /// order and time.
+    ///
+    /// Sequence numbers count the codes this issuer issued, from 0, and are
+    /// assigned as soon as each code is reserved.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`RateLimitedIssuer::issue`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{collections::HashSet, sync::Mutex};
+    /// use referral_codes::{Config, RateLimitedIssuer};
+    ///
+    /// let issuer = RateLimitedIssuer::new(Config::default(), Mutex::new(HashSet::new()), 10, 1.0)
+    ///     .unwrap()
+    ///     .with_monotonic_timestamps();
+    ///
+    /// let first = issuer.issue_recorded().unwrap();
+    /// let second = issuer.issue_recorded().unwrap();
+    ///
+    /// assert_eq!((first.sequence, second.sequence), (0, 1));
+    /// assert!(first.issued_at < second.issued_at);
+    /// assert_eq!(first.code.random().len(), 8);
+    /// ```
+    pub fn issue_recorded(&self) -> Result<IssuedCode, ReferralCodeError> {
+        let mut rng = rng_for(&self.config);
+        let budget = Attempts::new(&self.config, 0, 1);
+        loop {
+            self.acquire(Instant::now())?;
+
+            let mut attempts = budget;
+            let Some(code) = generate_one_with(&self.config, &mut rng, &mut attempts) else {
+                return Err(attempts.exceeded(Vec::new()));
+            };
+            if reserve(&self.store, &dedup_key(&self.config, &code))? == Reservation::Reserved {
+                let (sequence, issued_at) = self
+                    .clock
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .tick(SystemTime::now());
+
+                return Ok(IssuedCode {
+                    code: Code::parse(&self.config, &code)
+                        .expect("generated codes match their configuration"),
+                    sequence,
+                    issued_at,
+                });
+            }
+        }
+    }
+
+    /// Takes a token, as of `now`.
+    fn acquire(&self, now: Instant) -> Result<(), ReferralCodeError> {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+        bucket.last = now;
+
+        if bucket.tokens < 1.0 {
+            let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second);
+            return Err(ReferralCodeError::RateLimited { retry_after });
+        }
+
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_rate_limited_issuer_refills() {
+    use std::collections::HashSet;
+
+    let issuer =
+        RateLimitedIssuer::new(Config::default(), Mutex::new(HashSet::new()), 2, 4.0).unwrap();
+    let start = Instant::now();
+
+    assert!(issuer.acquire(start).is_ok());
+    assert!(issuer.acquire(start).is_ok());
+    match issuer.acquire(start) {
+        Err(ReferralCodeError::RateLimited { retry_after }) => {
+            assert!(retry_after <= Duration::from_millis(250))
+        }
+        other => panic!("expected RateLimited, got {other:?}"),
+    }
+
+    assert!(issuer.acquire(start + Duration::from_millis(250)).is_ok());
+    // The bucket never holds more than `burst` tokens.
+    let later = start + Duration::from_secs(60);
+    assert!(issuer.acquire(later).is_ok());
+    assert!(issuer.acquire(later).is_ok());
+    assert!(issuer.acquire(later).is_err());
+
+    for (burst, per_second) in [(0, 1.0), (1, 0.0), (1, -1.0), (1, f64::NAN)] {
+        assert!(matches!(
+            RateLimitedIssuer::new(
+                Config::default(),
+                Mutex::new(HashSet::new()),
+                burst,
+                per_second
+            ),
+            Err(ReferralCodeError::InvalidRateLimit)
+        ));
+    }
+}
+
+#[test]
+fn test_monotonic_clock_survives_clock_steps() {
+    use std::time::UNIX_EPOCH;
+
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    let mut clock = Clock {
+        sequence: 0,
+        last: None,
+        monotonic: true,
+    };
+
+    assert_eq!(clock.tick(at(100)), (0, at(100)));
+    assert_eq!(clock.tick(at(100)), (1, at(100) + Duration::from_nanos(1)));
+    assert_eq!(clock.tick(at(50)), (2, at(100) + Duration::from_nanos(2)));
+    assert_eq!(clock.tick(at(200)), (3, at(200)));
+
+    clock.monotonic = false;
+    assert_eq!(clock.tick(at(50)), (4, at(50)));
+}
@@ -0,0 +1,1437 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+#[cfg(feature = "rng")]
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    Assignment, Batch, Charset, Checksum, ChecksumCoverage, Config, EmbeddedFields, Expiry,
+    FieldLayout, FormatId, FormatTag, Granularity, Grouping, LetterCase, OcrCheck, Pattern,
+    Provenance, RngPolicy, RngSource, Strategy,
+};
+#[cfg(feature = "rng")]
+use crate::{
+    Attempts, ReferralCodeError, Saturation, batch::place_canaries, check_config, dedup_key,
+    generate_one_with, rng::rng_for, saturation::SaturationHook, segment,
+};
+
+const HEADER: &str = "referral-codes-registry ";
+
+/// The file version written by [`Registry::save`].
+///
+/// Version 1 spelled charsets and patterns with registry-specific keys;
+/// version 2 uses their canonical serialization.
+pub(crate) const VERSION: u32 = 2;
+
+/// An in-process source of truth for every code issued in a namespace.
+///
+/// A registry owns any number of batches and guarantees that no code is
+/// issued twice across all of them. Codes of batches using `random_case` are
+/// compared case-insensitively against every other code. Lookups are backed
+/// by hash indexes.
+///
+/// Registries can be saved to and loaded from a compact line-based file.
+#[derive(Clone, Debug)]
+pub struct Registry {
+    namespace: String,
+    batches: Vec<Batch>,
+    /// Code to `(batch, position)`.
+    index: HashMap<String, (usize, usize)>,
+    /// Lowercased code to `(batch, position)`, preferring `random_case` batches.
+    folded: HashMap<String, (usize, usize)>,
+    #[cfg(feature = "rng")]
+    saturation_hook: Option<SaturationHook>,
+    /// Saturation above which [`Registry::extend`] grows the batch format.
+    #[cfg(feature = "rng")]
+    extend_threshold: Option<f64>,
+}
+
+impl Registry {
+    /// Creates an empty registry for the given namespace.
+    pub fn new(namespace: impl Into<String>) -> Self {
+        Registry {
+            namespace: namespace.into(),
+            batches: Vec::new(),
+            index: HashMap::new(),
+            folded: HashMap::new(),
+            #[cfg(feature = "rng")]
+            saturation_hook: None,
+            #[cfg(feature = "rng")]
+            extend_threshold: None,
+        }
+    }
+
+    /// Returns the namespace of the registry.
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Returns every batch, in creation order.
+    pub fn batches(&self) -> &[Batch] {
+        &self.batches
+    }
+
+    /// Returns the batch with the given identifier.
+    pub fn batch(&self, id: &str) -> Option<&Batch> {
+        self.batches.iter().find(|b| b.id == id)
+    }
+
+    /// Returns the total number of codes across all batches.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns `true` if the registry holds no codes.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns the position of a `random_case` code matching `key` in any case.
+    fn folded_random_case(&self, key: &str) -> Option<(usize, usize)> {
+        self.folded
+            .get(key)
+            .copied()
+            .filter(|(b, _)| self.batches[*b].config.random_case.is_some())
+    }
+
+    fn lookup(&self, code: &str) -> Option<(usize, usize)> {
+        self.index
+            .get(code)
+            .copied()
+            .or_else(|| self.folded_random_case(&code.to_lowercase()))
+    }
+
+    /// Returns `true` if issuing `code` under `config` would duplicate an issued code.
+    fn conflicts(&self, config: &Config, code: &str) -> bool {
+        let key = code.to_lowercase();
+
+        match config.random_case {
+            Some(_) => self.folded.contains_key(&key),
+            None => self.index.contains_key(code) || self.folded_random_case(&key).is_some(),
+        }
+    }
+
+    /// Returns `true` if `code` was issued in any batch of the registry.
+    ///
+    /// Codes of batches using `random_case` match in any case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Config, Registry};
+    ///
+    /// let mut registry = Registry::new("acme");
+    /// let code = registry.generate("spring", &Config::default().with_count(10)).unwrap().codes[0].clone();
+    ///
+    /// assert!(registry.contains(&code));
+    /// assert!(!registry.contains("unknown"));
+    /// # }
+    /// ```
+    pub fn contains(&self, code: &str) -> bool {
+        self.lookup(code).is_some()
+    }
+
+    /// Returns the batch `code` was issued in.
+    pub fn batch_of(&self, code: &str) -> Option<&Batch> {
+        self.lookup(code).map(|(b, _)| &self.batches[b])
+    }
+
+    /// Returns the number of issued codes that match `config`.
+    #[cfg(feature = "rng")]
+    fn occupied(&self, config: &Config) -> usize {
+        self.batches
+            .iter()
+            .flat_map(|b| &b.codes)
+            .filter(|c| segment::random_chars(config, c).is_some())
+            .count()
+    }
+
+    /// Generates codes not yet issued in the registry.
+    #[cfg(feature = "rng")]
+    fn fresh(&self, config: &Config, count: usize) -> Result<Vec<String>, ReferralCodeError> {
+        let occupied = self.occupied(config);
+        check_config(&config.with_count(occupied + count))?;
+
+        let mut seen = HashSet::with_capacity(count);
+        let mut codes = Vec::with_capacity(count);
+        let mut attempts = Attempts::new(config, occupied, count);
+        let mut rng = rng_for(config);
+
+        while codes.len() < count {
+            let Some(candidate) = generate_one_with(config, &mut rng, &mut attempts) else {
+                return Err(attempts.exceeded(codes));
+            };
+
+            if !self.conflicts(config, &candidate)
+                && seen.insert(dedup_key(config, &candidate).into_owned())
+            {
+                codes.push(candidate);
+            }
+        }
+
+        Ok(codes)
+    }
+
+    fn push_codes(&mut self, batch: usize, codes: Vec<String>) {
+        let random_case = self.batches[batch].config.random_case.is_some();
+
+        for code in codes {
+            let position = self.batches[batch].codes.len();
+            let key = code.to_lowercase();
+
+            if random_case || !self.folded.contains_key(&key) {
+                self.folded.insert(key, (batch, position));
+            }
+            self.index.insert(code.clone(), (batch, position));
+            self.batches[batch].codes.push(code);
+        }
+    }
+
+    /// Sets a callback reporting saturation whenever codes are issued.
+    ///
+    /// After every successful [`Registry::generate`] or [`Registry::extend`],
+    /// the callback receives how much of the batch's keyspace is used, counting
+    /// codes of every batch that fit the same format. Feed it to a gauge to be
+    /// alerted before a format runs out of room.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use referral_codes::{Charset, Config, Pattern, Registry};
+    ///
+    /// let gauge = Arc::new(Mutex::new(0.0));
+    /// let mut registry = Registry::new("acme");
+    /// registry.on_saturation({
+    ///     let gauge = gauge.clone();
+    ///     move |saturation| *gauge.lock().unwrap() = saturation.ratio()
+    /// });
+    ///
+    /// let legacy = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Length(2),
+    ///     count: 25,
+    ///     ..Config::default()
+    /// };
+    /// registry.generate("spring", &legacy).unwrap();
+    /// registry.generate("summer", &legacy).unwrap();
+    ///
+    /// assert_eq!(*gauge.lock().unwrap(), 0.5);
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn on_saturation(&mut self, hook: impl Fn(&Saturation) + Send + Sync + 'static) {
+        self.saturation_hook = Some(SaturationHook(Arc::new(hook)));
+    }
+
+    #[cfg(feature = "rng")]
+    fn saturation(&self, batch: usize) -> Saturation<'_> {
+        let batch = &self.batches[batch];
+
+        Saturation {
+            batch: &batch.id,
+            issued: self.occupied(&batch.config),
+            keyspace: batch.config.keyspace(),
+        }
+    }
+
+    #[cfg(feature = "rng")]
+    fn report_saturation(&self, batch: usize) {
+        if let Some(SaturationHook(hook)) = &self.saturation_hook {
+            hook(&self.saturation(batch));
+        }
+    }
+
+    /// Returns how many more codes can be issued in a batch's format.
+    ///
+    /// This is the keyspace of the batch's configuration, with reserved
+    /// prefixes excluded, minus the codes of every batch that fit the same
+    /// format, saturating at zero; see [`Saturation::remaining`].
+    ///
+    /// # Returns
+    ///
+    /// * `Some(remaining)` - The headroom of the batch's format
+    /// * `None` - If there is no batch `id`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern, Registry};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Length(2),
+    ///     count: 30,
+    ///     reserved_prefixes: vec!["0".to_string()],
+    ///     ..Config::default()
+    /// };
+    /// let mut registry = Registry::new("acme");
+    /// registry.generate("spring", &config).unwrap();
+    ///
+    /// assert_eq!(registry.remaining_capacity("spring"), Some(60));
+    /// assert_eq!(registry.remaining_capacity("summer"), None);
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn remaining_capacity(&self, id: &str) -> Option<u128> {
+        let batch = self.batches.iter().position(|b| b.id == id)?;
+
+        Some(self.saturation(batch).remaining())
+    }
+
+    /// Makes [`Registry::extend`] grow a batch's format instead of failing
+    /// once its keyspace saturates.
+    ///
+    /// When extending a batch would take the saturation of its format above
+    /// `threshold`, the batch's configuration gets one more random position,
+    /// appended to its pattern, as many times as needed. The previous
+    /// configuration is kept in [`Batch::superseded`], so codes issued
+    /// earlier can still be told apart and verified, and the change shows up
+    /// in [`Batch::stats`] and in saved registries. This keeps long-lived
+    /// issuance endpoints available at the cost of longer codes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::InvalidProbability` if `threshold` is not
+    /// between 0 and 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern, Registry};
+    ///
+    /// let mut registry = Registry::new("acme");
+    /// registry.extend_when_saturated(0.8).unwrap();
+    ///
+    /// let legacy = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Length(1),
+    ///     count: 8,
+    ///     ..Config::default()
+    /// };
+    /// registry.generate("legacy", &legacy).unwrap();
+    ///
+    /// assert_eq!(registry.extend("legacy", 5).unwrap()[0].len(), 2);
+    /// assert_eq!(registry.batch("legacy").unwrap().stats().length_extensions, 1);
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn extend_when_saturated(&mut self, threshold: f64) -> Result<(), ReferralCodeError> {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(ReferralCodeError::InvalidProbability);
+        }
+
+        self.extend_threshold = Some(threshold);
+        Ok(())
+    }
+
+    /// Grows the configuration of `batch` until `additional` codes fit under
+    /// the saturation threshold.
+    #[cfg(feature = "rng")]
+    fn grow(&mut self, batch: usize, additional: usize) {
+        let Some(threshold) = self.extend_threshold else {
+            return;
+        };
+
+        loop {
+            let config = &self.batches[batch].config;
+            let Some(keyspace) = config.keyspace() else {
+                return;
+            };
+            let saturation = (self.occupied(config) + additional) as f64 / keyspace as f64;
+            if saturation <= threshold {
+                return;
+            }
+
+            let grown = config.with_extra_position();
+            // A single-character charset can't make room by growing.
+            if grown.keyspace().is_some_and(|k| k <= keyspace) {
+                return;
+            }
+
+            let batch = &mut self.batches[batch];
+            let previous = std::mem::replace(&mut batch.config, grown);
+            batch.superseded.push(previous);
+            if let Some(provenance) = &mut batch.provenance {
+                provenance.config_hash = config_hash(&batch.config);
+            }
+        }
+    }
+
+    /// Generates a new batch of `config.count` codes, unique across the registry.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Identifier of the new batch
+    /// * `config` - Configuration specifying the pattern, charset, and count of codes to generate
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::DuplicateBatch` if a batch with this identifier
+    /// exists, or the same errors as [`crate::generate`], with feasibility
+    /// accounting for codes already issued in the same format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, Registry};
+    ///
+    /// let mut registry = Registry::new("acme");
+    /// registry.generate("spring", &Config::default().with_count(10)).unwrap();
+    /// registry.generate("summer", &Config::default().with_count(10)).unwrap();
+    ///
+    /// assert_eq!(registry.len(), 20);
+    /// assert!(registry.generate("spring", &Config::default()).is_err());
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn generate(
+        &mut self,
+        id: impl Into<String>,
+        config: &Config,
+    ) -> Result<&Batch, ReferralCodeError> {
+        let id = id.into();
+        if self.batch(&id).is_some() {
+            return Err(ReferralCodeError::DuplicateBatch);
+        }
+
+        let codes = self.fresh(config, config.count)?;
+        self.batches.push(Batch {
+            id,
+            config: config.clone(),
+            codes: Vec::with_capacity(codes.len()),
+            superseded: Vec::new(),
+            assignment: None,
+            canaries: Vec::new(),
+            provenance: Some(Provenance::new(
+                config,
+                Strategy::RegistryRejection,
+                RngPolicy::OsSeeded,
+            )),
+        });
+        let batch = self.batches.len() - 1;
+        self.push_codes(batch, codes);
+        self.report_saturation(batch);
+
+        Ok(&self.batches[batch])
+    }
+
+    /// Generates a new batch of `config.count` codes plus a fraction of
+    /// canaries, unique across the registry.
+    ///
+    /// See [`Batch::generate_with_canaries`]. Canaries are saved with the
+    /// registry, so redemption services loading it can check every attempt
+    /// with [`Registry::is_canary`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::InvalidProbability` if `fraction` is not
+    /// between 0 and 1, or the same errors as [`Registry::generate`].
+    #[cfg(feature = "rng")]
+    pub fn generate_with_canaries(
+        &mut self,
+        id: impl Into<String>,
+        config: &Config,
+        fraction: f64,
+    ) -> Result<&Batch, ReferralCodeError> {
+        let id = id.into();
+        if self.batch(&id).is_some() {
+            return Err(ReferralCodeError::DuplicateBatch);
+        }
+
+        let canaries = place_canaries(config.count, fraction, &mut rng_for(config))?;
+        let codes = self.fresh(config, config.count + canaries.len())?;
+        self.batches.push(Batch {
+            id,
+            config: config.clone(),
+            codes: Vec::with_capacity(codes.len()),
+            superseded: Vec::new(),
+            assignment: None,
+            canaries,
+            provenance: Some(Provenance::new(
+                config,
+                Strategy::RegistryRejection,
+                RngPolicy::OsSeeded,
+            )),
+        });
+        let batch = self.batches.len() - 1;
+        self.push_codes(batch, codes);
+        self.report_saturation(batch);
+
+        Ok(&self.batches[batch])
+    }
+
+    /// Returns `true` if `code` is a canary of any batch of the registry.
+    ///
+    /// Codes of batches using `random_case` match in any case.
+    pub fn is_canary(&self, code: &str) -> bool {
+        self.lookup(code)
+            .is_some_and(|(b, i)| self.batches[b].canaries.binary_search(&i).is_ok())
+    }
+
+    /// Appends `additional` codes to an existing batch, unique across the registry.
+    ///
+    /// The new codes use the batch's own configuration, extended first if
+    /// [`Registry::extend_when_saturated`] is enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::NotInBatch` if no batch has this
+    /// identifier, or the same errors as [`Registry::generate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, Registry};
+    ///
+    /// let mut registry = Registry::new("acme");
+    /// registry.generate("spring", &Config::default().with_count(10)).unwrap();
+    ///
+    /// assert_eq!(registry.extend("spring", 5).unwrap().len(), 5);
+    /// assert_eq!(registry.batch("spring").unwrap().codes.len(), 15);
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn extend(&mut self, id: &str, additional: usize) -> Result<&[String], ReferralCodeError> {
+        let batch = self
+            .batches
+            .iter()
+            .position(|b| b.id == id)
+            .ok_or(ReferralCodeError::NotInBatch)?;
+
+        self.grow(batch, additional);
+        let codes = self.fresh(&self.batches[batch].config, additional)?;
+        let start = self.batches[batch].codes.len();
+        self.push_codes(batch, codes);
+        if additional > 0 {
+            let source = self.batches[batch].config.rng;
+            self.batches[batch].mix_in_os_seeded(source);
+        }
+        self.report_saturation(batch);
+
+        Ok(&self.batches[batch].codes[start..])
+    }
+
+    /// Generates one batch per variant of a format experiment, unique across
+    /// the registry.
+    ///
+    /// Each variant gets a batch of `config.count` codes with the identifier
+    /// `experiment/variant`, recording its [`Assignment`] so redemptions can
+    /// be attributed to the variant that issued the code, with
+    /// [`Registry::batch_of`]. Codes are unique across variants, whatever
+    /// their formats. Either every batch is generated, or none is.
+    ///
+    /// # Arguments
+    ///
+    /// * `experiment` - Identifier of the experiment
+    /// * `variants` - Names and configurations of the variants
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferralCodeError::DuplicateBatch` if two variants have the
+    /// same name or a batch of the experiment exists, or the same errors as
+    /// [`Registry::generate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern, Registry};
+    ///
+    /// let short = Config::default().with_count(50).with_pattern(Pattern::Length(6));
+    /// let numeric = Config {
+    ///     charset: Charset::Numeric,
+    ///     ..short.clone()
+    /// };
+    ///
+    /// let mut registry = Registry::new("acme");
+    /// let batches = registry
+    ///     .generate_experiment("launch", &[("letters", &short), ("digits", &numeric)])
+    ///     .unwrap();
+    /// let code = batches[1].codes[0].clone();
+    ///
+    /// let assignment = registry.batch_of(&code).unwrap().assignment.as_ref().unwrap();
+    /// assert_eq!(assignment.experiment, "launch");
+    /// assert_eq!(assignment.variant, "digits");
+    /// assert_eq!(registry.experiment("launch").count(), 2);
+    /// ```
+    #[cfg(feature = "rng")]
+    pub fn generate_experiment(
+        &mut self,
+        experiment: impl Into<String>,
+        variants: &[(&str, &Config)],
+    ) -> Result<Vec<&Batch>, ReferralCodeError> {
+        let experiment = experiment.into();
+        let ids: Vec<String> = variants
+            .iter()
+            .map(|(variant, _)| format!("{experiment}/{variant}"))
+            .collect();
+        let unique: HashSet<&String> = ids.iter().collect();
+        if unique.len() < ids.len() || ids.iter().any(|id| self.batch(id).is_some()) {
+            return Err(ReferralCodeError::DuplicateBatch);
+        }
+
+        let first = self.batches.len();
+        for (id, (variant, config)) in ids.into_iter().zip(variants) {
+            let codes = match self.fresh(config, config.count) {
+                Ok(codes) => codes,
+                Err(e) => {
+                    self.truncate(first);
+                    return Err(e);
+                }
+            };
+
+            self.batches.push(Batch {
+                id,
+                config: (*config).clone(),
+                codes: Vec::with_capacity(codes.len()),
+                superseded: Vec::new(),
+                assignment: Some(Assignment {
+                    experiment: experiment.clone(),
+                    variant: variant.to_string(),
+                }),
+                canaries: Vec::new(),
+                provenance: Some(Provenance::new(
+                    config,
+                    Strategy::RegistryRejection,
+                    RngPolicy::OsSeeded,
+                )),
+            });
+            self.push_codes(self.batches.len() - 1, codes);
+        }
+        for batch in first..self.batches.len() {
+            self.report_saturation(batch);
+        }
+
+        Ok(self.batches[first..].iter().collect())
+    }
+
+    /// Returns the batches of every variant of an experiment, in creation order.
+    pub fn experiment<'a>(&'a self, experiment: &'a str) -> impl Iterator<Item = &'a Batch> {
+        self.batches.iter().filter(move |b| {
+            b.assignment
+                .as_ref()
+                .is_some_and(|a| a.experiment == experiment)
+        })
+    }
+
+    /// Removes the batches from `len` on, and their codes.
+    #[cfg(feature = "rng")]
+    fn truncate(&mut self, len: usize) {
+        for batch in self.batches.drain(len..) {
+            for code in batch.codes {
+                self.index.remove(&code);
+                let key = code.to_lowercase();
+                if self.folded.get(&key).is_some_and(|(b, _)| *b >= len) {
+                    self.folded.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Writes the registry in its compact file format.
+    ///
+    /// The format is line-based: a header line, the namespace, then for each
+    /// batch a `batch` line carrying its identifier and configuration,
+    /// a `was` line for each superseded configuration, an `experiment` line
+    /// with its assignment, if any, a `canaries` line with the positions of
+    /// its canaries, if any, a `provenance` line, if known, and its codes,
+    /// one per line. Charsets and patterns are written in their canonical
+    /// form, see [`Charset::to_canonical`]. Values are escaped so that any
+    /// character can appear in codes, identifiers and configurations.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error produced by the writer.
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{HEADER}{VERSION}")?;
+        writeln!(writer, "{}", escape(&self.namespace))?;
+
+        for batch in &self.batches {
+            writeln!(
+                writer,
+                "batch\t{}\t{}",
+                escape(&batch.id),
+                encode_config(&batch.config)
+            )?;
+            for config in &batch.superseded {
+                writeln!(writer, "was\t{}", encode_config(config))?;
+            }
+            if let Some(Assignment {
+                experiment,
+                variant,
+            }) = &batch.assignment
+            {
+                writeln!(
+                    writer,
+                    "experiment\t{}\t{}",
+                    escape(experiment),
+                    escape(variant)
+                )?;
+            }
+            if !batch.canaries.is_empty() {
+                let positions: Vec<String> = batch.canaries.iter().map(usize::to_string).collect();
+                writeln!(writer, "canaries\t{}", positions.join(","))?;
+            }
+            if let Some(provenance) = &batch.provenance {
+                let strategy = match provenance.strategy {
+                    Strategy::Rejection => "rejection",
+                    Strategy::RegistryRejection => "registry-rejection",
+                };
+                let rng = match provenance.rng {
+                    RngPolicy::OsSeeded => "os-seeded",
+                    RngPolicy::Seeded => "seeded",
+                };
+                write!(
+                    writer,
+                    "provenance\t{}\t{strategy}\t{rng}\t{:016x}",
+                    escape(&provenance.crate_version),
+                    provenance.config_hash
+                )?;
+                // Omitted for the default, as files written before sources
+                // were recorded are.
+                match provenance.source {
+                    Some(RngSource::Secure) | None => writeln!(writer)?,
+                    Some(source) => writeln!(writer, "\t{}", rng_source_name(source))?,
+                }
+            }
+            for code in &batch.codes {
+                writeln!(writer, "\t{}", escape(code))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a registry written by [`Registry::save`].
+    ///
+    /// Files written by older versions of the crate remain readable.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error produced by the reader, or an error of kind
+    /// `InvalidData` if the file is malformed or contains duplicate codes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Config, Registry};
+    ///
+    /// let mut registry = Registry::new("acme");
+    /// registry.generate("spring", &Config::default().with_count(10).with_prefix("SP-")).unwrap();
+    ///
+    /// let mut file = Vec::new();
+    /// registry.save(&mut file).unwrap();
+    /// let loaded = Registry::load(file.as_slice()).unwrap();
+    ///
+    /// assert_eq!(loaded.namespace(), "acme");
+    /// assert!(loaded.contains(&registry.batches()[0].codes[0]));
+    /// # }
+    /// ```
+    pub fn load<R: BufRead>(reader: R) -> io::Result<Self> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut lines = reader.lines();
+
+        let version = lines
+            .next()
+            .transpose()?
+            .and_then(|header| header.strip_prefix(HEADER)?.parse::<u32>().ok())
+            .ok_or_else(|| invalid("not a registry file"))?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(invalid("unsupported registry version"));
+        }
+
+        let namespace = lines
+            .next()
+            .transpose()?
+            .ok_or_else(|| invalid("missing namespace"))?;
+        let mut registry =
+            Registry::new(unescape(&namespace).ok_or_else(|| invalid("bad namespace"))?);
+
+        for line in lines {
+            let line = line?;
+
+            if let Some(code) = line.strip_prefix('\t') {
+                let code = unescape(code).ok_or_else(|| invalid("bad code"))?;
+                let batch = registry
+                    .batches
+                    .len()
+                    .checked_sub(1)
+                    .ok_or_else(|| invalid("code outside of a batch"))?;
+
+                if registry.conflicts(&registry.batches[batch].config, &code) {
+                    return Err(invalid("duplicate code"));
+                }
+                registry.push_codes(batch, vec![code]);
+            } else if let Some(rest) = line.strip_prefix("batch\t") {
+                let (id, config) = rest.split_once('\t').ok_or_else(|| invalid("bad batch"))?;
+                let id = unescape(id).ok_or_else(|| invalid("bad batch id"))?;
+                let config = decode_config(config, version).ok_or_else(|| invalid("bad config"))?;
+
+                if registry.batch(&id).is_some() {
+                    return Err(invalid("duplicate batch"));
+                }
+                registry.batches.push(Batch {
+                    id,
+                    config,
+                    codes: Vec::new(),
+                    superseded: Vec::new(),
+                    assignment: None,
+                    canaries: Vec::new(),
+                    provenance: None,
+                });
+            } else if let Some(config) = line.strip_prefix("was\t") {
+                let config = decode_config(config, version).ok_or_else(|| invalid("bad config"))?;
+                registry
+                    .batches
+                    .last_mut()
+                    .ok_or_else(|| invalid("config outside of a batch"))?
+                    .superseded
+                    .push(config);
+            } else if let Some(positions) = line.strip_prefix("canaries\t") {
+                let mut canaries = positions
+                    .split(',')
+                    .map(|p| p.parse().ok())
+                    .collect::<Option<Vec<usize>>>()
+                    .ok_or_else(|| invalid("bad canaries"))?;
+                canaries.sort_unstable();
+                registry
+                    .batches
+                    .last_mut()
+                    .ok_or_else(|| invalid("canaries outside of a batch"))?
+                    .canaries = canaries;
+            } else if let Some(rest) = line.strip_prefix("provenance\t") {
+                let provenance =
+                    decode_provenance(rest).ok_or_else(|| invalid("bad provenance"))?;
+                registry
+                    .batches
+                    .last_mut()
+                    .ok_or_else(|| invalid("provenance outside of a batch"))?
+                    .provenance = Some(provenance);
+            } else if let Some(rest) = line.strip_prefix("experiment\t") {
+                let (experiment, variant) = rest
+                    .split_once('\t')
+                    .and_then(|(e, v)| Some((unescape(e)?, unescape(v)?)))
+                    .ok_or_else(|| invalid("bad experiment"))?;
+                registry
+                    .batches
+                    .last_mut()
+                    .ok_or_else(|| invalid("experiment outside of a batch"))?
+                    .assignment = Some(Assignment {
+                    experiment,
+                    variant,
+                });
+            } else {
+                return Err(invalid("unexpected line"));
+            }
+        }
+
+        Ok(registry)
+    }
+}
+
+fn decode_provenance(line: &str) -> Option<Provenance> {
+    let mut fields = line.split('\t');
+    let crate_version = unescape(fields.next()?)?;
+    let strategy = match fields.next()? {
+        "rejection" => Strategy::Rejection,
+        "registry-rejection" => Strategy::RegistryRejection,
+        _ => return None,
+    };
+    let rng = match fields.next()? {
+        "os-seeded" => RngPolicy::OsSeeded,
+        "seeded" => RngPolicy::Seeded,
+        _ => return None,
+    };
+    let config_hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let source = match fields.next() {
+        Some(name) => Some(
+            RNG_SOURCES
+                .into_iter()
+                .find(|s| rng_source_name(*s) == name)?,
+        ),
+        None => (rng == RngPolicy::OsSeeded).then_some(RngSource::Secure),
+    };
+
+    fields.next().is_none().then_some(Provenance {
+        crate_version,
+        strategy,
+        rng,
+        source,
+        config_hash,
+    })
+}
+
+/// Returns the 64-bit FNV-1a hash of a configuration as written in registry files.
+pub(crate) fn config_hash(config: &Config) -> u64 {
+    encode_config(config)
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            ';' => escaped.push_str("\\s"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn unescape(s: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        unescaped.push(match chars.next()? {
+            '\\' => '\\',
+            't' => '\t',
+            'n' => '\n',
+            'r' => '\r',
+            's' => ';',
+            _ => return None,
+        });
+    }
+
+    Some(unescaped)
+}
+
+const CHECKSUMS: [Checksum; 8] = [
+    Checksum::Iso7064Mod11_2,
+    Checksum::Iso7064Mod37_2,
+    Checksum::Iso7064Mod97_10,
+    Checksum::Iso7064Mod11_10,
+    Checksum::Iso7064Mod27_26,
+    Checksum::Iso7064Mod37_36,
+    Checksum::Luhn,
+    Checksum::LuhnMod36,
+];
+
+const RNG_SOURCES: [RngSource; 3] = [RngSource::Secure, RngSource::Os, RngSource::Fast];
+
+fn rng_source_name(source: RngSource) -> &'static str {
+    match source {
+        RngSource::Secure => "secure",
+        RngSource::Os => "os",
+        RngSource::Fast => "fast",
+    }
+}
+
+fn checksum_name(checksum: &Checksum) -> &'static str {
+    match checksum {
+        Checksum::Iso7064Mod11_2 => "iso7064-11-2",
+        Checksum::Iso7064Mod37_2 => "iso7064-37-2",
+        Checksum::Iso7064Mod97_10 => "iso7064-97-10",
+        Checksum::Iso7064Mod11_10 => "iso7064-11-10",
+        Checksum::Iso7064Mod27_26 => "iso7064-27-26",
+        Checksum::Iso7064Mod37_36 => "iso7064-37-36",
+        Checksum::Luhn => "luhn",
+        Checksum::LuhnMod36 => "luhn-mod-36",
+    }
+}
+
+/// Encodes a configuration as `;`-separated `key=value` pairs.
+pub(crate) fn encode_config(config: &Config) -> String {
+    let mut fields = vec![
+        format!("pattern={}", escape(&config.pattern.to_canonical())),
+        format!("charset={}", escape(&config.charset.to_canonical())),
+        format!("count={}", config.count),
+    ];
+
+    if let Some(prefix) = &config.prefix {
+        fields.push(format!("prefix={}", escape(prefix)));
+    }
+    if let Some(suffix) = &config.suffix {
+        fields.push(format!("suffix={}", escape(suffix)));
+    }
+    if let Some(OcrCheck { positions: [a, b] }) = &config.ocr_check {
+        fields.push(format!("ocr={a},{b}"));
+    }
+    if let Some(FormatTag { position, id }) = &config.format_tag {
+        fields.push(format!("format={position},{}", id.0));
+    }
+    if let Some(expiry) = &config.expiry {
+        let secs = |t: SystemTime| t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let granularity = match expiry.granularity {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+        };
+        fields.push(format!(
+            "expiry={},{},{granularity},{},{}",
+            expiry.position,
+            expiry.width,
+            secs(expiry.epoch),
+            secs(expiry.expires)
+        ));
+    }
+    if let Some(embedded) = &config.fields {
+        let layout = &embedded.layout;
+        fields.push(format!("layout={},{}", layout.position(), layout.width()));
+        for ((name, values), value) in layout.fields().zip(&embedded.values) {
+            fields.push(format!("field={values},{value},{}", escape(name)));
+        }
+    }
+    if let Some(checksum) = &config.checksum {
+        fields.push(format!("checksum={}", checksum_name(checksum)));
+    }
+    if config.checksum_coverage == ChecksumCoverage::Full {
+        fields.push("checksum_coverage=full".to_string());
+    }
+    if let Some(p) = config.random_case {
+        fields.push(format!("random_case={p}"));
+    }
+    for prefix in &config.reserved_prefixes {
+        fields.push(format!("reserved={}", escape(prefix)));
+    }
+    if config.no_repeats {
+        fields.push("no_repeats=true".to_string());
+    }
+    for word in &config.blocked_words {
+        fields.push(format!("blocked={}", escape(word)));
+    }
+    if let Some(Grouping { size, separator }) = config.formatting.group {
+        fields.push(format!("group={size},{}", escape(&separator.to_string())));
+    }
+    if let Some(case) = config.formatting.case {
+        let case = match case {
+            LetterCase::Upper => "upper",
+            LetterCase::Lower => "lower",
+        };
+        fields.push(format!("case={case}"));
+    }
+    // Omitted for the default, so files and hashes of configurations
+    // written before sources were recorded stay the same.
+    if config.rng != RngSource::Secure {
+        fields.push(format!("rng={}", rng_source_name(config.rng)));
+    }
+    if let Some(max) = config.max_attempts {
+        fields.push(format!("max_attempts={max}"));
+    }
+
+    fields.join(";")
+}
+
+/// Decodes a configuration written by [`encode_config`] in file version `version`.
+pub(crate) fn decode_config(s: &str, version: u32) -> Option<Config> {
+    let mut config = Config::default();
+
+    for field in s.split(';') {
+        let (key, value) = field.split_once('=')?;
+        let value = unescape(value)?;
+
+        match key {
+            "pattern" if version >= 2 => config.pattern = Pattern::from_canonical(&value).ok()?,
+            "charset" if version >= 2 => config.charset = Charset::from_canonical(&value).ok()?,
+            "length" if version == 1 => config.pattern = Pattern::Length(value.parse().ok()?),
+            "pattern" if version == 1 => config.pattern = Pattern::Pattern(value),
+            "template" if version == 1 => config.pattern = Pattern::Template(value),
+            "charset" if version == 1 => {
+                config.charset = match value.as_str() {
+                    "numeric" => Charset::Numeric,
+                    "alphabetic" => Charset::Alphabetic,
+                    "alphanumeric" => Charset::Alphanumeric,
+                    _ => return None,
+                }
+            }
+            "custom" if version == 1 => config.charset = Charset::Custom(value),
+            "count" => config.count = value.parse().ok()?,
+            "prefix" => config.prefix = Some(value),
+            "suffix" => config.suffix = Some(value),
+            "ocr" => {
+                let (a, b) = value.split_once(',')?;
+                config.ocr_check = Some(OcrCheck::new(a.parse().ok()?, b.parse().ok()?));
+            }
+            "checksum" => {
+                config.checksum = Some(CHECKSUMS.into_iter().find(|c| checksum_name(c) == value)?)
+            }
+            "format" => {
+                let (position, id) = value.split_once(',')?;
+                config.format_tag = Some(FormatTag {
+                    position: position.parse().ok()?,
+                    id: FormatId(id.parse().ok()?),
+                });
+            }
+            "expiry" => {
+                let parts: Vec<&str> = value.split(',').collect();
+                let [position, width, granularity, epoch, expires] = parts[..] else {
+                    return None;
+                };
+                let moment =
+                    |secs: &str| UNIX_EPOCH.checked_add(Duration::from_secs(secs.parse().ok()?));
+
+                config.expiry = Some(Expiry {
+                    position: position.parse().ok()?,
+                    width: width.parse().ok()?,
+                    granularity: match granularity {
+                        "day" => Granularity::Day,
+                        "week" => Granularity::Week,
+                        "month" => Granularity::Month,
+                        _ => return None,
+                    },
+                    epoch: moment(epoch)?,
+                    expires: moment(expires)?,
+                });
+            }
+            "layout" => {
+                let (position, width) = value.split_once(',')?;
+                config.fields = Some(EmbeddedFields {
+                    layout: FieldLayout::new(position.parse().ok()?, width.parse().ok()?),
+                    values: Vec::new(),
+                });
+            }
+            "field" => {
+                let embedded = config.fields.as_mut()?;
+                let (values, rest) = value.split_once(',')?;
+                let (value, name) = rest.split_once(',')?;
+                embedded.layout = embedded.layout.clone().field(name, values.parse().ok()?);
+                embedded.values.push(value.parse().ok()?);
+            }
+            "checksum_coverage" => {
+                config.checksum_coverage = match value.as_str() {
+                    "random" => ChecksumCoverage::Random,
+                    "full" => ChecksumCoverage::Full,
+                    _ => return None,
+                }
+            }
+            "random_case" => config.random_case = Some(value.parse().ok()?),
+            "reserved" => config.reserved_prefixes.push(value),
+            "no_repeats" => config.no_repeats = value.parse().ok()?,
+            "blocked" => config.blocked_words.push(value),
+            "group" => {
+                let (size, separator) = value.split_once(',')?;
+                let mut separator = separator.chars();
+                config.formatting.group = Some(Grouping {
+                    size: size.parse().ok()?,
+                    separator: separator.next().filter(|_| separator.next().is_none())?,
+                });
+            }
+            "case" => {
+                config.formatting.case = Some(match value.as_str() {
+                    "upper" => LetterCase::Upper,
+                    "lower" => LetterCase::Lower,
+                    _ => return None,
+                })
+            }
+            "rng" => {
+                config.rng = RNG_SOURCES
+                    .into_iter()
+                    .find(|s| rng_source_name(*s) == value)?
+            }
+            "max_attempts" => config.max_attempts = Some(value.parse().ok()?),
+            _ => return None,
+        }
+    }
+
+    Some(config)
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_registry_round_trip_preserves_configs() {
+    let mut registry = Registry::new("tab\tname");
+    let config = Config {
+        charset: Charset::Custom("a;b\\c\t".to_string()),
+        pattern: Pattern::parse("AA-#").unwrap(),
+        suffix: Some("\n".to_string()),
+        ocr_check: None,
+        random_case: Some(0.5),
+        reserved_prefixes: vec!["x;".to_string(), "ab".to_string()],
+        no_repeats: true,
+        blocked_words: vec!["n;o".to_string()],
+        formatting: crate::Formatting {
+            group: Some(Grouping {
+                size: 2,
+                separator: '=',
+            }),
+            case: Some(LetterCase::Upper),
+        },
+        ..Config::default()
+    };
+    let dated = Config {
+        expiry: Some(Expiry {
+            position: 7,
+            width: 1,
+            granularity: Granularity::Month,
+            epoch: UNIX_EPOCH,
+            expires: UNIX_EPOCH + Duration::from_secs(40 * 86_400),
+        }),
+        fields: Some(EmbeddedFields {
+            layout: FieldLayout::new(5, 2).field("a;b,c", 3).field("tier", 4),
+            values: vec![2, 3],
+        }),
+        rng: RngSource::Fast,
+        max_attempts: Some(1_000),
+        ..Config::default()
+    };
+    registry.generate("one", &config.with_count(5)).unwrap();
+    registry.generate("two", &dated.with_count(5)).unwrap();
+
+    let mut file = Vec::new();
+    registry.save(&mut file).unwrap();
+    let mut loaded = Registry::load(file.as_slice()).unwrap();
+
+    assert_eq!(loaded.namespace(), "tab\tname");
+    assert_eq!(loaded.len(), 10);
+    assert_eq!(
+        encode_config(&loaded.batches()[0].config),
+        encode_config(&config.with_count(5))
+    );
+    assert_eq!(loaded.batches()[1].config.expiry, dated.expiry);
+    assert_eq!(loaded.batches()[1].config.fields, dated.fields);
+    assert_eq!(loaded.batches()[1].config.rng, RngSource::Fast);
+    assert_eq!(loaded.batches()[1].config.max_attempts, Some(1_000));
+    assert_eq!(
+        loaded.batches()[1].provenance.as_ref().unwrap().source,
+        Some(RngSource::Fast)
+    );
+    assert!(!encode_config(&config).contains("rng="));
+    for code in registry.batches().iter().flat_map(|b| &b.codes) {
+        assert_eq!(
+            loaded.batch_of(code).unwrap().id,
+            registry.batch_of(code).unwrap().id
+        );
+    }
+
+    loaded.extend("one", 3).unwrap();
+    assert_eq!(loaded.len(), 13);
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_registry_enforces_global_uniqueness() {
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(2),
+        count: 50,
+        ..Config::default()
+    };
+    let mut registry = Registry::new("acme");
+
+    registry.generate("a", &config).unwrap();
+    registry.generate("b", &config).unwrap();
+
+    assert!(registry.generate("c", &config.with_count(1)).is_err());
+    assert!(registry.extend("a", 1).is_err());
+    assert_eq!(registry.len(), 100);
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_registry_folds_case_only_for_random_case_batches() {
+    let config = Config {
+        charset: Charset::Alphabetic,
+        pattern: Pattern::Length(1),
+        count: 52,
+        ..Config::default()
+    };
+    let mut registry = Registry::new("acme");
+
+    // Case-sensitive batches can use the whole keyspace...
+    registry.generate("sensitive", &config).unwrap();
+    assert!(registry.contains("a") && registry.contains("A"));
+
+    // ...while random-case codes are unique against every case variant.
+    let mut registry = Registry::new("acme");
+    let random_case = Config {
+        charset: Charset::Custom("ab".to_string()),
+        count: 2,
+        random_case: Some(0.5),
+        ..config.clone()
+    };
+    registry.generate("random", &random_case).unwrap();
+    assert!(registry.contains("a") && registry.contains("A"));
+    assert!(
+        registry
+            .generate("extra", &random_case.with_count(1))
+            .is_err()
+    );
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_registry_round_trip_preserves_superseded_configs() {
+    let mut registry = Registry::new("acme");
+    registry.extend_when_saturated(0.5).unwrap();
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Pattern("X-#".to_string()),
+        count: 5,
+        ..Config::default()
+    };
+    registry.generate("legacy", &config).unwrap();
+    registry.extend("legacy", 5).unwrap();
+
+    let mut file = Vec::new();
+    registry.save(&mut file).unwrap();
+    let loaded = Registry::load(file.as_slice()).unwrap();
+    let batch = loaded.batch("legacy").unwrap();
+
+    assert_eq!(batch.config.pattern.pattern(), "X-##");
+    assert_eq!(batch.superseded.len(), 1);
+    assert_eq!(batch.stats().mismatched, 0);
+}
+
+#[test]
+fn test_registry_loads_version_1_files() {
+    let file =
+        "referral-codes-registry 1\nacme\nbatch\tspring\tlength=2;custom=ab\\s;count=1\n\taa\n";
+    let loaded = Registry::load(file.as_bytes()).unwrap();
+    let config = &loaded.batch("spring").unwrap().config;
+
+    assert_eq!(config.pattern.to_canonical(), "pattern:v1:length:2");
+    assert_eq!(config.charset.to_canonical(), "charset:v1:custom:ab;");
+    assert!(loaded.contains("aa"));
+    assert!(Registry::load("referral-codes-registry 3\nacme\n".as_bytes()).is_err());
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_experiments_are_unique_across_variants_and_atomic() {
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(2),
+        count: 40,
+        ..Config::default()
+    };
+    // Codes of the first variant are also codes of the second, and the
+    // second always has room left for its codes.
+    let narrow = Config {
+        charset: Charset::Custom("0123".to_string()),
+        count: 5,
+        ..config.clone()
+    };
+
+    let mut registry = Registry::new("acme");
+    registry
+        .generate_experiment("split", &[("b", &narrow), ("a", &config)])
+        .unwrap();
+    let codes: HashSet<&String> = registry
+        .experiment("split")
+        .flat_map(|b| &b.codes)
+        .collect();
+    assert_eq!(codes.len(), 45);
+
+    // The second variant doesn't fit: nothing of the experiment is issued.
+    let fuller = config.with_count(70);
+    assert!(
+        registry
+            .generate_experiment("more", &[("a", &config.with_count(10)), ("b", &fuller)])
+            .is_err()
+    );
+    assert_eq!(registry.len(), 45);
+    assert!(registry.batch("more/a").is_none());
+    assert!(
+        registry
+            .generate_experiment("twice", &[("a", &narrow), ("a", &narrow)])
+            .is_err()
+    );
+
+    let mut file = Vec::new();
+    registry.save(&mut file).unwrap();
+    let loaded = Registry::load(file.as_slice()).unwrap();
+    let batch = loaded.batch("split/b").unwrap();
+    assert_eq!(
+        batch.assignment,
+        Some(Assignment {
+            experiment: "split".to_string(),
+            variant: "b".to_string(),
+        })
+    );
+    assert_eq!(loaded.experiment("split").count(), 2);
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_registry_canaries_survive_round_trip() {
+    let mut registry = Registry::new("acme");
+    let config = Config::default().with_count(20);
+    let batch = registry
+        .generate_with_canaries("spring", &config, 0.1)
+        .unwrap();
+    let canaries: Vec<String> = batch
+        .canaries
+        .iter()
+        .map(|i| batch.codes[*i].clone())
+        .collect();
+    let handed_out: Vec<String> = batch.distributable().cloned().collect();
+
+    assert_eq!(canaries.len(), 2);
+    assert_eq!(handed_out.len(), 20);
+    assert!(
+        registry
+            .generate_with_canaries("summer", &config, 1.5)
+            .is_err()
+    );
+
+    let mut file = Vec::new();
+    registry.save(&mut file).unwrap();
+    let loaded = Registry::load(file.as_slice()).unwrap();
+
+    assert!(canaries.iter().all(|code| loaded.is_canary(code)));
+    assert!(!handed_out.iter().any(|code| loaded.is_canary(code)));
+    assert!(!loaded.is_canary("unknown"));
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_registry_keeps_batch_provenance() {
+    let mut registry = Registry::new("acme");
+    let config = Config::default().with_count(5);
+    registry.generate("spring", &config).unwrap();
+    registry
+        .generate_with_canaries("summer", &config.with_prefix("S-"), 0.2)
+        .unwrap();
+
+    let mut file = Vec::new();
+    registry.save(&mut file).unwrap();
+    let loaded = Registry::load(file.as_slice()).unwrap();
+
+    let spring = loaded.batch("spring").unwrap().provenance.clone().unwrap();
+    assert_eq!(spring.strategy, Strategy::RegistryRejection);
+    assert_eq!(spring.rng, RngPolicy::OsSeeded);
+    assert_eq!(spring.crate_version, env!("CARGO_PKG_VERSION"));
+    assert!(spring.matches(&config));
+    assert!(!spring.matches(&config.with_prefix("S-")));
+    for (loaded, saved) in loaded.batches().iter().zip(registry.batches()) {
+        assert_eq!(loaded.provenance, saved.provenance);
+    }
+
+    assert_eq!(
+        decode_provenance("0.1.1\trejection\tseeded\t00000000000000ff")
+            .unwrap()
+            .config_hash,
+        255
+    );
+    assert!(decode_provenance("0.1.1\trejection\tquantum\t00").is_none());
+
+    // Growing a batch records the configuration it grew into.
+    registry.extend_when_saturated(0.8).unwrap();
+    let legacy = Config {
+        charset: crate::Charset::Numeric,
+        pattern: crate::Pattern::Length(1),
+        count: 8,
+        ..Config::default()
+    };
+    registry.generate("legacy", &legacy).unwrap();
+    registry.extend("legacy", 5).unwrap();
+    let legacy = registry.batch("legacy").unwrap();
+    let provenance = legacy.provenance.as_ref().unwrap();
+    assert_eq!(legacy.superseded.len(), 1);
+    assert!(provenance.matches(&legacy.config));
+    assert!(!provenance.matches(&legacy.superseded[0]));
+}
@@ -0,0 +1,125 @@
+use alloc::vec::Vec;
+
+use crate::{
+    Config,
+    segment::{self, CharClass},
+};
+
+impl Config {
+    /// Returns the runs of adjacent freely drawn positions, as random
+    /// positions and their class.
+    ///
+    /// Literals and derived positions end a run.
+    fn free_runs(&self) -> Vec<Vec<(usize, CharClass)>> {
+        let derived = self.derived_positions();
+        let mut runs = Vec::new();
+        let mut run = Vec::new();
+        let mut index = 0;
+
+        for (_, class) in self.pattern.chars() {
+            match class {
+                Some(class) if !derived.contains(&index) => run.push((index, class)),
+                _ => runs.push(core::mem::take(&mut run)),
+            }
+            if class.is_some() {
+                index += 1;
+            }
+        }
+        runs.push(run);
+
+        runs.retain(|run| run.len() > 1);
+        runs
+    }
+
+    /// Returns `true` if `code` repeats a drawn character that `no_repeats` forbids.
+    pub(crate) fn has_repeats(&self, code: &str) -> bool {
+        if !self.no_repeats {
+            return false;
+        }
+        let Some(random) = segment::random_chars(self, code) else {
+            return false;
+        };
+
+        self.free_runs()
+            .iter()
+            .any(|run| run.windows(2).any(|w| random[w[0].0] == random[w[1].0]))
+    }
+
+    /// Returns the number of ways to draw the free positions without
+    /// repeats, or `None` if it overflows a `u128`.
+    ///
+    /// Each run is counted by how many of its draws end in each character.
+    pub(crate) fn repeat_free_count(&self) -> Option<u128> {
+        let runs = self.free_runs();
+        let in_runs: Vec<usize> = runs.iter().flatten().map(|(i, _)| *i).collect();
+        let derived = self.derived_positions();
+
+        let alone = self
+            .pattern
+            .classes()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !derived.contains(i) && !in_runs.contains(i))
+            .try_fold(1u128, |acc, (_, class)| {
+                acc.checked_mul(segment::distinct_alphabet(self, *class).len() as u128)
+            })?;
+
+        runs.iter().try_fold(alone, |acc, run| {
+            let mut ending: Vec<(char, u128)> = Vec::new();
+            for (_, class) in run {
+                let total = ending
+                    .iter()
+                    .try_fold(0u128, |sum, (_, n)| sum.checked_add(*n))?;
+                ending = segment::distinct_alphabet(self, *class)
+                    .into_iter()
+                    .map(|c| {
+                        let same = ending.iter().find(|(e, _)| *e == c).map_or(0, |(_, n)| *n);
+                        (c, if total == 0 { 1 } else { total - same })
+                    })
+                    .collect();
+            }
+
+            let run = ending
+                .iter()
+                .try_fold(0u128, |sum, (_, n)| sum.checked_add(*n))?;
+            acc.checked_mul(run)
+        })
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_no_repeats_feasibility_is_exact() {
+    use crate::{Charset, Pattern, generate};
+
+    let config = Config {
+        charset: Charset::Custom("ABC".to_string()),
+        pattern: Pattern::Pattern("###-#".to_string()),
+        no_repeats: true,
+        ..Config::default()
+    };
+
+    // 3 * 2 * 2 for the run of three, times 3 for the lone position.
+    assert_eq!(config.keyspace(), Some(36));
+    let codes = generate(&config.with_count(36)).unwrap();
+    assert!(codes.iter().all(|code| !config.has_repeats(code)));
+    assert!(generate(&config.with_count(37)).is_err());
+    assert!(config.has_repeats("ABB-A"));
+    assert!(!config.has_repeats("ABA-A"));
+
+    // A single character can't fill two adjacent positions.
+    let single = Config {
+        charset: Charset::Custom("A".to_string()),
+        pattern: Pattern::Length(2),
+        ..config.clone()
+    };
+    assert_eq!(single.keyspace(), Some(0));
+    assert!(generate(&single).is_err());
+
+    // Digits then letters never repeat.
+    let mixed = Config {
+        pattern: Pattern::Template("9A".to_string()),
+        ..config
+    };
+    assert_eq!(mixed.keyspace(), Some(10 * 52));
+}
@@ -0,0 +1,279 @@
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    error::Error,
+    sync::{Mutex, RwLock},
+};
+
+#[cfg(feature = "rng")]
+use crate::{
+    Attempts, Code, Config, ReferralCodeError, check_config, dedup_key, generate_one_with,
+    rng::rng_for,
+};
+
+/// Candidates in a row a store may turn down before generators give up on it.
+#[cfg(feature = "rng")]
+pub(crate) const RESERVE_ATTEMPTS: usize = 100_000;
+
+/// The outcome of [`UniquenessStore::reserve`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reservation {
+    /// The code was free and now belongs to the caller.
+    Reserved,
+    /// The code had already been reserved.
+    Taken,
+}
+
+/// A shared record of every code issued so far.
+///
+/// Implement this against your store of issued codes (a database table with
+/// a unique index, a key-value store with set-if-absent, ...) to keep
+/// several generators, possibly in different processes, from emitting the
+/// same code.
+///
+/// Checking whether a code exists and inserting it afterwards races with
+/// other generators, so [`UniquenessStore::reserve`] must do both atomically:
+/// of all concurrent calls reserving the same code, exactly one may return
+/// [`Reservation::Reserved`].
+///
+/// A store that can't be reached reports it through [`UniquenessStore::Error`]
+/// rather than guessing an outcome; generators stop and return it as
+/// `ReferralCodeError::Store`.
+pub trait UniquenessStore {
+    /// The error returned when the store fails, such as a lost database
+    /// connection. In-memory stores use [`Infallible`].
+    type Error: Error + Send + Sync + 'static;
+
+    /// Atomically reserves `code` if no one has reserved it before.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store failed to tell whether the code was
+    /// free. The code may or may not have been reserved.
+    fn reserve(&self, code: &str) -> Result<Reservation, Self::Error>;
+}
+
+impl UniquenessStore for Mutex<HashSet<String>> {
+    type Error = Infallible;
+
+    fn reserve(&self, code: &str) -> Result<Reservation, Infallible> {
+        let mut codes = self.lock().unwrap_or_else(|e| e.into_inner());
+
+        Ok(match codes.insert(code.to_string()) {
+            true => Reservation::Reserved,
+            false => Reservation::Taken,
+        })
+    }
+}
+
+impl UniquenessStore for RwLock<HashSet<String>> {
+    type Error = Infallible;
+
+    fn reserve(&self, code: &str) -> Result<Reservation, Infallible> {
+        let mut codes = self.write().unwrap_or_else(|e| e.into_inner());
+
+        Ok(match codes.insert(code.to_string()) {
+            true => Reservation::Reserved,
+            false => Reservation::Taken,
+        })
+    }
+}
+
+/// Reserves `code`, turning a failure of the store into a `ReferralCodeError`.
+#[cfg(feature = "rng")]
+pub(crate) fn reserve<S: UniquenessStore + ?Sized>(
+    store: &S,
+    code: &str,
+) -> Result<Reservation, ReferralCodeError> {
+    store
+        .reserve(code)
+        .map_err(|e| ReferralCodeError::Store(Box::new(e)))
+}
+
+/// Generates `config.count` codes, each reserved in a shared store.
+///
+/// Every candidate is reserved before it is returned, and taken candidates
+/// are discarded, so generators sharing the same store never return the same
+/// code. With `random_case`, the lowercased code is reserved, so codes
+/// differing only in case are taken as well.
+///
+/// Generation continues until enough codes have been reserved, so the store
+/// must leave room for `config.count` more codes. After 100,000 candidates
+/// in a row are taken, the store is taken to be full: by chance, this only
+/// happens once fewer than one code in several thousand is still free.
+///
+/// # Arguments
+///
+/// * `config` - Configuration specifying the pattern, charset, and count of codes to generate
+/// * `store` - The store shared by every generator
+///
+/// # Errors
+///
+/// Returns the same errors as [`crate::generate`], and also:
+///
+/// * `ReferralCodeError::Store` - If the store fails
+/// * `ReferralCodeError::StoreSaturated` - If the store turned down too many
+///   candidates in a row
+///
+/// # Examples
+///
+/// ```
+/// use std::{collections::HashSet, sync::Mutex, thread};
+/// use referral_codes::{Charset, Config, Pattern, generate_reserved};
+///
+/// let store = Mutex::new(HashSet::new());
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Length(2),
+///     count: 50,
+///     ..Config::default()
+/// };
+///
+/// let (a, b) = thread::scope(|s| {
+///     let a = s.spawn(|| generate_reserved(&config, &store).unwrap());
+///     let b = s.spawn(|| generate_reserved(&config, &store).unwrap());
+///     (a.join().unwrap(), b.join().unwrap())
+/// });
+///
+/// let all: HashSet<_> = a.iter().chain(&b).collect();
+/// assert_eq!(all.len(), 100);
+/// ```
+#[cfg(feature = "rng")]
+pub fn generate_reserved<S: UniquenessStore + ?Sized>(
+    config: &Config,
+    store: &S,
+) -> Result<Vec<String>, ReferralCodeError> {
+    check_config(config)?;
+
+    (0..config.count)
+        .map(|_| reserve_one(config, store))
+        .collect()
+}
+
+/// Generates a single code, reserved in a shared store.
+///
+/// This is the retry loop around [`crate::generate_one`] that issuing a code
+/// on demand takes: candidates are drawn and reserved until the store
+/// accepts one, as [`generate_reserved`] does, and after 100,000 taken
+/// candidates in a row, the store is taken to be full. `config.count` is
+/// ignored.
+///
+/// # Arguments
+///
+/// * `config` - Configuration specifying the pattern and charset of the code
+/// * `store` - The store of issued codes
+///
+/// # Errors
+///
+/// Returns the same errors as [`generate_reserved`] for a single code.
+///
+/// # Examples
+///
+/// ```
+/// use std::{collections::HashSet, sync::Mutex};
+/// use referral_codes::{Config, issue_one};
+///
+/// let store = Mutex::new(HashSet::new());
+/// let code = issue_one(&Config::default().with_prefix("R-"), &store).unwrap();
+///
+/// assert_eq!(code.random().len(), 8);
+/// assert!(store.lock().unwrap().contains(code.as_str()));
+/// ```
+#[cfg(feature = "rng")]
+pub fn issue_one<S: UniquenessStore + ?Sized>(
+    config: &Config,
+    store: &S,
+) -> Result<Code, ReferralCodeError> {
+    check_config(&config.with_count(1))?;
+    let code = reserve_one(config, store)?;
+
+    Ok(Code::parse(config, &code).expect("generated codes match their configuration"))
+}
+
+/// Draws candidates until `store` reserves one, for a checked configuration.
+#[cfg(feature = "rng")]
+fn reserve_one<S: UniquenessStore + ?Sized>(
+    config: &Config,
+    store: &S,
+) -> Result<String, ReferralCodeError> {
+    let mut rng = rng_for(config);
+    // Candidates the store turns down are counted apart, and each
+    // candidate gets the budget of a single code.
+    let budget = Attempts::new(config, 0, 1);
+    for _ in 0..RESERVE_ATTEMPTS {
+        let mut attempts = budget;
+        let Some(code) = generate_one_with(config, &mut rng, &mut attempts) else {
+            return Err(attempts.exceeded(Vec::new()));
+        };
+
+        if reserve(store, &dedup_key(config, &code))? == Reservation::Reserved {
+            return Ok(code);
+        }
+    }
+
+    Err(ReferralCodeError::StoreSaturated)
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_generate_reserved_skips_taken_codes() {
+    use crate::{Charset, Pattern};
+
+    let store = Mutex::new(HashSet::from(["a".to_string()]));
+    let config = Config {
+        charset: Charset::Custom("ab".to_string()),
+        pattern: Pattern::Length(1),
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+
+    let codes = generate_reserved(&config, &store).unwrap();
+
+    assert_eq!(codes[0].to_lowercase(), "b");
+    assert_eq!(store.reserve("b"), Ok(Reservation::Taken));
+
+    // A full store is given up on instead of retried forever.
+    assert!(matches!(
+        generate_reserved(&config, &store),
+        Err(ReferralCodeError::StoreSaturated)
+    ));
+    assert!(matches!(
+        issue_one(&config.with_count(0), &store),
+        Err(ReferralCodeError::StoreSaturated)
+    ));
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_generate_reserved_returns_store_errors() {
+    use std::fmt;
+
+    struct Unreachable;
+
+    #[derive(Debug)]
+    struct Disconnected;
+
+    impl fmt::Display for Disconnected {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("disconnected")
+        }
+    }
+
+    impl Error for Disconnected {}
+
+    impl UniquenessStore for Unreachable {
+        type Error = Disconnected;
+
+        fn reserve(&self, _code: &str) -> Result<Reservation, Disconnected> {
+            Err(Disconnected)
+        }
+    }
+
+    let error = generate_reserved(&Config::default(), &Unreachable).unwrap_err();
+    assert!(matches!(error, ReferralCodeError::Store(_)));
+    assert!(matches!(
+        issue_one(&Config::default(), &Unreachable),
+        Err(ReferralCodeError::Store(_))
+    ));
+    assert_eq!(error.to_string(), "Uniqueness store error: disconnected");
+}
@@ -0,0 +1,284 @@
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{
+    Config, ReferralCodeError,
+    segment::{Slot, distinct_alphabet, slots},
+};
+
+impl Config {
+    /// Returns `true` if `code` begins with one of the reserved prefixes.
+    ///
+    /// With `random_case`, prefixes match in any case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Config;
+    ///
+    /// let config = Config {
+    ///     reserved_prefixes: vec!["VIP".to_string()],
+    ///     ..Config::default()
+    /// };
+    ///
+    /// assert!(config.is_reserved("VIP2024X"));
+    /// assert!(!config.is_reserved("XVIP2024"));
+    /// ```
+    pub fn is_reserved(&self, code: &str) -> bool {
+        match self.random_case {
+            Some(_) => {
+                let code = code.to_lowercase();
+                self.reserved_prefixes
+                    .iter()
+                    .any(|p| code.starts_with(&p.to_lowercase()))
+            }
+            None => self
+                .reserved_prefixes
+                .iter()
+                .any(|p| code.starts_with(p.as_str())),
+        }
+    }
+
+    /// Returns this configuration with a share of its codes held back for
+    /// later, by the character of their first random position.
+    ///
+    /// The last `fraction` of the characters the first random position can
+    /// hold, rounded up and in charset order, become reserved prefixes,
+    /// preceded by the literals before the position. Codes starting with
+    /// them are never generated, so they stay free for partners added later,
+    /// in the same printed format: to issue them, remove the prefixes again.
+    /// The characters only depend on the format and `fraction`, so every
+    /// service reserves the same ones.
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::InvalidProbability` - If `fraction` is not
+    ///   between 0 and 1
+    /// * `ReferralCodeError::NoReservablePosition` - If the first position
+    ///   that isn't a literal is not drawn freely, such as an OCR check or
+    ///   format tag position, or if there is no such position
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Pattern("R-####".to_string()),
+    ///     ..Config::default()
+    /// };
+    /// let config = config.with_reserved_fraction(0.2).unwrap();
+    ///
+    /// assert_eq!(config.reserved_prefixes, ["R-8", "R-9"]);
+    /// assert!(config.is_reserved("R-9123"));
+    /// ```
+    pub fn with_reserved_fraction(&self, fraction: f64) -> Result<Config, ReferralCodeError> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(ReferralCodeError::InvalidProbability);
+        }
+
+        let slots = slots(self);
+        let lead: String = slots
+            .iter()
+            .map_while(|slot| match slot {
+                Slot::Literal(c) => Some(*c),
+                _ => None,
+            })
+            .collect();
+        let class = match slots.get(lead.chars().count()) {
+            Some(Slot::Random(class)) if !self.derived_positions().contains(&0) => *class,
+            _ => return Err(ReferralCodeError::NoReservablePosition),
+        };
+
+        let alphabet = distinct_alphabet(self, class);
+        // Rounded up by hand, as `f64::ceil` needs `std`.
+        let share = alphabet.len() as f64 * fraction;
+        let reserved =
+            (share as usize + usize::from((share as usize as f64) < share)).min(alphabet.len());
+        let mut config = self.clone();
+        for c in &alphabet[alphabet.len() - reserved..] {
+            let prefix = format!("{lead}{c}");
+            if !config.reserved_prefixes.contains(&prefix) {
+                config.reserved_prefixes.push(prefix);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Returns an upper bound on the number of possible codes that begin with
+    /// a reserved prefix.
+    ///
+    /// Check characters falling inside a reserved prefix are assumed to always
+    /// match, so the bound is only exact when there are none.
+    pub(crate) fn reserved_count(&self) -> u128 {
+        let caseless = self.random_case.is_some();
+        let mut prefixes: Vec<String> = self
+            .reserved_prefixes
+            .iter()
+            .map(|p| match caseless {
+                true => p.to_lowercase(),
+                false => p.clone(),
+            })
+            .collect();
+        prefixes.sort();
+        prefixes.dedup();
+
+        // Codes starting with a longer prefix are already counted by the
+        // shorter one it extends.
+        let mut disjoint: Vec<String> = Vec::with_capacity(prefixes.len());
+        for prefix in prefixes {
+            if disjoint
+                .last()
+                .is_none_or(|last| !prefix.starts_with(last.as_str()))
+            {
+                disjoint.push(prefix);
+            }
+        }
+
+        let slots = slots(self);
+        disjoint
+            .iter()
+            .map(|prefix| self.count_starting_with(&slots, prefix))
+            .fold(0, u128::saturating_add)
+    }
+
+    /// Counts the codes laid out as `slots` that begin with `prefix`.
+    fn count_starting_with(&self, slots: &[Slot], prefix: &str) -> u128 {
+        let caseless = self.random_case.is_some();
+        let checks = self.derived_positions();
+        let mut prefix = prefix.chars();
+        let mut index = 0;
+        let mut count = 1u128;
+
+        for slot in slots {
+            let next = prefix.next();
+
+            match (slot, next) {
+                (Slot::Literal(l), Some(c)) => {
+                    let matches = match caseless {
+                        true => l.to_lowercase().eq(c.to_lowercase()),
+                        false => *l == c,
+                    };
+                    if !matches {
+                        return 0;
+                    }
+                }
+                (Slot::Literal(_), None) => {}
+                (Slot::Random(class), Some(c)) => {
+                    if class.canonical(self, c, caseless).is_none() {
+                        return 0;
+                    }
+                    index += 1;
+                }
+                // Check characters are derived, so they add no codes, and
+                // are assumed to match like check positions.
+                (Slot::Check, _) => {}
+                (Slot::Random(class), None) => {
+                    if !checks.contains(&index) {
+                        let len = match caseless {
+                            true => class.caseless_len(self),
+                            false => class.len(self),
+                        };
+                        count = count.saturating_mul(len as u128);
+                    }
+                    index += 1;
+                }
+            }
+        }
+
+        match prefix.next() {
+            // The prefix is longer than any code.
+            Some(_) => 0,
+            None => count,
+        }
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_reserved_prefixes_reduce_keyspace() {
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(3),
+        reserved_prefixes: vec![
+            "1".to_string(),
+            "12".to_string(),
+            "99".to_string(),
+            "A".to_string(),
+        ],
+        ..Config::default()
+    };
+
+    assert_eq!(config.reserved_count(), 100 + 10);
+    assert_eq!(config.keyspace(), Some(1000 - 110));
+    assert!(crate::generate(&config.with_count(890)).is_ok());
+    assert!(crate::generate(&config.with_count(891)).is_err());
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_reserved_fraction_holds_back_first_characters() {
+    use crate::{Charset, OcrCheck, Pattern, generate};
+
+    let config = Config {
+        charset: Charset::Custom("ABCD".to_string()),
+        pattern: Pattern::Length(3),
+        ..Config::default()
+    };
+
+    // A tenth of four characters rounds up to one.
+    let quarter = config.with_reserved_fraction(0.1).unwrap();
+    assert_eq!(quarter.reserved_prefixes, ["D"]);
+    assert_eq!(quarter.keyspace(), Some(48));
+    let codes = generate(&quarter.with_count(48)).unwrap();
+    assert!(codes.iter().all(|code| !code.starts_with('D')));
+    assert!(generate(&quarter.with_count(49)).is_err());
+
+    // Reserving again adds nothing, and literals lead every prefix.
+    let half = quarter
+        .with_prefix("X-")
+        .with_reserved_fraction(0.5)
+        .unwrap();
+    assert_eq!(half.reserved_prefixes, ["D", "X-C", "X-D"]);
+    assert_eq!(config.with_reserved_fraction(0.0).unwrap(), config);
+    assert_eq!(
+        config.with_reserved_fraction(1.0).unwrap().keyspace(),
+        Some(0)
+    );
+
+    // Letters count once with random case.
+    let caseless = Config {
+        charset: Charset::Custom("abAB".to_string()),
+        random_case: Some(0.5),
+        ..config.clone()
+    };
+    assert_eq!(
+        caseless
+            .with_reserved_fraction(0.5)
+            .unwrap()
+            .reserved_prefixes,
+        ["b"]
+    );
+
+    assert!(matches!(
+        config.with_reserved_fraction(1.5),
+        Err(ReferralCodeError::InvalidProbability)
+    ));
+    let checked = Config {
+        ocr_check: Some(OcrCheck::new(0, 2)),
+        ..config.clone()
+    };
+    assert!(matches!(
+        checked.with_reserved_fraction(0.5),
+        Err(ReferralCodeError::NoReservablePosition)
+    ));
+    assert!(matches!(
+        config
+            .with_pattern(Pattern::Pattern("ABC".to_string()))
+            .with_reserved_fraction(0.5),
+        Err(ReferralCodeError::NoReservablePosition)
+    ));
+}
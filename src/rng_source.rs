@@ -0,0 +1,40 @@
+/// Where the random characters of codes come from, see [`Config::rng`].
+///
+/// Codes that gate money or access must not be guessable from codes seen
+/// before, so the default draws from a cryptographically secure generator.
+/// [`RngSource::Fast`] trades that guarantee for speed, for codes that only
+/// need to be unique, such as test fixtures.
+///
+/// [`crate::Generator`] and seeded batches draw from the generator they are
+/// given instead.
+///
+/// [`Config::rng`]: crate::Config::rng
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum RngSource {
+    /// A thread-local cryptographically secure generator, seeded by the
+    /// operating system and reseeded from it periodically.
+    #[default]
+    Secure,
+    /// The operating system's generator, asked for every random number.
+    ///
+    /// Also cryptographically secure, and slower than [`RngSource::Secure`]
+    /// as every draw is a system call, for setups that must not keep
+    /// generator state in the process.
+    Os,
+    /// A small, fast generator seeded by the operating system.
+    ///
+    /// It is not cryptographically secure: someone who sees enough codes of
+    /// a batch can predict the others.
+    Fast,
+}
+
+impl RngSource {
+    /// Returns `true` if codes drawn from this source can't be predicted
+    /// from other codes.
+    pub fn is_secure(self) -> bool {
+        !matches!(self, RngSource::Fast)
+    }
+}
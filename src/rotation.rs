@@ -0,0 +1,165 @@
+use std::{borrow::Cow, collections::HashSet, io::Write};
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+use crate::{
+    Attempts, Config, ReferralCodeError, check_config, dedup_key, generate_one_with,
+    rng::{SeedableRng, StdRng},
+};
+
+/// The result of rotating a batch of codes to a new configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rotation {
+    /// Pairs of `(old, new)` codes, in the order of the old batch.
+    pub mappings: Vec<(String, String)>,
+}
+
+impl Rotation {
+    /// Returns the replacement for an old code, if it was part of the rotation.
+    pub fn replacement(&self, old: &str) -> Option<&str> {
+        self.mappings
+            .iter()
+            .find(|(o, _)| o == old)
+            .map(|(_, new)| new.as_str())
+    }
+
+    /// Writes the mapping as a two-column `old,new` CSV file with a header row.
+    ///
+    /// Codes holding a comma, a double quote or a line break are quoted as
+    /// RFC 4180 requires, with double quotes doubled.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error produced by the writer.
+    pub fn write_mapping<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "old,new")?;
+
+        for (old, new) in &self.mappings {
+            writeln!(writer, "{},{}", csv_field(old), csv_field(new))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Quotes a CSV field if it needs quoting.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    match field.contains([',', '"', '\r', '\n']) {
+        true => Cow::Owned(format!("\"{}\"", field.replace('"', "\"\""))),
+        false => Cow::Borrowed(field),
+    }
+}
+
+fn derive_seed(key: &[u8], old: &str, attempt: u64) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(old.as_bytes());
+    mac.update(&[0]);
+    mac.update(&attempt.to_le_bytes());
+
+    mac.finalize().into_bytes().into()
+}
+
+/// Generates a replacement code for each code of an old batch.
+///
+/// Each replacement is derived deterministically from the old code with a
+/// keyed hash (HMAC-SHA256), so rerunning the rotation with the same key,
+/// batch and configuration reproduces the same mapping, while the link
+/// between old and new codes cannot be recomputed without the key.
+/// Should two old codes derive the same replacement, the later one is
+/// re-derived until it is unique.
+///
+/// Duplicate codes in the old batch are mapped once.
+///
+/// # Arguments
+///
+/// * `old_batch` - The codes being retired
+/// * `new_config` - The configuration for the replacement codes; its `count` is ignored
+/// * `key` - Secret key linking old codes to their replacements
+///
+/// # Returns
+///
+/// * `Ok(Rotation)` - The old-to-new mapping
+/// * `Err(ReferralCodeError::NonFeasibleConfig)` - If the new configuration
+///   cannot produce one unique code per old code
+/// * `Err(ReferralCodeError::InvalidCheckPositions)` - If the OCR check
+///   positions of the new configuration do not fit its pattern
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, Pattern, rotate};
+///
+/// let old = vec!["ABC123".to_string(), "XYZ789".to_string()];
+/// let config = Config::default().with_pattern(Pattern::Length(10));
+///
+/// let rotation = rotate(&old, &config, b"secret").unwrap();
+///
+/// assert_eq!(rotation.mappings.len(), 2);
+/// assert_eq!(rotation, rotate(&old, &config, b"secret").unwrap());
+/// ```
+pub fn rotate<S: AsRef<str>>(
+    old_batch: &[S],
+    new_config: &Config,
+    key: &[u8],
+) -> Result<Rotation, ReferralCodeError> {
+    let mut seen_old = HashSet::new();
+    let old: Vec<&str> = old_batch
+        .iter()
+        .map(|s| s.as_ref())
+        .filter(|s| seen_old.insert(*s))
+        .collect();
+
+    check_config(&new_config.with_count(old.len()))?;
+
+    let mut issued = HashSet::new();
+    let mut mappings: Vec<(String, String)> = Vec::with_capacity(old.len());
+    let mut attempts = Attempts::new(new_config, 0, old.len());
+
+    for code in old {
+        let mut attempt = 0;
+        let new = loop {
+            let mut rng = StdRng::from_seed(derive_seed(key, code, attempt));
+            let Some(candidate) = generate_one_with(new_config, &mut rng, &mut attempts) else {
+                let generated = mappings.into_iter().map(|(_, new)| new).collect();
+                return Err(attempts.exceeded(generated));
+            };
+
+            if issued.insert(dedup_key(new_config, &candidate).into_owned()) {
+                break candidate;
+            }
+
+            attempt += 1;
+        };
+
+        mappings.push((code.to_string(), new));
+    }
+
+    Ok(Rotation { mappings })
+}
+
+#[test]
+fn test_rotate_mapping_file() {
+    let old = ["A1", "B2", "A1"];
+    let config = Config::default().with_prefix("V2-");
+
+    let rotation = rotate(&old, &config, b"key").unwrap();
+    let other_key = rotate(&old, &config, b"other").unwrap();
+
+    let mut file = Vec::new();
+    rotation.write_mapping(&mut file).unwrap();
+    let file = String::from_utf8(file).unwrap();
+
+    assert_eq!(rotation.mappings.len(), 2);
+    assert_ne!(rotation, other_key);
+    assert!(rotation.replacement("B2").unwrap().starts_with("V2-"));
+    assert_eq!(file.lines().count(), 3);
+    assert!(file.starts_with("old,new\nA1,V2-"));
+
+    let quoted = Rotation {
+        mappings: vec![("A,1".to_string(), "B\"2".to_string())],
+    };
+    let mut file = Vec::new();
+    quoted.write_mapping(&mut file).unwrap();
+    assert_eq!(file, b"old,new\n\"A,1\",\"B\"\"2\"\n");
+}
@@ -0,0 +1,301 @@
+use std::fmt;
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+use crate::{Config, assemble, segment};
+
+/// Number of Feistel rounds of the permutation.
+const ROUNDS: u8 = 8;
+
+/// A keyed permutation of the random part of codes.
+///
+/// Salting maps every code of a format to another code of the same format,
+/// reversibly and only with the key. Give each partner its own salt to
+/// export the same logical batch to several partners: each partner receives
+/// different code strings, and a code redeemed through a partner is mapped
+/// back to the logical code with that partner's key.
+///
+/// Only the freely drawn characters are permuted. The prefix, literals,
+/// suffix and format tag are kept, and OCR check and checksum characters are
+/// recomputed, so salted codes verify against the same configuration. With
+/// `random_case`, salted codes are written in the charset's own spelling
+/// and letter case is ignored when salting or unsalting.
+///
+/// Salts with different keys map a code to the same string with probability
+/// one in the size of the keyspace. To rule out any overlap between
+/// partners, give each partner's exports a distinct prefix or format tag.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, Salt, verify_ocr_check};
+///
+/// let config = Config::default();
+/// let acme = Salt::new(b"acme key".to_vec());
+/// let globex = Salt::new(b"globex key".to_vec());
+///
+/// let salted = acme.apply(&config, "AbC123xY").unwrap();
+///
+/// assert_ne!(salted, globex.apply(&config, "AbC123xY").unwrap());
+/// assert!(verify_ocr_check(&salted, &config));
+/// assert_eq!(acme.remove(&config, &salted).unwrap(), "AbC123xY");
+/// ```
+#[derive(Clone)]
+pub struct Salt {
+    key: Vec<u8>,
+}
+
+impl fmt::Debug for Salt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Salt").finish_non_exhaustive()
+    }
+}
+
+impl Salt {
+    /// Creates a salt from a secret key.
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Salt { key: key.into() }
+    }
+
+    /// Salts a code.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration the code was generated with
+    /// * `code` - The logical code
+    ///
+    /// # Returns
+    ///
+    /// * `Some(code)` - The salted code
+    /// * `None` - If the code does not match the configuration, or the
+    ///   configuration has more than 2^128 possible codes
+    pub fn apply(&self, config: &Config, code: &str) -> Option<String> {
+        self.permute(config, code, true)
+    }
+
+    /// Recovers the logical code from a salted one.
+    ///
+    /// This is the inverse of [`Salt::apply`] with the same key.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(code)` - The logical code
+    /// * `None` - If the code does not match the configuration, or the
+    ///   configuration has more than 2^128 possible codes
+    pub fn remove(&self, config: &Config, code: &str) -> Option<String> {
+        self.permute(config, code, false)
+    }
+
+    /// Returns the code a counter value maps to.
+    ///
+    /// Counters below the number of possible codes map to distinct codes, so
+    /// issuing codes for 0, 1, 2 and on never repeats one, and a code is
+    /// mapped back to its counter with [`Salt::counter_of`]. See
+    /// [`Salt::sequence`] to skip reserved, blocked and repeating codes.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(code)` - The code of the counter
+    /// * `None` - If the counter is not below the number of possible codes,
+    ///   or the configuration has more than 2^128 possible codes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Config, Pattern, Salt};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Numeric,
+    ///     pattern: Pattern::Length(2),
+    ///     ..Config::default()
+    /// };
+    /// let salt = Salt::new(b"campaign".to_vec());
+    ///
+    /// let code = salt.code_at(&config, 7).unwrap();
+    /// assert_eq!(salt.counter_of(&config, &code), Some(7));
+    /// assert_eq!(salt.code_at(&config, 100), None);
+    /// ```
+    pub fn code_at(&self, config: &Config, counter: u128) -> Option<String> {
+        let size = keyspace_size(config)?;
+        if counter >= size {
+            return None;
+        }
+
+        Some(encode(config, self.feistel(size, counter, true)?))
+    }
+
+    /// Returns the counter value a code maps from.
+    ///
+    /// This is the inverse of [`Salt::code_at`] with the same key.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(counter)` - The counter of the code
+    /// * `None` - If the code does not match the configuration, or the
+    ///   configuration has more than 2^128 possible codes
+    pub fn counter_of(&self, config: &Config, code: &str) -> Option<u128> {
+        let (size, value) = decode(config, code)?;
+
+        self.feistel(size, value, false)
+    }
+
+    fn permute(&self, config: &Config, code: &str, forward: bool) -> Option<String> {
+        let (size, value) = decode(config, code)?;
+
+        Some(encode(config, self.feistel(size, value, forward)?))
+    }
+
+    /// Permutes `[0, size)` by cycle walking a Feistel network.
+    ///
+    /// The network permutes `[0, a * b)` with `a * b` just above `size`, and
+    /// is reapplied until the value falls back into range.
+    fn feistel(&self, size: u128, value: u128, forward: bool) -> Option<u128> {
+        if size <= 1 {
+            return Some(value);
+        }
+
+        let a = size.isqrt() + u128::from(size.isqrt().pow(2) < size);
+        let b = size.div_ceil(a);
+        a.checked_mul(b)?;
+
+        let mut x = value;
+        loop {
+            let (mut l, mut r) = (x / b, x % b);
+
+            for round in 0..ROUNDS {
+                let round = if forward { round } else { ROUNDS - 1 - round };
+
+                if round % 2 == 0 {
+                    let f = self.round(size, round, r, a);
+                    l = if forward {
+                        (l + f) % a
+                    } else {
+                        (l + a - f) % a
+                    };
+                } else {
+                    let f = self.round(size, round, l, b);
+                    r = if forward {
+                        (r + f) % b
+                    } else {
+                        (r + b - f) % b
+                    };
+                }
+            }
+
+            x = l * b + r;
+            if x < size {
+                return Some(x);
+            }
+        }
+    }
+
+    /// The keyed round function, reduced modulo `modulus`.
+    fn round(&self, size: u128, round: u8, half: u128, modulus: u128) -> u128 {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(&size.to_le_bytes());
+        mac.update(&[round]);
+        mac.update(&half.to_le_bytes());
+
+        let bytes: [u8; 32] = mac.finalize().into_bytes().into();
+        u128::from_le_bytes(std::array::from_fn(|i| bytes[i])) % modulus
+    }
+}
+
+/// Returns the number of ways the freely drawn characters can be written,
+/// or `None` if more than a `u128` holds.
+pub(crate) fn keyspace_size(config: &Config) -> Option<u128> {
+    segment::free_alphabets(config)
+        .iter()
+        .try_fold(1u128, |size, alphabet| {
+            size.checked_mul(alphabet.len() as u128)
+        })
+}
+
+/// Returns the number of ways the freely drawn characters can be written,
+/// and the index of the way `code` writes them.
+fn decode(config: &Config, code: &str) -> Option<(u128, u128)> {
+    let random = segment::random_chars(config, code)?;
+    let derived = config.derived_positions();
+    let free = segment::free_alphabets(config);
+
+    let mut size: u128 = 1;
+    let mut value: u128 = 0;
+    let free_chars = random
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !derived.contains(i));
+    for ((_, c), alphabet) in free_chars.zip(&free) {
+        let digit = alphabet.iter().position(|a| same(config, *a, *c))?;
+        size = size.checked_mul(alphabet.len() as u128)?;
+        value = value * alphabet.len() as u128 + digit as u128;
+    }
+
+    Some((size, value))
+}
+
+/// Returns the code writing its freely drawn characters the `value`th way.
+fn encode(config: &Config, mut value: u128) -> String {
+    let free = segment::free_alphabets(config);
+    let mut digits = Vec::with_capacity(free.len());
+    for alphabet in free.iter().rev() {
+        digits.push(alphabet[(value % alphabet.len() as u128) as usize]);
+        value /= alphabet.len() as u128;
+    }
+
+    digits.reverse();
+
+    assemble(config, segment::with_derived(config, digits))
+}
+
+fn same(config: &Config, a: char, b: char) -> bool {
+    match config.random_case {
+        Some(_) => a.to_lowercase().eq(b.to_lowercase()),
+        None => a == b,
+    }
+}
+
+#[test]
+fn test_salt_permutes_the_keyspace() {
+    use std::collections::HashSet;
+
+    use crate::{
+        Charset, Checksum, FormatId, FormatTag, OcrCheck, Pattern, verify_checksum,
+        verify_ocr_check,
+    };
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Pattern("P-#####".to_string()),
+        ocr_check: Some(OcrCheck::new(1, 3)),
+        format_tag: Some(FormatTag {
+            position: 0,
+            id: FormatId(7),
+        }),
+        checksum: Some(Checksum::Iso7064Mod11_2),
+        ..Config::default()
+    };
+    let salt = Salt::new(b"partner".to_vec());
+
+    let codes: Vec<String> = (0..100u8)
+        .map(|i| {
+            let free = [char::from(b'0' + i / 10), char::from(b'0' + i % 10)];
+            assemble(&config, segment::with_derived(&config, free))
+        })
+        .collect();
+
+    let salted: HashSet<String> = codes
+        .iter()
+        .map(|code| salt.apply(&config, code).unwrap())
+        .collect();
+
+    assert_eq!(salted.len(), 100);
+    assert!(salted.iter().all(|code| verify_checksum(code, &config)));
+    assert!(salted.iter().all(|code| verify_ocr_check(code, &config)));
+    for code in &codes {
+        let salted = salt.apply(&config, code).unwrap();
+        assert_eq!(salt.remove(&config, &salted).unwrap(), *code);
+    }
+    assert!(salt.apply(&config, "P-123").is_none());
+}
@@ -0,0 +1,137 @@
+use crate::{
+    Batch,
+    segment::{self, Slot, slots},
+};
+
+/// The character replacing hidden characters in masked samples.
+const MASK: char = '*';
+
+/// Which random characters of a code remain visible in a masked sample.
+///
+/// Literals, the prefix and the suffix are always visible, since they are
+/// shared by every code; check characters are always hidden.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MaskPolicy {
+    /// Shows the first `n` random characters.
+    ShowFirst(usize),
+    /// Shows the last `n` random characters.
+    ShowLast(usize),
+    /// Shows no random characters.
+    HideAll,
+}
+
+impl MaskPolicy {
+    fn shows(&self, index: usize, len: usize) -> bool {
+        match *self {
+            Self::ShowFirst(n) => index < n,
+            Self::ShowLast(n) => index + n >= len,
+            Self::HideAll => false,
+        }
+    }
+}
+
+impl Batch {
+    /// Returns a small masked sample of the batch's codes.
+    ///
+    /// The sample shows what codes look like without exposing redeemable
+    /// codes, e.g. for tickets and design documents. Codes are picked evenly
+    /// across the batch, so the same batch always yields the same sample.
+    /// Codes matching neither the batch's configuration nor a superseded one
+    /// are masked entirely.
+    ///
+    /// A policy showing as many characters as a code has random positions
+    /// exposes the whole code, so keep `n` small.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of codes to sample; at most the batch size
+    /// * `policy` - Which random characters remain visible
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Batch, Config, MaskPolicy, Pattern};
+    ///
+    /// let config = Config::default()
+    ///     .with_pattern(Pattern::Pattern("####-####".to_string()))
+    ///     .with_prefix("SP-");
+    /// let batch = Batch::generate("spring", &config.with_count(100)).unwrap();
+    ///
+    /// let sample = batch.sample(3, MaskPolicy::ShowFirst(2));
+    ///
+    /// assert_eq!(sample.len(), 3);
+    /// assert!(sample[0].starts_with("SP-"));
+    /// assert!(sample[0].ends_with("**-****"));
+    /// # }
+    /// ```
+    pub fn sample(&self, n: usize, policy: MaskPolicy) -> Vec<String> {
+        let n = n.min(self.codes.len());
+
+        (0..n)
+            .map(|i| self.mask(&self.codes[i * self.codes.len() / n], policy))
+            .collect()
+    }
+
+    fn mask(&self, code: &str, policy: MaskPolicy) -> String {
+        let config = std::iter::once(&self.config)
+            .chain(self.superseded.iter().rev())
+            .find(|config| segment::random_chars(config, code).is_some());
+        let Some(config) = config else {
+            return code.chars().map(|_| MASK).collect();
+        };
+
+        let slots = slots(config);
+        let len = slots
+            .iter()
+            .filter(|slot| matches!(slot, Slot::Random(_)))
+            .count();
+        let mut index = 0;
+
+        code.chars()
+            .zip(slots)
+            .map(|(c, slot)| match slot {
+                Slot::Literal(_) => c,
+                Slot::Random(_) => {
+                    index += 1;
+                    if policy.shows(index - 1, len) {
+                        c
+                    } else {
+                        MASK
+                    }
+                }
+                Slot::Check => MASK,
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_sample_masks_random_and_check_characters() {
+    use crate::{Charset, Checksum, Config, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Pattern("A#-###".to_string()),
+        checksum: Some(Checksum::Iso7064Mod97_10),
+        suffix: Some("!".to_string()),
+        ..Config::default()
+    };
+    let batch = Batch {
+        id: "test".to_string(),
+        config,
+        codes: vec!["A1-23456!".to_string(), "unknown".to_string()],
+        superseded: Vec::new(),
+        assignment: None,
+        canaries: Vec::new(),
+        provenance: None,
+    };
+
+    assert_eq!(
+        batch.sample(5, MaskPolicy::ShowLast(1)),
+        vec!["A*-**4**!", "*******"]
+    );
+    assert_eq!(batch.sample(1, MaskPolicy::HideAll), vec!["A*-*****!"]);
+    assert_eq!(batch.sample(1, MaskPolicy::ShowFirst(2))[0], "A1-2****!");
+}
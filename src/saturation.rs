@@ -0,0 +1,60 @@
+use std::{fmt, sync::Arc};
+
+/// How full the keyspace of a format is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Saturation<'a> {
+    /// Identifier of the batch codes were just issued in.
+    pub batch: &'a str,
+    /// The number of issued codes in the batch's format, across all batches.
+    pub issued: usize,
+    /// The number of possible codes in the format, or `None` if it overflows a `u128`.
+    pub keyspace: Option<u128>,
+}
+
+impl Saturation<'_> {
+    /// Returns the issued fraction of the keyspace, between 0 and 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Saturation;
+    ///
+    /// let saturation = Saturation { batch: "legacy", issued: 250, keyspace: Some(1000) };
+    /// assert_eq!(saturation.ratio(), 0.25);
+    /// ```
+    pub fn ratio(&self) -> f64 {
+        match self.keyspace {
+            Some(0) => 1.0,
+            Some(keyspace) => self.issued as f64 / keyspace as f64,
+            None => 0.0,
+        }
+    }
+
+    /// Returns how many more codes the format can hold, saturating at zero.
+    ///
+    /// A keyspace overflowing a `u128` counts as `u128::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::Saturation;
+    ///
+    /// let saturation = Saturation { batch: "legacy", issued: 250, keyspace: Some(1000) };
+    /// assert_eq!(saturation.remaining(), 750);
+    /// ```
+    pub fn remaining(&self) -> u128 {
+        self.keyspace
+            .unwrap_or(u128::MAX)
+            .saturating_sub(self.issued as u128)
+    }
+}
+
+/// A callback receiving saturation reports.
+#[derive(Clone)]
+pub(crate) struct SaturationHook(pub(crate) Arc<dyn Fn(&Saturation) + Send + Sync>);
+
+impl fmt::Debug for SaturationHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SaturationHook")
+    }
+}
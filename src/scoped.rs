@@ -0,0 +1,87 @@
+use std::{collections::HashSet, sync::Mutex, thread};
+
+use crate::{Config, ReferralCodeError, check_config, generate_reserved};
+
+/// Generates `config.count` unique codes on several threads.
+///
+/// The count is split evenly between `threads` scoped threads, which
+/// deduplicate against a single shared set of reserved codes, so the result
+/// is unique as a whole. Codes are returned grouped by thread. No thread pool
+/// or extra dependency is involved, which suits environments where adding
+/// one is unwanted.
+///
+/// # Arguments
+///
+/// * `config` - Configuration specifying the pattern, charset, and count of codes to generate
+/// * `threads` - The number of threads to use; 0 is treated as 1
+///
+/// # Errors
+///
+/// Returns the same errors as [`crate::generate`].
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+/// use referral_codes::{Config, generate_scoped};
+///
+/// let codes = generate_scoped(&Config::default().with_count(1_000), 4).unwrap();
+///
+/// assert_eq!(codes.len(), 1_000);
+/// assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 1_000);
+/// ```
+pub fn generate_scoped(config: &Config, threads: usize) -> Result<Vec<String>, ReferralCodeError> {
+    check_config(config)?;
+
+    let threads = threads.clamp(1, config.count.max(1));
+    let store = Mutex::new(HashSet::with_capacity(config.count));
+    let shares =
+        (0..threads).map(|i| config.count / threads + usize::from(i < config.count % threads));
+
+    let results: Vec<_> = thread::scope(|s| {
+        let handles: Vec<_> = shares
+            .map(|share| {
+                let config = config.with_count(share);
+                let store = &store;
+                s.spawn(move || generate_reserved(&config, store))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+
+    let mut codes = Vec::with_capacity(config.count);
+    for result in results {
+        codes.extend(result?);
+    }
+
+    Ok(codes)
+}
+
+#[test]
+fn test_generate_scoped_saturated_keyspace() {
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(2),
+        count: 100,
+        ..Config::default()
+    };
+
+    let mut codes = generate_scoped(&config, 3).unwrap();
+    codes.sort();
+
+    assert_eq!(
+        codes,
+        (0..100).map(|i| format!("{i:02}")).collect::<Vec<_>>()
+    );
+    assert!(generate_scoped(&config.with_count(101), 3).is_err());
+}
@@ -0,0 +1,685 @@
+use alloc::{borrow::Cow, string::String, vec, vec::Vec};
+use core::ops::Deref;
+
+use crate::{Charset, Checksum, Config, Pattern, checksum};
+
+/// Number of random characters [`RandomChars`] holds without allocating.
+const INLINE: usize = 32;
+
+/// The pool a random position of a [`Pattern`] draws its character from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CharClass {
+    /// The configured charset.
+    Charset,
+    /// Letters a-z and A-Z, regardless of the configured charset.
+    Letter,
+    /// Digits 0-9, regardless of the configured charset.
+    Digit,
+    /// The charset of the contained segment of a [`Pattern::Segments`],
+    /// counting from zero.
+    Pool(usize),
+}
+
+impl CharClass {
+    /// Returns the charset this class draws from.
+    pub(crate) fn charset<'a>(&self, config: &'a Config) -> &'a Charset {
+        static EMPTY: Charset = Charset::Custom(String::new());
+
+        match self {
+            Self::Charset => &config.charset,
+            Self::Letter => &Charset::Alphabetic,
+            Self::Digit => &Charset::Numeric,
+            Self::Pool(i) => config.pattern.pool(*i).unwrap_or(&EMPTY),
+        }
+    }
+
+    /// Returns the characters this class draws from.
+    pub(crate) fn alphabet<'a>(&self, config: &'a Config) -> Cow<'a, str> {
+        self.charset(config).as_str()
+    }
+
+    /// Returns the number of distinct characters of this class.
+    ///
+    /// A custom charset repeating characters draws them more often, but
+    /// can't write more codes.
+    pub(crate) fn len(&self, config: &Config) -> usize {
+        match self {
+            Self::Charset => config.charset.effective_len(),
+            Self::Letter => Charset::Alphabetic.len(),
+            Self::Digit => Charset::Numeric.len(),
+            Self::Pool(i) => config.pattern.pool(*i).map_or(0, Charset::effective_len),
+        }
+    }
+
+    /// Returns the number of characters of this class when ignoring case.
+    pub(crate) fn caseless_len(&self, config: &Config) -> usize {
+        let mut folded: Vec<String> = self
+            .alphabet(config)
+            .chars()
+            .map(|c| c.to_lowercase().collect())
+            .collect();
+        folded.sort();
+        folded.dedup();
+        folded.len()
+    }
+
+    pub(crate) fn contains(&self, config: &Config, c: char) -> bool {
+        self.alphabet(config).contains(c)
+    }
+
+    /// Returns the member of this class matching `c`, optionally ignoring case.
+    ///
+    /// An exact match is preferred over a case-insensitive one.
+    pub(crate) fn canonical(&self, config: &Config, c: char, ignore_case: bool) -> Option<char> {
+        let alphabet = self.alphabet(config);
+
+        if alphabet.contains(c) {
+            return Some(c);
+        }
+
+        if !ignore_case {
+            return None;
+        }
+
+        alphabet
+            .chars()
+            .find(|a| a.to_lowercase().eq(c.to_lowercase()))
+    }
+
+    #[cfg(feature = "rng")]
+    pub(crate) fn sample<R: crate::rng::CodeRng + ?Sized>(
+        &self,
+        config: &Config,
+        rng: &mut R,
+    ) -> char {
+        crate::Alphabet::new(self.charset(config)).sample(rng)
+    }
+
+    /// Returns this class drawing from the charset of segment `pool`
+    /// instead of the configured one, if given.
+    fn in_pool(self, pool: Option<usize>) -> Self {
+        match (self, pool) {
+            (Self::Charset, Some(i)) => Self::Pool(i),
+            (class, _) => class,
+        }
+    }
+}
+
+/// A character of a pattern string.
+struct Token {
+    /// Byte offset of the character, after the backslash if escaped.
+    start: usize,
+    c: char,
+    class: Option<CharClass>,
+    /// Whether the character was escaped with a backslash.
+    escaped: bool,
+}
+
+/// A piece of a pattern: either literal text or a run of random positions.
+///
+/// See [`Pattern::segments`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// Literal text copied verbatim into every code.
+    Literal(&'a str),
+    /// A run of consecutive random positions drawn from the same class.
+    Placeholder(CharClass, usize),
+}
+
+impl Pattern {
+    /// Returns the class of a character of the pattern string, or `None`
+    /// for literals.
+    fn class_of(&self, p: char) -> Option<CharClass> {
+        match (self, p) {
+            (Self::WithPlaceholder(_, placeholder), _) => {
+                (p == *placeholder).then_some(CharClass::Charset)
+            }
+            (_, '#') => Some(CharClass::Charset),
+            (Self::Template(_), 'A') => Some(CharClass::Letter),
+            (Self::Template(_), '9') => Some(CharClass::Digit),
+            _ => None,
+        }
+    }
+
+    /// Returns every character of the pattern string with its byte offset
+    /// and class, resolving the escapes of templates.
+    fn tokens(&self) -> impl Iterator<Item = Token> + '_ {
+        let (s, _) = self.parts();
+        let mut chars = s.char_indices().peekable();
+
+        core::iter::from_fn(move || {
+            let (start, c) = chars.next()?;
+
+            if let Self::Template(_) = self
+                && c == '\\'
+                && let Some(&(start, escaped)) = chars.peek()
+                && matches!(escaped, '#' | 'A' | '9' | '\\')
+            {
+                chars.next();
+                return Some(Token {
+                    start,
+                    c: escaped,
+                    class: None,
+                    escaped: true,
+                });
+            }
+
+            Some(Token {
+                start,
+                c,
+                class: self.class_of(c),
+                escaped: false,
+            })
+        })
+    }
+
+    /// Returns the pattern string and the number of random positions before it.
+    ///
+    /// Segments have neither: their characters are those of their segments.
+    fn parts(&self) -> (&str, usize) {
+        match self {
+            Self::Length(n) => ("", *n),
+            Self::Pattern(s) | Self::Template(s) | Self::WithPlaceholder(s, _) => (s.as_str(), 0),
+            Self::Segments(_) => ("", 0),
+        }
+    }
+
+    /// Returns the patterns making up this one, with the index of their
+    /// charset if they are segments.
+    ///
+    /// Segments nested in segments are rejected by `check_format`, and make
+    /// up nothing.
+    fn pieces(&self) -> impl Iterator<Item = (&Pattern, Option<usize>)> + '_ {
+        let segments = match self {
+            Self::Segments(segments) => &segments[..],
+            _ => &[],
+        };
+        let own = (!matches!(self, Self::Segments(_))).then_some((self, None));
+
+        own.into_iter().chain(
+            segments
+                .iter()
+                .enumerate()
+                .map(|(i, (pattern, _))| (pattern, Some(i))),
+        )
+    }
+
+    /// Returns every character of the pattern with its class, or `None` for
+    /// literals, without allocating.
+    pub(crate) fn chars(&self) -> impl Iterator<Item = (char, Option<CharClass>)> + '_ {
+        self.pieces().flat_map(|(pattern, pool)| {
+            let (_, n) = pattern.parts();
+
+            core::iter::repeat_n(('#', Some(CharClass::Charset)), n)
+                .chain(pattern.tokens().map(|token| (token.c, token.class)))
+                .map(move |(c, class)| (c, class.map(|class| class.in_pool(pool))))
+        })
+    }
+
+    /// Splits the pattern into literal text and runs of random positions,
+    /// without allocating.
+    ///
+    /// Adjacent random positions form a single run only if they draw from
+    /// the same class. An escaped character of a [`Pattern::Template`] is a
+    /// literal segment of its own, without the backslash, and the pieces of
+    /// a [`Pattern::Segments`] follow each other without merging, their
+    /// charset placeholders belonging to [`CharClass::Pool`]. Formatters such as
+    /// masks, groupings or input masks can be built on segments instead of
+    /// parsing pattern strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{CharClass, Pattern, Segment};
+    ///
+    /// let pattern = Pattern::parse("REF-AA99").unwrap();
+    ///
+    /// assert_eq!(
+    ///     pattern.segments().collect::<Vec<_>>(),
+    ///     [
+    ///         Segment::Literal("REF-"),
+    ///         Segment::Placeholder(CharClass::Letter, 2),
+    ///         Segment::Placeholder(CharClass::Digit, 2),
+    ///     ]
+    /// );
+    /// ```
+    pub fn segments(&self) -> impl Iterator<Item = Segment<'_>> + '_ {
+        self.pieces().flat_map(|(pattern, pool)| {
+            pattern.own_segments().map(move |segment| match segment {
+                Segment::Placeholder(class, n) => Segment::Placeholder(class.in_pool(pool), n),
+                literal => literal,
+            })
+        })
+    }
+
+    /// Splits a pattern that isn't made of segments, see [`Pattern::segments`].
+    fn own_segments(&self) -> impl Iterator<Item = Segment<'_>> + '_ {
+        let (s, n) = self.parts();
+        let mut length = (n > 0).then_some(Segment::Placeholder(CharClass::Charset, n));
+        let mut rest = self.tokens().peekable();
+
+        core::iter::from_fn(move || {
+            if let Some(segment) = length.take() {
+                return Some(segment);
+            }
+
+            let first = rest.next()?;
+            let class = first.class;
+            let mut end = first.start + first.c.len_utf8();
+            let mut count = 1;
+            while let Some(token) = rest.peek().filter(|_| !first.escaped) {
+                if token.escaped || token.class != class {
+                    break;
+                }
+                end = token.start + token.c.len_utf8();
+                count += 1;
+                rest.next();
+            }
+
+            Some(match class {
+                Some(class) => Segment::Placeholder(class, count),
+                None => Segment::Literal(&s[first.start..end]),
+            })
+        })
+    }
+
+    /// Returns the character class of every random position, in order.
+    pub(crate) fn classes(&self) -> Vec<CharClass> {
+        self.segments()
+            .flat_map(|segment| match segment {
+                Segment::Literal(_) => vec![],
+                Segment::Placeholder(class, n) => vec![class; n],
+            })
+            .collect()
+    }
+}
+
+/// A single position of a code.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Slot {
+    Literal(char),
+    Random(CharClass),
+    /// A character of the appended checksum.
+    Check,
+}
+
+/// The expanded shape of a code, prefix and suffix included.
+pub(crate) fn slots(config: &Config) -> Vec<Slot> {
+    let prefix = config
+        .prefix
+        .as_deref()
+        .unwrap_or("")
+        .chars()
+        .map(Slot::Literal);
+    let pattern = config.pattern.segments().flat_map(|segment| match segment {
+        Segment::Literal(s) => s.chars().map(Slot::Literal).collect::<Vec<_>>(),
+        Segment::Placeholder(class, n) => vec![Slot::Random(class); n],
+    });
+    let check = vec![Slot::Check; config.checksum.as_ref().map_or(0, Checksum::len)];
+
+    let suffix = config
+        .suffix
+        .as_deref()
+        .unwrap_or("")
+        .chars()
+        .map(Slot::Literal);
+
+    prefix.chain(pattern).chain(check).chain(suffix).collect()
+}
+
+/// Returns the characters each freely drawn position can hold, in order.
+///
+/// Freely drawn positions are the random positions whose character is not
+/// derived from the others. With `random_case`, characters differing only
+/// in case count once.
+pub(crate) fn free_alphabets(config: &Config) -> Vec<Vec<char>> {
+    let derived = config.derived_positions();
+
+    config
+        .pattern
+        .classes()
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !derived.contains(i))
+        .map(|(_, class)| distinct_alphabet(config, *class))
+        .collect()
+}
+
+/// Returns the characters a random position of `class` can hold, each once.
+///
+/// With `random_case`, characters differing only in case count once.
+pub(crate) fn distinct_alphabet(config: &Config, class: CharClass) -> Vec<char> {
+    let mut alphabet: Vec<char> = Vec::new();
+
+    for c in class.alphabet(config).chars() {
+        let seen = match config.random_case {
+            Some(_) => alphabet
+                .iter()
+                .any(|a| a.to_lowercase().eq(c.to_lowercase())),
+            None => alphabet.contains(&c),
+        };
+        if !seen {
+            alphabet.push(c);
+        }
+    }
+
+    alphabet
+}
+
+/// Returns the expiry character of random position `i`, if the expiry covers it.
+#[cfg(feature = "std")]
+fn expiry_char(config: &Config, i: usize) -> Option<char> {
+    let expiry = config
+        .expiry
+        .filter(|expiry| expiry.positions().contains(&i))?;
+
+    Some(expiry.chars(config)[i - expiry.position])
+}
+
+/// Without `std`, configurations have no expiry.
+#[cfg(not(feature = "std"))]
+fn expiry_char(_: &Config, _: usize) -> Option<char> {
+    None
+}
+
+/// Returns the characters of every random position from those of the freely drawn ones.
+///
+/// The format tag, expiry, embedded field and OCR check characters are filled in.
+///
+/// # Panics
+///
+/// Panics if `free` holds fewer characters than the freely drawn positions,
+/// or if the format tag doesn't fit the charset. Codes are only assembled
+/// for configurations [`crate::check_format`] accepted, from one character
+/// per freely drawn position, so neither can happen.
+pub(crate) fn with_derived(config: &Config, free: impl IntoIterator<Item = char>) -> Vec<char> {
+    let checks = config
+        .ocr_check
+        .as_ref()
+        .map_or(&[][..], |c| &c.positions[..]);
+    let mut free = free.into_iter();
+
+    let mut random: Vec<char> = (0..config.pattern.size())
+        .filter(|i| !checks.contains(i))
+        .map(|i| match &config.format_tag {
+            Some(tag) if tag.position == i => tag
+                .char(&config.charset)
+                .expect("format tags are checked against the charset"),
+            _ => match (expiry_char(config, i), &config.fields) {
+                (Some(c), _) => c,
+                (None, Some(fields)) if fields.layout.positions().contains(&i) => {
+                    fields.chars(config)[i - fields.layout.position()]
+                }
+                _ => free.next().expect("too few free characters"),
+            },
+        })
+        .collect();
+
+    if let Some(check) = &config.ocr_check {
+        check.insert(&config.charset, &mut random);
+    }
+
+    random
+}
+
+/// The characters found at the random positions of a code.
+///
+/// Up to [`INLINE`] characters are held without allocating, so verifying
+/// codes of usual lengths doesn't touch the heap.
+#[derive(Clone)]
+pub(crate) struct RandomChars {
+    inline: [char; INLINE],
+    len: usize,
+    spilled: Vec<char>,
+}
+
+impl RandomChars {
+    fn new() -> Self {
+        RandomChars {
+            inline: ['\0'; INLINE],
+            len: 0,
+            spilled: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        if self.spilled.is_empty() && self.len < INLINE {
+            self.inline[self.len] = c;
+            self.len += 1;
+            return;
+        }
+
+        if self.spilled.is_empty() {
+            self.spilled.extend_from_slice(&self.inline);
+        }
+        self.spilled.push(c);
+    }
+}
+
+impl Deref for RandomChars {
+    type Target = [char];
+
+    fn deref(&self) -> &[char] {
+        match self.spilled.is_empty() {
+            true => &self.inline[..self.len],
+            false => &self.spilled,
+        }
+    }
+}
+
+/// Decomposes a code into the characters found at its random positions.
+///
+/// Returns `None` if the code does not start with the configured prefix or end
+/// with the configured suffix, if a
+/// literal does not match, if it is too short to hold its checksum, if the
+/// format tag is not the configured one, if a random position holds a character outside its
+/// class, or if the code is too short or too long.
+///
+/// With `random_case`, random positions match case-insensitively and the
+/// returned characters are the charset's own spelling of each character.
+pub(crate) fn random_chars(config: &Config, code: &str) -> Option<RandomChars> {
+    let body = code
+        .strip_prefix(config.prefix.as_deref().unwrap_or(""))?
+        .strip_suffix(config.suffix.as_deref().unwrap_or(""))?;
+    let (pattern, _) = checksum::split(config, body)?;
+    let mut rest = pattern.chars();
+    let mut random = RandomChars::new();
+
+    for (p, class) in config.pattern.chars() {
+        let c = rest.next()?;
+        match class {
+            Some(class) => random.push(class.canonical(config, c, config.random_case.is_some())?),
+            None if c == p => {}
+            None => return None,
+        }
+    }
+
+    if let Some(tag) = &config.format_tag
+        && random.get(tag.position) != tag.char(&config.charset).as_ref()
+    {
+        return None;
+    }
+
+    rest.next().is_none().then_some(random)
+}
+
+#[test]
+fn test_segments() {
+    let pattern = Pattern::Pattern("AB##-#".to_string());
+
+    assert_eq!(
+        pattern.segments().collect::<Vec<_>>(),
+        [
+            Segment::Literal("AB"),
+            Segment::Placeholder(CharClass::Charset, 2),
+            Segment::Literal("-"),
+            Segment::Placeholder(CharClass::Charset, 1),
+        ]
+    );
+    assert_eq!(
+        Pattern::Length(3).segments().collect::<Vec<_>>(),
+        [Segment::Placeholder(CharClass::Charset, 3)]
+    );
+    assert_eq!(Pattern::Length(0).segments().count(), 0);
+    assert_eq!(
+        Pattern::Pattern("é#ü".to_string())
+            .segments()
+            .collect::<Vec<_>>(),
+        [
+            Segment::Literal("é"),
+            Segment::Placeholder(CharClass::Charset, 1),
+            Segment::Literal("ü"),
+        ]
+    );
+}
+
+#[test]
+fn test_custom_placeholder_segments() {
+    let pattern = Pattern::WithPlaceholder("#??-?".to_string(), '?');
+
+    assert_eq!(pattern.size(), 3);
+    assert_eq!(
+        pattern.segments().collect::<Vec<_>>(),
+        [
+            Segment::Literal("#"),
+            Segment::Placeholder(CharClass::Charset, 2),
+            Segment::Literal("-"),
+            Segment::Placeholder(CharClass::Charset, 1),
+        ]
+    );
+}
+
+#[test]
+fn test_template_segments() {
+    let pattern = Pattern::parse("AA-99#").unwrap();
+
+    assert_eq!(
+        pattern.segments().collect::<Vec<_>>(),
+        [
+            Segment::Placeholder(CharClass::Letter, 2),
+            Segment::Literal("-"),
+            Segment::Placeholder(CharClass::Digit, 2),
+            Segment::Placeholder(CharClass::Charset, 1),
+        ]
+    );
+}
+
+#[test]
+fn test_template_escapes() {
+    use crate::verify_checksum;
+
+    let pattern = Pattern::parse(r"R\A-\9A\#\\x\y99").unwrap();
+    assert_eq!(
+        pattern.segments().collect::<Vec<_>>(),
+        [
+            Segment::Literal("R"),
+            Segment::Literal("A"),
+            Segment::Literal("-"),
+            Segment::Literal("9"),
+            Segment::Placeholder(CharClass::Letter, 1),
+            Segment::Literal("#"),
+            Segment::Literal("\\"),
+            Segment::Literal(r"x\y"),
+            Segment::Placeholder(CharClass::Digit, 2),
+        ]
+    );
+    assert_eq!(pattern.size(), 3);
+
+    let config = Config::default().with_pattern(pattern);
+    assert!(verify_checksum(r"RA-9q#\x\y42", &config));
+    assert!(!verify_checksum(r"R\A-9q#\x\y42", &config));
+    assert!(!verify_checksum(r"RA-99#\x\y42", &config));
+
+    // Canonical templates escape every backslash, and describe the same codes.
+    let canonical = config.canonicalize();
+    assert_eq!(
+        canonical.pattern,
+        Pattern::Template(r"R\A-\9A\#\\x\\y99".to_string())
+    );
+    assert!(verify_checksum(r"RA-9q#\x\y42", &canonical));
+    assert_eq!(canonical, canonical.canonicalize());
+}
+
+#[test]
+fn test_random_chars_spills_long_codes() {
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Pattern(format!("{}-{}", "#".repeat(INLINE), "#".repeat(3))),
+        ..Config::default()
+    };
+    let code = format!("{}-123", "9".repeat(INLINE));
+
+    let random = random_chars(&config, &code).unwrap();
+
+    assert_eq!(random.len(), INLINE + 3);
+    assert_eq!(random[INLINE - 1..], ['9', '1', '2', '3']);
+    assert!(random_chars(&config, &format!("{code}4")).is_none());
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_segments_draw_from_their_own_charsets() {
+    use crate::{ReferralCodeError, generate, validate};
+
+    let config = Config {
+        pattern: Pattern::Segments(vec![
+            (
+                Pattern::Pattern("###-".to_string()),
+                Charset::Custom("ABCD".to_string()),
+            ),
+            (
+                Pattern::Template("9-##".to_string()),
+                Charset::Custom("xy".to_string()),
+            ),
+        ]),
+        ..Config::default()
+    };
+
+    assert_eq!(config.pattern.size(), 6);
+    assert_eq!(config.pattern.pattern(), "###-9-##");
+    assert_eq!(
+        config.pattern.segments().collect::<Vec<_>>(),
+        [
+            Segment::Placeholder(CharClass::Pool(0), 3),
+            Segment::Literal("-"),
+            Segment::Placeholder(CharClass::Digit, 1),
+            Segment::Literal("-"),
+            Segment::Placeholder(CharClass::Pool(1), 2),
+        ]
+    );
+    assert_eq!(config.keyspace(), Some(4 * 4 * 4 * 10 * 2 * 2));
+
+    let codes = generate(&config.with_count(2_560)).unwrap();
+    for code in &codes {
+        let (head, tail) = code.split_at(4);
+        assert!(head[..3].chars().all(|c| "ABCD".contains(c)), "{code}");
+        assert!(tail[2..].chars().all(|c| "xy".contains(c)), "{code}");
+        assert!(validate(code, &config).is_ok());
+    }
+    assert!(validate("ABx-1-xy", &config).is_err());
+    assert!(matches!(
+        generate(&config.with_count(2_561)),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+
+    let placeholder = Config {
+        pattern: Pattern::Segments(vec![(
+            Pattern::Length(2),
+            Charset::Custom("A#".to_string()),
+        )]),
+        ..Config::default()
+    };
+    assert!(matches!(
+        generate(&placeholder),
+        Err(ReferralCodeError::PlaceholderInCharset('#'))
+    ));
+    let nested = Config {
+        pattern: Pattern::Segments(vec![(config.pattern.clone(), Charset::Numeric)]),
+        ..Config::default()
+    };
+    assert!(matches!(
+        generate(&nested),
+        Err(ReferralCodeError::NestedSegments)
+    ));
+}
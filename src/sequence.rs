@@ -0,0 +1,154 @@
+use crate::{Config, ReferralCodeError, Salt, check_format, salt::keyspace_size};
+
+/// Codes issued in a keyed order, unique without remembering them.
+///
+/// A sequence maps the counter values 0, 1, 2 and on to codes through the
+/// permutation of a [`Salt`], so no code comes twice and no set of issued
+/// codes is kept, however large the campaign. Codes look random to anyone
+/// without the key. Store [`Sequence::position`] after issuing codes, and
+/// resume from it with [`Salt::sequence`] to carry on where the last run
+/// stopped.
+///
+/// Codes beginning with a reserved prefix, containing a blocked word or,
+/// with `no_repeats`, repeating a character are skipped, their counter
+/// values being spent. The sequence ends once every counter value was
+/// mapped. `config.count` is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+/// use referral_codes::{Charset, Config, Pattern, Salt};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Length(3),
+///     ..Config::default()
+/// };
+/// let salt = Salt::new(b"campaign key".to_vec());
+///
+/// let mut sequence = salt.sequence(&config, 0).unwrap();
+/// let first: Vec<String> = sequence.by_ref().take(400).collect();
+/// let position = sequence.position();
+///
+/// // Later, maybe in another process.
+/// let rest: Vec<String> = salt.sequence(&config, position).unwrap().collect();
+///
+/// let all: HashSet<&String> = first.iter().chain(&rest).collect();
+/// assert_eq!(all.len(), 1_000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sequence {
+    config: Config,
+    salt: Salt,
+    /// The number of possible codes, the end of the counter.
+    size: u128,
+    next: u128,
+}
+
+impl Sequence {
+    /// Returns the counter value the next code is mapped from, which is the
+    /// value to resume the sequence from.
+    pub fn position(&self) -> u128 {
+        self.next
+    }
+}
+
+impl Iterator for Sequence {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        while self.next < self.size {
+            let code = self.salt.code_at(&self.config, self.next)?;
+            self.next += 1;
+
+            if self.config.accepts(&code) {
+                return Some(code);
+            }
+        }
+
+        None
+    }
+}
+
+impl Salt {
+    /// Returns the codes of a configuration, in the order of this salt,
+    /// starting from a counter value.
+    ///
+    /// See [`Sequence`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration of the codes
+    /// * `start` - The counter value to start from: 0 for a new campaign, or
+    ///   the [`Sequence::position`] a previous run stopped at
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::KeyspaceTooLarge` - If the format has more than
+    ///   2^128 possible codes
+    /// * Any error of the format, such as `InvalidCheckPositions`
+    pub fn sequence(&self, config: &Config, start: u128) -> Result<Sequence, ReferralCodeError> {
+        check_format(config)?;
+        let size = keyspace_size(config).ok_or(ReferralCodeError::KeyspaceTooLarge)?;
+
+        Ok(Sequence {
+            config: config.clone(),
+            salt: self.clone(),
+            size,
+            next: start,
+        })
+    }
+}
+
+#[test]
+fn test_sequence_is_unique_and_resumable() {
+    use std::collections::HashSet;
+
+    use crate::{Charset, OcrCheck, Pattern, verify_ocr_check};
+
+    let config = Config {
+        charset: Charset::Custom("ABCD".to_string()),
+        pattern: Pattern::Pattern("Q-#####".to_string()),
+        ocr_check: Some(OcrCheck::new(3, 4)),
+        reserved_prefixes: vec!["Q-A".to_string()],
+        ..Config::default()
+    };
+    let salt = Salt::new(b"campaign".to_vec());
+
+    // 4^3 counter values, of which a quarter map to reserved codes.
+    let all: Vec<String> = salt.sequence(&config, 0).unwrap().collect();
+    assert_eq!(all.len(), 48);
+    assert_eq!(all.iter().collect::<HashSet<_>>().len(), 48);
+    assert!(
+        all.iter()
+            .all(|code| verify_ocr_check(code, &config) && !code.starts_with("Q-A"))
+    );
+    assert_ne!(all, {
+        let mut sorted = all.clone();
+        sorted.sort();
+        sorted
+    });
+
+    let mut sequence = salt.sequence(&config, 0).unwrap();
+    let first: Vec<String> = sequence.by_ref().take(20).collect();
+    let rest: Vec<String> = salt
+        .sequence(&config, sequence.position())
+        .unwrap()
+        .collect();
+    assert_eq!([first, rest].concat(), all);
+    assert_eq!(salt.sequence(&config, 64).unwrap().next(), None);
+
+    for (counter, code) in (0..64).filter_map(|n| Some((n, salt.code_at(&config, n)?))) {
+        assert_eq!(salt.counter_of(&config, &code), Some(counter));
+    }
+
+    let huge = Config {
+        pattern: Pattern::Length(30),
+        ..Config::default()
+    };
+    assert!(matches!(
+        salt.sequence(&huge, 0),
+        Err(ReferralCodeError::KeyspaceTooLarge)
+    ));
+}
@@ -0,0 +1,331 @@
+#[cfg(feature = "rng")]
+use std::collections::HashSet;
+use std::fmt;
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+#[cfg(feature = "rng")]
+use crate::{Attempts, ReferralCodeError, apply_random_case, check_config, rng::rng_for};
+use crate::{Config, assemble, dedup_key, segment};
+
+/// A secret key signing the codes it generates.
+///
+/// A signed code spends its last `length` freely drawn characters on a
+/// truncated HMAC-SHA256 of the other ones, so a service holding the key
+/// tells codes it issued from made-up ones offline, with
+/// [`verify_signature`], without storing them. Every signature character
+/// divides the odds of guessing a valid code by the size of its alphabet,
+/// and also divides the number of possible codes by as much. Signatures
+/// hold at most 128 bits: characters beyond them add nothing.
+///
+/// The prefix, literals, suffix and format tag are not signed, and OCR
+/// check and checksum characters are computed over the signed code. With
+/// `random_case`, letter case is ignored when signing and verifying.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "rng")] {
+/// use referral_codes::{Config, SigningKey, generate_signed, verify_signature};
+///
+/// let config = Config::default().with_count(3);
+/// let key = SigningKey::new(b"secret".to_vec(), 3);
+///
+/// for code in generate_signed(&config, &key).unwrap() {
+///     assert!(verify_signature(&code, &key, &config));
+///     assert!(!verify_signature(&code, &SigningKey::new(b"other".to_vec(), 3), &config));
+/// }
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SigningKey {
+    key: Vec<u8>,
+    length: usize,
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("length", &self.length)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningKey {
+    /// Creates a key from a secret, signing codes with `length` characters.
+    pub fn new(key: impl Into<Vec<u8>>, length: usize) -> Self {
+        SigningKey {
+            key: key.into(),
+            length,
+        }
+    }
+
+    /// Returns the number of signature characters of a code.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Signs a code, replacing its signature characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration the code was generated with
+    /// * `code` - The code, whose signature characters are ignored
+    ///
+    /// # Returns
+    ///
+    /// * `Some(code)` - The signed code
+    /// * `None` - If the code does not match the configuration, or the
+    ///   signature does not fit the freely drawn characters
+    pub fn sign(&self, config: &Config, code: &str) -> Option<String> {
+        let random = segment::random_chars(config, code)?;
+        let derived = config.derived_positions();
+        let free: Vec<char> = random
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !derived.contains(i))
+            .map(|(_, c)| *c)
+            .collect();
+
+        let signed = self.signed_len(free.len())?;
+
+        Some(assemble(config, self.with_tag(config, &free[..signed])))
+    }
+
+    /// Returns the number of signed characters among `free` freely drawn
+    /// ones, or `None` if the signature does not fit.
+    fn signed_len(&self, free: usize) -> Option<usize> {
+        free.checked_sub(self.length).filter(|_| self.length > 0)
+    }
+
+    /// Returns the characters of every random position from the signed
+    /// characters, with the signature and derived characters filled in.
+    fn with_tag(&self, config: &Config, signed: &[char]) -> Vec<char> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        for c in signed {
+            let mut buf = [0; 4];
+            match config.random_case {
+                Some(_) => c
+                    .to_lowercase()
+                    .for_each(|c| mac.update(c.encode_utf8(&mut buf).as_bytes())),
+                None => mac.update(c.encode_utf8(&mut buf).as_bytes()),
+            }
+        }
+        let bytes: [u8; 32] = mac.finalize().into_bytes().into();
+        let mut value = u128::from_le_bytes(std::array::from_fn(|i| bytes[i]));
+
+        let mut free = signed.to_vec();
+        for alphabet in &segment::free_alphabets(config)[signed.len()..] {
+            free.push(alphabet[(value % alphabet.len() as u128) as usize]);
+            value /= alphabet.len() as u128;
+        }
+
+        segment::with_derived(config, free)
+    }
+}
+
+/// Checks that a code was signed with a key.
+///
+/// The code must match the configuration, including its OCR check and
+/// checksum, and carry the signature of its other characters. The
+/// comparison takes the same time wherever the code differs, so repeated
+/// attempts learn nothing about the signature.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, SigningKey, verify_signature};
+///
+/// let config = Config::default();
+/// let key = SigningKey::new(b"secret".to_vec(), 2);
+/// let code = key.sign(&config, "AbC123xY").unwrap();
+///
+/// assert!(code.starts_with("AbC123"));
+/// assert!(verify_signature(&code, &key, &config));
+/// ```
+pub fn verify_signature(code: &str, key: &SigningKey, config: &Config) -> bool {
+    let Some(signed) = key.sign(config, code) else {
+        return false;
+    };
+    let (signed, code) = (dedup_key(config, &signed), dedup_key(config, code));
+
+    signed.len() == code.len()
+        && signed
+            .bytes()
+            .zip(code.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Generates `config.count` unique codes signed with a key.
+///
+/// Only the characters before the signature are drawn, so the format must
+/// hold `config.count` codes without its signature characters. Reserved
+/// prefixes and `no_repeats` are honored by drawing again, up to
+/// [`Config::max_attempts`] draws.
+///
+/// # Errors
+///
+/// * `ReferralCodeError::InvalidSignatureLength` - If the key signs no
+///   character, or more than the freely drawn ones
+/// * `ReferralCodeError::NonFeasibleConfig` - If the characters before the
+///   signature can't tell `config.count` codes apart
+/// * Any other error of [`crate::generate`]
+#[cfg(feature = "rng")]
+pub fn generate_signed(
+    config: &Config,
+    key: &SigningKey,
+) -> Result<Vec<String>, ReferralCodeError> {
+    check_config(config)?;
+
+    let alphabets = segment::free_alphabets(config);
+    let signed = key
+        .signed_len(alphabets.len())
+        .ok_or(ReferralCodeError::InvalidSignatureLength)?;
+    let messages = alphabets[..signed].iter().try_fold(1u128, |acc, alphabet| {
+        acc.checked_mul(alphabet.len() as u128)
+    });
+    if messages.is_some_and(|messages| messages < config.count as u128) {
+        return Err(ReferralCodeError::NonFeasibleConfig);
+    }
+
+    let derived = config.derived_positions();
+    let classes: Vec<_> = config
+        .pattern
+        .classes()
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !derived.contains(i))
+        .map(|(_, class)| class)
+        .take(signed)
+        .collect();
+
+    let mut rng = rng_for(config);
+    let mut seen = HashSet::with_capacity(config.count);
+    let mut codes = Vec::with_capacity(config.count);
+    let mut attempts = Attempts::among(config, messages, config.count);
+
+    while codes.len() < config.count {
+        if !attempts.take() {
+            return Err(attempts.exceeded(codes));
+        }
+        let drawn: Vec<char> = classes
+            .iter()
+            .map(|class| class.sample(config, &mut rng))
+            .collect();
+        let mut random = key.with_tag(config, &drawn);
+        apply_random_case(config, &mut random, &mut rng);
+
+        let code = assemble(config, random);
+        if config.accepts(&code) && seen.insert(dedup_key(config, &code).into_owned()) {
+            codes.push(code);
+        }
+    }
+
+    Ok(codes)
+}
+
+#[test]
+fn test_signature_rejects_forgeries() {
+    use crate::{Charset, Checksum, OcrCheck, Pattern, verify_checksum, verify_ocr_check};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Pattern("S-######".to_string()),
+        ocr_check: Some(OcrCheck::new(0, 5)),
+        checksum: Some(Checksum::Luhn),
+        ..Config::default()
+    };
+    let key = SigningKey::new(b"issuer".to_vec(), 2);
+
+    // Every code of the format, of which one in a hundred is signed.
+    let mut valid = 0;
+    for n in 0..10_000 {
+        let free: Vec<char> = format!("{n:04}").chars().collect();
+        let code = assemble(&config, segment::with_derived(&config, free));
+        if verify_signature(&code, &key, &config) {
+            valid += 1;
+            assert_eq!(key.sign(&config, &code).unwrap(), code);
+        }
+    }
+    assert_eq!(valid, 100);
+
+    let code = key.sign(&config, "S-0000000").unwrap();
+    assert!(verify_checksum(&code, &config) && verify_ocr_check(&code, &config));
+    assert!(verify_signature(&code, &key, &config));
+    assert!(!verify_signature(
+        &code,
+        &SigningKey::new(b"other".to_vec(), 2),
+        &config
+    ));
+    assert!(!verify_signature(&code[..code.len() - 1], &key, &config));
+    assert!(key.sign(&config, "S-000000").is_none());
+    assert!(
+        SigningKey::new(b"issuer".to_vec(), 5)
+            .sign(&config, &code)
+            .is_none()
+    );
+    assert!(
+        SigningKey::new(b"issuer".to_vec(), 0)
+            .sign(&config, &code)
+            .is_none()
+    );
+    assert!(!format!("{key:?}").contains("issuer"));
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_generate_signed_codes() {
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Alphanumeric,
+        random_case: Some(0.5),
+        count: 200,
+        ..Config::default()
+    };
+    let key = SigningKey::new(b"issuer".to_vec(), 3);
+
+    let codes = generate_signed(&config, &key).unwrap();
+    assert_eq!(
+        codes
+            .iter()
+            .map(|code| code.to_lowercase())
+            .collect::<HashSet<_>>()
+            .len(),
+        200
+    );
+    assert!(
+        codes
+            .iter()
+            .all(|code| verify_signature(code, &key, &config))
+    );
+    assert!(
+        codes
+            .iter()
+            .all(|code| verify_signature(&code.to_uppercase(), &key, &config))
+    );
+
+    let small = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(3),
+        count: 11,
+        ..Config::default()
+    };
+    assert!(matches!(
+        generate_signed(&small, &SigningKey::new(b"k".to_vec(), 2)),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+    assert_eq!(
+        generate_signed(&small.with_count(10), &SigningKey::new(b"k".to_vec(), 2))
+            .unwrap()
+            .len(),
+        10
+    );
+    assert!(matches!(
+        generate_signed(&small, &SigningKey::new(b"k".to_vec(), 4)),
+        Err(ReferralCodeError::InvalidSignatureLength)
+    ));
+}
@@ -0,0 +1,81 @@
+use crate::{Config, verify_checksum, verify_ocr_check};
+
+/// A version of the rules codes are verified by.
+///
+/// Each version fixes how a code is normalized and validated against its
+/// configuration. When a later release of the crate changes these rules, it
+/// adds a version instead of changing an existing one, so codes issued under
+/// older rules can still be checked by the rules they were issued under.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum SpecVersion {
+    /// Codes match the prefix, pattern, charset and suffix exactly, or
+    /// case-insensitively with `random_case`, and carry correct format tag,
+    /// OCR check and checksum characters.
+    V1,
+}
+
+impl SpecVersion {
+    /// The version implemented by this release, used for newly issued codes.
+    pub const LATEST: Self = Self::V1;
+}
+
+/// Verifies a code by the rules of a given specification version.
+///
+/// Record the version codes were issued under, e.g. [`SpecVersion::LATEST`]
+/// at issuance time, and verify with it: upgrading the crate then never
+/// silently starts rejecting codes that were valid when issued.
+///
+/// Verification doesn't allocate for codes of up to 32 random characters,
+/// so it can run on every redemption request.
+///
+/// # Arguments
+///
+/// * `code` - The code to verify
+/// * `config` - The configuration the code was generated with
+/// * `version` - The version of the rules the code was issued under
+///
+/// # Returns
+///
+/// `true` if the code is valid under `version`.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, Pattern, SpecVersion, verify_as};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Pattern("REF-####".to_string()),
+///     ..Config::default()
+/// };
+///
+/// assert!(verify_as("REF-1234", &config, SpecVersion::V1));
+/// assert!(!verify_as("REF-12A4", &config, SpecVersion::LATEST));
+/// ```
+pub fn verify_as(code: &str, config: &Config, version: SpecVersion) -> bool {
+    match version {
+        SpecVersion::V1 => verify_ocr_check(code, config) && verify_checksum(code, config),
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_verify_as_accepts_generated_codes() {
+    use crate::{Checksum, OcrCheck, Pattern};
+
+    let config = Config {
+        pattern: Pattern::Length(10),
+        ocr_check: Some(OcrCheck::new(0, 9)),
+        checksum: Some(Checksum::Iso7064Mod37_36),
+        charset: crate::Charset::Custom("ABCDEFGHJKLMNPQRSTUVWXYZ".to_string()),
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+
+    for code in crate::generate(&config.with_count(50)).unwrap() {
+        assert!(verify_as(&code, &config, SpecVersion::LATEST));
+        assert!(verify_as(&code.to_uppercase(), &config, SpecVersion::V1));
+        assert!(!verify_as(&code[1..], &config, SpecVersion::V1));
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use crate::{Batch, segment};
+
+/// Character and length statistics for a batch of codes.
+///
+/// Character and prefix statistics only consider random positions, since
+/// literals and configured prefixes are the same in every code and would
+/// drown out any bias.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct BatchStats {
+    /// Number of codes in the batch.
+    pub codes: usize,
+    /// Number of occurrences of each character at random positions.
+    pub char_frequency: BTreeMap<char, usize>,
+    /// Number of codes of each length, in characters.
+    pub length_distribution: BTreeMap<usize, usize>,
+    /// Number of codes starting with each character at the first random position.
+    pub prefix_distribution: BTreeMap<char, usize>,
+    /// Number of codes that match neither the batch configuration nor a
+    /// superseded one.
+    ///
+    /// These are only counted in `codes` and `length_distribution`.
+    pub mismatched: usize,
+    /// Number of times the batch's random length was extended.
+    pub length_extensions: usize,
+}
+
+impl Batch {
+    /// Computes character frequency, length and prefix distributions for the batch.
+    ///
+    /// Use this to sanity-check that generation was neither biased nor
+    /// truncated: with a uniform charset, every character should appear at
+    /// roughly the same rate, every code should have the same length, and
+    /// `mismatched` should be zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "rng")] {
+    /// use referral_codes::{Batch, Charset, Config};
+    ///
+    /// let config = Config {
+    ///     charset: Charset::Numeric,
+    ///     count: 100,
+    ///     ..Config::default()
+    /// };
+    /// let batch = Batch::generate("spring", &config).unwrap();
+    ///
+    /// let stats = batch.stats();
+    /// assert_eq!(stats.codes, 100);
+    /// assert_eq!(stats.char_frequency.values().sum::<usize>(), 800);
+    /// assert_eq!(stats.length_distribution.get(&8), Some(&100));
+    /// assert_eq!(stats.mismatched, 0);
+    /// # }
+    /// ```
+    pub fn stats(&self) -> BatchStats {
+        let mut stats = BatchStats {
+            codes: self.codes.len(),
+            length_extensions: self.superseded.len(),
+            ..BatchStats::default()
+        };
+
+        for code in &self.codes {
+            *stats
+                .length_distribution
+                .entry(code.chars().count())
+                .or_default() += 1;
+
+            let random = std::iter::once(&self.config)
+                .chain(self.superseded.iter().rev())
+                .find_map(|config| segment::random_chars(config, code));
+            let Some(random) = random else {
+                stats.mismatched += 1;
+                continue;
+            };
+
+            if let Some(first) = random.first() {
+                *stats.prefix_distribution.entry(*first).or_default() += 1;
+            }
+
+            for &c in random.iter() {
+                *stats.char_frequency.entry(c).or_default() += 1;
+            }
+        }
+
+        stats
+    }
+}
+
+#[test]
+fn test_stats_ignores_literals_and_flags_mismatches() {
+    let config = crate::Config {
+        charset: crate::Charset::Custom("ab".to_string()),
+        pattern: crate::Pattern::Pattern("X#".to_string()),
+        ..crate::Config::default()
+    };
+    let batch = Batch {
+        id: "test".to_string(),
+        config,
+        codes: vec!["Xa".to_string(), "Xb".to_string(), "Xbb".to_string()],
+        superseded: Vec::new(),
+        assignment: None,
+        canaries: Vec::new(),
+        provenance: None,
+    };
+
+    let stats = batch.stats();
+
+    assert_eq!(stats.char_frequency, BTreeMap::from([('a', 1), ('b', 1)]));
+    assert_eq!(
+        stats.prefix_distribution,
+        BTreeMap::from([('a', 1), ('b', 1)])
+    );
+    assert_eq!(stats.length_distribution, BTreeMap::from([(2, 2), (3, 1)]));
+    assert_eq!(stats.mismatched, 1);
+}
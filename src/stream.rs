@@ -0,0 +1,263 @@
+use std::io::{BufWriter, Write};
+
+use crate::{
+    Attempts, Config, ReferralCodeError,
+    bloom::BloomFilter,
+    check_config, dedup_key, generate_one_with,
+    rng::{SourceRng, rng_for},
+};
+
+/// Iterator over unique random codes kept apart by a Bloom filter, see
+/// [`Config::stream`].
+struct Stream {
+    config: Config,
+    rng: SourceRng,
+    filter: BloomFilter,
+    remaining: usize,
+    attempts: Attempts,
+    /// Whether the stream ended early, for lack of draws.
+    spent: bool,
+    /// Draws the filter turned away, duplicates and false positives alike.
+    rejected: usize,
+}
+
+impl Iterator for Stream {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        loop {
+            let Some(code) = generate_one_with(&self.config, &mut self.rng, &mut self.attempts)
+            else {
+                self.remaining = 0;
+                self.spent = true;
+                return None;
+            };
+            if self.filter.insert(&dedup_key(&self.config, &code)) {
+                self.remaining -= 1;
+                return Some(code);
+            }
+            self.rejected += 1;
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl Stream {
+    fn new(config: &Config, false_positive_rate: f64) -> Result<Self, ReferralCodeError> {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(ReferralCodeError::InvalidProbability);
+        }
+        // False positives take their share of the codes never drawn.
+        let needed = (config.count as f64 / (1.0 - false_positive_rate)).ceil() as usize;
+        check_config(&config.with_count(needed.max(config.count)))?;
+
+        Ok(Stream {
+            config: config.clone(),
+            rng: rng_for(config),
+            filter: BloomFilter::new(config.count, false_positive_rate),
+            remaining: config.count,
+            attempts: Attempts::new(config, 0, needed),
+            spent: false,
+            rejected: 0,
+        })
+    }
+}
+
+/// The outcome of a [`generate_to_writer`] run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StreamReport {
+    /// Codes written.
+    pub written: usize,
+    /// Draws turned away as possible duplicates.
+    ///
+    /// Most are real duplicates, the others false positives of the filter.
+    pub rejected: usize,
+    /// The chance that a code never written was taken for a duplicate, by
+    /// the end of the run.
+    pub false_positive_rate: f64,
+}
+
+impl Config {
+    /// Returns an iterator drawing `config.count` unique codes, kept apart
+    /// by a Bloom filter rather than by remembering them.
+    ///
+    /// [`Config::iter`] remembers every code it yields, which does not fit
+    /// in memory for tens of millions of codes. This iterator only keeps a
+    /// Bloom filter sized for `config.count` codes, about 1.44 ×
+    /// log2(1 / `false_positive_rate`) bits per code: around 18 MB for 10
+    /// million codes at 0.1%. Codes are never repeated, but a code never
+    /// drawn is taken for a duplicate with probability up to
+    /// `false_positive_rate`, and drawn again. The codes left out that way
+    /// are random, so a batch is only biased by their share. When they
+    /// leave too few codes to draw, as in tiny keyspaces, the iterator ends
+    /// early, after [`Config::max_attempts`] draws; [`generate_to_writer`]
+    /// reports it.
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::InvalidProbability` - If `false_positive_rate`
+    ///   is not strictly between 0 and 1
+    /// * `ReferralCodeError::NonFeasibleConfig` - If the keyspace can't
+    ///   hold `config.count` codes and the false positives of the filter
+    /// * Any other error of [`crate::generate`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use referral_codes::Config;
+    ///
+    /// let codes: Vec<String> = Config::default()
+    ///     .with_count(1_000)
+    ///     .stream(0.001)
+    ///     .unwrap()
+    ///     .collect();
+    ///
+    /// assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 1_000);
+    /// ```
+    pub fn stream(
+        &self,
+        false_positive_rate: f64,
+    ) -> Result<impl Iterator<Item = String> + use<>, ReferralCodeError> {
+        Stream::new(self, false_positive_rate)
+    }
+}
+
+/// Generates `config.count` unique codes into a writer, one per line, with
+/// memory bounded by a Bloom filter.
+///
+/// Codes are written as they are drawn, as [`Config::stream`] yields them,
+/// so they come in random order, unlike with [`crate::generate_external`],
+/// and nothing but the filter is held in memory. The writer is buffered.
+///
+/// # Returns
+///
+/// * `Ok(report)` - The number of codes written and of draws turned away
+/// * `Err(ReferralCodeError::CollisionBudgetExceeded)` - If
+///   [`Config::max_attempts`] draws did not give `config.count` codes the
+///   filter took for new, once the codes drawn until then were written
+/// * `Err(ReferralCodeError::Io)` - If writing the output fails
+/// * `Err(ReferralCodeError)` - The same errors as [`Config::stream`]
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Config, generate_to_writer};
+///
+/// let mut output = Vec::new();
+/// let report = generate_to_writer(&Config::default().with_count(1_000), 0.001, &mut output).unwrap();
+///
+/// assert_eq!(report.written, 1_000);
+/// assert!(report.false_positive_rate < 0.002);
+/// assert_eq!(String::from_utf8(output).unwrap().lines().count(), 1_000);
+/// ```
+pub fn generate_to_writer<W: Write>(
+    config: &Config,
+    false_positive_rate: f64,
+    writer: W,
+) -> Result<StreamReport, ReferralCodeError> {
+    let mut stream = Stream::new(config, false_positive_rate)?;
+    let mut writer = BufWriter::new(writer);
+    let mut written = 0;
+
+    for code in stream.by_ref() {
+        writeln!(writer, "{code}")?;
+        written += 1;
+    }
+    writer.flush()?;
+    if stream.spent {
+        // The codes drawn were written rather than kept.
+        return Err(stream.attempts.exceeded(Vec::new()));
+    }
+
+    Ok(StreamReport {
+        written,
+        rejected: stream.rejected,
+        false_positive_rate: stream.filter.false_positive_rate(),
+    })
+}
+
+#[test]
+fn test_stream_never_repeats_codes() {
+    use std::collections::HashSet;
+
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Custom("ABCDabcd".to_string()),
+        pattern: Pattern::Length(4),
+        random_case: Some(0.5),
+        count: 200,
+        ..Config::default()
+    };
+
+    let mut output = Vec::new();
+    let report = generate_to_writer(&config, 0.01, &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let keys: HashSet<String> = output.lines().map(|code| code.to_lowercase()).collect();
+
+    // 200 of the 256 caseless codes leave many draws to turn away.
+    assert_eq!(report.written, 200);
+    assert_eq!(keys.len(), 200);
+    assert!(report.rejected > 0);
+    assert!(report.false_positive_rate > 0.0 && report.false_positive_rate < 0.05);
+
+    assert_eq!(config.stream(0.01).unwrap().count(), 200);
+    assert!(matches!(
+        config.stream(0.0),
+        Err(ReferralCodeError::InvalidProbability)
+    ));
+    assert!(matches!(
+        config.with_count(255).stream(0.01),
+        Err(ReferralCodeError::NonFeasibleConfig)
+    ));
+}
+
+#[test]
+fn test_stream_gives_up_in_a_saturated_keyspace() {
+    use std::collections::HashSet;
+
+    use crate::{Charset, Pattern};
+
+    // Once the filter holds most digits, the last ones left are likely
+    // false positives, which used to be drawn forever.
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(1),
+        count: 9,
+        ..Config::default()
+    };
+    for _ in 0..20 {
+        let codes: Vec<String> = config.stream(0.1).unwrap().collect();
+        assert!(codes.len() <= 9);
+        assert_eq!(codes.iter().collect::<HashSet<_>>().len(), codes.len());
+
+        let mut output = Vec::new();
+        match generate_to_writer(&config, 0.1, &mut output) {
+            Ok(report) => assert_eq!(report.written, 9),
+            Err(ReferralCodeError::CollisionBudgetExceeded { attempts, .. }) => {
+                assert_eq!(attempts, 1_000);
+                assert!(String::from_utf8(output).unwrap().lines().count() < 9);
+            }
+            Err(error) => panic!("unexpected {error:?}"),
+        }
+    }
+
+    let limited = Config {
+        max_attempts: Some(3),
+        ..config
+    };
+    let mut output = Vec::new();
+    assert!(matches!(
+        generate_to_writer(&limited, 0.1, &mut output),
+        Err(ReferralCodeError::CollisionBudgetExceeded { attempts: 3, .. })
+    ));
+    assert!(String::from_utf8(output).unwrap().lines().count() <= 3);
+}
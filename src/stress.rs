@@ -0,0 +1,218 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    Attempts, Config, ReferralCodeError, Reservation, UniquenessStore, check_config, dedup_key,
+    generate_one_with, reservation::reserve, rng::rng_for,
+};
+
+/// Sub-buckets per power of two of the latency histogram.
+///
+/// Percentiles are reported within 1 / `SUB_BUCKETS` of the true latency.
+const SUB_BUCKETS: u64 = 8;
+
+/// The outcome of a [`stress`] run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StressReport {
+    /// Codes that were reserved in the store.
+    pub issued: u64,
+    /// Candidates the store reported as already taken.
+    pub collisions: u64,
+    /// How long the run lasted.
+    pub elapsed: Duration,
+    /// Median latency of issuing a code, collisions included.
+    pub p50: Duration,
+    /// 90th percentile latency of issuing a code.
+    pub p90: Duration,
+    /// 99th percentile latency of issuing a code.
+    pub p99: Duration,
+    /// 99.9th percentile latency of issuing a code.
+    pub p999: Duration,
+    /// Highest latency of issuing a code.
+    pub max: Duration,
+}
+
+impl StressReport {
+    /// Returns the fraction of candidates that collided with a reserved code.
+    pub fn collision_rate(&self) -> f64 {
+        match self.issued + self.collisions {
+            0 => 0.0,
+            attempts => self.collisions as f64 / attempts as f64,
+        }
+    }
+
+    /// Returns the number of codes issued per second.
+    pub fn throughput(&self) -> f64 {
+        self.issued as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// A log-linear histogram of latencies in nanoseconds, of constant size.
+#[derive(Default)]
+struct Histogram {
+    counts: Vec<u64>,
+    total: u64,
+    max: u64,
+}
+
+impl Histogram {
+    fn record(&mut self, nanos: u64) {
+        let bucket = bucket(nanos);
+        if self.counts.len() <= bucket {
+            self.counts.resize(bucket + 1, 0);
+        }
+
+        self.counts[bucket] += 1;
+        self.total += 1;
+        self.max = self.max.max(nanos);
+    }
+
+    /// Returns the lower bound of the bucket holding the `q` quantile.
+    fn quantile(&self, q: f64) -> Duration {
+        let rank = ((self.total as f64 * q).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_nanos(lower_bound(bucket).min(self.max));
+            }
+        }
+
+        Duration::from_nanos(self.max)
+    }
+}
+
+fn bucket(nanos: u64) -> usize {
+    if nanos < 2 * SUB_BUCKETS {
+        return nanos as usize;
+    }
+
+    let exponent = u64::from(nanos.ilog2());
+    let sub = (nanos >> (exponent - SUB_BUCKETS.ilog2() as u64)) - SUB_BUCKETS;
+
+    (2 * SUB_BUCKETS + (exponent - SUB_BUCKETS.ilog2() as u64 - 1) * SUB_BUCKETS + sub) as usize
+}
+
+fn lower_bound(bucket: usize) -> u64 {
+    let bucket = bucket as u64;
+    if bucket < 2 * SUB_BUCKETS {
+        return bucket;
+    }
+
+    let shift = (bucket - 2 * SUB_BUCKETS) / SUB_BUCKETS + 1;
+    let sub = (bucket - 2 * SUB_BUCKETS) % SUB_BUCKETS;
+
+    (SUB_BUCKETS + sub) << shift
+}
+
+/// Issues codes continuously into a store for a given duration.
+///
+/// Use this to validate a format and store combination before launch:
+/// candidates are generated and reserved one after another, exactly as
+/// [`crate::generate_reserved`] does, until `duration` has elapsed, and the
+/// report tells how often the store was hit with a taken code and how long
+/// issuing took, from drawing the candidate to the store's answer. A rising
+/// collision rate means the keyspace is filling up; high percentiles point
+/// at the store.
+///
+/// Run it against a staging copy of the store: every issued code stays
+/// reserved. Latencies are kept in a histogram of constant size, so runs
+/// can last for hours.
+///
+/// # Arguments
+///
+/// * `config` - Configuration specifying the pattern and charset of issued codes
+/// * `store` - The store codes are reserved in
+/// * `duration` - How long to keep issuing codes
+///
+/// # Errors
+///
+/// Returns the same errors as [`crate::generate`] for a single code, and
+/// `ReferralCodeError::Store` if the store fails.
+///
+/// # Examples
+///
+/// ```
+/// use std::{collections::HashSet, sync::Mutex, time::Duration};
+/// use referral_codes::{Config, stress};
+///
+/// let store = Mutex::new(HashSet::new());
+/// let report = stress(&Config::default(), &store, Duration::from_millis(20)).unwrap();
+///
+/// assert_eq!(store.lock().unwrap().len() as u64, report.issued);
+/// assert!(report.p50 <= report.p99 && report.p99 <= report.max);
+/// ```
+pub fn stress<S: UniquenessStore + ?Sized>(
+    config: &Config,
+    store: &S,
+    duration: Duration,
+) -> Result<StressReport, ReferralCodeError> {
+    let config = config.with_count(1);
+    check_config(&config)?;
+
+    let mut histogram = Histogram::default();
+    let mut issued = 0;
+    let mut collisions = 0;
+    let mut rng = rng_for(&config);
+    let budget = Attempts::new(&config, 0, 1);
+    let start = Instant::now();
+
+    while start.elapsed() < duration {
+        let attempt = Instant::now();
+        let mut attempts = budget;
+        let Some(code) = generate_one_with(&config, &mut rng, &mut attempts) else {
+            return Err(attempts.exceeded(Vec::new()));
+        };
+        let reservation = reserve(store, &dedup_key(&config, &code))?;
+        histogram.record(attempt.elapsed().as_nanos().try_into().unwrap_or(u64::MAX));
+
+        match reservation {
+            Reservation::Reserved => issued += 1,
+            Reservation::Taken => collisions += 1,
+        }
+    }
+
+    Ok(StressReport {
+        issued,
+        collisions,
+        elapsed: start.elapsed(),
+        p50: histogram.quantile(0.5),
+        p90: histogram.quantile(0.9),
+        p99: histogram.quantile(0.99),
+        p999: histogram.quantile(0.999),
+        max: Duration::from_nanos(histogram.max),
+    })
+}
+
+#[test]
+fn test_histogram_buckets_are_ordered_and_tight() {
+    let mut previous = 0;
+
+    for nanos in (0..100_000).chain([u64::MAX / 3, u64::MAX]) {
+        let bucket = bucket(nanos);
+        assert!(bucket >= previous);
+        assert!(lower_bound(bucket) <= nanos);
+        assert!(nanos - lower_bound(bucket) <= nanos / SUB_BUCKETS);
+        previous = bucket;
+    }
+}
+
+#[test]
+fn test_stress_reports_collisions_of_a_full_keyspace() {
+    use std::{collections::HashSet, sync::Mutex};
+
+    use crate::{Charset, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(1),
+        ..Config::default()
+    };
+    let store = Mutex::new(HashSet::new());
+
+    let report = stress(&config, &store, Duration::from_millis(20)).unwrap();
+
+    assert_eq!(report.issued, 10);
+    assert!(report.collisions > 0);
+    assert!(report.collision_rate() > 0.5);
+}
@@ -0,0 +1,306 @@
+use alloc::string::ToString;
+
+use crate::{Charset, Config, Pattern, ReferralCodeError, Set, check_format};
+
+/// Size limits applied to untrusted input.
+///
+/// Formats accepted from outside, e.g. through an admin API open to
+/// customers, should go through [`Pattern::parse_untrusted`],
+/// [`Charset::parse_untrusted`] and [`Config::check_untrusted`], so that a
+/// malicious value can neither exhaust memory nor make generation run for
+/// an unbounded time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of characters in a pattern.
+    pub max_pattern_len: usize,
+    /// Maximum number of characters in a custom charset.
+    pub max_charset_len: usize,
+    /// Maximum number of characters in a prefix, suffix, reserved prefix or
+    /// blocked word.
+    pub max_affix_len: usize,
+    /// Maximum number of reserved prefixes.
+    pub max_reserved_prefixes: usize,
+    /// Maximum number of blocked words.
+    pub max_blocked_words: usize,
+    /// Maximum number of codes requested at once.
+    pub max_count: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_pattern_len: 64,
+            max_charset_len: 256,
+            max_affix_len: 64,
+            max_reserved_prefixes: 1024,
+            max_blocked_words: 1024,
+            max_count: 1_000_000,
+        }
+    }
+}
+
+fn check_len(field: &'static str, len: usize, limit: usize) -> Result<(), ReferralCodeError> {
+    match len <= limit {
+        true => Ok(()),
+        false => Err(ReferralCodeError::TooLarge { field, limit }),
+    }
+}
+
+/// Checks that `s` is short enough and contains no control characters.
+fn check_text(field: &'static str, s: &str, limit: usize) -> Result<(), ReferralCodeError> {
+    // Counting bytes first bounds the work done on huge inputs.
+    if s.len() > limit.saturating_mul(4) {
+        return Err(ReferralCodeError::TooLarge { field, limit });
+    }
+    check_len(field, s.chars().count(), limit)?;
+
+    match s.chars().find(|c| c.is_control()) {
+        Some(c) => Err(ReferralCodeError::InvalidCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+impl Pattern {
+    /// Parses a shorthand template from an untrusted source.
+    ///
+    /// This is [`Pattern::parse`] with size limits and without control
+    /// characters.
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::EmptyPattern` - If the template is empty
+    /// * `ReferralCodeError::TooLarge` - If the template is longer than
+    ///   `limits.max_pattern_len`
+    /// * `ReferralCodeError::InvalidCharacter` - If the template contains a
+    ///   control character
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Limits, Pattern};
+    ///
+    /// let limits = Limits::default();
+    ///
+    /// assert!(Pattern::parse_untrusted("AA-99-##", &limits).is_ok());
+    /// assert!(Pattern::parse_untrusted(&"#".repeat(1_000), &limits).is_err());
+    /// assert!(Pattern::parse_untrusted("AA\n99", &limits).is_err());
+    /// ```
+    pub fn parse_untrusted(template: &str, limits: &Limits) -> Result<Self, ReferralCodeError> {
+        check_text("pattern", template, limits.max_pattern_len)?;
+        Pattern::parse(template)
+    }
+}
+
+impl Charset {
+    /// Parses a custom charset from an untrusted source.
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::EmptyCharset` - If `chars` is empty
+    /// * `ReferralCodeError::TooLarge` - If `chars` is longer than
+    ///   `limits.max_charset_len`
+    /// * `ReferralCodeError::InvalidCharacter` - If `chars` contains a control
+    ///   or whitespace character, or repeats a character
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Charset, Limits};
+    ///
+    /// let limits = Limits::default();
+    ///
+    /// assert!(Charset::parse_untrusted("ABCDEF0123", &limits).is_ok());
+    /// assert!(Charset::parse_untrusted("ABCA", &limits).is_err());
+    /// assert!(Charset::parse_untrusted("AB C", &limits).is_err());
+    /// ```
+    pub fn parse_untrusted(chars: &str, limits: &Limits) -> Result<Self, ReferralCodeError> {
+        check_charset(chars, limits)?;
+        Ok(Charset::Custom(chars.to_string()))
+    }
+}
+
+fn check_charset(chars: &str, limits: &Limits) -> Result<(), ReferralCodeError> {
+    if chars.is_empty() {
+        return Err(ReferralCodeError::EmptyCharset);
+    }
+    check_text("charset", chars, limits.max_charset_len)?;
+
+    let mut seen = Set::new();
+    match chars
+        .chars()
+        .find(|c| c.is_whitespace() || !seen.insert(*c))
+    {
+        Some(c) => Err(ReferralCodeError::InvalidCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that a pattern is not empty, short enough and contains no
+/// control characters, and returns its length.
+///
+/// The segments of a pattern and their charsets are checked one by one,
+/// and their lengths add up.
+fn check_pattern(pattern: &Pattern, limits: &Limits) -> Result<usize, ReferralCodeError> {
+    let len = match pattern {
+        Pattern::Length(n) => *n,
+        Pattern::Pattern(s) | Pattern::Template(s) => {
+            check_text("pattern", s, limits.max_pattern_len)?;
+            s.chars().count()
+        }
+        Pattern::WithPlaceholder(s, placeholder) => {
+            if placeholder.is_control() {
+                return Err(ReferralCodeError::InvalidCharacter(*placeholder));
+            }
+            check_text("pattern", s, limits.max_pattern_len)?;
+            s.chars().count()
+        }
+        Pattern::Segments(segments) => {
+            check_len("pattern", segments.len(), limits.max_pattern_len)?;
+            let mut len = 0;
+            for (pattern, charset) in segments {
+                len += check_pattern(pattern, limits)?;
+                check_len("pattern", len, limits.max_pattern_len)?;
+                if let Charset::Custom(_) | Charset::Weighted(_) = charset {
+                    check_charset(&charset.as_str(), limits)?;
+                }
+            }
+            len
+        }
+    };
+
+    match len {
+        0 => Err(ReferralCodeError::EmptyPattern),
+        len => check_len("pattern", len, limits.max_pattern_len).map(|_| len),
+    }
+}
+
+impl Config {
+    /// Checks a configuration built from untrusted input.
+    ///
+    /// Every field is checked against `limits` and for control characters,
+    /// and the configuration must be consistent: OCR check positions must fit
+    /// the pattern, the checksum must accept what it covers and `random_case`
+    /// must be a probability. Feasibility is
+    /// not checked; [`crate::generate`] still reports it.
+    ///
+    /// # Errors
+    ///
+    /// * `ReferralCodeError::TooLarge` - If a field exceeds its limit
+    /// * `ReferralCodeError::InvalidCharacter` - If a field contains a
+    ///   character it may not contain
+    /// * `ReferralCodeError::EmptyPattern` or `ReferralCodeError::EmptyCharset` -
+    ///   If the pattern or a custom charset is empty
+    /// * `ReferralCodeError::PlaceholderInCharset` - If a custom charset
+    ///   contains the placeholder of the pattern
+    /// * `ReferralCodeError::InvalidCheckPositions` - If the OCR check
+    ///   positions do not fit the pattern
+    /// * `ReferralCodeError::InvalidFormatTag` - If the format tag does not fit
+    ///   the configuration
+    /// * `ReferralCodeError::InvalidExpiry` - If the expiry does not fit the
+    ///   configuration
+    /// * `ReferralCodeError::InvalidFields` - If the embedded fields do not fit
+    ///   the configuration
+    /// * `ReferralCodeError::InvalidFormatting` - If the formatting does not
+    ///   fit the configuration
+    /// * `ReferralCodeError::IncompatibleChecksum` - If the checksum cannot be
+    ///   computed over the characters it covers
+    /// * `ReferralCodeError::InvalidProbability` - If `random_case` is not
+    ///   between 0 and 1
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use referral_codes::{Config, Limits, Pattern};
+    ///
+    /// let limits = Limits::default();
+    ///
+    /// assert!(Config::default().check_untrusted(&limits).is_ok());
+    /// assert!(Config::default().with_count(usize::MAX).check_untrusted(&limits).is_err());
+    /// assert!(Config::default()
+    ///     .with_pattern(Pattern::Length(usize::MAX))
+    ///     .check_untrusted(&limits)
+    ///     .is_err());
+    /// ```
+    pub fn check_untrusted(&self, limits: &Limits) -> Result<(), ReferralCodeError> {
+        check_pattern(&self.pattern, limits)?;
+
+        if let Charset::Custom(_) | Charset::Weighted(_) = &self.charset {
+            check_charset(&self.charset.as_str(), limits)?;
+        }
+
+        for (field, affix) in [("prefix", &self.prefix), ("suffix", &self.suffix)] {
+            if let Some(affix) = affix {
+                check_text(field, affix, limits.max_affix_len)?;
+            }
+        }
+
+        check_len(
+            "reserved prefixes",
+            self.reserved_prefixes.len(),
+            limits.max_reserved_prefixes,
+        )?;
+        for prefix in &self.reserved_prefixes {
+            check_text("reserved prefix", prefix, limits.max_affix_len)?;
+        }
+
+        check_len(
+            "blocked words",
+            self.blocked_words.len(),
+            limits.max_blocked_words,
+        )?;
+        for word in &self.blocked_words {
+            check_text("blocked word", word, limits.max_affix_len)?;
+        }
+
+        check_len("count", self.count, limits.max_count)?;
+
+        if let Some(group) = self.formatting.group
+            && group.separator.is_control()
+        {
+            return Err(ReferralCodeError::InvalidCharacter(group.separator));
+        }
+
+        check_format(self)
+    }
+}
+
+#[test]
+fn test_check_untrusted_rejects_hostile_fields() {
+    let limits = Limits::default();
+    let config = Config::default();
+
+    let hostile = [
+        Config {
+            charset: Charset::Custom(String::new()),
+            ..config.clone()
+        },
+        Config {
+            prefix: Some("\u{1b}[2J".to_string()),
+            ..config.clone()
+        },
+        Config {
+            suffix: Some("x".repeat(limits.max_affix_len + 1)),
+            ..config.clone()
+        },
+        Config {
+            reserved_prefixes: vec![String::new(); limits.max_reserved_prefixes + 1],
+            ..config.clone()
+        },
+        Config {
+            blocked_words: vec!["\0".to_string()],
+            ..config.clone()
+        },
+        Config {
+            random_case: Some(f64::NAN),
+            ..config.clone()
+        },
+        Config {
+            ocr_check: Some(crate::OcrCheck::new(0, 100)),
+            ..config.clone()
+        },
+    ];
+
+    for config in hostile {
+        assert!(config.check_untrusted(&limits).is_err(), "{config:?}");
+    }
+}
@@ -0,0 +1,231 @@
+use core::{error::Error, fmt};
+
+use crate::{Config, SpecVersion, checksum::Checksum, verify_as};
+
+/// Why a code does not match a configuration, see [`validate`].
+///
+/// Positions count characters from the start of the code, prefix included,
+/// starting at 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// The code does not begin with the prefix.
+    MissingPrefix,
+    /// The code does not end with the suffix.
+    MissingSuffix,
+    /// The code has another number of characters than the format.
+    WrongLength {
+        /// The number of characters of codes of the format.
+        expected: usize,
+        /// The number of characters of the code.
+        found: usize,
+    },
+    /// A literal of the pattern holds another character.
+    LiteralMismatch {
+        /// The position of the literal.
+        position: usize,
+        /// The literal of the pattern.
+        expected: char,
+        /// The character of the code.
+        found: char,
+    },
+    /// A random position holds a character it can't be drawn from.
+    NotInCharset {
+        /// The random position.
+        position: usize,
+        /// The character of the code.
+        found: char,
+    },
+    /// The code has the right shape, but a wrong format tag, OCR check or
+    /// checksum character.
+    BadCheck,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPrefix => f.write_str("Missing prefix"),
+            Self::MissingSuffix => f.write_str("Missing suffix"),
+            Self::WrongLength { expected, found } => {
+                write!(f, "Code has {found} characters instead of {expected}")
+            }
+            Self::LiteralMismatch {
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Expected {expected:?} at position {position}, found {found:?}"
+            ),
+            Self::NotInCharset { position, found } => {
+                write!(f, "{found:?} at position {position} is not in the charset")
+            }
+            Self::BadCheck => f.write_str("Wrong check character"),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Checks that a code matches a configuration, telling where it does not.
+///
+/// The code must begin with the prefix and end with the suffix, have as
+/// many characters as the format, hold the literals of the pattern, and
+/// hold characters of their class at random positions, ignoring case with
+/// `random_case`. A code of the right shape must also have the right format
+/// tag, OCR check and checksum characters, as [`crate::verify_as`] checks
+/// under the latest [`SpecVersion`].
+///
+/// This answers the same question as [`crate::Verifier::verify`], with
+/// details to show to whoever typed the code. Use it to reject malformed
+/// codes before looking them up in a database. The code is first
+/// normalized with [`Config::normalize_code`], and positions count the
+/// characters of the normalized code.
+///
+/// # Errors
+///
+/// Returns the first difference found, from the start of the code.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, Pattern, ValidationError, validate};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Pattern("REF-####".to_string()),
+///     ..Config::default()
+/// };
+///
+/// assert_eq!(validate("REF-1234", &config), Ok(()));
+/// assert_eq!(
+///     validate("REF-12A4", &config),
+///     Err(ValidationError::NotInCharset { position: 6, found: 'A' })
+/// );
+/// assert_eq!(
+///     validate("REF_1234", &config),
+///     Err(ValidationError::LiteralMismatch { position: 3, expected: '-', found: '_' })
+/// );
+/// assert_eq!(
+///     validate("REF-123", &config),
+///     Err(ValidationError::WrongLength { expected: 8, found: 7 })
+/// );
+/// ```
+pub fn validate(code: &str, config: &Config) -> Result<(), ValidationError> {
+    let code = &config.normalize_code(code);
+    let prefix = config.prefix.as_deref().unwrap_or("");
+    let suffix = config.suffix.as_deref().unwrap_or("");
+    let body = code
+        .strip_prefix(prefix)
+        .ok_or(ValidationError::MissingPrefix)?;
+
+    let checksum = config.checksum.as_ref().map_or(0, Checksum::len);
+    let expected =
+        prefix.chars().count() + config.pattern.chars().count() + checksum + suffix.chars().count();
+    let found = code.chars().count();
+    if found != expected {
+        return Err(ValidationError::WrongLength { expected, found });
+    }
+
+    let body = body
+        .strip_suffix(suffix)
+        .ok_or(ValidationError::MissingSuffix)?;
+
+    let offset = prefix.chars().count();
+    for (position, ((p, class), c)) in config.pattern.chars().zip(body.chars()).enumerate() {
+        let position = offset + position;
+        match class {
+            Some(class) => {
+                if class
+                    .canonical(config, c, config.random_case.is_some())
+                    .is_none()
+                {
+                    return Err(ValidationError::NotInCharset { position, found: c });
+                }
+            }
+            None if c == p => {}
+            None => {
+                return Err(ValidationError::LiteralMismatch {
+                    position,
+                    expected: p,
+                    found: c,
+                });
+            }
+        }
+    }
+
+    match verify_as(code, config, SpecVersion::LATEST) {
+        true => Ok(()),
+        false => Err(ValidationError::BadCheck),
+    }
+}
+
+#[test]
+fn test_validate_reports_the_failing_position() {
+    use crate::{Charset, Checksum, OcrCheck, Pattern, assemble, segment};
+
+    let config = Config {
+        charset: Charset::Custom("ABCD".to_string()),
+        pattern: Pattern::Pattern("#-##".to_string()),
+        prefix: Some("P".to_string()),
+        suffix: Some("!".to_string()),
+        ocr_check: Some(OcrCheck::new(0, 2)),
+        checksum: Some(Checksum::LuhnMod36),
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+    let code = assemble(&config, segment::with_derived(&config, ['A']));
+    let with = |position: usize, c: char| -> String {
+        let mut chars: Vec<char> = code.chars().collect();
+        chars[position] = c;
+        chars.into_iter().collect()
+    };
+
+    assert_eq!(validate(&code, &config), Ok(()));
+    assert_eq!(
+        validate(&code.to_lowercase().replace('p', "P"), &config),
+        Ok(())
+    );
+    assert_eq!(
+        validate(&code[1..], &config),
+        Err(ValidationError::MissingPrefix)
+    );
+    assert_eq!(
+        validate(&format!("{}?", &code[..code.len() - 1]), &config),
+        Err(ValidationError::MissingSuffix)
+    );
+    assert_eq!(
+        validate(&format!("{code}!"), &config),
+        Err(ValidationError::WrongLength {
+            expected: 7,
+            found: 8
+        })
+    );
+    assert_eq!(
+        validate(&with(2, '+'), &config),
+        Err(ValidationError::LiteralMismatch {
+            position: 2,
+            expected: '-',
+            found: '+'
+        })
+    );
+    assert_eq!(
+        validate(&with(3, 'E'), &config),
+        Err(ValidationError::NotInCharset {
+            position: 3,
+            found: 'E'
+        })
+    );
+    assert_eq!(
+        validate(&with(3, 'B'), &config),
+        Err(ValidationError::BadCheck)
+    );
+    assert_eq!(
+        ValidationError::NotInCharset {
+            position: 1,
+            found: 'E'
+        }
+        .to_string(),
+        "'E' at position 1 is not in the charset"
+    );
+}
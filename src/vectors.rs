@@ -0,0 +1,237 @@
+use crate::{
+    Config, ReferralCodeError, SpecVersion, generate_with,
+    rng::{CodeRng, SeedableRng, StdRng},
+    segment::{self, Slot},
+    verify_as,
+};
+
+/// How a [`TestVector`] was made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum VectorKind {
+    /// A valid code of the configuration.
+    Valid,
+    /// A valid code with one random character replaced.
+    ///
+    /// The replacement is drawn from the position's own alphabet when the
+    /// format tag, OCR check or checksum catches it, and from outside of it
+    /// otherwise.
+    OneCharOff,
+    /// A valid code with one check character replaced.
+    BadChecksum,
+    /// A valid code with one character dropped or doubled.
+    WrongLength,
+}
+
+/// A code for seeding test fixtures along with whether it should verify.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TestVector {
+    /// The code.
+    pub code: String,
+    /// How the code was made.
+    pub kind: VectorKind,
+}
+
+impl TestVector {
+    /// Returns `true` if the code verifies under its configuration.
+    pub fn is_valid(&self) -> bool {
+        self.kind == VectorKind::Valid
+    }
+}
+
+/// Generates `n` valid codes of a configuration, each followed by near-miss
+/// invalid codes derived from it.
+///
+/// Every valid code is followed by a [`VectorKind::OneCharOff`] and a
+/// [`VectorKind::WrongLength`] code, and, with a checksum, a
+/// [`VectorKind::BadChecksum`] code. Near misses are only kept if they fail
+/// verification by [`SpecVersion::LATEST`]. The same seed and configuration
+/// always produce the same vectors with a given version of the crate, so
+/// fixtures can be regenerated instead of checked in.
+///
+/// # Errors
+///
+/// Returns the same errors as [`crate::generate`] for `n` codes.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Checksum, Config, SpecVersion, test_vectors, verify_as};
+///
+/// let config = Config {
+///     checksum: Some(Checksum::Iso7064Mod37_36),
+///     ..Config::default()
+/// };
+/// let vectors = test_vectors(&config, 5, 7).unwrap();
+///
+/// assert_eq!(vectors, test_vectors(&config, 5, 7).unwrap());
+/// assert_eq!(vectors.iter().filter(|v| v.is_valid()).count(), 5);
+/// for vector in &vectors {
+///     assert_eq!(
+///         verify_as(&vector.code, &config, SpecVersion::LATEST),
+///         vector.is_valid()
+///     );
+/// }
+/// ```
+pub fn test_vectors(
+    config: &Config,
+    n: usize,
+    seed: u64,
+) -> Result<Vec<TestVector>, ReferralCodeError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let codes = generate_with(&config.with_count(n), &mut rng)?;
+    let slots = segment::slots(config);
+    let mut vectors = Vec::with_capacity(n * 4);
+
+    for code in codes {
+        let chars: Vec<char> = code.chars().collect();
+        let near_misses = [
+            (
+                VectorKind::OneCharOff,
+                one_char_off(config, &slots, &chars, &mut rng),
+            ),
+            (
+                VectorKind::BadChecksum,
+                bad_checksum(config, &slots, &chars, &mut rng),
+            ),
+            (VectorKind::WrongLength, wrong_length(&chars, &mut rng)),
+        ];
+
+        vectors.push(TestVector {
+            code,
+            kind: VectorKind::Valid,
+        });
+        for (kind, near_miss) in near_misses {
+            if let Some(code) = near_miss.filter(|c| !verify_as(c, config, SpecVersion::LATEST)) {
+                vectors.push(TestVector { code, kind });
+            }
+        }
+    }
+
+    Ok(vectors)
+}
+
+/// Replaces a random character of a code, keeping it in its alphabet if
+/// the code is caught anyway.
+fn one_char_off<R: CodeRng + ?Sized>(
+    config: &Config,
+    slots: &[Slot],
+    chars: &[char],
+    rng: &mut R,
+) -> Option<String> {
+    let random: Vec<usize> = (0..slots.len())
+        .filter(|i| matches!(slots[*i], Slot::Random(_)))
+        .collect();
+    let i = *random.get(rng.index(random.len().max(1)))?;
+    let Slot::Random(class) = slots[i] else {
+        return None;
+    };
+    let alphabet = segment::distinct_alphabet(config, class);
+    let same = |a: char, b: char| match config.random_case {
+        Some(_) => a.to_lowercase().eq(b.to_lowercase()),
+        None => a == b,
+    };
+
+    let others: Vec<char> = alphabet
+        .iter()
+        .copied()
+        .filter(|c| !same(*c, chars[i]))
+        .collect();
+    let mut replaced = chars.to_vec();
+    if !others.is_empty() {
+        replaced[i] = others[rng.index(others.len())];
+        let code: String = replaced.iter().collect();
+        if !verify_as(&code, config, SpecVersion::LATEST) {
+            return Some(code);
+        }
+    }
+
+    replaced[i] = ('!'..='~').find(|c| !alphabet.iter().any(|a| same(*a, *c)))?;
+    Some(replaced.into_iter().collect())
+}
+
+/// Replaces a check character of a code, if it has a checksum.
+fn bad_checksum<R: CodeRng + ?Sized>(
+    config: &Config,
+    slots: &[Slot],
+    chars: &[char],
+    rng: &mut R,
+) -> Option<String> {
+    let checksum = config.checksum.as_ref()?;
+    let start = slots.iter().position(|slot| *slot == Slot::Check)?;
+    let i = start + rng.index(checksum.len());
+
+    let others: Vec<char> = checksum
+        .output_alphabet()
+        .chars()
+        .filter(|c| !c.eq_ignore_ascii_case(&chars[i]))
+        .collect();
+    let mut replaced = chars.to_vec();
+    replaced[i] = others[rng.index(others.len())];
+
+    Some(replaced.into_iter().collect())
+}
+
+/// Drops or doubles a random character of a code.
+fn wrong_length<R: CodeRng + ?Sized>(chars: &[char], rng: &mut R) -> Option<String> {
+    if chars.is_empty() {
+        return None;
+    }
+
+    let i = rng.index(chars.len());
+    let mut changed = chars.to_vec();
+    if rng.chance(0.5) {
+        changed.remove(i);
+    } else {
+        changed.insert(i, chars[i]);
+    }
+
+    Some(changed.into_iter().collect())
+}
+
+#[test]
+fn test_vectors_are_stable_and_classified() {
+    use crate::{Charset, Checksum, OcrCheck, Pattern};
+
+    let config = Config {
+        charset: Charset::Custom("ABCDEFGHJKLMNPQRSTUVWXYZ".to_string()),
+        pattern: Pattern::Pattern("REF-######".to_string()),
+        ocr_check: Some(OcrCheck::new(0, 5)),
+        checksum: Some(Checksum::Iso7064Mod37_36),
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+    let vectors = test_vectors(&config, 30, 1).unwrap();
+
+    assert_eq!(vectors, test_vectors(&config, 30, 1).unwrap());
+    assert_ne!(vectors, test_vectors(&config, 30, 2).unwrap());
+    for kind in [
+        VectorKind::Valid,
+        VectorKind::OneCharOff,
+        VectorKind::BadChecksum,
+        VectorKind::WrongLength,
+    ] {
+        assert!(vectors.iter().filter(|v| v.kind == kind).count() >= 25);
+    }
+    for vector in &vectors {
+        assert_eq!(
+            verify_as(&vector.code, &config, SpecVersion::LATEST),
+            vector.is_valid(),
+            "{vector:?}"
+        );
+    }
+
+    // Without any check, a substitution has to leave the alphabet.
+    let unchecked = Config {
+        charset: Charset::Numeric,
+        ..Config::default()
+    };
+    let vectors = test_vectors(&unchecked, 10, 1).unwrap();
+    assert!(
+        vectors
+            .iter()
+            .filter(|v| v.kind == VectorKind::OneCharOff)
+            .all(|v| v.code.contains('!'))
+    );
+    assert!(!vectors.iter().any(|v| v.kind == VectorKind::BadChecksum));
+}
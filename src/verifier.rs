@@ -0,0 +1,379 @@
+use alloc::{string::String, sync::Arc};
+use core::fmt;
+
+use crate::{Config, Set, SpecVersion, dedup_key, segment, verify_as};
+
+/// The outcome of verifying a code with a [`Verifier`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum VerifyOutcome {
+    /// The code is valid.
+    Valid,
+    /// The code doesn't match the prefix, pattern, charset or suffix.
+    Malformed,
+    /// The code has the right shape, but a wrong format tag, OCR check or
+    /// checksum character.
+    ///
+    /// Many of these from one client suggest someone guessing codes that
+    /// know the format.
+    BadCheck,
+    /// The code is valid, but was revoked, see [`Verifier::with_revoked`].
+    Revoked,
+}
+
+/// Receives the outcome of every verification made by a [`Verifier`].
+///
+/// Each method is called with the normalized code, as the callback of
+/// [`Verifier::on_verify`] is, and does nothing by default: implement the
+/// ones a dashboard or counter needs. Methods run on the verifying thread,
+/// so keep them fast.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::{
+///     Arc,
+///     atomic::{AtomicU64, Ordering},
+/// };
+/// use referral_codes::{Config, VerificationObserver, Verifier};
+///
+/// #[derive(Default)]
+/// struct Counters {
+///     malformed: AtomicU64,
+///     revoked: AtomicU64,
+/// }
+///
+/// impl VerificationObserver for Counters {
+///     fn on_invalid_format(&self, _code: &str) {
+///         self.malformed.fetch_add(1, Ordering::Relaxed);
+///     }
+///
+///     fn on_revoked(&self, _code: &str) {
+///         self.revoked.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let counters = Arc::new(Counters::default());
+/// let verifier = Verifier::new(Config::default())
+///     .with_revoked(["AbC123xY"])
+///     .with_observer(counters.clone());
+///
+/// assert!(!verifier.verify("AbC123xY"));
+/// assert!(!verifier.verify("short"));
+/// assert_eq!(counters.revoked.load(Ordering::Relaxed), 1);
+/// assert_eq!(counters.malformed.load(Ordering::Relaxed), 1);
+/// ```
+pub trait VerificationObserver: Send + Sync {
+    /// Called when a code is valid.
+    fn on_valid(&self, code: &str) {
+        let _ = code;
+    }
+
+    /// Called when a code has the right shape, but a wrong format tag, OCR
+    /// check or checksum character.
+    fn on_invalid_checksum(&self, code: &str) {
+        let _ = code;
+    }
+
+    /// Called when a code doesn't match the prefix, pattern, charset or
+    /// suffix.
+    fn on_invalid_format(&self, code: &str) {
+        let _ = code;
+    }
+
+    /// Called when a code is valid but revoked.
+    fn on_revoked(&self, code: &str) {
+        let _ = code;
+    }
+}
+
+impl<O: VerificationObserver + ?Sized> VerificationObserver for Arc<O> {
+    fn on_valid(&self, code: &str) {
+        (**self).on_valid(code);
+    }
+
+    fn on_invalid_checksum(&self, code: &str) {
+        (**self).on_invalid_checksum(code);
+    }
+
+    fn on_invalid_format(&self, code: &str) {
+        (**self).on_invalid_format(code);
+    }
+
+    fn on_revoked(&self, code: &str) {
+        (**self).on_revoked(code);
+    }
+}
+
+/// An observer receiving every verification.
+#[derive(Clone)]
+struct Observer(Arc<dyn VerificationObserver>);
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Observer")
+    }
+}
+
+/// The signature of verification callbacks.
+type VerifyFn = dyn Fn(&str, VerifyOutcome) + Send + Sync;
+
+/// A callback receiving every verification.
+#[derive(Clone)]
+struct VerifyHook(Arc<VerifyFn>);
+
+impl fmt::Debug for VerifyHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("VerifyHook")
+    }
+}
+
+/// Verifies codes of a configuration, reporting every outcome to a callback.
+///
+/// Codes are first normalized with [`Config::normalize_code`], undoing
+/// their formatting. The callback receives the normalized code, the form
+/// issuers deduplicate on, lowercased with `random_case`, so repeated
+/// attempts in different cases count as one code. It runs on the verifying thread, so abuse
+/// detection such as rate or entropy tracking of failed attempts can be
+/// plugged in once instead of around every call site; keep it fast. A
+/// [`VerificationObserver`] receives the same outcomes, one method each.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use referral_codes::{Charset, Config, Pattern, Verifier, VerifyOutcome};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Pattern("REF-####".to_string()),
+///     ..Config::default()
+/// };
+/// let failures = Arc::new(Mutex::new(Vec::new()));
+/// let verifier = Verifier::new(config).on_verify({
+///     let failures = failures.clone();
+///     move |code, outcome| {
+///         if outcome != VerifyOutcome::Valid {
+///             failures.lock().unwrap().push(code.to_string());
+///         }
+///     }
+/// });
+///
+/// assert!(verifier.verify("REF-1234"));
+/// assert!(!verifier.verify("REF-12A4"));
+/// assert_eq!(*failures.lock().unwrap(), vec!["REF-12A4"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Verifier {
+    config: Config,
+    version: SpecVersion,
+    revoked: Arc<Set<String>>,
+    hook: Option<VerifyHook>,
+    observer: Option<Observer>,
+}
+
+impl Verifier {
+    /// Creates a verifier of codes of `config`, by the rules of [`SpecVersion::LATEST`].
+    pub fn new(config: Config) -> Self {
+        Verifier {
+            config,
+            version: SpecVersion::LATEST,
+            revoked: Arc::default(),
+            hook: None,
+            observer: None,
+        }
+    }
+
+    /// Returns this verifier verifying codes by the rules of `version` instead.
+    pub fn with_spec_version(self, version: SpecVersion) -> Self {
+        Verifier { version, ..self }
+    }
+
+    /// Returns this verifier calling `hook` with the normalized code and
+    /// the outcome of every verification.
+    pub fn on_verify(self, hook: impl Fn(&str, VerifyOutcome) + Send + Sync + 'static) -> Self {
+        Verifier {
+            hook: Some(VerifyHook(Arc::new(hook))),
+            ..self
+        }
+    }
+
+    /// Returns this verifier reporting every verification to `observer`.
+    pub fn with_observer(self, observer: impl VerificationObserver + 'static) -> Self {
+        Verifier {
+            observer: Some(Observer(Arc::new(observer))),
+            ..self
+        }
+    }
+
+    /// Returns this verifier turning down the given codes, however valid.
+    ///
+    /// Codes are compared normalized, so with `random_case`, revoking a
+    /// code revokes it in every case.
+    pub fn with_revoked<I, S>(self, codes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let revoked = codes
+            .into_iter()
+            .map(|code| dedup_key(&self.config, code.as_ref()).into_owned())
+            .collect();
+
+        Verifier {
+            revoked: Arc::new(revoked),
+            ..self
+        }
+    }
+
+    /// Returns the configuration codes are verified against.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Verifies a code, as [`verify_as`] does, and reports the outcome.
+    ///
+    /// Revoked codes are turned down.
+    pub fn verify(&self, code: &str) -> bool {
+        self.check(code) == VerifyOutcome::Valid
+    }
+
+    /// Verifies a code and reports the outcome.
+    pub fn check(&self, code: &str) -> VerifyOutcome {
+        let code = &self.config.normalize_code(code);
+        let key = dedup_key(&self.config, code);
+        let outcome = if verify_as(code, &self.config, self.version) {
+            match self.revoked.contains(key.as_ref()) {
+                true => VerifyOutcome::Revoked,
+                false => VerifyOutcome::Valid,
+            }
+        } else if segment::random_chars(&self.config, code).is_none() {
+            VerifyOutcome::Malformed
+        } else {
+            VerifyOutcome::BadCheck
+        };
+
+        if let Some(VerifyHook(hook)) = &self.hook {
+            hook(&key, outcome);
+        }
+        if let Some(Observer(observer)) = &self.observer {
+            match outcome {
+                VerifyOutcome::Valid => observer.on_valid(&key),
+                VerifyOutcome::Malformed => observer.on_invalid_format(&key),
+                VerifyOutcome::BadCheck => observer.on_invalid_checksum(&key),
+                VerifyOutcome::Revoked => observer.on_revoked(&key),
+            }
+        }
+
+        outcome
+    }
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_verifier_reports_normalized_outcomes() {
+    use std::sync::Mutex;
+
+    use crate::{Charset, OcrCheck, Pattern, generate_one};
+
+    let config = Config {
+        charset: Charset::Custom("ABCDEFGHJKLMNPQRSTUVWXYZ".to_string()),
+        pattern: Pattern::Length(6),
+        ocr_check: Some(OcrCheck::new(4, 5)),
+        random_case: Some(0.5),
+        ..Config::default()
+    };
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let verifier = Verifier::new(config.clone()).on_verify({
+        let seen = seen.clone();
+        move |code, outcome| seen.lock().unwrap().push((code.to_string(), outcome))
+    });
+
+    let code = generate_one(&config).unwrap();
+    let mut tampered: Vec<char> = code.chars().collect();
+    tampered[0] = if tampered[0].eq_ignore_ascii_case(&'A') {
+        'B'
+    } else {
+        'A'
+    };
+    let tampered: String = tampered.into_iter().collect();
+
+    assert_eq!(verifier.check(&code.to_uppercase()), VerifyOutcome::Valid);
+    assert_eq!(verifier.check(&tampered), VerifyOutcome::BadCheck);
+    assert_eq!(verifier.check("short"), VerifyOutcome::Malformed);
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            (code.to_lowercase(), VerifyOutcome::Valid),
+            (tampered.to_lowercase(), VerifyOutcome::BadCheck),
+            ("short".to_string(), VerifyOutcome::Malformed),
+        ]
+    );
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_verifier_notifies_observers_and_revokes() {
+    use std::sync::Mutex;
+
+    use crate::{Charset, Checksum, Pattern, generate};
+
+    #[derive(Default)]
+    struct Log(Mutex<Vec<(&'static str, String)>>);
+
+    impl VerificationObserver for Log {
+        fn on_valid(&self, code: &str) {
+            self.0.lock().unwrap().push(("valid", code.to_string()));
+        }
+
+        fn on_invalid_checksum(&self, code: &str) {
+            self.0.lock().unwrap().push(("checksum", code.to_string()));
+        }
+
+        fn on_invalid_format(&self, code: &str) {
+            self.0.lock().unwrap().push(("format", code.to_string()));
+        }
+
+        fn on_revoked(&self, code: &str) {
+            self.0.lock().unwrap().push(("revoked", code.to_string()));
+        }
+    }
+
+    let config = Config {
+        charset: Charset::Custom("ABCDEFGH".to_string()),
+        pattern: Pattern::Length(4),
+        checksum: Some(Checksum::Iso7064Mod37_2),
+        random_case: Some(0.5),
+        count: 2,
+        ..Config::default()
+    };
+    let codes = generate(&config).unwrap();
+    let log = Arc::new(Log::default());
+    let verifier = Verifier::new(config)
+        .with_revoked([&codes[1]])
+        .with_observer(log.clone());
+
+    let mut tampered = codes[0].to_lowercase();
+    tampered.replace_range(..1, if tampered.starts_with('a') { "b" } else { "a" });
+
+    assert!(verifier.verify(&codes[0]));
+    assert_eq!(
+        verifier.check(&codes[1].to_uppercase()),
+        VerifyOutcome::Revoked
+    );
+    assert!(!verifier.verify(&codes[1]));
+    assert_eq!(verifier.check(&tampered), VerifyOutcome::BadCheck);
+    assert_eq!(verifier.check("AB"), VerifyOutcome::Malformed);
+
+    let keys: Vec<String> = codes.iter().map(|code| code.to_lowercase()).collect();
+    assert_eq!(
+        *log.0.lock().unwrap(),
+        [
+            ("valid", keys[0].clone()),
+            ("revoked", keys[1].clone()),
+            ("revoked", keys[1].clone()),
+            ("checksum", tampered),
+            ("format", "ab".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, RandomState},
+    io::{self, BufRead},
+};
+
+use crate::{Config, SpecVersion, dedup_key, verify_as};
+
+/// A line that repeats a code seen earlier in the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Duplicate {
+    /// One-based line number of the repeated code.
+    pub line: usize,
+    /// One-based line number where the code first appeared.
+    pub first_line: usize,
+}
+
+/// The result of verifying a file of codes.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct FileReport {
+    /// Number of lines read.
+    pub lines: usize,
+    /// Number of lines holding a valid code seen for the first time.
+    pub unique: usize,
+    /// One-based line numbers of lines that are not valid codes.
+    pub invalid: Vec<usize>,
+    /// Lines repeating an earlier code.
+    pub duplicates: Vec<Duplicate>,
+}
+
+impl FileReport {
+    /// Returns `true` if every line is a valid, unique code.
+    pub fn is_clean(&self) -> bool {
+        self.invalid.is_empty() && self.duplicates.is_empty()
+    }
+}
+
+/// Computes a 128-bit fingerprint of a code from two independently keyed hashers.
+struct Fingerprinter([RandomState; 2]);
+
+impl Fingerprinter {
+    fn new() -> Self {
+        Fingerprinter([RandomState::new(), RandomState::new()])
+    }
+
+    fn fingerprint(&self, code: &str) -> u128 {
+        let [a, b] = &self.0;
+        (u128::from(a.hash_one(code)) << 64) | u128::from(b.hash_one(code))
+    }
+}
+
+/// Verifies a list of codes, one per line, against a configuration.
+///
+/// The input is streamed line by line, so arbitrarily large files can be
+/// processed. Duplicate detection keeps a 128-bit fingerprint per distinct
+/// code rather than the code itself, which bounds memory to a small fixed
+/// size per code whatever the code length; the probability of two distinct
+/// codes sharing a fingerprint is negligible (around 2^-60 for a billion codes).
+///
+/// A line is valid if [`verify_as`] accepts it under [`SpecVersion::LATEST`]:
+/// it matches the configuration's prefix, pattern, charset and suffix, and
+/// carries correct format tag, OCR check and checksum characters when they
+/// are configured. Lines ending in `\r\n` are accepted.
+///
+/// # Arguments
+///
+/// * `reader` - The file to verify
+/// * `config` - The configuration the codes were generated with
+///
+/// # Errors
+///
+/// Returns any I/O error produced by the reader, including invalid UTF-8.
+///
+/// # Examples
+///
+/// ```
+/// use referral_codes::{Charset, Config, Pattern, verify_file};
+///
+/// let config = Config {
+///     charset: Charset::Numeric,
+///     pattern: Pattern::Length(4),
+///     ..Config::default()
+/// };
+/// let file = "1234\n5678\nABCD\n1234\n";
+///
+/// let report = verify_file(file.as_bytes(), &config).unwrap();
+///
+/// assert_eq!(report.unique, 2);
+/// assert_eq!(report.invalid, vec![3]);
+/// assert_eq!(report.duplicates[0].line, 4);
+/// assert_eq!(report.duplicates[0].first_line, 1);
+/// ```
+pub fn verify_file<R: BufRead>(reader: R, config: &Config) -> io::Result<FileReport> {
+    let fingerprinter = Fingerprinter::new();
+    let mut seen: HashMap<u128, usize> = HashMap::new();
+    let mut report = FileReport::default();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let code = line.strip_suffix('\r').unwrap_or(&line);
+        let number = i + 1;
+        report.lines = number;
+
+        if !verify_as(code, config, SpecVersion::LATEST) {
+            report.invalid.push(number);
+            continue;
+        }
+
+        let fingerprint = fingerprinter.fingerprint(&dedup_key(config, code));
+        match seen.get(&fingerprint) {
+            Some(first_line) => report.duplicates.push(Duplicate {
+                line: number,
+                first_line: *first_line,
+            }),
+            None => {
+                seen.insert(fingerprint, number);
+                report.unique += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[test]
+fn test_verify_file_rejects_bad_checksums() {
+    use crate::{Charset, Checksum, Pattern};
+
+    let config = Config {
+        charset: Charset::Numeric,
+        pattern: Pattern::Length(3),
+        checksum: Some(Checksum::Iso7064Mod97_10),
+        ..Config::default()
+    };
+    let file = "79444\n79445\n79444\n";
+
+    let report = verify_file(file.as_bytes(), &config).unwrap();
+
+    assert_eq!(report.unique, 1);
+    assert_eq!(report.invalid, vec![2]);
+    assert_eq!(report.duplicates.len(), 1);
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn test_verify_file_generated_batch() {
+    let config = Config::default().with_count(1_000).with_prefix("REF-");
+    let codes = crate::generate(&config).unwrap();
+    let file = codes.join("\r\n");
+
+    let report = verify_file(file.as_bytes(), &config).unwrap();
+
+    assert!(report.is_clean());
+    assert_eq!(report.lines, 1_000);
+    assert_eq!(report.unique, 1_000);
+}